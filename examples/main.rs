@@ -39,7 +39,7 @@ fn main() {
                     let root = r.alloc(|_|{Obj { ix: vec![ex2.ix().unwrap()] }}).root();
                     v.push(root);
                 }
-                let i = v.get(v.len()-1).unwrap().ix();
+                let i = v.last().unwrap().ix();
                 for root in &mut v {
                     root.get_mut(&mut r).ix.push(i);
                 }
@@ -55,17 +55,17 @@ fn main() {
                 if e.ix.len() >= 5 {
                     e.ix.pop();
                     e.ix.pop();
-                    e.ix.extend(v.get(0).map(gc::Root::ix));
+                    e.ix.extend(v.first().map(gc::Root::ix));
                 }
             }
         }
     }
 
     r.gc();
-    println!("{:?} -> {:?} []-> {:?}", ex3, ex3.get(&r), ex3.get(&r).ix.get(0).unwrap().get(&r));
+    println!("{:?} -> {:?} []-> {:?}", ex3, ex3.get(&r), ex3.get(&r).ix.first().unwrap().get(&r));
 
     ex2.get_mut(&mut r).ix = vec![];
 
     r.gc();
-    println!("{:?} -> {:?} []-> {:?}", ex3, ex3.get(&r), ex3.get(&r).ix.get(0).unwrap().get(&r));
+    println!("{:?} -> {:?} []-> {:?}", ex3, ex3.get(&r), ex3.get(&r).ix.first().unwrap().get(&r));
 }
@@ -0,0 +1,108 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use alloc::vec::Vec;
+
+use crate::{Ix, Region};
+use crate::ix_set::Bits;
+use crate::remap::{Forwarding, Remap};
+
+#[derive(Default)]
+pub(crate) struct MarkState {
+    // Every object at or after this position is new
+    start: usize,
+    // New objects which have been moved before start
+    moved: Bits,
+}
+impl Remap for MarkState {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        let mut moved = Bits::default();
+        let old = self.moved.iter().chain(self.start..forwarding.old_len());
+        for j in old.filter_map(|i| forwarding.get(i)) {
+            moved.set(j, true);
+        }
+        self.moved = moved;
+        self.start = forwarding.len();
+    }
+}
+
+/**
+ * A token recording a point in the allocation history of a region,
+ * created by [`Region::alloc_mark`](struct.Region.html#method.alloc_mark).
+ *
+ * The token is registered with the region, so the objects allocated
+ * after it are tracked across collections.
+ */
+pub struct AllocMark<T> {
+    state: Rc<RefCell<MarkState>>,
+    _t: PhantomData<Ix<T>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Record the current point in allocation, so that objects
+     * allocated later can be visited with [`iter_since`](#method.iter_since).
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let _old = r.alloc(|_|{()}).root();
+     * let mark = r.alloc_mark();
+     * let new = r.alloc(|_|{()}).root();
+     *
+     * r.gc();
+     * let since: Vec<_> = r.iter_since(&mark).map(|(ix, _)| ix.identifier()).collect();
+     * assert_eq!(since, vec![new.ix().identifier()]);
+     * ```
+     */
+    pub fn alloc_mark(&mut self) -> AllocMark<T> {
+        let state = Rc::new(RefCell::new(MarkState {
+            start: self.data.len(),
+            moved: Bits::default(),
+        }));
+        self.remaps.push(Rc::downgrade(&state) as _);
+        AllocMark { state, _t: PhantomData }
+    }
+    /**
+     * Iterate over the surviving objects which were allocated after
+     * the given mark. The mark must have been created by this region.
+     */
+    pub fn iter_since(&self, mark: &AllocMark<T>) -> impl Iterator<Item=(Ix<T>, &T)> {
+        let state = mark.state.borrow();
+        let moved: Vec<usize> = state.moved.iter().collect();
+        moved.into_iter().chain(state.start..self.data.len())
+            .filter_map(move |i| Some((self.ix_at(i), self.data.get(i)?.get()?.get())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn iter_since_tracks_across_collections() {
+        let mut r = Region::new();
+        let old = r.alloc(|_| {Elem::new()}).root();
+        let mark = r.alloc_mark();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem::new()}).root();
+        assert_eq!(r.iter_since(&mark).count(), 2);
+
+        // old is copied before the mark, and late and c after a and b
+        let late = r.alloc(|_| {Elem::new()}).root();
+        let c = r.alloc(|_| {Elem::new()}).root();
+        late.get_mut(&mut r).ix = Some(c.ix());
+        drop(c);
+        r.gc();
+        assert_eq!(old.ix().identifier(), 0);
+        let since: Vec<usize> = r.iter_since(&mark).map(|(ix, _)| ix.identifier()).collect();
+        assert_eq!(since, vec![a.ix().identifier(), b.ix().identifier(), late.ix().identifier(), late.get(&r).ix.unwrap().identifier()]);
+    }
+}
@@ -0,0 +1,144 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::Location;
+use std::rc::Rc;
+
+use crate::Region;
+use crate::remap::{Forwarding, Remap};
+
+type Site = &'static Location<'static>;
+
+/**
+ * The objects allocated at one location, as reported by
+ * [`Region::allocation_sites`](struct.Region.html#method.allocation_sites).
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocSite {
+    pub location: &'static Location<'static>,
+    /**
+     * The number of objects allocated here which are still in the region.
+     */
+    pub live: usize,
+    /**
+     * The number of objects ever allocated here.
+     */
+    pub total: usize,
+}
+
+pub(crate) struct AllocSites {
+    // The site of each object, by position, where it is known
+    of: Vec<Option<Site>>,
+    totals: HashMap<Site, usize>,
+}
+impl Remap for AllocSites {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        let mut of = vec![None; forwarding.len()];
+        for (i, &site) in self.of.iter().enumerate() {
+            if let Some(j) = forwarding.get(i) {
+                of[j] = site;
+            }
+        }
+        self.of = of;
+    }
+}
+
+impl <T> Region<T> {
+    // Record the caller as the site of every object from start
+    #[track_caller]
+    pub(crate) fn record_alloc_site(&mut self, start: usize) {
+        let site = Location::caller();
+        let len = self.data.len();
+        let sites = match self.alloc_sites {
+            Some(ref sites) => sites.clone(),
+            None => {
+                let sites = Rc::new(RefCell::new(AllocSites { of: Vec::new(), totals: HashMap::new() }));
+                self.remaps.push(Rc::downgrade(&sites) as _);
+                self.alloc_sites = Some(sites.clone());
+                sites
+            },
+        };
+        let mut sites = sites.borrow_mut();
+        // Objects which arrived by other means have no known site
+        sites.of.resize(start, None);
+        sites.of.resize(len, Some(site));
+        *sites.totals.entry(site).or_insert(0) += len - start;
+    }
+
+    /**
+     * Report the locations which have allocated objects in this region,
+     * with the number of those objects which are still live,
+     * most live first.
+     *
+     * Locations are recorded by [`alloc`](#method.alloc),
+     * [`alloc_slice`](#method.alloc_slice) and their fallible counterparts,
+     * so a location within a function which allocates
+     * on behalf of its caller is reported as that function,
+     * unless it is itself marked `#[track_caller]`.
+     * Objects which arrive by other means, such as
+     * [`gc_into`](#method.gc_into), are not counted.
+     *
+     * This requires the feature "alloc-sites".
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(3);
+     * let root = r.alloc(|_|{()}).root();
+     * for _ in 0..2 {
+     *     r.alloc(|_|{()});
+     * }
+     * r.gc();
+     *
+     * let sites = r.allocation_sites();
+     * assert_eq!((sites[0].live, sites[0].total), (1, 1));
+     * assert_eq!((sites[1].live, sites[1].total), (0, 2));
+     * ```
+     */
+    pub fn allocation_sites(&self) -> Vec<AllocSite> {
+        let sites = match self.alloc_sites {
+            Some(ref sites) => sites.borrow(),
+            None => return Vec::new(),
+        };
+        let mut live: HashMap<Site, usize> = HashMap::new();
+        for &site in sites.of.iter().flatten() {
+            *live.entry(site).or_insert(0) += 1;
+        }
+        let mut report: Vec<AllocSite> = sites.totals.iter().map(|(&location, &total)| AllocSite {
+            location,
+            live: live.get(&location).copied().unwrap_or(0),
+            total,
+        }).collect();
+        report.sort_by_key(|s| std::cmp::Reverse((s.live, s.total)));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn sites_count_live_objects() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let line = line!() + 1;
+        let range = r.alloc_slice(vec![Elem::new(), Elem { ix: Some(a) }]);
+        let _root = r.alloc(|_| {Elem { ix: range.get(1) }}).root();
+        r.gc();
+
+        let sites = r.allocation_sites();
+        assert_eq!(sites.len(), 3);
+        assert_eq!(sites[0].location.line(), line);
+        assert_eq!((sites[0].live, sites[0].total), (2, 2));
+        assert_eq!(sites.iter().map(|s| s.live).sum::<usize>(), r.len());
+        assert!(sites.iter().all(|s| s.location.file().ends_with("alloc_sites.rs")));
+    }
+}
@@ -0,0 +1,257 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::vec::Vec;
+
+use crate::{Error, HasIx, Ix};
+use crate::has_ix::foreach_edge;
+#[cfg(feature = "debug-arena")]
+use crate::nonce;
+
+enum Slot<T> {
+    Present(T),
+    // Only present in the old space during a collection
+    Forwarded(Ix<T>),
+}
+
+/**
+ * A plain moving arena, without roots or weak pointers.
+ *
+ * Objects are only ever referred to by raw indices ([`Ix`](struct.Ix.html)),
+ * and a collection must be explicitly requested with
+ * [`gc_from`](#method.gc_from), passing every index
+ * which should be kept alive and updated. As such, no
+ * per-object headers or reference-counted cells are needed,
+ * and allocation never invalidates indices.
+ *
+ * This is intended for embedders which already track
+ * their own roots. Otherwise, [`Region`](struct.Region.html)
+ * should be preferred.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut a = gc::Arena::new();
+ * let mut roots = vec![a.alloc(()), a.alloc(())];
+ * a.alloc(());
+ *
+ * a.gc_from(roots.iter_mut().skip(1));
+ * assert_eq!(a.len(), 1);
+ * assert!(a.try_get(roots[1]).is_ok());
+ * ```
+ */
+pub struct Arena<T> {
+    data: Vec<Slot<T>>,
+
+    #[cfg(feature = "debug-arena")]
+    nonce: crate::nonce::Word,
+    #[cfg(feature = "debug-arena")]
+    generation: crate::nonce::Word,
+}
+
+impl <T> Arena<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Arena {
+            data: Vec::with_capacity(capacity),
+            #[cfg(feature = "debug-arena")]
+            nonce: nonce::next(),
+            #[cfg(feature = "debug-arena")]
+            generation: 0,
+        }
+    }
+
+    #[inline]
+    fn ix_at(&self, i: usize) -> Ix<T> {
+        Ix::new(i,
+            #[cfg(feature = "debug-arena")]
+            self.nonce,
+            #[cfg(feature = "debug-arena")]
+            self.generation,
+        )
+    }
+
+    /**
+     * Allocate a new object, returning its index.
+     *
+     * Unlike [`Region::alloc`](struct.Region.html#method.alloc),
+     * this never triggers a collection, and so never invalidates indices.
+     */
+    #[inline]
+    pub fn alloc(&mut self, t: T) -> Ix<T> {
+        let ix = self.ix_at(self.data.len());
+        self.data.push(Slot::Present(t));
+        ix
+    }
+
+    #[inline]
+    pub fn get(&self, ix: Ix<T>) -> &T {
+        self.try_get(ix).expect("Arena::get")
+    }
+    #[inline]
+    pub fn get_mut(&mut self, ix: Ix<T>) -> &mut T {
+        self.try_get_mut(ix).expect("Arena::get_mut")
+    }
+    #[inline]
+    pub fn try_get(&self, ix: Ix<T>) -> Result<&T, Error> {
+        ix.check_current(self.ix_at(0),
+            #[cfg(feature = "debug-arena")]
+            &[])?;
+        match self.data.get(ix.ix()) {
+            Some(Slot::Present(t)) => Ok(t),
+            _ => Err(Error::Indeterminable),
+        }
+    }
+    #[inline]
+    pub fn try_get_mut(&mut self, ix: Ix<T>) -> Result<&mut T, Error> {
+        ix.check_current(self.ix_at(0),
+            #[cfg(feature = "debug-arena")]
+            &[])?;
+        match self.data.get_mut(ix.ix()) {
+            Some(Slot::Present(t)) => Ok(t),
+            _ => Err(Error::Indeterminable),
+        }
+    }
+
+    /**
+     * Return the current capacity of this arena.
+     */
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+    /**
+     * Return the current number of objects in the arena.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+    /**
+     * Returns true if there are currently no objects in this arena.
+     */
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+impl <T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <T: 'static + HasIx<T>> Arena<T> {
+    /**
+     * Collect every object which is not reachable from the given roots,
+     * updating the roots to point to the new locations.
+     *
+     * This invalidates all other indices.
+     */
+    pub fn gc_from<'r, I>(&mut self, roots: I) where
+        I: IntoIterator<Item=&'r mut Ix<T>>
+    {
+        #[cfg(feature = "debug-arena")]
+        let new_gen = self.generation.wrapping_add(1);
+        #[cfg(feature = "debug-arena")]
+        let (nonce, old_gen) = (self.nonce, self.generation);
+
+        let mut src = core::mem::take(&mut self.data);
+        let mut dst: Vec<Slot<T>> = Vec::with_capacity(src.len());
+
+        // Move an object to the end of dst (or to pending, if dst
+        // is borrowed), returning its new index
+        let mut forward = |ix: &mut Ix<T>, dst_len: usize, pending: &mut Vec<Slot<T>>| {
+            #[cfg(feature = "debug-arena")]
+            {
+                if ix.nonce != nonce || ix.generation != old_gen {
+                    panic!("GC: Invalid index for arena");
+                }
+            }
+            let slot = match src.get_mut(ix.ix()) {
+                Some(slot) => slot,
+                None => panic!("Invalid index {} found during GC.", ix.ix()),
+            };
+            *ix = match slot {
+                Slot::Forwarded(new_ix) => *new_ix,
+                Slot::Present(_) => {
+                    let new_ix = Ix::new(dst_len + pending.len(),
+                        #[cfg(feature = "debug-arena")]
+                        nonce,
+                        #[cfg(feature = "debug-arena")]
+                        new_gen,
+                    );
+                    pending.push(core::mem::replace(slot, Slot::Forwarded(new_ix)));
+                    new_ix
+                },
+            };
+        };
+
+        let mut pending = Vec::new();
+        #[cfg(feature = "std")]
+        let _scope = crate::shared_ix::RewriteScope::enter();
+        for root in roots {
+            forward(root, 0, &mut dst);
+        }
+        let mut scan = 0;
+        while scan < dst.len() {
+            let len = dst.len();
+            if let Slot::Present(ref mut t) = dst[scan] {
+                foreach_edge(t, |ix| forward(ix, len, &mut pending));
+            }
+            dst.append(&mut pending);
+            scan += 1;
+        }
+
+        self.data = dst;
+        #[cfg(feature = "debug-arena")]
+        {
+            self.generation = new_gen;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Arena;
+    use crate::Ix;
+    use crate::HasIx;
+
+    struct Node(Vec<Ix<Node>>, usize);
+    impl HasIx<Node> for Node {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Node>)
+        {
+            self.0.iter_mut().for_each(f)
+        }
+    }
+
+    #[test]
+    pub fn arena_gc_keeps_reachable() {
+        let mut a = Arena::new();
+        let x = a.alloc(Node(vec![], 0));
+        a.alloc(Node(vec![], 1));
+        let y = a.alloc(Node(vec![x], 2));
+        a.get_mut(x).0.push(y);
+        let z = a.alloc(Node(vec![x, y], 3));
+
+        let mut roots = [z, x];
+        a.gc_from(roots.iter_mut());
+        assert_eq!(a.len(), 3);
+        let [z, x] = roots;
+        assert_eq!(a.get(z).1, 3);
+        assert_eq!(a.get(x).1, 0);
+        let y = a.get(x).0[0];
+        assert_eq!(a.get(y).1, 2);
+        assert_eq!(a.get(y).0[0].identifier(), x.identifier());
+
+        a.gc_from(None);
+        assert!(a.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{Error, Ix, Region};
+
+/**
+ * A type which can be used as an index into a region,
+ * by wrapping an [`Ix`](struct.Ix.html).
+ *
+ * This allows several distinct index types over the same
+ * region, such as `ExprIx` and `StmtIx` into a `Region<Node>`,
+ * while the collector works with the underlying indices. The
+ * [`new_index_type`](macro.new_index_type.html) macro defines such a type.
+ *
+ * An index type is updated by collections as an Ix is, so
+ * objects which hold them must expose the underlying index
+ * in [`HasIx::foreach_ix`](trait.HasIx.html#tymethod.foreach_ix),
+ * using [`ix_mut`](#tymethod.ix_mut).
+ */
+pub trait ArenaIndex<T>: Copy {
+    /**
+     * Wrap a raw index.
+     */
+    fn from_ix(ix: Ix<T>) -> Self;
+    /**
+     * Get the underlying raw index.
+     */
+    fn to_ix(self) -> Ix<T>;
+    /**
+     * Get the underlying raw index, to be updated by the collector.
+     */
+    fn ix_mut(&mut self) -> &mut Ix<T>;
+
+    /**
+     * Get the object at this index, as with [`Ix::get`](struct.Ix.html#method.get).
+     */
+    #[inline(always)]
+    #[track_caller]
+    fn get(self, region: &Region<T>) -> &T {
+        self.to_ix().get(region)
+    }
+    /**
+     * Get the object at this index, as with [`Ix::get_mut`](struct.Ix.html#method.get_mut).
+     */
+    #[inline(always)]
+    #[track_caller]
+    fn get_mut(self, region: &mut Region<T>) -> &mut T {
+        self.to_ix().get_mut(region)
+    }
+    /**
+     * Get the object at this index, as with [`Ix::try_get`](struct.Ix.html#method.try_get).
+     */
+    #[inline(always)]
+    fn try_get(self, region: &Region<T>) -> Result<&T, Error> {
+        self.to_ix().try_get(region)
+    }
+    /**
+     * Get the object at this index, as with [`Ix::try_get_mut`](struct.Ix.html#method.try_get_mut).
+     */
+    #[inline(always)]
+    fn try_get_mut(self, region: &mut Region<T>) -> Result<&mut T, Error> {
+        self.to_ix().try_get_mut(region)
+    }
+}
+
+impl <T> ArenaIndex<T> for Ix<T> {
+    #[inline(always)]
+    fn from_ix(ix: Ix<T>) -> Self {
+        ix
+    }
+    #[inline(always)]
+    fn to_ix(self) -> Ix<T> {
+        self
+    }
+    #[inline(always)]
+    fn ix_mut(&mut self) -> &mut Ix<T> {
+        self
+    }
+}
+
+/**
+ * Define a newtype index into a region, implementing
+ * [`ArenaIndex`](trait.ArenaIndex.html) and [`HasIx`](trait.HasIx.html).
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use gc::ArenaIndex;
+ *
+ * struct Node(Option<gc::Ix<Node>>);
+ * impl gc::HasIx<Node> for Node {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Node>)
+ *     {
+ *         self.0.foreach_ix(f)
+ *     }
+ * }
+ * gc::new_index_type! {
+ *     /// An index to an expression node
+ *     pub struct ExprIx(Node);
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * let e = ExprIx::from_ix(r.alloc(|_| Node(None)).ix());
+ * assert!(e.get(&r).0.is_none());
+ * ```
+ */
+#[macro_export]
+macro_rules! new_index_type {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident($t:ty);) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy)]
+        $vis struct $name($crate::Ix<$t>);
+
+        impl $crate::ArenaIndex<$t> for $name {
+            #[inline(always)]
+            fn from_ix(ix: $crate::Ix<$t>) -> Self {
+                $name(ix)
+            }
+            #[inline(always)]
+            fn to_ix(self) -> $crate::Ix<$t> {
+                self.0
+            }
+            #[inline(always)]
+            fn ix_mut(&mut self) -> &mut $crate::Ix<$t> {
+                &mut self.0
+            }
+        }
+        impl $crate::HasIx<$t> for $name {
+            fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+                F: FnMut(&'b mut $crate::Ix<$t>)
+            {
+                f(&mut self.0)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArenaIndex, HasIx, Ix, Region};
+
+    struct Node {
+        stmts: Vec<StmtIx>,
+        expr: Option<ExprIx>,
+    }
+    impl HasIx<Node> for Node {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+            F: FnMut(&'b mut Ix<Node>)
+        {
+            self.stmts.foreach_ix(&mut f);
+            self.expr.foreach_ix(&mut f);
+        }
+    }
+    new_index_type! {
+        struct ExprIx(Node);
+    }
+    new_index_type! {
+        struct StmtIx(Node);
+    }
+
+    #[test]
+    pub fn index_types_are_traced() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let expr = ExprIx::from_ix(r.alloc(|_| {Node { stmts: vec![], expr: None }}).ix());
+        r.alloc(|_| {Node { stmts: vec![], expr: None }});
+        let stmt = r.alloc(|_| {Node { stmts: vec![], expr: Some(expr) }}).root();
+        r.gc();
+        let expr = stmt.get(&r).expr.unwrap();
+        assert_eq!(r.len(), 2);
+        assert!(expr.try_get(&r).is_ok());
+        assert_eq!(expr.to_ix().identifier(), 1);
+        assert!(StmtIx::from_ix(stmt.ix()).get(&r).expr.is_some());
+    }
+}
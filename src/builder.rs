@@ -0,0 +1,147 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+use crate::{HasIx, Ix, Region, Root};
+
+/**
+ * A declarative description of a graph, which
+ * can be built into a new region, such as for test fixtures.
+ *
+ * Nodes are named by keys, and edges may refer to
+ * nodes which are added later, so cyclic graphs
+ * need no manual wiring. Each edge is added to its
+ * source object by the function the builder was created with.
+ *
+ * This requires the feature "std", which is enabled by default.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ *
+ * struct Node(Vec<gc::Ix<Node>>);
+ * impl gc::HasIx<Node> for Node {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Node>)
+ *     {
+ *         self.0.iter_mut().for_each(f)
+ *     }
+ * }
+ *
+ * let (mut r, nodes) = gc::RegionBuilder::new(|n: &mut Node, ix| n.0.push(ix))
+ *     .node("a", Node(vec![]))
+ *     .edge("a", "b")
+ *     .node("b", Node(vec![]))
+ *     .edge("b", "a")
+ *     .finish();
+ * let b = nodes["a"].get(&r).0[0];
+ * assert_eq!(b.identifier(), nodes["b"].ix().identifier());
+ * ```
+ */
+pub struct RegionBuilder<K, T, F> {
+    nodes: Vec<(K, T)>,
+    edges: Vec<(K, K)>,
+    connect: F,
+}
+
+impl <K, T, F> RegionBuilder<K, T, F> where
+    K: Hash + Eq + Clone + Debug,
+    T: 'static + HasIx<T>,
+    F: FnMut(&mut T, Ix<T>),
+{
+    /**
+     * Create an empty builder, which adds each edge to
+     * its source object by calling `connect`.
+     */
+    pub fn new(connect: F) -> Self {
+        RegionBuilder {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            connect,
+        }
+    }
+    /**
+     * Add an object with the given key.
+     */
+    pub fn node(mut self, key: K, value: T) -> Self {
+        self.nodes.push((key, value));
+        self
+    }
+    /**
+     * Add an edge between the objects with the given keys,
+     * which do not need to have been added yet.
+     * Edges are added to each object in order.
+     */
+    pub fn edge(mut self, from: K, to: K) -> Self {
+        self.edges.push((from, to));
+        self
+    }
+    /**
+     * Build the region, returning a root for each key.
+     *
+     * This panics if a key is used twice, or
+     * if an edge refers to a key with no object.
+     */
+    pub fn finish(mut self) -> (Region<T>, HashMap<K, Root<T>>) {
+        let mut region = Region::new();
+        region.ensure(self.nodes.len());
+        let mut roots = HashMap::new();
+        for (key, value) in self.nodes {
+            let root = region.alloc(|_| value).root();
+            if roots.insert(key.clone(), root).is_some() {
+                panic!("RegionBuilder: duplicate node {:?}", key);
+            }
+        }
+        let find = |key: &K| match roots.get(key) {
+            Some(root) => root.ix(),
+            None => panic!("RegionBuilder: no node {:?}", key),
+        };
+        for (from, to) in self.edges {
+            let (from, to) = (find(&from), find(&to));
+            (self.connect)(from.get_mut(&mut region), to);
+        }
+        (region, roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RegionBuilder, Ix};
+    use crate::tests::Elem;
+
+    fn connect(e: &mut Elem, ix: Ix<Elem>) {
+        e.ix = Some(ix);
+    }
+
+    #[test]
+    pub fn builder_resolves_forward_references() {
+        let (mut r, mut nodes) = RegionBuilder::new(connect)
+            .edge(1, 2)
+            .edge(2, 3)
+            .edge(3, 1)
+            .node(1, Elem::new())
+            .node(2, Elem::new())
+            .node(3, Elem::new())
+            .node(4, Elem::new())
+            .finish();
+        nodes.remove(&4);
+        nodes.remove(&2);
+        nodes.remove(&3);
+        r.gc();
+        assert_eq!(r.len(), 3);
+        let two = nodes[&1].get(&r).ix.unwrap();
+        let three = two.get(&r).ix.unwrap();
+        assert_eq!(three.get(&r).ix.unwrap().identifier(), nodes[&1].ix().identifier());
+    }
+
+    #[test]
+    #[should_panic(expected = "no node 5")]
+    pub fn builder_rejects_missing_nodes() {
+        RegionBuilder::new(connect).node(1, Elem::new()).edge(1, 5).finish();
+    }
+}
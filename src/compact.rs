@@ -0,0 +1,300 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Collection by sliding the live objects down in place.
+//!
+//! Unlike the copying collector, this needs no second space: objects
+//! reachable from the roots are marked in a bitmap, and are then moved
+//! down over the garbage in order, with a table of their new positions
+//! used to update every index.
+
+use alloc::{vec, vec::Vec};
+
+use crate::partition::group_of;
+use crate::remap::{self, Forwarding};
+use crate::{Error, GcEvent, GcTrigger, HasIx, Ix, Region};
+use crate::has_ix::foreach_edge;
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    // Slide the objects from the boundary which are reachable from the roots,
+    // or from the objects before the boundary at the given positions,
+    // down to the boundary, freeing the rest in place, and update every
+    // index which may point to them. Dangling edges are only handled by
+    // the policy if every object is updated, that is, if the boundary is 0.
+    // Returns the new position of every object, and the new length.
+    //
+    // As with a collection, the objects are moved, and the roots, groups and
+    // remaps are updated, before any user code runs, so that the region
+    // remains consistent if a panic occurs while tracing an object or
+    // dropping a removed one, though objects not yet traced may contain
+    // stale indices. The removed objects are dropped last.
+    pub(crate) fn slide(&mut self, boundary: usize, from: &[usize]) -> (Vec<Option<usize>>, usize) {
+        #[cfg(feature = "shadow-heap")]
+        let shadow_roots = self.shadow_pre_slide(boundary);
+        #[cfg(feature = "history")]
+        self.history_slide(boundary, from);
+        let len = self.data.len();
+        let groups = &self.groups;
+        let mut marked = vec![false; len - boundary];
+        let mut stack = Vec::new();
+        let mut mark = |p: usize, stack: &mut Vec<usize>| {
+            if p < boundary || p >= len || marked[p - boundary] { return }
+            for q in group_of(groups, p) {
+                if !marked[q - boundary] {
+                    marked[q - boundary] = true;
+                    stack.push(q);
+                }
+            }
+        };
+        self.foreach_root(|ix| mark(ix.ix(), &mut stack));
+        for &i in from {
+            if let Some(e) = self.data[i].get_mut() {
+                foreach_edge(e.get_mut(), |ix| mark(ix.ix(), &mut stack));
+            }
+        }
+        while let Some(i) = stack.pop() {
+            if let Some(e) = self.data[i].get_mut() {
+                foreach_edge(e.get_mut(), |ix| mark(ix.ix(), &mut stack));
+            }
+        }
+
+        // Slide the survivors down, keeping the rest until the end.
+        // Edges may also mark the positions of removed objects.
+        let mut next = boundary;
+        let moved: Vec<Option<usize>> = marked.iter().zip(&self.data[boundary..])
+            .map(|(&m, spot)| if m && spot.get().is_some() { next += 1; Some(next - 1) } else { None })
+            .collect();
+        let current = self.ix_at(0);
+        let dead = self.ix_at(usize::MAX);
+        let mut removed = Vec::new();
+        for i in boundary..len {
+            match moved[i - boundary] {
+                Some(j) => {
+                    let spot = self.data[i].move_to(current.offset(j));
+                    self.data[j] = spot;
+                },
+                None => {
+                    let spot = self.data[i].move_to(dead);
+                    removed.push((self.is_finalizable(i), spot));
+                },
+            }
+        }
+        self.data.truncate(next);
+
+        let forward = |ix: Ix<T>| match ix.ix() {
+            p if p >= boundary && p < len => moved[p - boundary].map(|j| current.offset(j)),
+            // Objects before the boundary stay, in the current generation
+            p if p < boundary => Some(current.offset(p)),
+            _ => Some(ix),
+        };
+        // Roots are forwarded first, for the dangling edge policy
+        self.roots.for_each_mut(|ix| if let Some(new) = forward(*ix) { *ix = new });
+        for set in &self.root_sets {
+            if let Some(set) = set.upgrade() {
+                set.borrow_mut().iter_mut().for_each(|ix| if let Some(new) = forward(*ix) { *ix = new });
+            }
+        }
+        self.groups.retain_mut(|g| {
+            if g.start < boundary { return true }
+            match moved[g.start - boundary] {
+                Some(j) => { *g = j..j + g.len(); true },
+                None => false,
+            }
+        });
+        let map: Vec<_> = (0..boundary).map(Some).chain(moved.iter().copied()).collect();
+        remap::remap_all(&mut self.remaps, || Forwarding::from_positions(map.clone(), next));
+
+        let full = boundary == 0;
+        if full {
+            self.dangling.found.clear();
+        }
+        {
+            #[cfg(feature = "std")]
+            let _scope = crate::shared_ix::RewriteScope::enter();
+            let dangling = &mut self.dangling;
+            for i in from.iter().copied().chain(boundary..next) {
+                if let Some(e) = self.data[i].get_mut() {
+                    foreach_edge(e.get_mut(), |ix| {
+                        // Indices of other regions are also dangling
+                        #[cfg(feature = "debug-arena")]
+                        if ix.nonce != current.nonce {
+                            if full { dangling.handle(i, ix) }
+                            return
+                        }
+                        match ix.ix() {
+                            p if p >= boundary && p < len => match forward(*ix) {
+                                Some(new) => *ix = new,
+                                // The object was removed in place, such as by take
+                                None if full => dangling.handle_removed(i, ix, dead),
+                                None => *ix = dead,
+                            },
+                            p if p >= len && full => dangling.handle(i, ix),
+                            p if p < boundary => *ix = current.offset(p),
+                            _ => (),
+                        }
+                    });
+                    e.get_mut().foreach_weak_ix(|weak| weak.forward(forward));
+                }
+            }
+        }
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_post_slide(&map, shadow_roots);
+        for (queue, spot) in removed {
+            self.finalize_removed(queue, spot);
+        }
+        (map, next)
+    }
+
+    /**
+     * Collect the region in place, by sliding every reachable object
+     * down over the garbage, preserving their order. This invalidates
+     * raw indices, as with [`gc`](#method.gc).
+     *
+     * A standard collection copies the reachable objects into a second
+     * space, so that memory use peaks at the size of the region plus the
+     * size of its live objects. This instead needs only a bit and a new
+     * position for each object, and releases the second space kept from
+     * any previous collection, at the cost of a slower collection.
+     * The capacity of the region is unchanged.
+     *
+     * Returns the number of objects freed, or an error if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(3);
+     * r.alloc(|_|{()});
+     * let a = r.alloc(|_|{()}).root();
+     * let w = r.alloc(|_|{()}).weak();
+     *
+     * assert_eq!(r.compact(), Ok(2));
+     * assert_eq!(a.ix().identifier(), 0);
+     * assert!(w.try_get(&r).is_err());
+     * ```
+     */
+    pub fn compact(&mut self) -> Result<usize, Error> {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        let before = self.data.len();
+        self.spare = None;
+        self.sweep_ephemerons();
+        self.gc_starting(GcTrigger::Compact);
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        #[cfg(all(feature = "debug-arena", feature = "refresh"))]
+        let old_gen = self.generation;
+        #[cfg(feature = "debug-arena")]
+        self.advance_generation();
+        self.roots.prune();
+        self.root_sets.retain(|set| set.strong_count() > 0);
+        self.pins.deferred = false;
+        self.epoch += 1;
+
+        let (_map, len) = self.slide(0, &[]);
+        #[cfg(feature = "refresh")]
+        self.retain_forwarding(Forwarding::from_positions(_map, len),
+            #[cfg(feature = "debug-arena")]
+            old_gen);
+
+        let event = GcEvent {
+            trigger: GcTrigger::Compact,
+            before,
+            after: len,
+            capacity: self.data.capacity(),
+            #[cfg(feature = "std")]
+            duration: start.elapsed(),
+            #[cfg(not(feature = "std"))]
+            duration: core::time::Duration::ZERO,
+            epoch: self.epoch,
+        };
+        self.policy.on_collected(&event);
+        self.gc_finished(event, len);
+        Ok(before - len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DanglingEdgePolicy, GcTrigger, HasIx, Ix, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn compaction_slides_objects_in_place() {
+        let mut r = Region::new();
+        r.ensure(8);
+        r.alloc(|_| {Elem::new()});
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let mut set = r.ix_set();
+        r.alloc(|_| {Elem::new()});
+        let slice = r.alloc_slice(vec![Elem::new(), Elem { ix: Some(a) }]);
+        let b = r.alloc(|_| {Elem { ix: slice.get(0) }}).root();
+        let w = b.downgrade(&mut r);
+        set.insert(a);
+        let removed = r.alloc(|_| {Elem::new()}).ix();
+        let c = r.alloc(|_| {Elem { ix: Some(removed) }}).root();
+        r.take(removed).unwrap();
+        r.set_dangling_edge_policy(DanglingEdgePolicy::Skip);
+
+        assert_eq!(r.compact(), Ok(3));
+        assert_eq!(r.dangling_edges().len(), 1);
+        assert_eq!(r.len(), 5);
+        assert_eq!(r.recent_gcs().last().unwrap().trigger, GcTrigger::Compact);
+        let first = b.get(&r).ix.unwrap();
+        let a = r.ix_at(first.identifier() + 1).get(&r).ix.unwrap();
+        assert!(set.contains(a));
+        assert_eq!((a.identifier(), first.identifier(), b.ix().identifier()), (0, 1, 3));
+        assert!(w.try_get(&r).is_ok());
+        assert!(c.get(&r).ix.unwrap().try_get(&r).is_err());
+
+        // Edges past the end are handled by the policy
+        c.get_mut(&mut r).ix = Some(r.ix_at(100));
+        assert_eq!(r.compact(), Ok(0));
+        assert_eq!(r.dangling_edges().len(), 1);
+    }
+
+    // Panics when traced for the last time of its fuse
+    struct Fuse {
+        ix: Option<Ix<Fuse>>,
+        fuse: usize,
+    }
+    impl HasIx<Fuse> for Fuse {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Fuse>)
+        {
+            if self.fuse > 0 {
+                self.fuse -= 1;
+                if self.fuse == 0 { panic!("Fuse") }
+            }
+            self.ix.iter_mut().for_each(f)
+        }
+    }
+
+    #[test]
+    pub fn panics_during_compaction_leave_region_consistent() {
+        let mut r = Region::new();
+        r.ensure(4);
+        r.alloc(|_| {Fuse { ix: None, fuse: 0 }});
+        let a = r.alloc(|_| {Fuse { ix: None, fuse: 0 }}).root();
+        // Marked, and then traced again to update its edges
+        let mut e = r.alloc(|_| {Fuse { ix: None, fuse: 2 }});
+        let (b, wb) = (e.ix(), e.weak());
+        let mut set = r.ix_set();
+        set.insert(b);
+        a.get_mut(&mut r).ix = Some(b);
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| r.compact()));
+        assert!(res.is_err());
+        assert_eq!(r.len(), 2);
+        let b = a.get(&r).ix.unwrap();
+        assert_eq!(b.identifier(), 1);
+        assert_eq!(wb.ix().unwrap().identifier(), 1);
+        assert!(set.contains(b));
+
+        assert_eq!(r.compact(), Ok(0));
+        assert!(wb.try_get(&r).is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*!
+ * An interface resembling that of `generational-arena` and `slotmap`,
+ * for incrementally migrating code which uses arena keys.
+ *
+ * A [`SlotRegion`](struct.SlotRegion.html) is a region in which
+ * every inserted object is rooted by a copyable [`Index`](struct.Index.html)
+ * until it is removed. Unlike the arenas it mimics, removing an
+ * object does not drop it immediately: the object is only dropped by
+ * a later collection, and only if it is not reachable through the
+ * [`Ix`](../struct.Ix.html) edges of another object.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use gc::compat::SlotRegion;
+ *
+ * let mut arena = SlotRegion::new();
+ * let a = arena.insert(());
+ * let b = arena.insert(());
+ * assert!(arena.remove(a));
+ * assert!(arena.get(a).is_none());
+ * arena.compact();
+ * assert_eq!(arena.iter().map(|(i, _)| i).collect::<Vec<_>>(), vec![b]);
+ * ```
+ */
+
+use alloc::vec::Vec;
+
+use crate::{HasIx, Ix, Region, Root};
+
+/**
+ * A key into a [`SlotRegion`](struct.SlotRegion.html).
+ *
+ * Keys are never reused: once removed, a key will not
+ * refer to any other object.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Index {
+    index: usize,
+    generation: u64,
+}
+impl Index {
+    /**
+     * Create an index from its raw parts. This
+     * should only be used with parts obtained from
+     * [`into_raw_parts`](#method.into_raw_parts).
+     */
+    pub fn from_raw_parts(index: usize, generation: u64) -> Self {
+        Index { index, generation }
+    }
+    /**
+     * Convert this index into its raw parts, the slot
+     * and the generation of that slot.
+     */
+    pub fn into_raw_parts(self) -> (usize, u64) {
+        (self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    generation: u64,
+    root: Option<Root<T>>,
+}
+
+/**
+ * A region in which objects are addressed by stable keys.
+ * See the [module documentation](index.html).
+ */
+pub struct SlotRegion<T> {
+    region: Region<T>,
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+impl <T> SlotRegion<T> {
+    pub fn new() -> Self {
+        SlotRegion {
+            region: Region::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    fn root(&self, i: Index) -> Option<&Root<T>> {
+        let slot = self.slots.get(i.index)?;
+        if slot.generation == i.generation { slot.root.as_ref() } else { None }
+    }
+
+    /**
+     * Release the object with the given key, returning true
+     * if the key was present. The object will be dropped
+     * by a later collection, unless it is still reachable.
+     */
+    pub fn remove(&mut self, i: Index) -> bool {
+        if self.root(i).is_none() { return false }
+        let slot = &mut self.slots[i.index];
+        slot.root = None;
+        slot.generation += 1;
+        self.free.push(i.index);
+        self.len -= 1;
+        true
+    }
+    #[inline]
+    pub fn contains(&self, i: Index) -> bool {
+        self.root(i).is_some()
+    }
+    #[inline]
+    pub fn get(&self, i: Index) -> Option<&T> {
+        self.root(i)?.try_get(&self.region).ok()
+    }
+    #[inline]
+    pub fn get_mut(&mut self, i: Index) -> Option<&mut T> {
+        let root = self.root(i)?.clone();
+        root.try_get_mut(&mut self.region).ok()
+    }
+    /**
+     * Get the current raw index of the object with the given key,
+     * for use in edges between objects.
+     */
+    #[inline]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
+    pub fn ix(&self, i: Index) -> Option<Ix<T>> {
+        Some(self.root(i)?.get_ix().tracked())
+    }
+    /**
+     * Iterate over every key which is present, along with its object.
+     */
+    pub fn iter(&self) -> impl Iterator<Item=(Index, &T)> {
+        let region = &self.region;
+        self.slots.iter().enumerate().filter_map(move |(index, slot)| {
+            let root = slot.root.as_ref()?;
+            Some((Index { index, generation: slot.generation }, root.get(region)))
+        })
+    }
+    /**
+     * Return the number of keys which are present. This
+     * may be smaller than the number of objects in the region.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /**
+     * Access the underlying region, such as to follow
+     * the edges of objects.
+     */
+    #[inline]
+    pub fn region(&self) -> &Region<T> {
+        &self.region
+    }
+    #[inline]
+    pub fn region_mut(&mut self) -> &mut Region<T> {
+        &mut self.region
+    }
+}
+impl <T> Default for SlotRegion<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <T: 'static + HasIx<T>> SlotRegion<T> {
+    /**
+     * Insert an object, returning its key.
+     *
+     * As with [`Region::alloc`](../struct.Region.html#method.alloc),
+     * this may trigger a collection, invalidating raw indices
+     * but not keys.
+     */
+    pub fn insert(&mut self, t: T) -> Index {
+        let root = self.region.alloc(|_| t).root();
+        self.len += 1;
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.root = Some(root);
+                Index { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, root: Some(root) });
+                Index { index: self.slots.len() - 1, generation: 0 }
+            },
+        }
+    }
+    /**
+     * Collect objects which have been removed and are unreachable,
+     * compacting the remaining objects.
+     */
+    #[inline]
+    pub fn compact(&mut self) {
+        self.region.gc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlotRegion;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn removed_objects_survive_while_reachable() {
+        let mut arena = SlotRegion::new();
+        let a = arena.insert(Elem::new());
+        let b = arena.insert(Elem::new());
+        let b_ix = arena.ix(b);
+        arena.get_mut(a).unwrap().ix = b_ix;
+
+        assert!(arena.remove(b));
+        assert!(!arena.remove(b));
+        let c = arena.insert(Elem::new());
+        assert_ne!(b, c);
+        assert!(arena.get(b).is_none());
+        assert_eq!(arena.len(), 2);
+
+        arena.compact();
+        assert_eq!(arena.region().len(), 3);
+        assert!(arena.remove(a));
+        arena.compact();
+        assert_eq!(arena.region().len(), 1);
+        assert!(arena.get(c).is_some());
+    }
+}
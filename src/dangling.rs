@@ -0,0 +1,175 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{Ix, Region, Root};
+
+/**
+ * How a collection handles a dangling edge, that is, an index
+ * exposed by [`HasIx::foreach_ix`](trait.HasIx.html#tymethod.foreach_ix)
+ * which is out of range for the region, or which refers to an object
+ * removed by [`Region::take`](struct.Region.html#method.take), as set by
+ * [`Region::set_dangling_edge_policy`](struct.Region.html#method.set_dangling_edge_policy).
+ *
+ * With the feature "debug-arena", indices of other
+ * regions are also dangling.
+ *
+ * Every dangling edge is reported by
+ * [`Region::dangling_edges`](struct.Region.html#method.dangling_edges)
+ * unless the collection panics.
+ */
+#[derive(Debug)]
+pub enum DanglingEdgePolicy<T> {
+    /**
+     * Panic, leaving the region in a consistent state. This is the default.
+     */
+    Panic,
+    /**
+     * Leave the edge unchanged, which is an invalid index.
+     * An edge to a removed object is left invalid for later collections.
+     */
+    Skip,
+    /**
+     * Point the edge to the object of this root, such as a null object.
+     */
+    Rewrite(Root<T>),
+}
+/**
+ * A dangling edge found by a collection.
+ */
+#[derive(Debug)]
+pub struct DanglingEdge<T> {
+    /**
+     * The object holding the edge, after the collection.
+     */
+    pub object: Ix<T>,
+    /**
+     * The identifier of the dangling index.
+     */
+    pub index: usize,
+}
+
+impl <T> Clone for DanglingEdge<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl <T> Copy for DanglingEdge<T> {}
+
+pub(crate) struct Dangling<T> {
+    pub(crate) policy: DanglingEdgePolicy<T>,
+    // The positions of objects with dangling edges,
+    // and the edges, found by the last collection
+    pub(crate) found: Vec<(usize, usize)>,
+    // Positions of edges which are kept for another owner, such as the
+    // imports of a nursery, and are never dangling
+    pub(crate) external: Range<usize>,
+}
+impl <T> Dangling<T> {
+    pub(crate) fn new() -> Self {
+        Dangling { policy: DanglingEdgePolicy::Panic, found: Vec::new(), external: 0..0 }
+    }
+    // Handle a dangling edge of the object at position object
+    pub(crate) fn handle(&mut self, object: usize, edge: &mut Ix<T>) {
+        let index = edge.ix();
+        if self.external.contains(&index) { return }
+        match self.policy {
+            DanglingEdgePolicy::Panic =>
+                panic!("Invalid index {} found from HasIx<T> at {} during GC.", index, object),
+            DanglingEdgePolicy::Skip => (),
+            // The root has already been forwarded
+            DanglingEdgePolicy::Rewrite(ref root) => *edge = root.get_ix(),
+        }
+        self.found.push((object, index));
+    }
+    // Handle an edge to an object which was removed in place. A skipped edge
+    // points nowhere, rather than to a position which another object may take
+    pub(crate) fn handle_removed(&mut self, object: usize, edge: &mut Ix<T>, dead: Ix<T>) {
+        self.handle(object, edge);
+        if let DanglingEdgePolicy::Skip = self.policy {
+            *edge = dead;
+        }
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Choose how collections handle dangling edges,
+     * such as so that a single bad edge in a large heap can be
+     * survived and diagnosed, rather than panicking.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     *
+     * struct Node(Option<gc::Ix<Node>>);
+     * impl gc::HasIx<Node> for Node {
+     *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+     *         F: FnMut(&'b mut gc::Ix<Node>)
+     *     {
+     *         self.0.foreach_ix(f)
+     *     }
+     * }
+     *
+     * let mut other = gc::Region::new();
+     * other.ensure(5);
+     * let far = (0..5).map(|_| other.alloc(|_| Node(None)).ix()).last().unwrap();
+     *
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * let null = r.alloc(|_| Node(None)).root();
+     * r.set_dangling_edge_policy(gc::DanglingEdgePolicy::Rewrite(null.clone()));
+     * let bad = r.alloc(|_| Node(Some(far))).root();
+     * r.gc();
+     *
+     * let edges = r.dangling_edges();
+     * assert_eq!(edges.len(), 1);
+     * assert_eq!(edges[0].object.identifier(), bad.ix().identifier());
+     * assert_eq!(bad.get(&r).0.unwrap().identifier(), null.ix().identifier());
+     * ```
+     */
+    pub fn set_dangling_edge_policy(&mut self, policy: DanglingEdgePolicy<T>) {
+        self.dangling.policy = policy;
+    }
+    /**
+     * Get the dangling edges found by the most recent collection.
+     * As with other raw indices, the objects are only
+     * valid until the region is next collected.
+     */
+    pub fn dangling_edges(&self) -> Vec<DanglingEdge<T>> {
+        self.dangling.found.iter()
+            .map(|&(object, index)| DanglingEdge { object: self.ix_at(object), index })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DanglingEdgePolicy, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn dangling_edges_are_skipped() {
+        let mut other = Region::new();
+        other.ensure(4);
+        let far = (0..4).map(|_| other.alloc(|_| {Elem::new()}).ix()).last().unwrap();
+
+        let mut r = Region::new();
+        r.ensure(2);
+        r.set_dangling_edge_policy(DanglingEdgePolicy::Skip);
+        let a = r.alloc(|_| {Elem { ix: Some(far) }}).root();
+        r.alloc(|_| {Elem::new()});
+        r.gc();
+        assert_eq!(r.len(), 1);
+        let edges = r.dangling_edges();
+        assert_eq!((edges[0].object.identifier(), edges[0].index), (0, 3));
+        assert_eq!(a.get(&r).ix.unwrap().identifier(), 3);
+        a.get_mut(&mut r).ix = None;
+        r.gc();
+        assert!(r.dangling_edges().is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::fmt::{Debug, Formatter};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::{HasIx, Ix};
+
+/**
+ * A field of a [`DynObject`](struct.DynObject.html).
+ */
+#[derive(Debug, Clone)]
+pub enum DynField<V> {
+    Ix(Ix<DynObject<V>>),
+    Value(V),
+    Empty,
+}
+
+/**
+ * A function which exposes the edges among the fields of a
+ * [`DynObject`](struct.DynObject.html).
+ */
+pub type TraceFn<V> = for<'b> fn(&'b mut [DynField<V>], &mut dyn FnMut(&'b mut Ix<DynObject<V>>));
+
+/**
+ * A description of which fields of a [`DynObject`](struct.DynObject.html)
+ * hold edges, for object layouts which are only known at runtime,
+ * such as classes defined by a script. Typically, one descriptor
+ * is shared by every object of a class.
+ */
+pub enum TraceDescriptor<V> {
+    /**
+     * The edges are the fields at these positions. Positions which are
+     * past the end of an object, or hold no index, are skipped,
+     * and each field is exposed once, even if listed more than once.
+     */
+    Offsets(Vec<usize>),
+    /**
+     * The edges are exposed by this function, as with
+     * [`HasIx::foreach_ix`](trait.HasIx.html#tymethod.foreach_ix).
+     */
+    Trace(TraceFn<V>),
+}
+impl <V> Debug for TraceDescriptor<V> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            TraceDescriptor::Offsets(offsets) => f.debug_tuple("Offsets").field(offsets).finish(),
+            TraceDescriptor::Trace(_) => f.debug_tuple("Trace").finish(),
+        }
+    }
+}
+
+/**
+ * An object whose edges are described at runtime by a
+ * [`TraceDescriptor`](enum.TraceDescriptor.html), rather than
+ * by a static implementation of [`HasIx`](trait.HasIx.html).
+ *
+ * As with `HasIx`, indices in fields which the descriptor
+ * does not expose are invalidated by collections.
+ *
+ * ```rust
+ * use std::rc::Rc;
+ * use moving_gc_arena as gc;
+ * use gc::{DynField, DynObject, TraceDescriptor};
+ *
+ * let pair = Rc::new(TraceDescriptor::Offsets(vec![0, 1]));
+ * let mut r = gc::Region::new();
+ * r.ensure(2);
+ * let leaf = r.alloc(|_| DynObject::new(pair.clone(), vec![])).ix();
+ * let fields = vec![DynField::Ix(leaf), DynField::Value(3)];
+ * let root = r.alloc(|_| DynObject::new(pair.clone(), fields)).root();
+ * r.gc();
+ * assert_eq!(r.len(), 2);
+ * ```
+ */
+pub struct DynObject<V> {
+    descriptor: Rc<TraceDescriptor<V>>,
+    pub fields: Vec<DynField<V>>,
+}
+impl <V> DynObject<V> {
+    pub fn new(descriptor: Rc<TraceDescriptor<V>>, fields: Vec<DynField<V>>) -> Self {
+        DynObject { descriptor, fields }
+    }
+    #[inline]
+    pub fn descriptor(&self) -> &Rc<TraceDescriptor<V>> {
+        &self.descriptor
+    }
+}
+impl <V: Debug> Debug for DynObject<V> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("DynObject")
+            .field("descriptor", &self.descriptor)
+            .field("fields", &self.fields)
+            .finish()
+    }
+}
+impl <V: 'static> HasIx<DynObject<V>> for DynObject<V> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<DynObject<V>>)
+    {
+        match *self.descriptor {
+            TraceDescriptor::Offsets(ref offsets) => {
+                for (i, field) in self.fields.iter_mut().enumerate() {
+                    if let DynField::Ix(ix) = field {
+                        if offsets.contains(&i) { f(ix) }
+                    }
+                }
+            },
+            TraceDescriptor::Trace(trace) => trace(&mut self.fields, &mut f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use crate::{DynField, DynObject, Ix, Region, TraceDescriptor};
+
+    fn trace_last<'b>(fields: &'b mut [DynField<u32>], f: &mut dyn FnMut(&'b mut Ix<DynObject<u32>>)) {
+        if let Some(DynField::Ix(ix)) = fields.last_mut() {
+            f(ix)
+        }
+    }
+
+    #[test]
+    pub fn descriptors_trace_objects() {
+        let first = Rc::new(TraceDescriptor::Offsets(vec![0, 0, 5]));
+        let last = Rc::new(TraceDescriptor::Trace(trace_last));
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {DynObject::new(last.clone(), vec![])}).ix();
+        let b = r.alloc(|_| {DynObject::new(last.clone(), vec![])}).ix();
+        let c = r.alloc(|_| {DynObject::new(last.clone(), vec![DynField::Ix(b), DynField::Ix(a)])}).ix();
+        let root = r.alloc(|_| {DynObject::new(first.clone(), vec![DynField::Ix(c), DynField::Value(1)])}).root();
+
+        r.gc();
+        assert_eq!(r.len(), 3);
+        let c = match root.get(&r).fields[0] {
+            DynField::Ix(c) => c,
+            _ => panic!(),
+        };
+        assert!(matches!(c.get(&r).fields[1], DynField::Ix(a) if a.try_get(&r).is_ok()));
+    }
+}
@@ -0,0 +1,108 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::Region;
+use crate::space::Space;
+
+// A thread which drops the values it is sent
+pub(crate) struct DropThread<T> {
+    sender: Sender<Vec<T>>,
+    handle: JoinHandle<()>,
+}
+
+impl <T: Send + 'static> Region<T> {
+    /**
+     * Choose whether the values of collected objects are dropped on
+     * a background thread, rather than during the collection,
+     * such as when objects own large buffers whose destructors
+     * would dominate the pause. Each region has its own thread.
+     *
+     * Values are still moved out of the heap during the collection,
+     * and other ways of removing objects, such as
+     * [`truncate_to`](#method.truncate_to), drop them immediately.
+     * Disabling background dropping waits for every
+     * value already sent to the thread to be dropped.
+     *
+     * This fails if the thread cannot be spawned.
+     *
+     * This requires the feature "std", which is enabled by default.
+     */
+    pub fn set_background_drop(&mut self, enabled: bool) -> io::Result<()> {
+        if enabled && self.drop_thread.is_none() {
+            let (sender, receiver) = mpsc::channel::<Vec<T>>();
+            let handle = thread::Builder::new()
+                .name("moving_gc_arena drop".to_string())
+                .spawn(move || receiver.into_iter().for_each(drop))?;
+            self.drop_thread = Some(DropThread { sender, handle });
+        } else if !enabled {
+            if let Some(DropThread { sender, handle }) = self.drop_thread.take() {
+                drop(sender);
+                // A panic in a destructor ends the thread, and is not propagated
+                let _ = handle.join();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl <T> Region<T> {
+    // Drop the old space of a collection, whose objects
+    // have all been either moved or collected
+    pub(crate) fn drop_space(&mut self, src: Space<T>) {
+        let thread = match self.drop_thread {
+            Some(ref thread) => thread,
+            None => return,
+        };
+        // Weak pointers are only dropped on this thread
+        let dead: Vec<T> = src.into_vec().into_iter().filter_map(|s| s.into_t()).collect();
+        if dead.is_empty() { return }
+        if let Err(mpsc::SendError(dead)) = thread.sender.send(dead) {
+            // The thread has panicked
+            self.drop_thread = None;
+            drop(dead);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+    use crate::{HasIx, Ix, Region};
+
+    struct Noisy(Arc<Mutex<Vec<ThreadId>>>);
+    impl Drop for Noisy {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push(thread::current().id());
+        }
+    }
+    impl HasIx<Noisy> for Noisy {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+            F: FnMut(&'b mut Ix<Noisy>)
+        { }
+    }
+
+    #[test]
+    pub fn values_drop_in_background() {
+        let drops = Arc::new(Mutex::new(Vec::new()));
+        let mut r = Region::new();
+        r.ensure(3);
+        r.set_background_drop(true).unwrap();
+        let a = r.alloc(|_| {Noisy(drops.clone())}).root();
+        r.alloc(|_| {Noisy(drops.clone())});
+        r.alloc(|_| {Noisy(drops.clone())});
+        r.gc();
+        r.set_background_drop(false).unwrap();
+        let dropped = drops.lock().unwrap().clone();
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped.iter().all(|&id| id != thread::current().id()));
+        assert!(a.try_get(&r).is_ok());
+    }
+}
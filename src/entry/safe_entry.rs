@@ -1,7 +1,7 @@
-use std::fmt::{Debug, Formatter};
-use std::rc::Rc;
-use std::rc;
-use std::cell::Cell;
+use core::fmt::{Debug, Formatter};
+use alloc::rc::Rc;
+use alloc::rc;
+use core::cell::Cell;
 
 use crate::types::{Ix, IxCell, SpotVariant, Weak};
 
@@ -74,7 +74,7 @@ impl <T> Spot<T> {
         Spot::Present(Entry::new(t))
     }
 
-    pub(crate) fn variant(&mut self) -> SpotVariant<Entry<T>, T> {
+    pub(crate) fn variant(&mut self) -> SpotVariant<'_, Entry<T>, T> {
         match self {
             Spot::Present(e) => SpotVariant::Present(e),
             Spot::BrokenHeart(i) => SpotVariant::BrokenHeart(*i)
@@ -95,6 +95,14 @@ impl <T> Spot<T> {
         }
     }
 
+    // The index this spot was moved to, if any
+    pub(crate) fn forwarded(&self) -> Option<Ix<T>> {
+        match self {
+            Spot::BrokenHeart(i) => Some(*i),
+            _ => None,
+        }
+    }
+
     #[allow(unused)]
     pub(crate) fn into_t(self) -> Option<T> {
         match self {
@@ -102,6 +110,19 @@ impl <T> Spot<T> {
             Spot::BrokenHeart(_) => None,
         }
     }
+    // Take the value and the cell of its weak pointers out of this spot
+    pub(crate) fn into_parts(self) -> Option<(T, Option<Rc<IxCell<T>>>)> {
+        match self {
+            Spot::Present(e) => Some((e.t, e.rc)),
+            Spot::BrokenHeart(_) => None,
+        }
+    }
+    pub(crate) fn broken_heart(ix: Ix<T>) -> Self {
+        Spot::BrokenHeart(ix)
+    }
+    pub(crate) fn from_parts(t: T, rc: Option<Rc<IxCell<T>>>) -> Self {
+        Spot::Present(Entry { t, rc })
+    }
     // Change this into a broken heart to other,
     // updating the external reference
     #[allow(unused)]
@@ -109,7 +130,7 @@ impl <T> Spot<T> {
         if let Spot::Present(ref mut e) = self {
             e.move_to(other);
         }
-        std::mem::replace(self, Spot::BrokenHeart(other))
+        core::mem::replace(self, Spot::BrokenHeart(other))
     }
 }
 impl <T> Weak<T> {
@@ -120,8 +141,9 @@ impl <T> Weak<T> {
      * that is owned by an element of the Region
      */
     #[inline(always)]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
     pub fn ix(&self) -> Option<Ix<T>> {
-        Some(self.cell.upgrade()?.get())
+        Some(self.cell.upgrade()?.get().tracked())
     }
 }
 impl <T> Clone for Weak<T> {
@@ -130,7 +152,7 @@ impl <T> Clone for Weak<T> {
     }
 }
 impl <T> Debug for Weak<T> {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         self.cell.upgrade().fmt(f)
     }
 }
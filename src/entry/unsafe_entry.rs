@@ -1,10 +1,10 @@
 
-use std::fmt::{Debug, Formatter};
-use std::rc::Rc;
-use std::rc;
-use std::cell::Cell;
-use std::mem::{forget, MaybeUninit, ManuallyDrop};
-use std::hint::unreachable_unchecked;
+use core::fmt::{Debug, Formatter};
+use alloc::rc::Rc;
+use alloc::rc;
+use core::cell::Cell;
+use core::mem::{forget, MaybeUninit, ManuallyDrop};
+use core::hint::unreachable_unchecked;
 
 use crate::types::{Ix, IxCell, SpotVariant, Weak};
 
@@ -72,7 +72,7 @@ impl PresentData {
  */
 #[repr(C)]
 #[derive(Clone, Copy)]
-struct BrokenHeart(usize, #[cfg(feature="debug-arena")] u64, #[cfg(feature="debug-arena")] u64,);
+struct BrokenHeart(usize, #[cfg(feature="debug-arena")] crate::nonce::Word, #[cfg(feature="debug-arena")] crate::nonce::Word,);
 impl BrokenHeart {
     #[inline]
     unsafe fn into_unchecked<T>(self) -> Ix<T> {
@@ -153,7 +153,7 @@ impl Header {
                 _ => panic!("Invalid header state")
             }});
         let cell = Rc::downgrade(&rc);
-        assert!(Rc::weak_count(&rc) == 1);
+        debug_assert!(Rc::weak_count(&rc) >= 1);
         Weak { cell }
     }
 
@@ -227,7 +227,7 @@ impl <T> Drop for Spot<T> {
         unsafe {
             if let TaggedHeader::Present(ptr) = self.header.get_tag::<T>() {
                 // drop contents
-                std::ptr::drop_in_place(self.value.as_mut_ptr());
+                core::ptr::drop_in_place(self.value.as_mut_ptr());
                 // drop rc
                 if let Some(ptr) = ptr {
                     Rc::from_raw(ptr);
@@ -268,14 +268,14 @@ impl <T> Spot<T> {
         if let Some(e) = self.get_mut() {
             e.move_to(other);
         };
-        std::mem::replace(self,
+        core::mem::replace(self,
             Spot {
                 header: Header::broken_heart(other),
                 value: MaybeUninit::uninit(),
             }
         )
     }
-    pub(crate) fn variant(&mut self) -> SpotVariant<Entry<T>, T> {
+    pub(crate) fn variant(&mut self) -> SpotVariant<'_, Entry<T>, T> {
         unsafe {
             match self.header.get_tag::<T>() {
                 TaggedHeader::Present(rc) => {
@@ -286,6 +286,53 @@ impl <T> Spot<T> {
             }
         }
     }
+    // The index this spot was moved to, if any
+    pub(crate) fn forwarded(&self) -> Option<Ix<T>> {
+        unsafe {
+            match self.header.get_tag::<T>() {
+                TaggedHeader::BrokenHeart(i) => Some(i),
+                _ => None,
+            }
+        }
+    }
+    // Take the value out of this spot, dropping its rc, if any
+    #[allow(unused)]
+    pub(crate) fn into_t(self) -> Option<T> {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe {
+            match this.header.get_tag::<T>() {
+                TaggedHeader::Present(ptr) => {
+                    if let Some(ptr) = ptr {
+                        drop(Rc::from_raw(ptr));
+                    }
+                    Some(this.value.as_ptr().read())
+                },
+                TaggedHeader::BrokenHeart(_) => None,
+            }
+        }
+    }
+}
+
+impl <T> Spot<T> {
+    // Take the value and the cell of its weak pointers out of this spot
+    pub(crate) fn into_parts(self) -> Option<(T, Option<Rc<IxCell<T>>>)> {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe {
+            match this.header.get_tag::<T>() {
+                TaggedHeader::Present(ptr) =>
+                    Some((this.value.as_ptr().read(), ptr.map(|ptr| Rc::from_raw(ptr)))),
+                TaggedHeader::BrokenHeart(_) => None,
+            }
+        }
+    }
+    pub(crate) fn broken_heart(ix: Ix<T>) -> Self {
+        Spot { header: Header::broken_heart(ix), value: MaybeUninit::uninit() }
+    }
+    pub(crate) fn from_parts(t: T, rc: Option<Rc<IxCell<T>>>) -> Self {
+        let mut spot = Spot::new(t);
+        spot.header.use_tag(|_: TaggedHeader<T>| (TaggedHeader::Present(rc.map(Rc::into_raw)), ()));
+        spot
+    }
 }
 
 // NOTE for safety: Header *must*
@@ -383,8 +430,9 @@ impl <T> Weak<T> {
      * that is owned by an element of the Region
      */
     #[inline(always)]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
     pub fn ix(&self) -> Option<Ix<T>> {
-        Some(self.cell.upgrade()?.get())
+        Some(self.cell.upgrade()?.get().tracked())
     }
 }
 impl <T> Clone for Weak<T> {
@@ -393,7 +441,7 @@ impl <T> Clone for Weak<T> {
     }
 }
 impl <T> Debug for Weak<T> {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         self.cell.upgrade().fmt(f)
     }
 }
@@ -0,0 +1,261 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Ephemeron tables, whose values are kept alive only while their keys are.
+//!
+//! The values of each table are a root set, so that every collection
+//! forwards them. Before a full collection, the region is marked from its
+//! other roots, and then from the values of entries whose keys are marked,
+//! until no more keys are marked. Entries whose keys were not marked are
+//! removed, so that the collection keeps only the values of live keys.
+//! Other collections treat every value as a root.
+
+use core::cell::RefCell;
+use alloc::collections::BTreeMap;
+use core::fmt::{Debug, Formatter};
+use alloc::rc::{self, Rc};
+use alloc::{vec, vec::Vec};
+
+use crate::{HasIx, Ix, Region, Weak};
+use crate::root_set::RootSetCell;
+use crate::types::IxCell;
+
+pub(crate) struct Entries<T> {
+    // The key of each value, by position in the values
+    keys: Vec<rc::Weak<IxCell<T>>>,
+    positions: BTreeMap<*const IxCell<T>, usize>,
+}
+// The entries and values of a table, as held by its region
+pub(crate) type Registration<T> = (rc::Weak<RefCell<Entries<T>>>, rc::Weak<RootSetCell<T>>);
+
+impl <T> Entries<T> {
+    fn swap_remove(&mut self, values: &mut Vec<Ix<T>>, i: usize) -> Ix<T> {
+        let key = self.keys.swap_remove(i);
+        self.positions.remove(&key.as_ptr());
+        if let Some(moved) = self.keys.get(i) {
+            self.positions.insert(moved.as_ptr(), i);
+        }
+        values.swap_remove(i)
+    }
+}
+
+/**
+ * A table of entries from keys to values, in which each value
+ * is kept alive only while its key is alive, such as for a weak cache
+ * or the property table of a language runtime.
+ *
+ * Unlike a value which points to its key from a root, a value
+ * does not keep its own key alive, even through the values of other
+ * entries. Keys are [`Weak`](struct.Weak.html) pointers, so entries remain
+ * valid across collections, and values are updated by each collection.
+ *
+ * Entries whose keys are collected are removed by the next full
+ * collection, such as [`gc`](struct.Region.html#method.gc) or
+ * [`compact`](struct.Region.html#method.compact). Other collections,
+ * such as [`minor_gc`](struct.Region.html#method.minor_gc), treat
+ * every value as a root.
+ *
+ * Values must be valid for the region the table was created from.
+ * If an invalid index is inserted, the behavior of the table is
+ * unspecified, as with other invalid indices.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * r.ensure(4);
+ * let mut cache = r.ephemeron_table();
+ *
+ * let key = r.alloc(|_|{()}).root();
+ * let weak = key.downgrade(&mut r);
+ * cache.insert(&weak, r.alloc(|_|{()}).ix());
+ * r.gc();
+ * assert_eq!(r.len(), 2);
+ * assert!(cache.get(&weak).unwrap().try_get(&r).is_ok());
+ *
+ * drop(key);
+ * r.gc();
+ * assert!(r.is_empty() && cache.is_empty());
+ * ```
+ */
+pub struct EphemeronTable<T> {
+    entries: Rc<RefCell<Entries<T>>>,
+    values: Rc<RootSetCell<T>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new, empty, ephemeron table which is registered with this region.
+     */
+    pub fn ephemeron_table(&mut self) -> EphemeronTable<T> {
+        let entries = Rc::new(RefCell::new(Entries { keys: Vec::new(), positions: BTreeMap::new() }));
+        let values = Rc::new(RefCell::new(Vec::new()));
+        self.root_sets.push(Rc::downgrade(&values));
+        self.ephemerons.push((Rc::downgrade(&entries), Rc::downgrade(&values)));
+        EphemeronTable { entries, values }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    // Remove the entries of ephemeron tables whose keys are not reachable,
+    // other than through the values of such entries, before a full collection
+    pub(crate) fn sweep_ephemerons(&mut self) {
+        self.ephemerons.retain(|(entries, _)| entries.strong_count() > 0);
+        if self.ephemerons.is_empty() { return }
+        let tables: Vec<_> = self.ephemerons.iter()
+            .filter_map(|(entries, values)| Some((entries.upgrade()?, values.upgrade()?)))
+            .collect();
+
+        let mut pending = Vec::new();
+        self.roots.for_each(|ix| pending.push(ix.ix()));
+        for set in self.root_sets.iter().filter_map(rc::Weak::upgrade) {
+            if !tables.iter().any(|(_, values)| Rc::ptr_eq(values, &set)) {
+                pending.extend(set.borrow().iter().map(|ix| ix.ix()));
+            }
+        }
+        let len = self.data.len();
+        let mut marked = vec![false; len];
+        let key_marked = |marked: &[bool], key: &rc::Weak<IxCell<T>>| {
+            key.upgrade().is_some_and(|cell| marked.get(cell.get().ix()) == Some(&true))
+        };
+        // Values are only marked once their keys are
+        while !pending.is_empty() {
+            self.mark_into(core::mem::take(&mut pending), &mut marked);
+            for (entries, values) in &tables {
+                let entries = entries.borrow();
+                for (key, value) in entries.keys.iter().zip(values.borrow().iter()) {
+                    if value.ix() < len && !marked[value.ix()] && key_marked(&marked, key) {
+                        pending.push(value.ix());
+                    }
+                }
+            }
+        }
+
+        for (entries, values) in &tables {
+            let mut entries = entries.borrow_mut();
+            let mut values = values.borrow_mut();
+            let mut i = 0;
+            while i < entries.keys.len() {
+                if key_marked(&marked, &entries.keys[i]) {
+                    i += 1;
+                } else {
+                    entries.swap_remove(&mut values, i);
+                }
+            }
+        }
+    }
+}
+
+impl <T> EphemeronTable<T> {
+    /**
+     * Insert a value for the given key, returning
+     * the previous value, if any.
+     * If the key's object has been collected,
+     * the value is not inserted, and is returned.
+     */
+    pub fn insert(&mut self, key: &Weak<T>, value: Ix<T>) -> Option<Ix<T>> {
+        if key.cell.strong_count() == 0 {
+            return Some(value)
+        }
+        let mut entries = self.entries.borrow_mut();
+        let mut values = self.values.borrow_mut();
+        match entries.positions.get(&key.cell.as_ptr()) {
+            Some(&i) => Some(core::mem::replace(&mut values[i], value)),
+            None => {
+                let i = entries.keys.len();
+                entries.positions.insert(key.cell.as_ptr(), i);
+                entries.keys.push(key.cell.clone());
+                values.push(value);
+                None
+            },
+        }
+    }
+    #[inline]
+    pub fn get(&self, key: &Weak<T>) -> Option<Ix<T>> {
+        if key.cell.strong_count() == 0 { return None }
+        let i = *self.entries.borrow().positions.get(&key.cell.as_ptr())?;
+        Some(self.values.borrow()[i])
+    }
+    #[inline]
+    pub fn contains_key(&self, key: &Weak<T>) -> bool {
+        self.get(key).is_some()
+    }
+    /**
+     * Remove the value for the given key, if any.
+     */
+    pub fn remove(&mut self, key: &Weak<T>) -> Option<Ix<T>> {
+        if key.cell.strong_count() == 0 { return None }
+        let mut entries = self.entries.borrow_mut();
+        let i = *entries.positions.get(&key.cell.as_ptr())?;
+        Some(entries.swap_remove(&mut self.values.borrow_mut(), i))
+    }
+    /**
+     * Return the number of entries, including any
+     * whose keys have been collected but not yet removed.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.borrow().keys.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn clear(&mut self) {
+        let mut entries = self.entries.borrow_mut();
+        entries.keys.clear();
+        entries.positions.clear();
+        self.values.borrow_mut().clear();
+    }
+}
+impl <T> Debug for EphemeronTable<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("EphemeronTable").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn ephemeron_values_live_with_their_keys() {
+        let mut r = Region::new();
+        r.ensure(8);
+        let mut table = r.ephemeron_table();
+        let k1 = r.alloc(|_| {Elem::new()}).root();
+        let w1 = k1.downgrade(&mut r);
+        let k2 = r.alloc(|_| {Elem::new()}).weak();
+        // The value of k1 keeps k2 alive, and so its value
+        let v1 = r.alloc(|_| {Elem { ix: k2.ix() }}).ix();
+        let v2 = r.alloc(|_| {Elem::new()}).ix();
+        table.insert(&w1, v1);
+        table.insert(&k2, v2);
+        // A value which points to its own key does not keep it alive
+        let k3 = r.alloc(|_| {Elem::new()}).weak();
+        let v3 = r.alloc(|_| {Elem { ix: k3.ix() }}).ix();
+        table.insert(&k3, v3);
+        assert_eq!(table.len(), 3);
+
+        r.gc();
+        assert_eq!(r.len(), 4);
+        assert_eq!(table.len(), 2);
+        assert!(k3.try_get(&r).is_err());
+        let v1 = table.get(&w1).unwrap();
+        assert_eq!(v1.get(&r).ix.unwrap().identifier(), k2.ix().unwrap().identifier());
+        assert!(table.get(&k2).unwrap().try_get(&r).is_ok());
+
+        assert_eq!(table.insert(&w1, v1), Some(v1));
+
+        // Other collections keep every value
+        drop(k1);
+        assert_eq!(r.minor_gc(), Ok(1));
+        assert_eq!(table.len(), 2);
+        assert!(table.get(&k2).is_some());
+        r.compact().unwrap();
+        assert!(r.is_empty() && table.is_empty());
+    }
+}
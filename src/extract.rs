@@ -0,0 +1,132 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc::Rc;
+use alloc::{vec, vec::Vec};
+
+use crate::{Error, HasIx, Region, Root};
+use crate::entry::Spot;
+use crate::ix_range::forward_groups;
+use crate::root_slab::Roots;
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Move every object reachable from a root out of this region,
+     * into a new region, such as to hand a subtree to another part of
+     * a program. Returns the new region, along with a root of it for
+     * the object of the given root, which is its first object.
+     *
+     * The moved objects are removed from this region as with
+     * [`take`](#method.take), so no other object moves, and every other
+     * index remains valid. Roots and indices which refer to moved objects,
+     * including the given root, become invalid, while weak pointers
+     * to them follow them into the new region. Edges to them from objects
+     * which remain are dangling, as with `take`.
+     *
+     * This panics if the root is not from this region, or if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Node(Option<gc::Ix<Node>>);
+     * gc::impl_has_ix!(Node { 0 });
+     *
+     * let mut r = gc::Region::new();
+     * r.ensure(3);
+     * let kept = r.alloc(|_| Node(None)).root();
+     * let leaf = r.alloc(|_| Node(None)).weak();
+     * let tree = r.alloc(|_| Node(leaf.ix())).root();
+     *
+     * let (subtree, root) = r.extract(&tree);
+     * assert_eq!((r.count_reachable(), subtree.len()), (1, 2));
+     * assert!(kept.try_get(&r).is_ok() && tree.try_get(&r).is_err());
+     * assert!(root.get(&subtree).0.unwrap().try_get(&subtree).is_ok());
+     * assert!(leaf.try_get(&subtree).is_ok());
+     * ```
+     */
+    pub fn extract(&mut self, root: &Root<T>) -> (Region<T>, Root<T>) {
+        #[cfg(feature = "debug-arena")]
+        self.restamp_kept();
+        let ix = root.get_ix();
+        if let Err(e) = ix.try_get(self) {
+            crate::access_failed("Region::extract", Some(ix), e);
+        }
+        if self.pins.is_pinned() {
+            panic!("Region::extract: {}", Error::Pinned);
+        }
+        let mut other = Region::new();
+        other.data.reserve(self.reachable_from(root).count());
+        let set = Rc::new(RefCell::new(vec![ix]));
+        let gc_to = self.gc_to();
+        gc_to(&mut self.data, &mut other.data,
+            &Roots::new(),
+            vec![Rc::downgrade(&set)], &mut Vec::new(),
+            &self.groups,
+            &mut self.dangling,
+            #[cfg(feature = "debug-arena")]
+            (self.nonce, self.generation),
+            #[cfg(feature = "debug-arena")]
+            (other.nonce, other.generation));
+        other.groups = forward_groups(&self.data, &self.groups);
+
+        // The positions of moved objects are left empty, as they
+        // would otherwise forward to positions of the new region
+        let dead = self.ix_at(usize::MAX);
+        let moved: Vec<usize> = (0..self.data.len())
+            .filter(|&i| self.data[i].forwarded().is_some_and(|ix| !ix.is_dead()))
+            .collect();
+        for &i in &moved {
+            self.data[i] = Spot::broken_heart(dead);
+        }
+        #[cfg(feature = "history")]
+        self.history_event(crate::HistoryEvent::Free { positions: moved });
+        #[cfg(not(feature = "history"))]
+        drop(moved);
+        self.forget_freed();
+
+        #[cfg(feature = "shadow-heap")]
+        other.shadow_alloc();
+        #[cfg(feature = "history")]
+        other.history_alloc(0);
+        let root = other.roots.insert(set.borrow()[0]);
+        (other, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DanglingEdgePolicy, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn extracted_objects_move_to_a_new_region() {
+        let mut r = Region::new();
+        r.ensure(6);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let slice = r.alloc_slice(vec![Elem::new(), Elem { ix: Some(a.ix()) }]);
+        let b = r.alloc(|_| {Elem { ix: slice.get(1) }}).root();
+        let from_outside = r.alloc(|_| {Elem { ix: Some(b.ix()) }}).root();
+        let weak = a.downgrade(&mut r);
+        r.alloc(|_| {Elem::new()});
+
+        let (mut other, root) = r.extract(&b);
+        assert_eq!(other.len(), 4);
+        assert_eq!(root.ix().identifier(), 0);
+        assert!(a.try_get(&r).is_err() && b.try_get(&r).is_err());
+        assert!(from_outside.get(&r).ix.unwrap().try_get(&r).is_err());
+        assert!(weak.try_get(&other).is_ok());
+
+        other.gc();
+        r.set_dangling_edge_policy(DanglingEdgePolicy::Skip);
+        r.gc();
+        r.gc();
+        assert_eq!((other.len(), r.len()), (4, 1));
+        assert_eq!(r.dangling_edges().len(), 1);
+        let next = root.get(&other).ix.unwrap();
+        assert_eq!(next.get(&other).ix.unwrap().identifier(), weak.ix().unwrap().identifier());
+        assert!(from_outside.get(&r).ix.unwrap().try_get(&r).is_err());
+    }
+}
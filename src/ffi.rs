@@ -0,0 +1,359 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*!
+ * A C interface to regions of foreign objects.
+ *
+ * Objects are opaque `void *` pointers owned by the C host,
+ * together with a trace callback that reports the location of every
+ * edge (stored as a `uintptr_t` index) within the object, and an
+ * optional finalizer which is called when the object is collected
+ * or its region is freed.
+ *
+ * ```c
+ * typedef void (*mgc_visit_fn)(void *ctx, uintptr_t *slot);
+ * typedef void (*mgc_trace_fn)(void *data, mgc_visit_fn visit, void *ctx);
+ * typedef void (*mgc_finalize_fn)(void *data);
+ *
+ * struct mgc_region *mgc_region_new(void);
+ * void mgc_region_free(struct mgc_region *region);
+ * uintptr_t mgc_alloc(struct mgc_region *region, void *data,
+ *                     mgc_trace_fn trace, mgc_finalize_fn finalize);
+ * struct mgc_root *mgc_root(struct mgc_region *region, uintptr_t ix);
+ * uintptr_t mgc_root_ix(const struct mgc_root *root);
+ * void mgc_unroot(struct mgc_root *root);
+ * void *mgc_get(const struct mgc_region *region, uintptr_t ix);
+ * void *mgc_set(struct mgc_region *region, uintptr_t ix, void *data);
+ * int mgc_gc(struct mgc_region *region);
+ * uintptr_t mgc_len(const struct mgc_region *region);
+ * ```
+ *
+ * As with [`Ix`](../struct.Ix.html), indices are invalidated
+ * by collections, including those triggered by `mgc_alloc`, so any index which
+ * must be kept should be rooted with `mgc_root`, or be reachable from a rooted object.
+ * Functions which return an index return `MGC_INVALID` (`UINTPTR_MAX`) on failure.
+ *
+ * No entry point unwinds into C: a panic is caught and reported as a failure,
+ * `MGC_INVALID`, null, or `MGC_ERROR` (`-1`) from `mgc_gc`.
+ * The region is left consistent, as with a panic during [`Region::gc`](../struct.Region.html#method.gc).
+ *
+ * Once allocated, `data` is owned by the region. Its finalizer is called exactly once,
+ * when it is collected or its region is freed, unless it is first replaced with `mgc_set`,
+ * which returns ownership of the previous data to the caller.
+ * Trace functions are called only during collections, and must not call back into the region
+ * nor free the data. Neither callback may unwind.
+ *
+ * This module is only available with the "ffi" feature, and is
+ * left out when "debug-arena" is also enabled, as indices then
+ * carry debugging data which does not fit in a `uintptr_t`.
+ */
+
+use std::os::raw::{c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{HasIx, Ix, Region, Root};
+
+/**
+ * The index returned when an operation fails.
+ */
+pub const MGC_INVALID: usize = usize::MAX;
+/**
+ * The status returned when an operation succeeds.
+ */
+pub const MGC_OK: c_int = 0;
+/**
+ * The status returned when an operation fails.
+ */
+pub const MGC_ERROR: c_int = -1;
+
+/**
+ * Callback to report a single edge slot of an object.
+ */
+pub type VisitFn = unsafe extern "C" fn(ctx: *mut c_void, slot: *mut usize);
+/**
+ * Callback which reports every edge slot of an object by calling `visit`
+ * with `ctx`.
+ */
+pub type TraceFn = unsafe extern "C" fn(data: *mut c_void, visit: VisitFn, ctx: *mut c_void);
+/**
+ * Callback to release an object which is no longer in the region.
+ */
+pub type FinalizeFn = unsafe extern "C" fn(data: *mut c_void);
+
+/**
+ * An object managed by a foreign host.
+ */
+pub struct FfiObject {
+    data: *mut c_void,
+    trace: Option<TraceFn>,
+    finalize: Option<FinalizeFn>,
+}
+impl Drop for FfiObject {
+    fn drop(&mut self) {
+        if let Some(finalize) = self.finalize {
+            unsafe { finalize(self.data) }
+        }
+    }
+}
+
+unsafe extern "C" fn push_slot(ctx: *mut c_void, slot: *mut usize) {
+    (*(ctx as *mut Vec<*mut usize>)).push(slot)
+}
+
+impl HasIx<FfiObject> for FfiObject {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<FfiObject>)
+    {
+        let trace = match self.trace {
+            Some(trace) => trace,
+            None => return,
+        };
+        let mut slots: Vec<*mut usize> = Vec::new();
+        unsafe {
+            trace(self.data, push_slot, &mut slots as *mut Vec<*mut usize> as *mut c_void);
+            // Ix is repr(C) and consists of only a usize
+            // without debug-arena. The slots are owned by
+            // the object, so the references are valid for 'a
+            for slot in slots {
+                f(&mut *(slot as *mut Ix<FfiObject>))
+            }
+        }
+    }
+}
+
+/**
+ * The opaque region type, `struct mgc_region` in C.
+ */
+pub type FfiRegion = Region<FfiObject>;
+/**
+ * The opaque root type, `struct mgc_root` in C.
+ */
+pub type FfiRoot = Root<FfiObject>;
+
+#[inline]
+fn ix(ix: usize) -> Ix<FfiObject> {
+    Ix::new(ix)
+}
+
+/**
+ * Run the body of an entry point, returning `failed` if it panics,
+ * as unwinding into C is undefined behavior.
+ */
+#[inline]
+fn catch<R, F: FnOnce() -> R>(failed: R, f: F) -> R {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(failed)
+}
+
+/**
+ * Create a new, empty region, or null on failure.
+ * This calls no trace or finalize functions.
+ */
+#[no_mangle]
+pub extern "C" fn mgc_region_new() -> *mut FfiRegion {
+    catch(std::ptr::null_mut(), || Box::into_raw(Box::new(Region::new())))
+}
+
+/**
+ * Free a region, calling the finalizer of every object in it.
+ * Roots of the region must still be released with `mgc_unroot`.
+ * A panic while freeing is caught, and the remaining objects may be leaked.
+ *
+ * # Safety
+ * The region must have been created by `mgc_region_new`, and not yet freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_region_free(region: *mut FfiRegion) {
+    catch((), || drop(Box::from_raw(region)))
+}
+
+/**
+ * Allocate a new object in the region, returning its index, or `MGC_INVALID` on failure.
+ * This may trigger a collection, which calls the trace functions of live objects
+ * and the finalizers of unreachable objects.
+ *
+ * On success, the region owns `data` until it is finalized. On failure,
+ * `data` is still owned by the caller and `finalize` is not called.
+ *
+ * # Safety
+ * The region must be valid. The trace function, if not null, must
+ * report only slots which are owned by `data` and contain valid indices.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_alloc(region: *mut FfiRegion, data: *mut c_void,
+                                   trace: Option<TraceFn>, finalize: Option<FinalizeFn>) -> usize {
+    // The finalizer is only attached once the region owns the data
+    catch(MGC_INVALID, || match (*region).try_alloc(|_| FfiObject { data, trace, finalize: None }) {
+        Ok(mut entry) => {
+            entry.get_mut().finalize = finalize;
+            entry.ix().ix()
+        }
+        Err(_) => MGC_INVALID,
+    })
+}
+
+/**
+ * Create a root for the object at a valid index, returning null if the index is invalid.
+ * This calls no trace or finalize functions.
+ *
+ * # Safety
+ * The region must be valid.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_root(region: *mut FfiRegion, ix_: usize) -> *mut FfiRoot {
+    catch(std::ptr::null_mut(), || match (*region).root(ix(ix_)) {
+        Ok(root) => Box::into_raw(Box::new(root)),
+        Err(_) => std::ptr::null_mut(),
+    })
+}
+
+/**
+ * Get the current index of a rooted object, or `MGC_INVALID` on failure.
+ * This calls no trace or finalize functions.
+ *
+ * # Safety
+ * The root must have been created by `mgc_root` and not yet released.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_root_ix(root: *const FfiRoot) -> usize {
+    catch(MGC_INVALID, || (*root).ix().ix())
+}
+
+/**
+ * Release a root. The object is not finalized until a later collection
+ * finds it unreachable, so this calls no trace or finalize functions.
+ *
+ * # Safety
+ * The root must have been created by `mgc_root` and not yet released.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_unroot(root: *mut FfiRoot) {
+    catch((), || drop(Box::from_raw(root)))
+}
+
+/**
+ * Get the data of the object at an index, or null if the index is invalid.
+ * The data remains owned by the region, and is valid until the object is finalized.
+ * This calls no trace or finalize functions.
+ *
+ * # Safety
+ * The region must be valid.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_get(region: *const FfiRegion, ix_: usize) -> *mut c_void {
+    catch(std::ptr::null_mut(), || match ix(ix_).try_get(&*region) {
+        Ok(obj) => obj.data,
+        Err(_) => std::ptr::null_mut(),
+    })
+}
+
+/**
+ * Replace the data of the object at an index, returning the previous data
+ * without finalizing it, or null if the index is invalid.
+ * The object keeps its trace and finalize functions.
+ *
+ * On success, the region owns `data` and the caller owns the previous data.
+ * On failure, `data` is still owned by the caller.
+ * This calls no trace or finalize functions.
+ *
+ * # Safety
+ * The region must be valid.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_set(region: *mut FfiRegion, ix_: usize, data: *mut c_void) -> *mut c_void {
+    catch(std::ptr::null_mut(), || match ix(ix_).try_get_mut(&mut *region) {
+        Ok(obj) => std::mem::replace(&mut obj.data, data),
+        Err(_) => std::ptr::null_mut(),
+    })
+}
+
+/**
+ * Collect the region, calling the trace functions of live objects
+ * and the finalizers of unreachable objects.
+ * Returns `MGC_OK`, or `MGC_ERROR` if the collection failed, in which case
+ * the region is still valid but some unreachable objects may not have been finalized.
+ *
+ * # Safety
+ * The region must be valid.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_gc(region: *mut FfiRegion) -> c_int {
+    catch(MGC_ERROR, || {
+        (*region).gc();
+        MGC_OK
+    })
+}
+
+/**
+ * Get the number of objects currently in the region, or `MGC_INVALID` on failure.
+ * This calls no trace or finalize functions.
+ *
+ * # Safety
+ * The region must be valid.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn mgc_len(region: *const FfiRegion) -> usize {
+    catch(MGC_INVALID, || (*region).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_void;
+    use super::*;
+
+    // A C-like object with a single edge
+    struct Node {
+        next: usize,
+        finalized: *mut usize,
+    }
+
+    unsafe extern "C" fn trace_node(data: *mut c_void, visit: VisitFn, ctx: *mut c_void) {
+        let node = data as *mut Node;
+        if (*node).next != MGC_INVALID {
+            visit(ctx, &mut (*node).next)
+        }
+    }
+    unsafe extern "C" fn finalize_node(data: *mut c_void) {
+        let node = Box::from_raw(data as *mut Node);
+        *node.finalized += 1;
+    }
+
+    #[test]
+    pub fn ffi_round_trip() {
+        let mut finalized = 0usize;
+        unsafe {
+            let r = mgc_region_new();
+            let mut alloc = |next| {
+                let node = Box::into_raw(Box::new(Node { next, finalized: &mut finalized }));
+                mgc_alloc(r, node as *mut c_void, Some(trace_node), Some(finalize_node))
+            };
+            let a = alloc(MGC_INVALID);
+            let root = mgc_root(r, a);
+            let b = alloc(MGC_INVALID);
+            let a = mgc_root_ix(root);
+            (*(mgc_get(r, a) as *mut Node)).next = b;
+            (*(mgc_get(r, b) as *mut Node)).next = a;
+            alloc(MGC_INVALID);
+
+            assert_eq!(mgc_gc(r), MGC_OK);
+            assert_eq!(mgc_len(r), 2);
+            let a = mgc_root_ix(root);
+            let b = (*(mgc_get(r, a) as *mut Node)).next;
+            assert_eq!((*(mgc_get(r, b) as *mut Node)).next, a);
+
+            mgc_unroot(root);
+            assert_eq!(mgc_gc(r), MGC_OK);
+            assert_eq!(mgc_len(r), 0);
+            mgc_region_free(r);
+        }
+        assert_eq!(finalized, 3);
+    }
+
+    #[test]
+    pub fn ffi_panics_are_caught() {
+        assert_eq!(catch(MGC_INVALID, || -> usize { panic!("unwind") }), MGC_INVALID);
+        assert_eq!(catch(MGC_ERROR, || -> c_int { panic!("unwind") }), MGC_ERROR);
+    }
+}
@@ -0,0 +1,187 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::entry::Spot;
+use crate::ix_set::Bits;
+use crate::{Error, HasIx, Ix, MutEntry, Region};
+
+impl <T> Region<T> {
+    // The positions of objects with finalizers, created if needed
+    fn finalizers(&mut self) -> Rc<RefCell<Bits>> {
+        match self.finalizers {
+            Some(ref marked) => marked.clone(),
+            None => {
+                let marked = Rc::new(RefCell::new(Bits::default()));
+                self.remaps.push(Rc::downgrade(&marked) as _);
+                self.finalizers = Some(marked.clone());
+                marked
+            }
+        }
+    }
+    /**
+     * Queue the object at an index for finalization when it is
+     * collected, as for [`alloc_with_finalizer`](#method.alloc_with_finalizer).
+     */
+    pub fn register_finalizer(&mut self, ix: Ix<T>) -> Result<(), Error> {
+        ix.try_get(self)?;
+        self.finalizers().borrow_mut().set(ix.ix(), true);
+        Ok(())
+    }
+    /**
+     * Take every object which has been queued for finalization,
+     * in the order in which they were removed from the region.
+     */
+    pub fn drain_finalized(&mut self) -> Vec<T> {
+        core::mem::take(&mut self.finalized)
+    }
+
+    // Queue or drop the object removed from position i
+    pub(crate) fn finalize_spot(&mut self, i: usize, spot: Spot<T>) {
+        let queue = self.is_finalizable(i);
+        self.finalize_removed(queue, spot)
+    }
+    // Whether the object at position i is queued rather than dropped when removed,
+    // which must be checked before the finalizers are remapped
+    pub(crate) fn is_finalizable(&self, i: usize) -> bool {
+        self.finalizers.as_ref().is_some_and(|m| m.borrow().get(i))
+    }
+    // Queue or drop a removed object
+    pub(crate) fn finalize_removed(&mut self, queue: bool, spot: Spot<T>) {
+        match spot.into_t() {
+            Some(t) if queue => self.finalized.push(t),
+            t => drop(t),
+        }
+    }
+    // The positions of objects with finalizers, from start
+    fn finalizable(&self, start: usize) -> Vec<usize> {
+        match self.finalizers {
+            Some(ref marked) => marked.borrow().iter().filter(|&i| i >= start).collect(),
+            None => Vec::new(),
+        }
+    }
+    // Queue the objects with finalizers which remain
+    // in the old storage of a collection
+    pub(crate) fn finalize_remaining(&mut self, spots: &mut [Spot<T>]) {
+        let dead = self.ix_at(usize::MAX);
+        for i in self.finalizable(0) {
+            if spots.get(i).is_some_and(|s| s.get().is_some()) {
+                self.finalize_spot(i, spots[i].move_to(dead));
+            }
+        }
+    }
+    // Take the finalizers and queue of a region moved into this one,
+    // whose finalizers have been remapped to positions in this region
+    pub(crate) fn adopt_finalizers(&mut self, other: &mut Region<T>) {
+        self.finalized.append(&mut other.finalized);
+        let marked = match other.finalizers.take() {
+            Some(marked) => marked,
+            None => return,
+        };
+        match self.finalizers {
+            Some(ref mine) => {
+                let mut mine = mine.borrow_mut();
+                for i in marked.borrow().iter() {
+                    mine.set(i, true);
+                }
+            },
+            None => self.finalizers = Some(marked),
+        }
+    }
+    // Queue the objects with finalizers which remain in
+    // this region from start, as they are about to be removed
+    pub(crate) fn finalize_from(&mut self, start: usize) {
+        let dead = self.ix_at(usize::MAX);
+        for i in self.finalizable(start) {
+            if self.data.get(i).is_some_and(|s| s.get().is_some()) {
+                let spot = self.data[i].move_to(dead);
+                self.finalize_spot(i, spot);
+            }
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Allocate a new object, as with [`alloc`](#method.alloc), which
+     * is queued for finalization rather than dropped once it is removed
+     * from the region, such as to release a file handle or foreign pointer
+     * outside of `Drop`, with access to the region.
+     * Queued objects are taken with [`drain_finalized`](#method.drain_finalized).
+     *
+     * An object is queued when a collection finds it unreachable,
+     * or it is otherwise removed, such as by
+     * [`reset_to`](#method.reset_to). Objects removed by the same
+     * collection are queued in order of position. By then, the object
+     * is no longer in the region: its indices are invalid, its weak
+     * pointers have expired, and its edges must not be followed, as
+     * its neighbours may have been removed along with it.
+     * Objects remaining when the region is dropped are dropped
+     * without finalization, as is the queue.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct File(&'static str);
+     * gc::impl_has_ix!(File {});
+     *
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * r.alloc_with_finalizer(|_| File("log"));
+     * r.alloc(|_| File("temp"));
+     * r.gc();
+     * let closed: Vec<_> = r.drain_finalized().iter().map(|f| f.0).collect();
+     * assert_eq!(closed, vec!["log"]);
+     * assert!(r.drain_finalized().is_empty());
+     * ```
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn alloc_with_finalizer<F>(&mut self, make_t: F) -> MutEntry<'_, T> where
+        F: FnOnce(&Self) -> T
+    {
+        let finalizers = self.finalizers();
+        self.alloc_cyclic(move |ix, r| {
+            let t = make_t(r);
+            finalizers.borrow_mut().set(ix.ix(), true);
+            t
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn finalized_objects_are_queued() {
+        let mut r = Region::new();
+        r.ensure(5);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc_with_finalizer(|_| {Elem::new()}).ix();
+        let c = r.alloc(|_| {Elem { ix: Some(b) }}).ix();
+        r.register_finalizer(c).unwrap();
+        a.get_mut(&mut r).ix = Some(c);
+        r.gc();
+        assert!(r.drain_finalized().is_empty());
+
+        // c is now at 1, and b at 2
+        let d = r.alloc_with_finalizer(|_| {Elem::new()}).root();
+        a.get_mut(&mut r).ix = None;
+        r.gc();
+        let finalized = r.drain_finalized();
+        assert_eq!(finalized.len(), 2);
+        assert!(finalized[0].ix.is_some());
+        assert_eq!(r.len(), 2);
+
+        drop(d);
+        r.set_partition(0);
+        r.gc_partition(0).unwrap();
+        assert_eq!(r.drain_finalized().len(), 1);
+    }
+}
@@ -0,0 +1,130 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::remap::{Forwarding, Remap};
+use crate::{Error, GcTrigger, HasIx, Region, RootSet};
+
+// The positions of the roots of a frame, and
+// where their objects were moved, if anywhere
+struct FrameForwarding {
+    old: Vec<usize>,
+    new: Vec<Option<usize>>,
+}
+impl Remap for FrameForwarding {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        self.new = self.old.iter().map(|&i| forwarding.get(i)).collect();
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * End the current frame of a region used for per-frame data,
+     * such as in a game loop, discarding every object except
+     * those reachable from the `persistent` root set.
+     *
+     * Other roots and root sets do not keep their objects alive,
+     * but are updated as usual if their objects survive through
+     * the persistent roots. Roots whose objects are discarded
+     * become invalid, and their indices are removed from root sets.
+     * The storage of the previous frame is kept, and reused for the
+     * next one, so that a steady state does not allocate.
+     *
+     * This is an error if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let mut persistent = r.root_set();
+     *
+     * let player = r.alloc(|_|{()}).ix();
+     * persistent.insert(player);
+     * for _ in 0..3 {
+     *     let particle = r.alloc(|_|{()}).root();
+     *     r.next_frame(&persistent).unwrap();
+     *     assert!(particle.try_get(&r).is_err());
+     * }
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    pub fn next_frame(&mut self, persistent: &RootSet<T>) -> Result<(), Error> {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        // Only the persistent set is registered during the collection
+        let roots = core::mem::take(&mut self.roots);
+        let sets: Vec<_> = core::mem::take(&mut self.root_sets).iter()
+            .filter_map(|set| set.upgrade())
+            .filter(|set| !Rc::ptr_eq(set, &persistent.ixs))
+            .collect();
+        self.root_sets.push(Rc::downgrade(&persistent.ixs));
+
+        let mut old = Vec::new();
+        roots.for_each(|ix| old.push(ix.ix()));
+        old.extend(sets.iter().flat_map(|set| set.borrow().iter().map(|ix| ix.ix()).collect::<Vec<_>>()));
+        let forwarding = Rc::new(RefCell::new(FrameForwarding { old, new: Vec::new() }));
+        let remap: Rc<RefCell<dyn Remap>> = forwarding.clone();
+        self.remaps.push(Rc::downgrade(&remap));
+        let capacity = self.data.capacity();
+        self.gc_with_capacity(capacity, GcTrigger::Frame);
+
+        let new = core::mem::take(&mut forwarding.borrow_mut().new);
+        let mut new = new.into_iter();
+        let (current, dead) = (self.ix_at(0), self.ix_at(usize::MAX));
+        roots.for_each_mut(|ix| *ix = match new.next().flatten() {
+            Some(n) => current.offset(n),
+            None => dead,
+        });
+        let during = core::mem::replace(&mut self.roots, roots);
+        self.roots.append(during);
+        for set in sets {
+            set.borrow_mut().retain_mut(|ix| match new.next().flatten() {
+                Some(n) => { *ix = self.ix_at(n); true },
+                None => false,
+            });
+            self.root_sets.push(Rc::downgrade(&set));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn frames_keep_persistent_objects() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let mut persistent = r.root_set();
+        let mut frame = r.root_set();
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).ix();
+        let c = r.alloc(|_| {Elem::new()}).ix();
+        persistent.insert(b);
+        let root_a = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        frame.insert(a);
+        frame.insert(c);
+
+        r.next_frame(&persistent).unwrap();
+        assert_eq!(r.len(), 2);
+        assert!(root_a.try_get(&r).is_err());
+        assert_eq!(frame.len(), 1);
+        assert!(frame.get(0).unwrap().try_get(&r).is_ok());
+        assert!(persistent.get(0).unwrap().get(&r).ix.is_some());
+
+        let capacity = r.capacity();
+        r.alloc(|_| {Elem::new()});
+        r.next_frame(&persistent).unwrap();
+        r.next_frame(&persistent).unwrap();
+        assert_eq!(r.capacity(), capacity);
+        assert_eq!(r.len(), 2);
+    }
+}
@@ -0,0 +1,155 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::boxed::Box;
+
+use crate::{Error, Region};
+use crate::entry::Spot;
+
+/**
+ * The unit in which allocation fuel is measured.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuelUnit {
+    /**
+     * Each allocation consumes one unit of fuel.
+     */
+    Allocation,
+    /**
+     * Each allocation consumes fuel equal to the number
+     * of bytes used by its entry, including the entry header.
+     */
+    Byte,
+}
+
+pub(crate) struct Fuel {
+    remaining: usize,
+    unit: FuelUnit,
+    refill: Option<Box<dyn FnMut() -> usize>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Limit further allocations in this region to the given
+     * amount of fuel. Once fuel is exhausted, allocation fails
+     * with [`Error::FuelExhausted`](enum.Error.html#variant.FuelExhausted)
+     * (or panics, for [`alloc`](#method.alloc)), unless a refill
+     * function provides more.
+     *
+     * This is intended for rate-limiting untrusted code.
+     * Fuel is consumed before any collection that
+     * allocation might trigger.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.set_fuel(2, gc::FuelUnit::Allocation);
+     *
+     * assert!(r.try_alloc(|_|{()}).is_ok());
+     * assert!(r.try_alloc(|_|{()}).is_ok());
+     * assert_eq!(r.try_alloc(|_|{()}).err(), Some(gc::Error::FuelExhausted));
+     * ```
+     */
+    pub fn set_fuel(&mut self, fuel: usize, unit: FuelUnit) {
+        let refill = self.fuel.take().and_then(|f| f.refill);
+        self.fuel = Some(Fuel { remaining: fuel, unit, refill });
+    }
+    /**
+     * Set a function to be called when fuel is exhausted.
+     * It returns the amount of fuel to add, where 0 indicates
+     * that the allocation should fail.
+     *
+     * This has no effect unless fuel has been set.
+     */
+    pub fn set_fuel_refill<F>(&mut self, refill: F) where
+        F: 'static + FnMut() -> usize
+    {
+        if let Some(ref mut fuel) = self.fuel {
+            fuel.refill = Some(Box::new(refill));
+        }
+    }
+    /**
+     * Remove any limit on fuel.
+     */
+    pub fn remove_fuel(&mut self) {
+        self.fuel = None;
+    }
+    /**
+     * Get the remaining fuel, if fuel has been set.
+     */
+    #[inline]
+    pub fn fuel(&self) -> Option<usize> {
+        self.fuel.as_ref().map(|f| f.remaining)
+    }
+
+    // Consume fuel for a number of new entries
+    pub(crate) fn consume_fuel(&mut self, entries: usize) -> Result<(), Error> {
+        let fuel = match self.fuel {
+            Some(ref mut fuel) => fuel,
+            None => return Ok(()),
+        };
+        let cost = match fuel.unit {
+            FuelUnit::Allocation => entries,
+            FuelUnit::Byte => entries.saturating_mul(core::mem::size_of::<Spot<T>>()),
+        };
+        while fuel.remaining < cost {
+            let added = match fuel.refill {
+                Some(ref mut refill) => refill(),
+                None => 0,
+            };
+            if added == 0 {
+                Err(Error::FuelExhausted)?;
+            }
+            fuel.remaining = fuel.remaining.saturating_add(added);
+        }
+        fuel.remaining -= cost;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Region, FuelUnit};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn fuel_is_refilled() {
+        let mut r = Region::new();
+        r.set_fuel(1, FuelUnit::Allocation);
+        let mut refills = 2;
+        r.set_fuel_refill(move || {
+            if refills > 0 { refills -= 1; 2 } else { 0 }
+        });
+        for _ in 0..5 {
+            assert!(r.try_alloc(|_| {Elem::new()}).is_ok());
+        }
+        assert_eq!(r.try_alloc(|_| {Elem::new()}).err(), Some(Error::FuelExhausted));
+
+        r.remove_fuel();
+        assert!(r.try_alloc(|_| {Elem::new()}).is_ok());
+    }
+
+    #[test]
+    pub fn fuel_counts_bytes() {
+        let mut r: Region<Elem> = Region::new();
+        r.set_fuel(1, FuelUnit::Byte);
+        assert!(r.try_alloc(|_| {Elem::new()}).is_err());
+        assert_eq!(r.fuel(), Some(1));
+    }
+
+    #[test]
+    pub fn fuel_is_kept_while_pinned() {
+        let mut r: Region<Elem> = Region::new();
+        r.set_fuel(3, FuelUnit::Allocation);
+        let pin = r.pin();
+        assert_eq!(r.try_alloc(|_| {Elem::new()}).err(), Some(Error::Pinned));
+        assert_eq!(r.try_alloc_extend(vec![Elem::new(), Elem::new()]).err(), Some(Error::Pinned));
+        assert_eq!(r.fuel(), Some(3));
+        drop(pin);
+        assert!(r.try_alloc(|_| {Elem::new()}).is_ok());
+        assert_eq!(r.fuel(), Some(2));
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::collections::VecDeque;
+use core::time::Duration;
+use alloc::boxed::Box;
+
+use crate::Region;
+use crate::entry::Spot;
+
+// The number of events which are kept
+const LOG_SIZE: usize = 32;
+
+/**
+ * The reason a collection was performed.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcTrigger {
+    /**
+     * An explicit call to [`Region::gc`](struct.Region.html#method.gc)
+     * or [`Region::try_gc`](struct.Region.html#method.try_gc).
+     */
+    Explicit,
+    /**
+     * An allocation, or a call to [`Region::ensure`](struct.Region.html#method.ensure),
+     * which required more capacity.
+     */
+    Allocation,
+    /**
+     * A call to [`Region::poll_gc`](struct.Region.html#method.poll_gc).
+     */
+    Poll,
+    /**
+     * A call to [`Region::gc_idle`](struct.Region.html#method.gc_idle).
+     */
+    Idle,
+    /**
+     * A call to [`Region::next_frame`](struct.Region.html#method.next_frame).
+     */
+    Frame,
+    /**
+     * A call to [`Region::minor_gc`](struct.Region.html#method.minor_gc), or an
+     * allocation in generational mode, which only collected the nursery.
+     */
+    Minor,
+    /**
+     * A call to [`Region::compact`](struct.Region.html#method.compact),
+     * which collected the region in place.
+     */
+    Compact,
+}
+
+/**
+ * A record of a single collection.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcEvent {
+    pub trigger: GcTrigger,
+    /**
+     * The number of objects before the collection.
+     */
+    pub before: usize,
+    /**
+     * The number of objects which survived the collection.
+     */
+    pub after: usize,
+    /**
+     * The capacity of the region after the collection.
+     */
+    pub capacity: usize,
+    pub duration: Duration,
+    /**
+     * The number of collections of this region, including this one.
+     */
+    pub epoch: u64,
+}
+
+/**
+ * Totals over every collection of a region, as reported by
+ * [`Region::stats`](struct.Region.html#method.stats).
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /**
+     * The number of collections, including minor collections.
+     */
+    pub collections: u64,
+    /**
+     * The number of objects copied or slid into a new position.
+     */
+    pub copied: u64,
+    /**
+     * The number of objects freed, including those
+     * freed in place, such as by [`Region::gc_partition`](struct.Region.html#method.gc_partition).
+     */
+    pub freed: u64,
+    /**
+     * The number of bytes of entries copied, including the entry headers.
+     */
+    pub bytes_moved: u64,
+    /**
+     * The time spent in collections.
+     */
+    pub time: Duration,
+}
+
+pub(crate) struct GcHooks {
+    before: Box<dyn FnMut(GcTrigger)>,
+    after: Box<dyn FnMut(&GcEvent)>,
+}
+
+#[derive(Default)]
+pub(crate) struct GcLog {
+    events: VecDeque<GcEvent>,
+    stats: GcStats,
+    hooks: Option<GcHooks>,
+}
+impl GcLog {
+    pub(crate) fn push(&mut self, event: GcEvent) {
+        if self.events.len() == LOG_SIZE {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+    // The mean time taken per object of the recent full collections
+    #[cfg(feature = "std")]
+    pub(crate) fn cost_per_object(&self) -> Option<Duration> {
+        let full = || self.events.iter().filter(|e| e.trigger != GcTrigger::Minor);
+        let objects: usize = full().map(|e| e.before).sum();
+        let total: Duration = full().map(|e| e.duration).sum();
+        if objects == 0 { return None }
+        Some(Duration::from_nanos((total.as_nanos() / objects as u128) as u64))
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Get the most recent collections of this region, oldest first.
+     * Only a small, fixed number of events are kept.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.alloc(|_|{()});
+     * r.gc();
+     *
+     * let last = r.recent_gcs().last().unwrap();
+     * assert_eq!(last.trigger, gc::GcTrigger::Explicit);
+     * assert_eq!((last.before, last.after), (1, 0));
+     * ```
+     */
+    pub fn recent_gcs(&self) -> impl Iterator<Item=&GcEvent> {
+        self.gc_log.events.iter()
+    }
+    /**
+     * Get the totals over every collection of this region,
+     * such as for tuning how often it is collected.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let _root = r.alloc(|_|{()}).root();
+     * r.gc();
+     * r.alloc(|_|{()});
+     * r.gc();
+     *
+     * let stats = r.stats();
+     * assert!(stats.collections >= 2);
+     * assert_eq!(stats.freed, 1);
+     * ```
+     */
+    pub fn stats(&self) -> GcStats {
+        self.gc_log.stats
+    }
+    /**
+     * Set functions to be called before and after every collection,
+     * including minor collections, replacing any set previously.
+     * The first is passed the reason for the collection, and
+     * the second its record, as reported by [`recent_gcs`](#method.recent_gcs).
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * use std::rc::Rc;
+     * use std::cell::Cell;
+     * let mut r = gc::Region::<()>::new();
+     * let count = Rc::new(Cell::new(0));
+     * let (before, after) = (count.clone(), count.clone());
+     * r.on_gc(move |_| before.set(before.get() + 1),
+     *         move |event| after.set(after.get() + event.epoch * 10));
+     * r.gc();
+     * assert_eq!(count.get(), 11);
+     * ```
+     */
+    pub fn on_gc<B, A>(&mut self, before: B, after: A) where
+        B: 'static + FnMut(GcTrigger),
+        A: 'static + FnMut(&GcEvent),
+    {
+        self.gc_log.hooks = Some(GcHooks { before: Box::new(before), after: Box::new(after) });
+    }
+    /**
+     * Remove the functions set by [`on_gc`](#method.on_gc).
+     */
+    pub fn clear_on_gc(&mut self) {
+        self.gc_log.hooks = None;
+    }
+
+    // Report that a collection is starting
+    pub(crate) fn gc_starting(&mut self, trigger: GcTrigger) {
+        if let Some(ref mut hooks) = self.gc_log.hooks {
+            (hooks.before)(trigger);
+        }
+    }
+    // Record a finished collection, which copied some of its survivors
+    pub(crate) fn gc_finished(&mut self, event: GcEvent, copied: usize) {
+        let stats = &mut self.gc_log.stats;
+        stats.collections += 1;
+        stats.copied += copied as u64;
+        stats.freed += (event.before - event.after) as u64;
+        stats.bytes_moved += (copied * core::mem::size_of::<Spot<T>>()) as u64;
+        stats.time += event.duration;
+        if let Some(ref mut hooks) = self.gc_log.hooks {
+            (hooks.after)(&event);
+        }
+        self.gc_log.push(event);
+    }
+    // Record objects freed in place
+    pub(crate) fn gc_freed(&mut self, freed: usize) {
+        self.gc_log.stats.freed += freed as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GcTrigger, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn log_is_bounded() {
+        let mut r = Region::new();
+        let _root = r.alloc(|_| {Elem::new()}).root();
+        for _ in 0..100 {
+            r.gc();
+        }
+        assert_eq!(r.recent_gcs().count(), super::LOG_SIZE);
+        let first = r.recent_gcs().next().unwrap();
+        let last = r.recent_gcs().last().unwrap();
+        assert_eq!(last.epoch, first.epoch + super::LOG_SIZE as u64 - 1);
+        assert_eq!(last.after, 1);
+        assert!(r.recent_gcs().all(|e| e.trigger == GcTrigger::Explicit));
+    }
+
+    #[test]
+    pub fn stats_count_every_collection() {
+        use core::cell::RefCell;
+        use alloc::rc::Rc;
+        let mut r = Region::new();
+        r.ensure(4);
+        let triggers = Rc::new(RefCell::new(vec![]));
+        let before = triggers.clone();
+        r.on_gc(move |t| before.borrow_mut().push(t), |_| {});
+        let a = r.alloc(|_| {Elem::new()}).root();
+        r.alloc(|_| {Elem::new()});
+        r.gc();
+        r.alloc(|_| {Elem::new()});
+        r.minor_gc().unwrap();
+        r.alloc(|_| {Elem { ix: Some(a.ix()) }});
+        r.gc_partition(0).unwrap();
+
+        let stats = r.stats();
+        assert_eq!(stats.collections, r.recent_gcs().count() as u64);
+        assert_eq!(stats.freed, 3);
+        assert!(stats.copied >= 1);
+        assert_eq!(stats.bytes_moved, stats.copied * core::mem::size_of::<crate::entry::Spot<Elem>>() as u64);
+        assert_eq!(stats.time, r.recent_gcs().map(|e| e.duration).sum());
+
+        r.clear_on_gc();
+        r.gc();
+        assert_eq!(*triggers.borrow(), vec![GcTrigger::Explicit, GcTrigger::Minor]);
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::fmt::{Debug, Formatter};
+use core::ops::Deref;
+
+use crate::{Error, Ix, Region, Root};
+
+/**
+ * A reference to an object which remembers its region and index,
+ * so that edges can be followed without passing the region again.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ *
+ * struct Tree { value: u32, left: Option<gc::Ix<Tree>>, right: Option<gc::Ix<Tree>> }
+ * impl gc::HasIx<Tree> for Tree {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Tree>)
+ *     {
+ *         self.left.foreach_ix(&mut f);
+ *         self.right.foreach_ix(&mut f);
+ *     }
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * r.ensure(3);
+ * let leaf = r.alloc(|_| Tree { value: 3, left: None, right: None }).ix();
+ * let mid = r.alloc(|_| Tree { value: 2, left: None, right: Some(leaf) }).ix();
+ * let root = r.alloc(|_| Tree { value: 1, left: Some(mid), right: None }).root();
+ *
+ * let node = root.gc_ref(&r).follow(|n| n.left.unwrap()).follow(|n| n.right.unwrap());
+ * assert_eq!(node.value, 3);
+ * assert_eq!(node.ix().identifier(), leaf.identifier());
+ * ```
+ */
+pub struct GcRef<'r, T> {
+    region: &'r Region<T>,
+    ix: Ix<T>,
+    value: &'r T,
+}
+impl <'r, T> GcRef<'r, T> {
+    /**
+     * Get the index of this object.
+     */
+    #[inline]
+    pub fn ix(&self) -> Ix<T> {
+        self.ix
+    }
+    /**
+     * Get the region containing this object.
+     */
+    #[inline]
+    pub fn region(&self) -> &'r Region<T> {
+        self.region
+    }
+    /**
+     * Get the object, for the full lifetime of the region borrow.
+     */
+    #[inline]
+    pub fn get(&self) -> &'r T {
+        self.value
+    }
+    /**
+     * Follow an edge of this object, as chosen by `f`.
+     *
+     * As with [`Ix::get`](struct.Ix.html#method.get), this may
+     * panic if the index is invalid.
+     */
+    #[inline]
+    #[track_caller]
+    pub fn follow<F>(&self, f: F) -> GcRef<'r, T> where
+        F: FnOnce(&'r T) -> Ix<T>
+    {
+        f(self.value).gc_ref(self.region)
+    }
+    /**
+     * Follow an edge of this object, if `f` chooses one,
+     * returning an error if its index is invalid.
+     */
+    #[inline]
+    pub fn try_follow<F>(&self, f: F) -> Option<Result<GcRef<'r, T>, Error>> where
+        F: FnOnce(&'r T) -> Option<Ix<T>>
+    {
+        f(self.value).map(|ix| ix.try_gc_ref(self.region))
+    }
+}
+impl <'r, T> Clone for GcRef<'r, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl <'r, T> Copy for GcRef<'r, T> {}
+impl <'r, T> Deref for GcRef<'r, T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+impl <'r, T: Debug> Debug for GcRef<'r, T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("GcRef").field("ix", &self.ix).field("value", self.value).finish()
+    }
+}
+
+impl <T> Ix<T> {
+    /**
+     * Get a [`GcRef`](struct.GcRef.html) to the object at this index,
+     * which can be used to follow its edges. This may panic
+     * if the index is invalid, as with [`get`](#method.get).
+     */
+    #[inline]
+    #[track_caller]
+    pub fn gc_ref(self, region: &Region<T>) -> GcRef<'_, T> {
+        GcRef { region, ix: self, value: self.get(region) }
+    }
+    /**
+     * Get a [`GcRef`](struct.GcRef.html) to the object at this index,
+     * returning an error if the index is invalid.
+     */
+    #[inline]
+    pub fn try_gc_ref(self, region: &Region<T>) -> Result<GcRef<'_, T>, Error> {
+        Ok(GcRef { region, ix: self, value: self.try_get(region)? })
+    }
+}
+impl <T> Root<T> {
+    /**
+     * Get a [`GcRef`](struct.GcRef.html) to the object of this root,
+     * which can be used to follow its edges.
+     */
+    #[inline]
+    #[track_caller]
+    pub fn gc_ref<'r>(&self, region: &'r Region<T>) -> GcRef<'r, T> {
+        self.ix().gc_ref(region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn refs_follow_edges() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let a = r.alloc(|_| {Elem { ix: Some(b) }}).root();
+        let a_ref = a.gc_ref(&r);
+        let b_ref = a_ref.try_follow(|e| e.ix).unwrap().unwrap();
+        assert_eq!(b_ref.ix().identifier(), b.identifier());
+        assert!(b_ref.try_follow(|e| e.ix).is_none());
+        assert!(b_ref.get().ix.is_none());
+    }
+}
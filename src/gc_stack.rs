@@ -0,0 +1,157 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::fmt::{Debug, Formatter};
+use alloc::vec::Vec;
+
+use crate::{Ix, Region};
+use crate::root_set::RootSetCell;
+
+/**
+ * A stack of raw indices which are all roots, such as the
+ * evaluation stack of an interpreter.
+ *
+ * The indices are stored contiguously, and the stack is
+ * registered with the region once, so pushing and popping
+ * is a plain vector operation. Each collection scans
+ * the stack and updates the indices in place, so the indices
+ * in the stack are always valid, and their order is kept.
+ *
+ * As with a [`RootSet`](struct.RootSet.html), an index
+ * which is invalid when a collection occurs is removed,
+ * shifting the indices above it, so every index pushed
+ * must be valid for the region.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut stack = r.gc_stack();
+ *
+ * stack.push(r.alloc(|_|{()}).ix());
+ * stack.push(r.alloc(|_|{()}).ix());
+ * stack.pop();
+ * r.gc();
+ * assert_eq!(r.len(), 1);
+ * assert!(stack.top().unwrap().try_get(&r).is_ok());
+ * ```
+ */
+pub struct GcStack<T> {
+    ixs: Rc<RootSetCell<T>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new, empty, stack of roots which is registered with this region.
+     */
+    pub fn gc_stack(&mut self) -> GcStack<T> {
+        let ixs = Rc::new(RefCell::new(Vec::new()));
+        self.root_sets.push(Rc::downgrade(&ixs));
+        GcStack { ixs }
+    }
+}
+
+impl <T> GcStack<T> {
+    #[inline]
+    pub fn push(&mut self, ix: Ix<T>) {
+        self.ixs.borrow_mut().push(ix)
+    }
+    #[inline]
+    pub fn pop(&mut self) -> Option<Ix<T>> {
+        self.ixs.borrow_mut().pop()
+    }
+    /**
+     * Get the index on the top of the stack, without removing it.
+     */
+    #[inline]
+    pub fn top(&self) -> Option<Ix<T>> {
+        self.ixs.borrow().last().copied()
+    }
+    /**
+     * Get the index at a position, counting from the bottom of the stack.
+     */
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<Ix<T>> {
+        self.ixs.borrow().get(i).copied()
+    }
+    /**
+     * Replace the index at a position, counting from the bottom of the stack.
+     *
+     * This panics if the position is out of range.
+     */
+    #[inline]
+    pub fn set(&mut self, i: usize, ix: Ix<T>) {
+        self.ixs.borrow_mut()[i] = ix
+    }
+    /**
+     * Pop every index above the given height.
+     */
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        self.ixs.borrow_mut().truncate(len)
+    }
+    #[inline]
+    pub fn clear(&mut self) {
+        self.ixs.borrow_mut().clear()
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ixs.borrow().len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ixs.borrow().is_empty()
+    }
+    /**
+     * Access the indices of the stack directly, from the bottom up,
+     * such as to operate on several entries at once.
+     */
+    #[inline]
+    pub fn with_slice<R, F>(&mut self, f: F) -> R where
+        F: FnOnce(&mut [Ix<T>]) -> R
+    {
+        f(&mut self.ixs.borrow_mut())
+    }
+}
+impl <T> Extend<Ix<T>> for GcStack<T> {
+    fn extend<I: IntoIterator<Item=Ix<T>>>(&mut self, iter: I) {
+        self.ixs.borrow_mut().extend(iter)
+    }
+}
+impl <T> Debug for GcStack<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        self.ixs.borrow().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn stack_entries_are_roots() {
+        let mut r = Region::new();
+        let mut stack = r.gc_stack();
+        r.ensure(4);
+        for _ in 0..4 {
+            stack.push(r.alloc(|_| {Elem::new()}).ix());
+        }
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let third = stack.get(2).unwrap();
+        third.get_mut(&mut r).ix = Some(b);
+        stack.truncate(3);
+        stack.set(0, third);
+
+        r.gc();
+        assert_eq!(r.len(), 3);
+        assert_eq!(stack.len(), 3);
+        // Entries keep their order, and duplicates are moved together
+        assert_eq!(stack.get(0).unwrap().identifier(), stack.get(2).unwrap().identifier());
+        assert!(stack.top().unwrap().get(&r).ix.is_some());
+    }
+}
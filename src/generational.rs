@@ -0,0 +1,317 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::remap::{Forwarding, Remap};
+use crate::{Error, GcEvent, GcTrigger, HasIx, Region};
+#[cfg(feature = "debug-arena")]
+use crate::{Ix, has_ix::foreach_edge};
+
+// Objects before the boundary are tenured, and the rest are in the nursery
+pub(crate) struct Generations {
+    boundary: usize,
+    // Tenured objects which may have gained edges into the nursery
+    remembered: Vec<usize>,
+    pub(crate) enabled: bool,
+}
+// After any collection, every survivor is tenured
+impl Remap for Generations {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        self.boundary = forwarding.len();
+        self.remembered.clear();
+    }
+}
+
+impl <T> Region<T> {
+    fn generations(&mut self) -> Rc<RefCell<Generations>> {
+        if let Some(ref gens) = self.generations {
+            return gens.clone()
+        }
+        let gens = Rc::new(RefCell::new(Generations { boundary: 0, remembered: Vec::new(), enabled: false }));
+        self.remaps.push(Rc::downgrade(&gens) as _);
+        self.generations = Some(gens.clone());
+        gens
+    }
+    // Record a write to the object at a position
+    pub(crate) fn generational_write(&mut self, index: usize) {
+        if let Some(ref gens) = self.generations {
+            let mut gens = gens.borrow_mut();
+            if index < gens.boundary {
+                gens.remembered.push(index);
+            }
+        }
+    }
+    // Returns true if generational mode is enabled, and the nursery has objects
+    pub(crate) fn nursery_pending(&self) -> bool {
+        self.generations.as_ref().is_some_and(|gens| {
+            let gens = gens.borrow();
+            gens.enabled && gens.boundary < self.data.len()
+        })
+    }
+
+    /**
+     * Enable or disable generational mode, in which a collection triggered
+     * by allocation first tries a [`minor_gc`](#method.minor_gc), and only
+     * performs a full collection if that does not free enough space.
+     *
+     * In generational mode, or when using `minor_gc`, every object
+     * which gains an edge after it was allocated must be reported with
+     * [`write_barrier`](#method.write_barrier), as for
+     * [`gc_partition`](#method.gc_partition).
+     */
+    pub fn set_generational(&mut self, enabled: bool) {
+        self.generations().borrow_mut().enabled = enabled;
+    }
+    /**
+     * The number of objects in the nursery, that is, those
+     * allocated since the last collection. Until generational
+     * mode is enabled, or `minor_gc` is first called, every
+     * object is in the nursery.
+     */
+    pub fn nursery_len(&self) -> usize {
+        let boundary = self.generations.as_ref().map_or(0, |gens| gens.borrow().boundary);
+        self.data.len().saturating_sub(boundary)
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Collect only the nursery, that is, the objects allocated since
+     * the last collection, which are most likely to be garbage.
+     * Surviving objects are tenured, and are only collected
+     * by a full collection, such as [`major_gc`](#method.major_gc).
+     *
+     * Tenured objects are not moved, so their indices remain
+     * valid, but indices to objects of the nursery are invalidated.
+     * Tenured objects which point into the nursery are found
+     * through [`write_barrier`](#method.write_barrier), so every
+     * tenured object which gains an edge must be reported.
+     * With the feature "debug-arena", this advances the generation of
+     * the region as a full collection does, so that invalidated indices
+     * to the nursery are detected, while indices of earlier generations
+     * to tenured objects stay valid. Every tenured object is then traced,
+     * to update its indices.
+     *
+     * Returns the number of objects freed, or an error if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(4);
+     * let old = r.alloc(|_|{()}).root();
+     * r.alloc(|_|{()});
+     * assert_eq!(r.minor_gc(), Ok(1));
+     *
+     * let young = r.alloc(|_|{()}).root();
+     * r.alloc(|_|{()});
+     * assert_eq!(r.nursery_len(), 2);
+     * assert_eq!(r.minor_gc(), Ok(1));
+     * assert_eq!(r.nursery_len(), 0);
+     * assert_eq!(old.ix().identifier(), 0);
+     * assert!(young.try_get(&r).is_ok());
+     * ```
+     */
+    pub fn minor_gc(&mut self) -> Result<usize, Error> {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        let before = self.data.len();
+        let gens = self.generations();
+        self.gc_starting(GcTrigger::Minor);
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let (boundary, mut remembered) = {
+            let mut gens = gens.borrow_mut();
+            (core::cmp::min(gens.boundary, before), core::mem::take(&mut gens.remembered))
+        };
+        drop(gens);
+        remembered.sort_unstable();
+        remembered.dedup();
+        #[cfg(all(feature = "debug-arena", feature = "refresh"))]
+        let old_gen = self.generation;
+        #[cfg(feature = "debug-arena")]
+        let kept = self.keep_tenured(boundary);
+        #[cfg(feature = "debug-arena")]
+        {
+            remembered = (0..boundary).collect();
+        }
+
+        // Slide the survivors of the nursery down to the boundary
+        let (_map, next) = self.slide(boundary, &remembered);
+        #[cfg(feature = "debug-arena")]
+        {
+            self.kept = kept;
+        }
+        #[cfg(feature = "refresh")]
+        self.retain_forwarding(Forwarding::from_positions(_map, next),
+            #[cfg(feature = "debug-arena")]
+            old_gen);
+
+        self.epoch += 1;
+        let event = GcEvent {
+            trigger: GcTrigger::Minor,
+            before,
+            after: next,
+            capacity: self.data.capacity(),
+            #[cfg(feature = "std")]
+            duration: start.elapsed(),
+            #[cfg(not(feature = "std"))]
+            duration: core::time::Duration::ZERO,
+            epoch: self.epoch,
+        };
+        self.gc_finished(event, next - boundary);
+        Ok(before - next)
+    }
+
+    // Advance the generation before a minor collection, returning the
+    // positions kept in place, whose earlier indices stay valid. The indices
+    // held by tenured objects are all given the new generation, so every
+    // tenured object must then be traced
+    #[cfg(feature = "debug-arena")]
+    pub(crate) fn keep_tenured(&mut self, boundary: usize) -> Vec<(usize, crate::nonce::Word)> {
+        let mut kept = core::mem::take(&mut self.kept);
+        kept.truncate(kept.partition_point(|&(end, _)| end < boundary) + 1);
+        match kept.last_mut() {
+            Some(last) if last.0 >= boundary => last.0 = boundary,
+            _ => kept.push((boundary, self.generation)),
+        }
+        self.advance_generation();
+        let current = self.ix_at(0);
+        for i in 0..boundary {
+            if let Some(e) = self.data[i].get_mut() {
+                e.move_to(current.offset(i));
+            }
+        }
+        kept
+    }
+
+    // Give the current generation to every index of the roots and objects
+    // which is only valid because a minor collection kept its position,
+    // as other collections only accept indices of the current generation
+    #[cfg(feature = "debug-arena")]
+    pub(crate) fn restamp_kept(&mut self) {
+        if self.kept.is_empty() { return }
+        let current = self.ix_at(0);
+        let kept = &self.kept;
+        let restamp = |ix: &mut Ix<T>| {
+            if ix.nonce == current.nonce && ix.generation != current.generation
+                && crate::nonce::is_kept(kept, ix.ix(), ix.generation) {
+                *ix = current.offset(ix.ix());
+            }
+        };
+        self.roots.for_each_mut(restamp);
+        for set in &self.root_sets {
+            if let Some(set) = set.upgrade() {
+                set.borrow_mut().iter_mut().for_each(restamp);
+            }
+        }
+        for spot in self.data.iter_mut() {
+            if let Some(e) = spot.get_mut() {
+                foreach_edge(e.get_mut(), restamp);
+            }
+        }
+    }
+
+    /**
+     * Perform a full collection, which also tenures every survivor.
+     * This is the same as [`gc`](#method.gc).
+     */
+    #[inline]
+    pub fn major_gc(&mut self) {
+        self.gc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GcTrigger, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn minor_collections_keep_tenured_objects() {
+        let mut r = Region::new();
+        r.ensure(8);
+        let old = r.alloc(|_| {Elem::new()}).root();
+        r.alloc(|_| {Elem::new()});
+        assert_eq!(r.minor_gc(), Ok(1));
+        r.alloc(|_| {Elem::new()});
+        let young = r.alloc(|_| {Elem::new()}).ix();
+        old.get_mut(&mut r).ix = Some(young);
+        r.write_barrier(old.ix());
+        // The group is kept whole
+        let slice = r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        let w = r.alloc(|_| {Elem { ix: slice.get(1) }}).weak();
+        let root = r.alloc(|_| {Elem::new()}).root();
+        root.get_mut(&mut r).ix = w.ix();
+
+        assert_eq!(r.nursery_len(), 6);
+        let raw = old.ix();
+        assert_eq!(r.minor_gc(), Ok(1));
+        assert_eq!(old.ix().identifier(), 0);
+        assert_eq!(r.recent_gcs().last().unwrap().trigger, GcTrigger::Minor);
+        // Raw indices to tenured objects stay valid
+        assert!(raw.try_get(&r).is_ok());
+        assert!(old.get(&r).ix.unwrap().try_get(&r).is_ok());
+        assert!(w.get(&r).ix.unwrap().try_get(&r).is_ok());
+        assert_eq!(root.get(&r).ix.unwrap().identifier(), w.ix().unwrap().identifier());
+        assert_eq!(r.nursery_len(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-arena")]
+    pub fn minor_collections_invalidate_nursery_indices() {
+        use crate::Error;
+        let mut r = Region::new();
+        r.ensure(4);
+        let old = r.alloc(|_| {Elem::new()}).root();
+        let weak = old.downgrade(&mut r);
+        r.minor_gc().unwrap();
+        r.alloc(|_| {Elem::new()});
+        let young = r.alloc(|_| {Elem::new()}).root();
+        let stale = young.ix();
+        old.get_mut(&mut r).ix = Some(stale);
+        r.write_barrier(old.ix());
+
+        let tenured = old.ix();
+        r.minor_gc().unwrap();
+        assert_eq!(young.ix().identifier(), 1);
+        assert_eq!(stale.try_get(&r).err(), Some(Error::EntryExpired));
+        assert_eq!(old.get(&r).ix.unwrap().identifier(), young.ix().identifier());
+        assert!(weak.try_get(&r).is_ok());
+        assert!(tenured.try_get(&r).is_ok());
+
+        // Objects tenured by the last minor collection are kept as well
+        let raw = young.ix();
+        r.alloc(|_| {Elem::new()});
+        r.minor_gc().unwrap();
+        assert!(tenured.try_get(&r).is_ok() && raw.try_get(&r).is_ok());
+        // A full collection accepts them in edges, and then invalidates them
+        young.get_mut(&mut r).ix = Some(tenured);
+        let mut set = r.root_set();
+        set.insert(raw);
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert_eq!(tenured.try_get(&r).err(), Some(Error::EntryExpired));
+        assert!(young.get(&r).ix.unwrap().try_get(&r).is_ok());
+        assert!(set.get(0).unwrap().try_get(&r).is_ok());
+    }
+
+    #[test]
+    pub fn generational_mode_collects_the_nursery() {
+        let mut r = Region::new();
+        r.set_generational(true);
+        let root = r.alloc(|_| {Elem::new()}).root();
+        r.major_gc();
+        for _ in 0..100 {
+            r.alloc(|_| {Elem::new()});
+        }
+        assert!(r.recent_gcs().any(|e| e.trigger == GcTrigger::Minor));
+        assert_eq!(root.ix().identifier(), 0);
+    }
+}
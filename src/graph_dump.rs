@@ -0,0 +1,194 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::fmt::{Debug, Write as _};
+use std::io::{self, Write};
+
+use crate::{HasIx, Region};
+use crate::has_ix::{foreach_edge, foreach_edge_labeled};
+
+/**
+ * The format written by [`Region::dump_graph`](struct.Region.html#method.dump_graph).
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /**
+     * A Graphviz digraph, with a node `n<identifier>` for each object.
+     * Roots are drawn with a double outline.
+     */
+    Dot,
+    /**
+     * A JSON object holding the identifiers of the roots,
+     * and an adjacency list of the objects, as in
+     * `{"roots":[0],"nodes":[{"id":0,"edges":[{"label":"next","to":1}]}, ...]}`.
+     * With [`dump_graph_debug`](struct.Region.html#method.dump_graph_debug),
+     * each node also has a `"debug"` string.
+     */
+    Json,
+}
+
+// Quote a string for DOT or JSON, which share their escapes
+// for the characters that matter here
+fn quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Write the objects reachable from the roots of this region, and
+     * the edges between them, for viewing or analysis by other tools,
+     * such as to find what keeps an object alive in a leaking heap.
+     * Objects are identified by [`Ix::identifier`](struct.Ix.html#method.identifier)
+     * and edges are labeled as by
+     * [`HasIx::foreach_ix_labeled`](trait.HasIx.html#method.foreach_ix_labeled).
+     * Edges which do not point to an object are omitted.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let _root = r.alloc(|_|{()}).root();
+     * r.alloc(|_|{()});
+     *
+     * let mut dot = Vec::new();
+     * r.dump_graph(&mut dot, gc::DumpFormat::Dot).unwrap();
+     * let dot = String::from_utf8(dot).unwrap();
+     * assert!(dot.contains("n0 [label=\"0\", peripheries=2];"));
+     * assert!(!dot.contains("n1"));
+     * ```
+     */
+    pub fn dump_graph<W: Write>(&mut self, out: &mut W, format: DumpFormat) -> io::Result<()> {
+        self.dump(out, format, |_| None)
+    }
+
+    fn dump<W, F>(&mut self, out: &mut W, format: DumpFormat, mut payload: F) -> io::Result<()> where
+        W: Write,
+        F: FnMut(&T) -> Option<String>,
+    {
+        let len = self.data.len();
+        let mut reachable = vec![false; len];
+        let mut roots = Vec::new();
+        self.foreach_root(|ix| roots.push(ix.ix()));
+        roots.retain(|&i| i < len && self.data[i].get().is_some());
+        roots.sort_unstable();
+        roots.dedup();
+        let mut stack = roots.clone();
+        for &i in &roots { reachable[i] = true }
+        let mut pointed = Vec::new();
+        while let Some(i) = stack.pop() {
+            foreach_edge(self.data[i].get_mut().unwrap().get_mut(), |ix| pointed.push(ix.ix()));
+            for j in pointed.drain(..) {
+                if j < len && !reachable[j] && self.data[j].get().is_some() {
+                    reachable[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+
+        let is_root = |i: usize| roots.binary_search(&i).is_ok();
+        match format {
+            DumpFormat::Dot => writeln!(out, "digraph region {{")?,
+            DumpFormat::Json => {
+                let roots: Vec<String> = roots.iter().map(usize::to_string).collect();
+                write!(out, "{{\"roots\":[{}],\"nodes\":[", roots.join(","))?;
+            },
+        }
+        let mut first = true;
+        for i in (0..len).filter(|&i| reachable[i]) {
+            let t = self.data[i].get_mut().unwrap().get_mut();
+            let debug = payload(t);
+            let mut edges = Vec::new();
+            foreach_edge_labeled(t, |label, ix| {
+                if reachable.get(ix.ix()) == Some(&true) {
+                    edges.push((label.to_string(), ix.ix()));
+                }
+            });
+            match format {
+                DumpFormat::Dot => {
+                    let label = match debug {
+                        Some(debug) => format!("{}: {}", i, debug),
+                        None => i.to_string(),
+                    };
+                    let outline = if is_root(i) { ", peripheries=2" } else { "" };
+                    writeln!(out, "    n{} [label={}{}];", i, quoted(&label), outline)?;
+                    for (label, j) in edges {
+                        writeln!(out, "    n{} -> n{} [label={}];", i, j, quoted(&label))?;
+                    }
+                },
+                DumpFormat::Json => {
+                    if !first { write!(out, ",")? }
+                    write!(out, "{{\"id\":{},\"edges\":[", i)?;
+                    for (n, (label, j)) in edges.into_iter().enumerate() {
+                        if n > 0 { write!(out, ",")? }
+                        write!(out, "{{\"label\":{},\"to\":{}}}", quoted(&label), j)?;
+                    }
+                    write!(out, "]")?;
+                    if let Some(debug) = debug {
+                        write!(out, ",\"debug\":{}", quoted(&debug))?;
+                    }
+                    write!(out, "}}")?;
+                },
+            }
+            first = false;
+        }
+        match format {
+            DumpFormat::Dot => writeln!(out, "}}"),
+            DumpFormat::Json => writeln!(out, "]}}"),
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T> + Debug> Region<T> {
+    /**
+     * Write the objects reachable from the roots of this region,
+     * as with [`dump_graph`](#method.dump_graph), labeling each object
+     * with its `Debug` representation.
+     */
+    pub fn dump_graph_debug<W: Write>(&mut self, out: &mut W, format: DumpFormat) -> io::Result<()> {
+        self.dump(out, format, |t| Some(format!("{:?}", t)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DumpFormat, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn dumps_show_reachable_objects() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        r.alloc(|_| {Elem::new()});
+        let root = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        a.get_mut(&mut r).ix = Some(root.ix());
+
+        let mut json = Vec::new();
+        r.dump_graph(&mut json, DumpFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["roots"], serde_json::json!([2]));
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(json["nodes"][1]["edges"][0], serde_json::json!({"label": "ix", "to": 0}));
+
+        let mut dot = Vec::new();
+        r.dump_graph_debug(&mut dot, DumpFormat::Dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph region {\n"));
+        assert!(dot.contains("n0 -> n2 [label=\"ix\"];"));
+        assert!(dot.contains("n2 [label=\"2: Elem { ix: Some(0) }\", peripheries=2];"));
+    }
+}
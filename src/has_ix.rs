@@ -1,5 +1,75 @@
 
+use alloc::collections::{BTreeMap, VecDeque};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+use core::fmt;
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+use alloc::string::String;
+
 use crate::types::Ix;
+use crate::{Region, WeakIx};
+#[cfg(feature = "ix32")]
+use crate::Ix32;
+
+/**
+ * A description of where an edge is held within its object,
+ * reported by [`HasIx::foreach_ix_labeled`](trait.HasIx.html#method.foreach_ix_labeled)
+ * for diagnostics and exports.
+ *
+ * These display as `name`, `[i]` and `name[i]` respectively.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeLabel {
+    /**
+     * An edge held directly in the named field.
+     */
+    Field(&'static str),
+    /**
+     * The edge at the given position among all edges of the object.
+     * This is the label used when an object does not provide its own.
+     */
+    Index(usize),
+    /**
+     * An edge at the given position of a collection in the named field.
+     */
+    Element(&'static str, usize),
+}
+impl fmt::Display for EdgeLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            EdgeLabel::Field(name) => write!(f, "{}", name),
+            EdgeLabel::Index(i) => write!(f, "[{}]", i),
+            EdgeLabel::Element(name, i) => write!(f, "{}[{}]", name, i),
+        }
+    }
+}
+
+/**
+ * The context in which an object is checked by
+ * [`HasIx::validate`](trait.HasIx.html#method.validate).
+ */
+pub struct ValidateCtx<'a, T> {
+    pub(crate) region: &'a Region<T>,
+    pub(crate) ix: Ix<T>,
+}
+impl <'a, T> ValidateCtx<'a, T> {
+    /**
+     * The region containing the object, which
+     * can be used to check the objects it points to.
+     */
+    #[inline]
+    pub fn region(&self) -> &'a Region<T> {
+        self.region
+    }
+    /**
+     * The index of the object being checked.
+     */
+    #[inline]
+    pub fn ix(&self) -> Ix<T> {
+        self.ix
+    }
+}
 
 /**
  * Trait to expose contained indices to the garbage collector.
@@ -20,19 +90,251 @@ pub trait HasIx<T : 'static> {
      * create additional space and time overheads,
      * ensuring uniqueness is a requirement of the implementer.
      *
-     * Avoid panicking in this method. A panic may
-     * cause some elements to never be dropped, leaking
-     * any owned memory outside the region.
+     * Avoid panicking in this method. A panic during
+     * a collection is safe: the region is left containing
+     * every object which had been reached, and all roots and weak
+     * pointers remain valid. However, indices in objects
+     * which had not yet been traced will be invalid, and some
+     * elements may never be dropped, leaking any owned memory
+     * outside the region.
      */
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
         F: FnMut(&'b mut Ix<T>);
+
+    /**
+     * Expose each Ix as in [`foreach_ix`](#tymethod.foreach_ix), along with
+     * a label describing where it is held, such as a field name.
+     * This is only used for diagnostics and exports, not by the collector,
+     * and must expose the same indices in the same order as `foreach_ix`.
+     *
+     * By default, each edge is labeled by its position.
+     */
+    fn foreach_ix_labeled<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(EdgeLabel, &'b mut Ix<T>)
+    {
+        let mut i = 0;
+        self.foreach_ix(|ix| {
+            f(EdgeLabel::Index(i), ix);
+            i += 1;
+        });
+    }
+
+    /**
+     * Expose a mutable reference to every [`WeakIx`](struct.WeakIx.html)
+     * owned by this datastructure, which are not exposed by
+     * [`foreach_ix`](#tymethod.foreach_ix). Each is updated by a
+     * collection if its object is kept alive by other edges, and
+     * cleared otherwise.
+     *
+     * By default, there are no weak indices.
+     */
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    { }
+
+    /**
+     * Expose a mutable reference to every compact [`Ix32`](struct.Ix32.html)
+     * owned by this datastructure, which are not exposed by
+     * [`foreach_ix`](#tymethod.foreach_ix). These are edges just as
+     * those indices are, and are traced and updated in the same way,
+     * after them.
+     *
+     * By default, there are no compact indices.
+     *
+     * This requires the feature "ix32", without which
+     * this method is not part of the trait.
+     */
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    { }
+
+    /**
+     * Check the invariants of this object, returning a description
+     * of any which are violated. This is called for each reachable
+     * object by [`Region::check_integrity`](struct.Region.html#method.check_integrity),
+     * and so by [`Region::try_gc`](struct.Region.html#method.try_gc),
+     * once every edge is known to be valid.
+     *
+     * By default, every object is valid.
+     */
+    fn validate(&self, _ctx: ValidateCtx<'_, T>) -> Result<(), String> {
+        Ok(())
+    }
 }
+/**
+ * An object-safe form of [`HasIx`](trait.HasIx.html), which is
+ * implemented for every implementor of `HasIx`.
+ *
+ * This allows a region to hold trait objects, by making a trait
+ * which extends `HasIxDyn`, and a struct which holds a box of that trait.
+ * Boxes implement `HasIx` through this trait.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ *
+ * struct Object(Box<dyn Shape>);
+ * trait Shape: gc::HasIxDyn<Object> {
+ *     fn sides(&self) -> u32;
+ * }
+ * gc::impl_has_ix!(Object { 0 });
+ *
+ * struct Triangle;
+ * impl gc::HasIx<Object> for Triangle {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Object>)
+ *     { }
+ * }
+ * impl Shape for Triangle {
+ *     fn sides(&self) -> u32 { 3 }
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * let root = r.alloc(|_| Object(Box::new(Triangle))).root();
+ * r.gc();
+ * assert_eq!(root.get(&r).0.sides(), 3);
+ * ```
+ */
+pub trait HasIxDyn<T : 'static> {
+    /**
+     * Expose every Ix, as in [`HasIx::foreach_ix`](trait.HasIx.html#tymethod.foreach_ix).
+     */
+    fn foreach_ix_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut Ix<T>));
+    /**
+     * Expose every WeakIx, as in [`HasIx::foreach_weak_ix`](trait.HasIx.html#method.foreach_weak_ix).
+     */
+    fn foreach_weak_ix_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut WeakIx<T>));
+    /**
+     * Expose every Ix32, as in [`HasIx::foreach_ix32`](trait.HasIx.html#method.foreach_ix32).
+     */
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32_dyn<'a>(&'a mut self, _f: &mut dyn FnMut(&'a mut Ix32<T>)) { }
+}
+impl <T : 'static, S: HasIx<T>> HasIxDyn<T> for S {
+    #[inline]
+    fn foreach_ix_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut Ix<T>)) {
+        self.foreach_ix(f)
+    }
+    #[inline]
+    fn foreach_weak_ix_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut WeakIx<T>)) {
+        self.foreach_weak_ix(f)
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut Ix32<T>)) {
+        self.foreach_ix32(f)
+    }
+}
+
 impl <T : 'static, S: HasIx<T>> HasIx<T> for Vec<S> {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
         F: FnMut(&'b mut Ix<T>)
     {
         self.iter_mut().for_each(|o| {o.foreach_ix(&mut f)});
     }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_weak_ix(&mut f)});
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_ix32(&mut f)});
+    }
+}
+impl <T : 'static, S: HasIx<T>> HasIx<T> for VecDeque<S> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_ix(&mut f)});
+    }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_weak_ix(&mut f)});
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_ix32(&mut f)});
+    }
+}
+impl <T : 'static, S: HasIx<T>, const N: usize> HasIx<T> for [S; N] {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_ix(&mut f)});
+    }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_weak_ix(&mut f)});
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.iter_mut().for_each(|o| {o.foreach_ix32(&mut f)});
+    }
+}
+/**
+ * A slice is traced through [`HasIxDyn`](trait.HasIxDyn.html),
+ * so that boxed slices can be traced.
+ */
+impl <T : 'static, S: HasIx<T>> HasIxDyn<T> for [S] {
+    fn foreach_ix_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut Ix<T>)) {
+        self.iter_mut().for_each(|o| {o.foreach_ix(&mut *f)});
+    }
+    fn foreach_weak_ix_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut WeakIx<T>)) {
+        self.iter_mut().for_each(|o| {o.foreach_weak_ix(&mut *f)});
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32_dyn<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut Ix32<T>)) {
+        self.iter_mut().for_each(|o| {o.foreach_ix32(&mut *f)});
+    }
+}
+/**
+ * Only the values of a map are traced, as changing
+ * the keys would break the map.
+ */
+#[cfg(feature = "std")]
+impl <T : 'static, K, S: HasIx<T>, H> HasIx<T> for HashMap<K, S, H> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        self.values_mut().for_each(|o| {o.foreach_ix(&mut f)});
+    }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.values_mut().for_each(|o| {o.foreach_weak_ix(&mut f)});
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.values_mut().for_each(|o| {o.foreach_ix32(&mut f)});
+    }
+}
+impl <T : 'static, K, S: HasIx<T>> HasIx<T> for BTreeMap<K, S> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        self.values_mut().for_each(|o| {o.foreach_ix(&mut f)});
+    }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.values_mut().for_each(|o| {o.foreach_weak_ix(&mut f)});
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.values_mut().for_each(|o| {o.foreach_ix32(&mut f)});
+    }
 }
 impl <T : 'static> HasIx<T> for () {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut _f: F) where
@@ -46,6 +348,19 @@ impl <T : 'static, S1: HasIx<T>, S2: HasIx<T>> HasIx<T> for (S1, S2) {
         self.0.foreach_ix(&mut f);
         self.1.foreach_ix(&mut f);
     }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.0.foreach_weak_ix(&mut f);
+        self.1.foreach_weak_ix(&mut f);
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.0.foreach_ix32(&mut f);
+        self.1.foreach_ix32(&mut f);
+    }
 }
 impl <T : 'static, S1: HasIx<T>, S2: HasIx<T>, S3: HasIx<T>> HasIx<T> for (S1, S2, S3) {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
@@ -55,6 +370,21 @@ impl <T : 'static, S1: HasIx<T>, S2: HasIx<T>, S3: HasIx<T>> HasIx<T> for (S1, S
         self.1.foreach_ix(&mut f);
         self.2.foreach_ix(&mut f);
     }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.0.foreach_weak_ix(&mut f);
+        self.1.foreach_weak_ix(&mut f);
+        self.2.foreach_weak_ix(&mut f);
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.0.foreach_ix32(&mut f);
+        self.1.foreach_ix32(&mut f);
+        self.2.foreach_ix32(&mut f);
+    }
 }
 impl <T : 'static, S1: HasIx<T>, S2: HasIx<T>, S3: HasIx<T>, S4: HasIx<T>> HasIx<T> for (S1, S2, S3, S4) {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
@@ -65,6 +395,23 @@ impl <T : 'static, S1: HasIx<T>, S2: HasIx<T>, S3: HasIx<T>, S4: HasIx<T>> HasIx
         self.2.foreach_ix(&mut f);
         self.3.foreach_ix(&mut f);
     }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.0.foreach_weak_ix(&mut f);
+        self.1.foreach_weak_ix(&mut f);
+        self.2.foreach_weak_ix(&mut f);
+        self.3.foreach_weak_ix(&mut f);
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.0.foreach_ix32(&mut f);
+        self.1.foreach_ix32(&mut f);
+        self.2.foreach_ix32(&mut f);
+        self.3.foreach_ix32(&mut f);
+    }
 }
 impl <T : 'static, S: HasIx<T>> HasIx<T> for Option<S> {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
@@ -72,12 +419,41 @@ impl <T : 'static, S: HasIx<T>> HasIx<T> for Option<S> {
     {
         self.iter_mut().for_each(|o|{o.foreach_ix(&mut f)})
     }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        self.iter_mut().for_each(|o|{o.foreach_weak_ix(&mut f)})
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.iter_mut().for_each(|o|{o.foreach_ix32(&mut f)})
+    }
 }
-impl <T : 'static, S: HasIx<T>> HasIx<T> for Box<S> {
+/**
+ * A box is traced through [`HasIxDyn`](trait.HasIxDyn.html),
+ * so that boxed trait objects can be traced.
+ */
+impl <T : 'static, S: ?Sized + HasIxDyn<T>> HasIx<T> for Box<S> {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
         F: FnMut(&'b mut Ix<T>)
     {
-        self.as_mut().foreach_ix(&mut f);
+        let s: &'b mut S = self.as_mut();
+        s.foreach_ix_dyn(&mut f);
+    }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        let s: &'b mut S = self.as_mut();
+        s.foreach_weak_ix_dyn(&mut f);
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        let s: &'b mut S = self.as_mut();
+        s.foreach_ix32_dyn(&mut f);
     }
 }
 impl <T : 'static, S: HasIx<T>> HasIx<T> for &mut S {
@@ -86,6 +462,17 @@ impl <T : 'static, S: HasIx<T>> HasIx<T> for &mut S {
     {
         (*self).foreach_ix(&mut f);
     }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        (*self).foreach_weak_ix(&mut f);
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        (*self).foreach_ix32(&mut f);
+    }
 }
 impl <T : 'static> HasIx<T> for Ix<T> {
     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
@@ -94,3 +481,327 @@ impl <T : 'static> HasIx<T> for Ix<T> {
         f(self);
     }
 }
+/**
+ * A region may be stored within the objects of another region,
+ * such as to form a hierarchy of heaps. The inner region is
+ * dropped, along with its objects, once its owner is collected.
+ *
+ * This implementation exposes the indices into the outer
+ * region held by objects of the inner region, allowing edges
+ * across the boundary. Owners without such edges need not
+ * expose the inner region at all.
+ */
+impl <T : 'static, S: HasIx<T>> HasIx<T> for Region<S> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        self.data.iter_mut()
+            .filter_map(|s| s.get_mut())
+            .for_each(|e| e.get_mut().foreach_ix(&mut f));
+    }
+    #[cfg(feature = "ix32")]
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        self.data.iter_mut()
+            .filter_map(|s| s.get_mut())
+            .for_each(|e| e.get_mut().foreach_ix32(&mut f));
+    }
+}
+
+// Expose every edge of an object, including its compact indices,
+// which are updated through their full indices
+#[track_caller]
+pub(crate) fn foreach_edge<T: 'static, S: HasIx<T> + ?Sized, F>(obj: &mut S, mut f: F) where
+    F: FnMut(&mut Ix<T>)
+{
+    obj.foreach_ix(&mut f);
+    #[cfg(feature = "ix32")]
+    obj.foreach_ix32(|ix| ix.with_ix(&mut f));
+}
+// As foreach_edge, with compact indices labeled after the others
+#[allow(unused)]
+#[track_caller]
+pub(crate) fn foreach_edge_labeled<T: 'static, S: HasIx<T> + ?Sized, F>(obj: &mut S, mut f: F) where
+    F: FnMut(EdgeLabel, &mut Ix<T>)
+{
+    let mut i = 0;
+    obj.foreach_ix_labeled(|label, ix| {
+        f(label, ix);
+        i += 1;
+    });
+    #[cfg(feature = "ix32")]
+    obj.foreach_ix32(|ix| {
+        ix.with_ix(|ix| f(EdgeLabel::Index(i), ix));
+        i += 1;
+    });
+}
+
+/**
+ * Implement [`HasIx`](trait.HasIx.html) for a struct by listing
+ * the fields which hold its indices, without a procedural macro.
+ *
+ * Each field is traced with its own `HasIx` implementation, which
+ * covers indices, compact indices, options, vectors and other implementors. A field
+ * followed by `[]` is instead traced through each of its elements,
+ * as given by `iter_mut`, covering other collections. Fields of
+ * nested structs are given by paths, such as `attr.target`, and
+ * fields of tuple structs by position, such as `0`.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use std::collections::VecDeque;
+ *
+ * #[derive(Default)]
+ * struct Attr { target: Option<gc::Ix<Node>> }
+ * #[derive(Default)]
+ * struct Node {
+ *     left: Option<gc::Ix<Node>>,
+ *     right: Option<gc::Ix<Node>>,
+ *     children: VecDeque<gc::Ix<Node>>,
+ *     attr: Attr,
+ *     name: String,
+ * }
+ * gc::impl_has_ix!(Node { left, right, children[], attr.target });
+ *
+ * let mut r = gc::Region::new();
+ * r.ensure(3);
+ * let leaf = r.alloc(|_| Node::default()).ix();
+ * r.alloc(|_| Node::default());
+ * let root = r.alloc(|_| Node { children: VecDeque::from(vec![leaf]), ..Node::default() }).root();
+ * r.gc();
+ * assert_eq!(r.len(), 2);
+ * ```
+ *
+ * Generic structs are given with their type parameters, as in `Node<V> { .. }`,
+ * which must be `'static`.
+ */
+#[macro_export]
+macro_rules! impl_has_ix {
+    ($name:ident $(<$($g:ident),+>)? { $($fields:tt)* }) => {
+        impl $(<$($g: 'static),+>)? $crate::HasIx<$name $(<$($g),+>)?> for $name $(<$($g),+>)? {
+            fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+                F: FnMut(&'b mut $crate::Ix<$name $(<$($g),+>)?>)
+            {
+                $crate::impl_has_ix!(@fields foreach_ix, self, f; $($fields)*);
+            }
+            $crate::__impl_has_ix32!($name $(<$($g),+>)? { $($fields)* });
+        }
+    };
+    (@fields $m:ident, $s:ident, $f:ident; ) => {};
+    (@fields $m:ident, $s:ident, $f:ident; $($p:tt).+ [] $(, $($rest:tt)*)?) => {
+        for e in $s.$($p).+.iter_mut() {
+            $crate::HasIx::$m(e, &mut $f);
+        }
+        $crate::impl_has_ix!(@fields $m, $s, $f; $($($rest)*)?);
+    };
+    (@fields $m:ident, $s:ident, $f:ident; $($p:tt).+ $(, $($rest:tt)*)?) => {
+        $crate::HasIx::$m(&mut $s.$($p).+, &mut $f);
+        $crate::impl_has_ix!(@fields $m, $s, $f; $($($rest)*)?);
+    };
+}
+
+// The compact indices of impl_has_ix, which are only traced with the feature "ix32"
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "ix32")]
+macro_rules! __impl_has_ix32 {
+    ($name:ident $(<$($g:ident),+>)? { $($fields:tt)* }) => {
+        fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+            F: FnMut(&'b mut $crate::Ix32<$name $(<$($g),+>)?>)
+        {
+            $crate::impl_has_ix!(@fields foreach_ix32, self, f; $($fields)*);
+        }
+    };
+}
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "ix32"))]
+macro_rules! __impl_has_ix32 {
+    ($($t:tt)*) => {};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EdgeLabel, GcError, HasIx, HasIxDyn, Ix, Region, ValidateCtx};
+    use crate::tests::Elem;
+
+    // A list node, whose successor must hold a greater value
+    struct Sorted {
+        value: u32,
+        next: Option<Ix<Sorted>>,
+    }
+    impl HasIx<Sorted> for Sorted {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Sorted>)
+        {
+            self.next.foreach_ix(f)
+        }
+        fn validate(&self, ctx: ValidateCtx<'_, Sorted>) -> Result<(), String> {
+            match self.next {
+                Some(next) if next.get(ctx.region()).value <= self.value =>
+                    Err(format!("{} is followed by {}", self.value, next.get(ctx.region()).value)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    pub fn collections_validate_objects() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let b = r.alloc(|_| {Sorted { value: 2, next: None }}).ix();
+        let a = r.alloc(|_| {Sorted { value: 1, next: Some(b) }}).root();
+        assert_eq!(r.try_gc(), Ok(()));
+
+        a.get_mut(&mut r).value = 3;
+        assert_eq!(r.try_gc(), Err(GcError::InvalidObject {
+            object: a.ix().identifier(),
+            message: "3 is followed by 2".to_string(),
+        }));
+        assert_eq!(r.len(), 2);
+    }
+
+    struct Edges<V> {
+        first: Option<Ix<Pair<V>>>,
+    }
+    struct Pair<V> {
+        value: V,
+        edges: Edges<V>,
+        rest: alloc::collections::LinkedList<Ix<Pair<V>>>,
+    }
+    impl_has_ix!(Pair<V> { edges.first, rest[] });
+    impl <V> Pair<V> {
+        fn new(value: V, first: Option<Ix<Pair<V>>>) -> Self {
+            Pair { value, edges: Edges { first }, rest: Default::default() }
+        }
+    }
+
+    #[test]
+    pub fn macro_traces_fields() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Pair::new(1, None)}).ix();
+        let b = r.alloc(|_| {Pair::new(2, None)}).ix();
+        r.alloc(|_| {Pair::new(3, Some(a))});
+        let root = r.alloc(|_| {Pair::new(4, Some(a))}).root();
+        root.get_mut(&mut r).rest.push_back(b);
+        r.gc();
+        assert_eq!(r.len(), 3);
+        assert_eq!(root.get(&r).rest.front().unwrap().get(&r).value, 2);
+        assert_eq!(root.get(&r).edges.first.unwrap().get(&r).value, 1);
+    }
+
+    #[cfg(feature = "std")]
+    struct Payload {
+        value: u32,
+        by_name: std::collections::HashMap<&'static str, Ix<Payload>>,
+        by_order: alloc::collections::BTreeMap<u32, Option<Ix<Payload>>>,
+        queue: alloc::collections::VecDeque<Ix<Payload>>,
+        pair: [Option<Ix<Payload>>; 2],
+        slice: Box<[Ix<Payload>]>,
+    }
+    #[cfg(feature = "std")]
+    impl_has_ix!(Payload { by_name, by_order, queue, pair, slice });
+    #[cfg(feature = "std")]
+    impl Payload {
+        fn new(value: u32) -> Self {
+            Payload {
+                value,
+                by_name: Default::default(),
+                by_order: Default::default(),
+                queue: Default::default(),
+                pair: [None; 2],
+                slice: Box::new([]),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn collections_are_traced() {
+        let mut r = Region::new();
+        r.ensure(7);
+        r.alloc(|_| {Payload::new(0)});
+        let ixs: Vec<_> = (1..6).map(|i| r.alloc(|_| {Payload::new(i)}).ix()).collect();
+        let root = r.alloc(|_| {Payload::new(6)}).root();
+        let p = root.get_mut(&mut r);
+        p.by_name.insert("one", ixs[0]);
+        p.by_order.insert(2, Some(ixs[1]));
+        p.queue.push_back(ixs[2]);
+        p.pair[1] = Some(ixs[3]);
+        p.slice = vec![ixs[4]].into_boxed_slice();
+        r.gc();
+        assert_eq!(r.len(), 6);
+        let p = root.get(&r);
+        let values = [
+            p.by_name["one"],
+            p.by_order[&2].unwrap(),
+            p.queue[0],
+            p.pair[1].unwrap(),
+            p.slice[0],
+        ].map(|ix| ix.get(&r).value);
+        assert_eq!(values, [1, 2, 3, 4, 5]);
+    }
+
+    struct Obj(Box<dyn Dyn>);
+    trait Dyn: HasIxDyn<Obj> {
+        fn value(&self) -> u32;
+    }
+    impl HasIx<Obj> for Obj {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Obj>)
+        {
+            self.0.foreach_ix(f)
+        }
+    }
+    struct Leaf(u32);
+    struct Branch(Vec<Ix<Obj>>);
+    impl HasIx<Obj> for Leaf {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+            F: FnMut(&'b mut Ix<Obj>)
+        { }
+    }
+    impl HasIx<Obj> for Branch {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Obj>)
+        {
+            self.0.foreach_ix(f)
+        }
+    }
+    impl Dyn for Leaf {
+        fn value(&self) -> u32 { self.0 }
+    }
+    impl Dyn for Branch {
+        fn value(&self) -> u32 { self.0.len() as u32 }
+    }
+
+    #[test]
+    pub fn trait_objects_are_traced() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Obj(Box::new(Leaf(5)))}).ix();
+        r.alloc(|_| {Obj(Box::new(Leaf(6)))});
+        let b = r.alloc(|_| {Obj(Box::new(Branch(vec![a, a])))}).root();
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert_eq!(b.get(&r).0.value(), 2);
+        let mut leaves = vec![];
+        b.get_mut(&mut r).0.foreach_ix_dyn(&mut |ix| leaves.push(*ix));
+        assert!(leaves.iter().all(|leaf| leaf.get(&r).0.value() == 5));
+    }
+
+    #[test]
+    pub fn edges_are_labeled() {
+        let mut r = Region::new();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let mut pair = (vec![a.ix(), a.ix()], Elem { ix: Some(a.ix()) });
+        let mut labels = vec![];
+        pair.foreach_ix_labeled(|l, _| labels.push(l.to_string()));
+        assert_eq!(labels, ["[0]", "[1]", "[2]"]);
+
+        let mut labels = vec![];
+        pair.1.foreach_ix_labeled(|l, _| labels.push(l));
+        assert_eq!(labels, [EdgeLabel::Field("ix")]);
+    }
+}
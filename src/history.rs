@@ -0,0 +1,345 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! A log of the operations on a region, which
+//! can be replayed to reproduce earlier states.
+
+use core::fmt::{Debug, Formatter};
+use alloc::vec::Vec;
+
+use crate::{GcAlgorithm, GcPolicy, GcTrigger, HasIx, Region};
+use crate::entry::Spot;
+
+/**
+ * An operation on a region, recorded by its
+ * [`History`](struct.History.html). Objects
+ * are identified by their position in the region at the time.
+ */
+pub enum HistoryEvent<T> {
+    /**
+     * An object was allocated, with the given value.
+     */
+    Alloc(T),
+    /**
+     * The objects allocated at these positions were made a group by
+     * [`Region::alloc_slice`](struct.Region.html#method.alloc_slice).
+     */
+    Group { start: usize, len: usize },
+    /**
+     * The object at a position was given a new value,
+     * as reported by [`Region::write_barrier`](struct.Region.html#method.write_barrier).
+     */
+    Write { index: usize, value: T },
+    /**
+     * The region was collected, with roots at these positions, in order.
+     */
+    Collect { roots: Vec<usize> },
+    /**
+     * The objects from a position which were reachable from the roots at
+     * these positions, or from the objects before it at the positions
+     * given by `from`, were moved down to it in place, in order, by
+     * [`Region::compact`](struct.Region.html#method.compact) or
+     * [`Region::minor_gc`](struct.Region.html#method.minor_gc).
+     */
+    Slide { boundary: usize, roots: Vec<usize>, from: Vec<usize> },
+    /**
+     * The objects at or after a position were freed,
+     * by [`Region::truncate_to`](struct.Region.html#method.truncate_to).
+     */
+    Truncate { len: usize },
+    /**
+     * The objects at these positions were freed in place,
+     * by [`Region::gc_partition`](struct.Region.html#method.gc_partition).
+     */
+    Free { positions: Vec<usize> },
+}
+impl <T: Debug> Debug for HistoryEvent<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            HistoryEvent::Alloc(t) => f.debug_tuple("Alloc").field(t).finish(),
+            HistoryEvent::Group { start, len } =>
+                f.debug_struct("Group").field("start", start).field("len", len).finish(),
+            HistoryEvent::Write { index, value } =>
+                f.debug_struct("Write").field("index", index).field("value", value).finish(),
+            HistoryEvent::Collect { roots } =>
+                f.debug_struct("Collect").field("roots", roots).finish(),
+            HistoryEvent::Slide { boundary, roots, from } =>
+                f.debug_struct("Slide").field("boundary", boundary).field("roots", roots).field("from", from).finish(),
+            HistoryEvent::Truncate { len } =>
+                f.debug_struct("Truncate").field("len", len).finish(),
+            HistoryEvent::Free { positions } =>
+                f.debug_struct("Free").field("positions", positions).finish(),
+        }
+    }
+}
+
+/**
+ * The recorded operations of a region, created by
+ * [`Region::record_history`](struct.Region.html#method.record_history).
+ *
+ * Values are recorded when they are allocated, and when
+ * [`write_barrier`](struct.Region.html#method.write_barrier)
+ * is called for them, so mutations which are not followed
+ * by a write barrier are not reproduced.
+ *
+ * Requires the feature "history".
+ */
+pub struct History<T> {
+    clone: fn(&T) -> T,
+    events: Vec<HistoryEvent<T>>,
+    #[cfg(feature = "debug-arena")]
+    start: (crate::nonce::Word, crate::nonce::Word),
+}
+
+// Replays never collect, except where the original region did
+struct Replay;
+impl GcPolicy for Replay {
+    fn on_full(&mut self, _len: usize, _capacity: usize, _additional: usize) -> GcAlgorithm {
+        GcAlgorithm::None
+    }
+    fn is_pending(&self, _len: usize, _capacity: usize) -> bool {
+        false
+    }
+}
+
+impl <T> History<T> {
+    /**
+     * The events recorded so far, in order.
+     */
+    #[inline]
+    pub fn events(&self) -> &[HistoryEvent<T>] {
+        &self.events
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+impl <T: 'static + HasIx<T>> History<T> {
+    /**
+     * Create a new region in the state of the recorded
+     * region after the first `n` events. Objects are
+     * at the same positions as they were in the recorded region.
+     * The new region has no roots, and does not record its history.
+     */
+    pub fn replay(&self, n: usize) -> Region<T> {
+        let mut r = Region::new();
+        r.set_gc_policy(Replay);
+        #[cfg(feature = "debug-arena")]
+        {
+            r.nonce = self.start.0;
+            r.generation = self.start.1;
+        }
+        for event in &self.events[..n] {
+            match event {
+                HistoryEvent::Alloc(t) => r.data.push(Spot::new((self.clone)(t))),
+                HistoryEvent::Group { start, len } => r.groups.push(*start..start + len),
+                HistoryEvent::Write { index, value } => {
+                    if let Some(e) = r.data[*index].get_mut() {
+                        *e.get_mut() = (self.clone)(value);
+                    }
+                },
+                HistoryEvent::Collect { roots } => {
+                    let roots: Vec<_> = roots.iter()
+                        .filter_map(|&i| r.root(r.ix_at(i)).ok())
+                        .collect();
+                    r.gc_with_capacity(r.data.len(), GcTrigger::Explicit);
+                    drop(roots);
+                },
+                HistoryEvent::Slide { boundary, roots, from } => {
+                    let roots: Vec<_> = roots.iter()
+                        .filter_map(|&i| r.root(r.ix_at(i)).ok())
+                        .collect();
+                    #[cfg(feature = "debug-arena")]
+                    r.keep_tenured(*boundary);
+                    r.slide(*boundary, from);
+                    drop(roots);
+                },
+                HistoryEvent::Truncate { len } => r.truncate_data(*len),
+                HistoryEvent::Free { positions } => r.free_in_place(positions),
+            }
+        }
+        r
+    }
+}
+
+impl <T: Clone> Region<T> {
+    /**
+     * Begin recording the operations on this region, so that its state
+     * at any later point can be reproduced with
+     * [`History::replay`](struct.History.html#method.replay).
+     * The objects currently in the region are recorded as allocations.
+     *
+     * This clones every allocated and written value, and is intended only
+     * for debugging. Requires the feature "history".
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * r.record_history();
+     * let a = r.alloc(|_|{()}).root();
+     * r.alloc(|_|{()});
+     * r.gc();
+     *
+     * let history = r.history().unwrap();
+     * assert_eq!(history.replay(2).len(), 2);
+     * assert_eq!(history.replay(history.len()).len(), 1);
+     * ```
+     */
+    pub fn record_history(&mut self) {
+        let events = self.data.iter()
+            .filter_map(|spot| Some(HistoryEvent::Alloc(spot.get()?.get().clone())))
+            .chain(self.groups.iter().map(|g| HistoryEvent::Group { start: g.start, len: g.len() }))
+            .collect();
+        self.history = Some(History {
+            clone: T::clone,
+            events,
+            #[cfg(feature = "debug-arena")]
+            start: (self.nonce, self.generation),
+        });
+    }
+}
+impl <T> Region<T> {
+    /**
+     * Stop recording operations, returning the history recorded so far.
+     */
+    pub fn stop_history(&mut self) -> Option<History<T>> {
+        self.history.take()
+    }
+    /**
+     * Get the history recorded so far, if recording.
+     */
+    pub fn history(&self) -> Option<&History<T>> {
+        self.history.as_ref()
+    }
+
+    // Record the allocation of every object from start
+    pub(crate) fn history_alloc(&mut self, start: usize) {
+        if let Some(ref mut history) = self.history {
+            for spot in &self.data[start..] {
+                if let Some(e) = spot.get() {
+                    history.events.push(HistoryEvent::Alloc((history.clone)(e.get())));
+                }
+            }
+        }
+    }
+    pub(crate) fn history_event(&mut self, event: HistoryEvent<T>) {
+        if let Some(ref mut history) = self.history {
+            history.events.push(event);
+        }
+    }
+    pub(crate) fn history_write(&mut self, index: usize) {
+        if let Some(ref mut history) = self.history {
+            if let Some(e) = self.data.get(index).and_then(Spot::get) {
+                let value = (history.clone)(e.get());
+                history.events.push(HistoryEvent::Write { index, value });
+            }
+        }
+    }
+    pub(crate) fn history_collect(&mut self) {
+        if self.history.is_none() { return }
+        let len = self.data.len();
+        let mut roots = Vec::new();
+        self.foreach_root(|ix| if ix.ix() < len { roots.push(ix.ix()) });
+        self.history_event(HistoryEvent::Collect { roots });
+    }
+    pub(crate) fn history_slide(&mut self, boundary: usize, from: &[usize]) {
+        if self.history.is_none() { return }
+        let len = self.data.len();
+        let mut roots = Vec::new();
+        self.foreach_root(|ix| if ix.ix() < len { roots.push(ix.ix()) });
+        self.history_event(HistoryEvent::Slide { boundary, roots, from: from.to_vec() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HasIx, HistoryEvent, Ix, Region};
+
+    #[derive(Clone, Debug)]
+    struct Node {
+        value: u32,
+        next: Option<Ix<Node>>,
+    }
+    impl HasIx<Node> for Node {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Node>)
+        {
+            self.next.foreach_ix(f);
+        }
+    }
+
+    fn values(r: &Region<Node>) -> Vec<u32> {
+        r.data.iter().map(|s| s.get().unwrap().get().value).collect()
+    }
+
+    #[test]
+    pub fn history_replays_states() {
+        let mut r = Region::new();
+        r.ensure(3);
+        r.record_history();
+        let a = r.alloc(|_| {Node { value: 1, next: None }}).root();
+        r.alloc(|_| {Node { value: 2, next: None }});
+        let c = r.alloc(|_| {Node { value: 3, next: None }}).ix();
+        a.get_mut(&mut r).next = Some(c);
+        r.write_barrier(a.ix());
+        r.gc();
+        a.get_mut(&mut r).value = 4;
+        r.write_barrier(a.ix());
+        let mid = values(&r);
+        r.alloc_slice(vec![Node { value: 5, next: None }, Node { value: 6, next: None }]);
+
+        let history = r.stop_history().unwrap();
+        assert_eq!(values(&history.replay(3)), vec![1, 2, 3]);
+        assert_eq!(values(&history.replay(6)), mid);
+        let end = history.replay(history.len());
+        assert_eq!(values(&end), values(&r));
+        assert_eq!(end.groups, r.groups);
+    }
+
+    #[test]
+    pub fn history_replays_minor_collections() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Node { value: 1, next: None }}).root();
+        r.minor_gc().unwrap();
+        r.record_history();
+        r.alloc(|_| {Node { value: 2, next: None }});
+        let c = r.alloc(|_| {Node { value: 3, next: None }}).ix();
+        a.get_mut(&mut r).next = Some(c);
+        r.write_barrier(a.ix());
+        r.minor_gc().unwrap();
+        assert_eq!(values(&r), vec![1, 3]);
+
+        let history = r.stop_history().unwrap();
+        assert!(matches!(history.events().last(), Some(HistoryEvent::Slide { boundary: 1, .. })));
+        let end = history.replay(history.len());
+        assert_eq!(values(&end), values(&r));
+        assert_eq!(end.data[0].get().unwrap().get().next.unwrap().identifier(), 1);
+    }
+
+    #[test]
+    pub fn history_replays_compaction() {
+        let mut r = Region::new();
+        r.ensure(3);
+        r.record_history();
+        r.alloc(|_| {Node { value: 1, next: None }});
+        let b = r.alloc(|_| {Node { value: 2, next: None }}).ix();
+        let _c = r.alloc(|_| {Node { value: 3, next: Some(b) }}).root();
+        assert_eq!(r.compact(), Ok(1));
+        assert_eq!(values(&r), vec![2, 3]);
+
+        let history = r.stop_history().unwrap();
+        let end = history.replay(history.len());
+        assert_eq!(values(&end), values(&r));
+        assert_eq!(end.data[1].get().unwrap().get().next.unwrap().identifier(), 0);
+    }
+}
@@ -0,0 +1,143 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::{Ref, RefCell, RefMut};
+use core::fmt::{Debug, Formatter};
+use core::marker::PhantomData;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::remap::{Forwarding, Remap};
+use crate::{Ix, Region};
+
+struct Column<H> {
+    values: Vec<H>,
+}
+impl <H: Default> Column<H> {
+    fn reach(&mut self, i: usize) {
+        if i >= self.values.len() {
+            self.values.resize_with(i + 1, H::default);
+        }
+    }
+}
+impl <H: Default> Remap for Column<H> {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        let mut values: Vec<H> = Vec::with_capacity(forwarding.len());
+        values.resize_with(forwarding.len(), H::default);
+        for (i, h) in self.values.drain(..).enumerate() {
+            if let Some(j) = forwarding.get(i) {
+                values[j] = h;
+            }
+        }
+        self.values = values;
+    }
+}
+
+/**
+ * A "hot" part of each object, stored in a tightly packed array
+ * alongside the region, such as the few fields read by a traversal,
+ * while the rest of each object remains in the region.
+ * The array is compacted along with the region by each collection,
+ * so each object keeps its hot part, in the same order.
+ *
+ * Objects whose hot part has not been set have the default value.
+ * The hot part is not traced, so it should not hold indices.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut depth = r.hot_column::<u32>();
+ *
+ * r.ensure(2);
+ * r.alloc(|_|{()});
+ * let root = r.alloc(|_|{()}).root();
+ * depth.set(root.ix(), 3);
+ * r.gc();
+ * assert_eq!(*depth.get(root.ix()), 3);
+ * assert_eq!(&*depth.values(), &[3]);
+ * ```
+ */
+pub struct HotColumn<T, H> {
+    column: Rc<RefCell<Column<H>>>,
+    _t: PhantomData<fn(&T)>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new hot column which is kept in
+     * step with the objects of this region.
+     */
+    pub fn hot_column<H: 'static + Default>(&mut self) -> HotColumn<T, H> {
+        let column = Rc::new(RefCell::new(Column { values: Vec::new() }));
+        self.remaps.push(Rc::downgrade(&column) as _);
+        HotColumn { column, _t: PhantomData }
+    }
+}
+
+impl <T, H: Default> HotColumn<T, H> {
+    /**
+     * Get the hot part of the object at an index. As with
+     * other side structures, the index must be valid for the region.
+     */
+    pub fn get(&self, ix: Ix<T>) -> Ref<'_, H> {
+        self.column.borrow_mut().reach(ix.ix());
+        Ref::map(self.column.borrow(), |c| &c.values[ix.ix()])
+    }
+    /**
+     * Get a mutable reference to the hot part of the object at an index.
+     */
+    pub fn get_mut(&mut self, ix: Ix<T>) -> RefMut<'_, H> {
+        let mut column = self.column.borrow_mut();
+        column.reach(ix.ix());
+        RefMut::map(column, |c| &mut c.values[ix.ix()])
+    }
+    /**
+     * Set the hot part of the object at an index.
+     */
+    pub fn set(&mut self, ix: Ix<T>, value: H) {
+        *self.get_mut(ix) = value;
+    }
+    /**
+     * Get the hot parts of every object, by position, as given by
+     * [`Ix::identifier`](struct.Ix.html#method.identifier).
+     * This is shorter than the region if its last objects were allocated
+     * since the last collection, and their hot parts have not been accessed.
+     */
+    pub fn values(&self) -> Ref<'_, [H]> {
+        Ref::map(self.column.borrow(), |c| &c.values[..])
+    }
+}
+impl <T, H: Debug> Debug for HotColumn<T, H> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_list().entries(self.column.borrow().values.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn hot_parts_follow_objects() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let mut hot = r.hot_column::<(bool, u8)>();
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let c = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        let d = r.alloc(|_| {Elem::new()}).root();
+        hot.set(a, (true, 1));
+        hot.set(b, (true, 2));
+        hot.get_mut(c.ix()).1 = 3;
+        r.gc();
+        assert_eq!(r.len(), 3);
+        assert_eq!(hot.values().len(), 3);
+        assert_eq!(*hot.get(c.ix()), (false, 3));
+        assert_eq!(*hot.get(c.get(&r).ix.unwrap()), (true, 1));
+        assert_eq!(*hot.get(d.ix()), (false, 0));
+    }
+}
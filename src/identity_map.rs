@@ -0,0 +1,169 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::collections::BTreeMap;
+use alloc::collections::btree_map;
+use core::fmt::{Debug, Formatter};
+use alloc::rc;
+
+use crate::Weak;
+use crate::types::IxCell;
+
+/**
+ * A map keyed by the identity of objects in a region.
+ *
+ * Keys are [`Weak`](struct.Weak.html) pointers, so unlike
+ * a map keyed by [`Ix`](struct.Ix.html), entries remain valid
+ * across collections, and do not keep their keys alive.
+ * Once a key's object has been collected, its entry can
+ * no longer be accessed, and will eventually be removed,
+ * or may be removed immediately with [`purge`](#method.purge).
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut map = gc::IdentityMap::new();
+ *
+ * let mut e = r.alloc(|_|{()});
+ * let (w, root) = (e.weak(), e.root());
+ * map.insert(&w, "memoized");
+ * r.gc();
+ * assert_eq!(map.get(&w), Some(&"memoized"));
+ *
+ * drop(root);
+ * r.gc();
+ * assert_eq!(map.get(&w), None);
+ * ```
+ */
+pub struct IdentityMap<T, V> {
+    map: BTreeMap<*const IxCell<T>, (rc::Weak<IxCell<T>>, V)>,
+    // The length after the last purge
+    purged_len: usize,
+}
+
+impl <T, V> IdentityMap<T, V> {
+    pub fn new() -> Self {
+        IdentityMap {
+            map: BTreeMap::new(),
+            purged_len: 0,
+        }
+    }
+
+    /**
+     * Insert a value for the given key, returning
+     * the previous value, if any.
+     * If the key's object has been collected,
+     * the value is not inserted, and is returned.
+     */
+    pub fn insert(&mut self, key: &Weak<T>, v: V) -> Option<V> {
+        if key.cell.strong_count() == 0 {
+            return Some(v)
+        }
+        if self.map.len() >= 2 * self.purged_len + 8 {
+            self.purge();
+        }
+        match self.map.entry(key.cell.as_ptr()) {
+            btree_map::Entry::Occupied(mut e) => Some(core::mem::replace(&mut e.get_mut().1, v)),
+            btree_map::Entry::Vacant(e) => {
+                e.insert((key.cell.clone(), v));
+                None
+            },
+        }
+    }
+    #[inline]
+    pub fn get(&self, key: &Weak<T>) -> Option<&V> {
+        if key.cell.strong_count() == 0 { return None }
+        self.map.get(&key.cell.as_ptr()).map(|e| &e.1)
+    }
+    #[inline]
+    pub fn get_mut(&mut self, key: &Weak<T>) -> Option<&mut V> {
+        if key.cell.strong_count() == 0 { return None }
+        self.map.get_mut(&key.cell.as_ptr()).map(|e| &mut e.1)
+    }
+    #[inline]
+    pub fn contains_key(&self, key: &Weak<T>) -> bool {
+        self.get(key).is_some()
+    }
+    /**
+     * Remove the value for the given key, if any.
+     */
+    pub fn remove(&mut self, key: &Weak<T>) -> Option<V> {
+        let (_, v) = self.map.remove(&key.cell.as_ptr())?;
+        if key.cell.strong_count() == 0 { None } else { Some(v) }
+    }
+    /**
+     * Remove every entry whose key has been collected.
+     */
+    pub fn purge(&mut self) {
+        self.map.retain(|_, (cell, _)| cell.strong_count() > 0);
+        self.purged_len = self.map.len();
+    }
+    /**
+     * Iterate over the entries whose keys have not been collected.
+     */
+    pub fn iter(&self) -> impl Iterator<Item=(Weak<T>, &V)> {
+        self.map.values().filter(|(cell, _)| cell.strong_count() > 0)
+            .map(|(cell, v)| (Weak { cell: cell.clone() }, v))
+    }
+    /**
+     * Return the number of entries, including any
+     * whose keys have been collected but not yet purged.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.purged_len = 0;
+    }
+}
+impl <T, V> Default for IdentityMap<T, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl <T, V: Debug> Debug for IdentityMap<T, V> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{IdentityMap, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn identity_map_follows_objects() {
+        let mut r = Region::new();
+        let mut map = IdentityMap::new();
+        r.ensure(3);
+        let mut e1 = r.alloc(|_| {Elem::new()});
+        let (w1, r1) = (e1.weak(), e1.root());
+        let w2 = r.alloc(|_| {Elem::new()}).weak();
+        let w3 = r.alloc(|_| {Elem::new()}).weak();
+        r1.get_mut(&mut r).ix = w3.ix();
+        assert_eq!(map.insert(&w1, 1), None);
+        assert_eq!(map.insert(&w2, 2), None);
+        assert_eq!(map.insert(&w3, 3), None);
+        assert_eq!(map.insert(&w3, 4), Some(3));
+
+        r.gc();
+        assert_eq!(map.get(&w1), Some(&1));
+        assert_eq!(map.get(&w2), None);
+        assert_eq!(map.get(&w3), Some(&4));
+        assert_eq!(map.iter().count(), 2);
+        assert_eq!(map.len(), 3);
+        map.purge();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.insert(&w2, 5), Some(5));
+    }
+}
@@ -0,0 +1,331 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::ops::Range;
+use alloc::vec::Vec;
+
+use crate::{Error, HasIx, Ix, Region, Root};
+use crate::entry::Spot;
+use crate::has_ix::foreach_edge;
+
+/**
+ * A relocatable copy of the objects of a region, created by
+ * [`Region::as_image`](struct.Region.html#method.as_image).
+ *
+ * The indices in an image are plain positions within it,
+ * with no reference to the region it was taken from, so an
+ * image can be stored, shipped and turned back into
+ * any number of regions with
+ * [`Region::from_image`](struct.Region.html#method.from_image),
+ * such as for a prebuilt standard library of a language runtime.
+ * If `T` is plain data, the objects can be written out and read back
+ * as bytes by the application.
+ */
+#[derive(Debug, Clone)]
+pub struct Image<T> {
+    objects: Vec<T>,
+    groups: Vec<Range<usize>>,
+}
+impl <T> Image<T> {
+    /**
+     * Create an image from objects whose indices are positions in `objects`,
+     * and the ranges of any groups created by
+     * [`alloc_slice`](struct.Region.html#method.alloc_slice).
+     */
+    pub fn from_raw_parts(objects: Vec<T>, groups: Vec<Range<usize>>) -> Self {
+        Image { objects, groups }
+    }
+    /**
+     * Convert this image into its objects and groups.
+     */
+    pub fn into_raw_parts(self) -> (Vec<T>, Vec<Range<usize>>) {
+        (self.objects, self.groups)
+    }
+    #[inline]
+    pub fn objects(&self) -> &[T] {
+        &self.objects
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl <T: 'static + HasIx<T> + Clone> Region<T> {
+    /**
+     * Copy every object in this region into an image, in order.
+     * The positions of objects which have been removed, such as by
+     * [`take`](#method.take), are closed up, so until then, indices
+     * into the region can be used as positions in the image with
+     * [`Ix::identifier`](struct.Ix.html#method.identifier).
+     * Edges to removed objects are out of range in the image,
+     * so [`from_image`](#method.from_image) fails for them.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let a = r.alloc(|_|{()}).root();
+     *
+     * let image = r.as_image();
+     * let copy = gc::Region::from_image(image.clone()).unwrap();
+     * assert_eq!(copy.len(), 1);
+     * ```
+     */
+    pub fn as_image(&self) -> Image<T> {
+        let mut len = 0;
+        let forward: Vec<Option<usize>> = self.data.iter()
+            .map(|spot| spot.get().map(|_| { len += 1; len - 1 }))
+            .collect();
+        // Strip the region from each index, leaving its position
+        let position = |i: usize| Ix::new(i,
+            #[cfg(feature = "debug-arena")]
+            0,
+            #[cfg(feature = "debug-arena")]
+            0);
+        let forward_ix = |ix: Ix<T>| forward.get(ix.ix()).copied().flatten().map(position);
+        let objects = self.data.iter().filter_map(|spot| {
+            let mut t = spot.get()?.get().clone();
+            foreach_edge(&mut t, |ix| *ix = forward_ix(*ix).unwrap_or_else(|| position(len)));
+            t.foreach_weak_ix(|weak| weak.forward(forward_ix));
+            Some(t)
+        }).collect();
+        // Groups with a removed position have been broken up
+        let groups = self.groups.iter().filter_map(|g| {
+            let start = forward.get(g.start).copied().flatten()?;
+            let last = forward.get(g.end.checked_sub(1)?).copied().flatten()?;
+            Some(start..last + 1).filter(|r| r.len() == g.len())
+        }).collect();
+        Image { objects, groups }
+    }
+
+    /**
+     * Copy every object in this region into a new region, at the same
+     * positions, along with a root of the new region for each root of
+     * this region, paired with a new handle to that root.
+     *
+     * As the objects keep their positions, an index into this region
+     * can be used with the copy by its [`Ix::identifier`](struct.Ix.html#method.identifier),
+     * such as to keep a snapshot of the state of an interpreter.
+     * Indices which are invalid for this region are also invalid in the copy.
+     *
+     * Root sets, side structures such as [`IdentityMap`](struct.IdentityMap.html)s,
+     * the policy and other settings are not copied.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * #[derive(Clone)]
+     * struct Counter(u32);
+     * gc::impl_has_ix!(Counter {});
+     *
+     * let mut r = gc::Region::new();
+     * let a = r.alloc(|_| Counter(1)).root();
+     *
+     * let (mut copy, roots) = r.clone_with_roots();
+     * let (original, root) = &roots[0];
+     * assert!(original.ptr_eq(&a));
+     * root.get_mut(&mut copy).0 = 2;
+     * assert_eq!((a.get(&r).0, root.get(&copy).0), (1, 2));
+     * ```
+     */
+    #[allow(clippy::type_complexity)]
+    pub fn clone_with_roots(&self) -> (Region<T>, Vec<(Root<T>, Root<T>)>) {
+        let mut region = Region::new();
+        region.data.reserve(self.data.len());
+        let current = self.ix_at(0);
+        let copy = region.ix_at(0);
+        let dead = region.ix_at(usize::MAX);
+        let forward = |ix: Ix<T>| ix.check_current(current, #[cfg(feature = "debug-arena")] &self.kept).ok()
+            .map(|_| copy.offset(ix.ix()));
+        region.data.extend(self.data.iter().map(|spot| match spot.get() {
+            Some(e) => {
+                let mut t = e.get().clone();
+                foreach_edge(&mut t, |ix| *ix = forward(*ix).unwrap_or(dead));
+                t.foreach_weak_ix(|weak| weak.forward(forward));
+                Spot::new(t)
+            },
+            None => Spot::broken_heart(dead),
+        }));
+        region.groups = self.groups.clone();
+        #[cfg(feature = "shadow-heap")]
+        region.shadow_alloc();
+        #[cfg(feature = "history")]
+        region.history_alloc(0);
+
+        let roots = self.roots.handles().into_iter().map(|root| {
+            let new = region.roots.insert(copy.offset(root.get_ix().ix()));
+            (root, new)
+        }).collect();
+        (region, roots)
+    }
+}
+
+/**
+ * Copy every object into a new region, as with
+ * [`Region::clone_with_roots`](struct.Region.html#method.clone_with_roots).
+ * The copy has no roots, so its objects are freed by its
+ * first collection unless roots are first created for them.
+ */
+impl <T: 'static + HasIx<T> + Clone> Clone for Region<T> {
+    fn clone(&self) -> Self {
+        self.clone_with_roots().0
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Create a region from an image, with each object at its
+     * position in the image. An error is returned if any index
+     * is out of range.
+     */
+    pub fn from_image(image: Image<T>) -> Result<Region<T>, Error> {
+        let mut r = Region::new();
+        r.ensure(image.objects.len());
+        r.append_positional(image.objects, image.groups)?;
+        Ok(r)
+    }
+
+    // Push objects whose indices are positions relative to the first,
+    // returning the position of the first
+    pub(crate) fn append_positional(&mut self, objects: Vec<T>, groups: Vec<Range<usize>>)
+        -> Result<usize, Error>
+    {
+        self.append_positional_with(objects, groups, |_| None)
+    }
+    // Push objects as with append_positional, replacing each edge
+    // past the objects with the index given by external
+    pub(crate) fn append_positional_with<F>(&mut self, objects: Vec<T>, groups: Vec<Range<usize>>, external: F)
+        -> Result<usize, Error> where
+        F: Fn(usize) -> Option<Ix<T>>
+    {
+        let start = self.data.len();
+        let base = self.ix_at(start);
+        let len = objects.len();
+        let mut objects = objects;
+        let mut valid = true;
+        for t in &mut objects {
+            foreach_edge(t, |ix| match ix.ix() {
+                p if p < len => *ix = base.offset(p),
+                p => match external(p) {
+                    Some(e) => *ix = e,
+                    None => valid = false,
+                },
+            });
+            t.foreach_weak_ix(|weak| weak.forward(|ix| {
+                Some(base.offset(ix.ix())).filter(|_| ix.ix() < len)
+            }));
+        }
+        if !valid || groups.iter().any(|g| g.end > len) {
+            Err(Error::Indeterminable)?;
+        }
+        self.data.extend(objects.into_iter().map(Spot::new));
+        self.groups.extend(groups.into_iter().filter(|g| g.len() > 1).map(|g| start + g.start..start + g.end));
+        self.groups.sort_unstable_by_key(|g| g.start);
+        #[cfg(feature = "std")]
+        self.index_keys(start);
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_alloc();
+        #[cfg(feature = "history")]
+        self.history_alloc(start);
+        Ok(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Image, Ix, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn images_are_relocatable() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        r.alloc_slice(vec![Elem::new(), Elem { ix: Some(a.ix()) }]);
+        let d = r.alloc(|_| {Elem::new()}).ix();
+        a.get_mut(&mut r).ix = Some(d);
+
+        let image = r.as_image();
+        assert_eq!(image.len(), 4);
+        assert_eq!(image.objects()[2].ix.unwrap().identifier(), 0);
+        let mut copy = Region::from_image(image.clone()).unwrap();
+        assert_eq!(copy.len(), 4);
+
+        // Embedding the image after other objects shifts its indices
+        let mut other = Region::new();
+        other.ensure(5);
+        other.alloc(|_| {Elem::new()});
+        let (objects, groups) = image.into_raw_parts();
+        assert_eq!(groups, r.groups);
+        let start = other.append_positional(objects, groups).unwrap();
+        assert_eq!(start, 1);
+        let first = other.ix_at(3).get(&other).ix.unwrap();
+        assert_eq!(first.identifier(), 1);
+        copy.gc();
+
+        let bad = Image::from_raw_parts(vec![Elem { ix: Some(Ix::new(7,
+            #[cfg(feature = "debug-arena")] 0,
+            #[cfg(feature = "debug-arena")] 0)) }], vec![]);
+        assert!(Region::from_image(bad).is_err());
+    }
+
+    #[test]
+    pub fn images_close_up_removed_objects() {
+        let mut r = Region::new();
+        r.ensure(5);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let slice = r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        let c = r.alloc(|_| {Elem::new()}).ix();
+        let _b = r.alloc(|_| {Elem { ix: Some(c) }}).root();
+        r.take(a).unwrap();
+
+        let image = r.as_image();
+        assert_eq!(image.len(), 4);
+        assert_eq!(image.objects()[3].ix.unwrap().identifier(), 2);
+        let (objects, groups) = image.clone().into_raw_parts();
+        assert_eq!(groups, vec![0..2]);
+        let copy = Region::from_image(Image::from_raw_parts(objects, groups)).unwrap();
+        let from_b = copy.ix_at(3).get(&copy).ix.unwrap();
+        assert_eq!(from_b.identifier(), 2);
+        assert!(from_b.get(&copy).ix.is_none());
+
+        // Edges to removed objects are out of range
+        slice.get(0).unwrap().get_mut(&mut r).ix = Some(c);
+        r.take(c).unwrap();
+        assert!(Region::from_image(r.as_image()).is_err());
+    }
+
+    #[test]
+    pub fn regions_are_cloned_with_their_roots() {
+        let mut r = Region::new();
+        r.ensure(4);
+        r.alloc(|_| {Elem::new()});
+        let slice = r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        let a = r.alloc(|_| {Elem { ix: slice.get(1) }}).root();
+        let w = a.downgrade(&mut r);
+        slice.get(1).unwrap().get_mut(&mut r).ix = Some(a.ix());
+
+        let (mut copy, roots) = r.clone_with_roots();
+        assert_eq!(copy.len(), 4);
+        assert_eq!(roots.len(), 1);
+        let (original, root) = &roots[0];
+        assert!(original.ptr_eq(&a));
+        let member = root.get(&copy).ix.unwrap();
+        assert_eq!(member.get(&copy).ix.unwrap().identifier(), root.ix().identifier());
+        // Weak pointers belong to the region they were created from
+        assert!(w.try_get(&r).is_ok());
+
+        copy.gc();
+        assert_eq!(copy.len(), 3);
+        assert_eq!(r.len(), 4);
+        root.get_mut(&mut copy).ix = None;
+        assert!(a.get(&r).ix.is_some());
+        assert_eq!(Region::clone(&r).len(), 4);
+    }
+}
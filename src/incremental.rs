@@ -0,0 +1,228 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc::Rc;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+use alloc::{vec, vec::Vec};
+
+use crate::partition::group_of;
+use crate::remap::{Forwarding, Remap};
+use crate::{HasIx, Region};
+#[cfg(feature = "std")]
+use crate::has_ix::foreach_edge;
+
+// The number of objects scanned between checks of the clock
+#[cfg(feature = "std")]
+const STEP_OBJECTS: usize = 64;
+
+// An incremental marking in progress. Objects before len are
+// white until marked, gray while in the stack, and then black.
+// Objects allocated since the marking began are considered live.
+pub(crate) struct Marking {
+    len: usize,
+    marked: Vec<bool>,
+    gray: Vec<usize>,
+    // Black objects which have been written to since they were scanned
+    dirty: Vec<usize>,
+}
+impl Marking {
+    fn mark(&mut self, groups: &[core::ops::Range<usize>], p: usize) {
+        if p >= self.len || self.marked[p] { return }
+        for q in group_of(groups, p) {
+            if !self.marked[q] {
+                self.marked[q] = true;
+                self.gray.push(q);
+            }
+        }
+    }
+}
+// Any collection which changes positions does the work of the
+// marking itself, so the marking is abandoned
+pub(crate) struct Incremental {
+    marking: Option<Marking>,
+}
+impl Remap for Incremental {
+    fn remap(&mut self, _forwarding: &Forwarding) {
+        self.marking = None;
+    }
+}
+
+impl <T> Region<T> {
+    fn incremental(&mut self) -> Rc<RefCell<Incremental>> {
+        if let Some(ref inc) = self.incremental {
+            return inc.clone()
+        }
+        let inc = Rc::new(RefCell::new(Incremental { marking: None }));
+        self.remaps.push(Rc::downgrade(&inc) as _);
+        self.incremental = Some(inc.clone());
+        inc
+    }
+    // Record a write to the object at a position
+    pub(crate) fn incremental_write(&mut self, index: usize) {
+        if let Some(ref inc) = self.incremental {
+            if let Some(ref mut m) = inc.borrow_mut().marking {
+                if index < m.len && m.marked[index] {
+                    m.dirty.push(index);
+                }
+            }
+        }
+    }
+    /**
+     * Returns true if an incremental collection has
+     * been started, and has not yet finished.
+     */
+    pub fn gc_incremental_active(&self) -> bool {
+        self.incremental.as_ref().is_some_and(|inc| inc.borrow().marking.is_some())
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Begin an incremental collection, if one is not already
+     * active, which is performed by [`gc_step`](#method.gc_step).
+     */
+    pub fn gc_incremental(&mut self) {
+        let len = self.data.len();
+        let inc = self.incremental();
+        let mut inc = inc.borrow_mut();
+        if inc.marking.is_some() { return }
+        let mut m = Marking { len, marked: vec![false; len], gray: Vec::new(), dirty: Vec::new() };
+        self.foreach_root(|ix| m.mark(&self.groups, ix.ix()));
+        inc.marking = Some(m);
+    }
+
+    /**
+     * Perform part of an incremental collection, starting one if
+     * none is active, for about as long as `budget`, so that the
+     * work of a collection can be spread across frames.
+     *
+     * An incremental collection finds the unreachable objects
+     * a few at a time, and then drops them in place. Unlike other
+     * collections, objects are not moved, so indices remain
+     * valid, and the space of dropped objects is only reclaimed
+     * by the next full collection. The final step also
+     * traces any objects allocated, rooted or written to since
+     * the collection began, which may exceed the budget.
+     *
+     * While a collection is active, every object which gains an edge
+     * after it was allocated must be reported with
+     * [`write_barrier`](#method.write_barrier),
+     * otherwise the object it points to may be dropped.
+     * A full collection abandons an active incremental collection.
+     *
+     * Returns true if a collection was finished by this step.
+     * No work is done if the region is pinned.
+     *
+     * This requires the feature "std", which is enabled by default.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * use std::time::Duration;
+     * let mut r = gc::Region::new();
+     * r.ensure(100);
+     * let root = r.alloc(|_|{()}).root();
+     * for _ in 0..99 {
+     *     r.alloc(|_|{()});
+     * }
+     * while !r.gc_step(Duration::from_micros(100)) {
+     *     // the rest of the frame
+     * }
+     * assert!(root.try_get(&r).is_ok());
+     * // The space is reclaimed by the next collection
+     * assert_eq!(r.len(), 100);
+     * r.gc();
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    #[cfg(feature = "std")]
+    pub fn gc_step(&mut self, budget: Duration) -> bool {
+        if self.pins.is_pinned() { return false }
+        let deadline = Instant::now() + budget;
+        self.gc_incremental();
+        let inc = self.incremental();
+        let mut inc = inc.borrow_mut();
+        let m = inc.marking.as_mut().unwrap();
+        let mut scanned = 0;
+        while let Some(i) = m.gray.pop() {
+            self.scan(m, i);
+            scanned += 1;
+            if scanned % STEP_OBJECTS == 0 && Instant::now() >= deadline {
+                return false
+            }
+        }
+
+        // Finish by tracing from everything which has changed
+        let len = self.data.len();
+        self.foreach_root(|ix| m.mark(&self.groups, ix.ix()));
+        for i in core::mem::take(&mut m.dirty).into_iter().chain(m.len..len) {
+            self.scan(m, i);
+        }
+        while let Some(i) = m.gray.pop() {
+            self.scan(m, i);
+        }
+        let m = inc.marking.take().unwrap();
+        drop(inc);
+        let freed: Vec<usize> = (0..m.len)
+            .filter(|&i| !m.marked[i] && self.data[i].get().is_some())
+            .collect();
+        #[cfg(feature = "history")]
+        self.history_event(crate::HistoryEvent::Free { positions: freed.clone() });
+        self.free_in_place(&freed);
+        true
+    }
+
+    // Mark every object pointed to by the object at a position
+    #[cfg(feature = "std")]
+    fn scan(&mut self, m: &mut Marking, i: usize) {
+        let groups = &self.groups;
+        if let Some(e) = self.data[i].get_mut() {
+            foreach_edge(e.get_mut(), |ix| m.mark(groups, ix.ix()));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use core::time::Duration;
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn incremental_collections_follow_writes() {
+        let mut r = Region::new();
+        r.ensure(110);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        let mut prev = r.alloc(|_| {Elem::new()}).ix();
+        for _ in 0..99 {
+            prev = r.alloc(|_| {Elem { ix: Some(prev) }}).ix();
+        }
+        let list = r.alloc(|_| {Elem { ix: Some(prev) }}).root();
+        let c = r.alloc(|_| {Elem::new()}).root();
+        let garbage = r.alloc(|_| {Elem::new()}).weak();
+
+        // c is scanned first, and then part of the list
+        assert!(!r.gc_step(Duration::ZERO));
+        assert!(r.gc_incremental_active());
+        c.get_mut(&mut r).ix = Some(a);
+        r.write_barrier(c.ix());
+        b.get_mut(&mut r).ix = None;
+        let d = r.alloc(|_| {Elem { ix: Some(a) }}).ix();
+
+        assert!(r.gc_step(Duration::from_secs(10)));
+        assert!(!r.gc_incremental_active());
+        // Dropped in place
+        assert_eq!(r.len(), 106);
+        assert!(garbage.try_get(&r).is_err());
+        assert!(a.try_get(&r).is_ok());
+        assert!(d.try_get(&r).is_ok());
+        assert!(list.try_get(&r).is_ok());
+        r.gc();
+        assert_eq!(r.len(), 104);
+    }
+}
@@ -0,0 +1,241 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::convert::TryFrom;
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::{Error, HasIx, Ix, Region};
+
+// The position of an index which points nowhere,
+// such as one for an object which has been removed
+const DEAD: u32 = u32::MAX;
+
+/**
+ * A compact raw index, which holds its position in 32 bits,
+ * for objects which store many edges.
+ *
+ * Without the feature "debug-arena", this is half the size of
+ * an [`Ix`](struct.Ix.html). It is valid exactly when the index
+ * it was created from is valid, and is updated by collections when
+ * it is exposed by [`HasIx::foreach_ix32`](trait.HasIx.html#method.foreach_ix32).
+ *
+ * Positions of at least [`MAX_ENTRIES`](#associatedconstant.MAX_ENTRIES)
+ * cannot be held, so a region holding compact indices should be
+ * bounded with [`Region::set_compact_indices`](struct.Region.html#method.set_compact_indices),
+ * after which allocations which would exceed that bound fail with
+ * [`Error::IndexOverflow`](enum.Error.html#variant.IndexOverflow).
+ * Otherwise, a collection which moves an object
+ * past the bound panics while updating a compact index to it.
+ *
+ * This requires the feature "ix32".
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use std::convert::TryFrom;
+ *
+ * struct Node { edges: Vec<gc::Ix32<Node>> }
+ * impl gc::HasIx<Node> for Node {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Node>)
+ *     { }
+ *     fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, f: F) where
+ *         F: FnMut(&'b mut gc::Ix32<Node>)
+ *     {
+ *         self.edges.foreach_ix32(f)
+ *     }
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * r.set_compact_indices(true);
+ * r.ensure(3);
+ * let leaf = r.alloc(|_| Node { edges: Vec::new() }).ix();
+ * r.alloc(|_| Node { edges: Vec::new() });
+ * let edge = gc::Ix32::try_from(leaf).unwrap();
+ * let root = r.alloc(|_| Node { edges: vec![edge] }).root();
+ * r.gc();
+ * assert_eq!(r.len(), 2);
+ * assert!(root.get(&r).edges[0].try_get(&r).is_ok());
+ * ```
+ */
+pub struct Ix32<T> {
+    ix: u32,
+    _t: PhantomData<*mut T>,
+    #[cfg(feature = "debug-arena")]
+    nonce: crate::nonce::Word,
+    #[cfg(feature = "debug-arena")]
+    generation: crate::nonce::Word,
+}
+impl <T> fmt::Debug for Ix32<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.ix.fmt(f)
+    }
+}
+impl <T> Clone for Ix32<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl <T> Copy for Ix32<T> {}
+unsafe impl <T> Send for Ix32<T> {}
+unsafe impl <T> Sync for Ix32<T> {}
+
+impl <T> Ix32<T> {
+    /**
+     * The number of entries whose positions can be held
+     * by a compact index.
+     */
+    pub const MAX_ENTRIES: usize = DEAD as usize;
+
+    /**
+     * The full index for this compact index.
+     */
+    #[inline]
+    pub fn ix(self) -> Ix<T> {
+        let ix = if self.ix == DEAD { usize::MAX } else { self.ix as usize };
+        Ix::new(ix,
+            #[cfg(feature = "debug-arena")]
+            self.nonce,
+            #[cfg(feature = "debug-arena")]
+            self.generation)
+    }
+    /**
+     * Get an identifier for this index, which is
+     * the same as that of its full index.
+     */
+    #[inline]
+    pub fn identifier(self) -> usize {
+        self.ix().identifier()
+    }
+    #[inline]
+    #[track_caller]
+    pub fn get(self, region: &Region<T>) -> &T {
+        self.ix().get(region)
+    }
+    #[inline]
+    #[track_caller]
+    pub fn get_mut(self, region: &mut Region<T>) -> &mut T {
+        self.ix().get_mut(region)
+    }
+    #[inline]
+    pub fn try_get(self, region: &Region<T>) -> Result<&T, Error> {
+        self.ix().try_get(region)
+    }
+    #[inline]
+    pub fn try_get_mut(self, region: &mut Region<T>) -> Result<&mut T, Error> {
+        self.ix().try_get_mut(region)
+    }
+
+    // Update this index through its full index
+    #[track_caller]
+    pub(crate) fn with_ix<F: FnOnce(&mut Ix<T>)>(&mut self, f: F) {
+        let mut ix = self.ix();
+        f(&mut ix);
+        *self = match Ix32::try_from(ix) {
+            Ok(ix32) => ix32,
+            Err(e) => panic!("Ix32: {} at index {}", e, ix.describe()),
+        };
+    }
+}
+
+/**
+ * Fails with [`Error::IndexOverflow`](enum.Error.html#variant.IndexOverflow)
+ * if the position of the index is at least
+ * [`Ix32::MAX_ENTRIES`](struct.Ix32.html#associatedconstant.MAX_ENTRIES).
+ */
+impl <T> TryFrom<Ix<T>> for Ix32<T> {
+    type Error = Error;
+    fn try_from(ix: Ix<T>) -> Result<Self, Error> {
+        let position = match ix.ix() {
+            usize::MAX => DEAD,
+            i if i < Self::MAX_ENTRIES => i as u32,
+            _ => Err(Error::IndexOverflow)?,
+        };
+        Ok(Ix32 { ix: position, _t: PhantomData,
+            #[cfg(feature = "debug-arena")]
+            nonce: ix.nonce,
+            #[cfg(feature = "debug-arena")]
+            generation: ix.generation,
+        })
+    }
+}
+impl <T> From<Ix32<T>> for Ix<T> {
+    #[inline]
+    fn from(ix: Ix32<T>) -> Self {
+        ix.ix()
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Bound the number of entries in this region by
+     * [`Ix32::MAX_ENTRIES`](struct.Ix32.html#associatedconstant.MAX_ENTRIES),
+     * so that the position of every object fits in a compact index.
+     * An allocation which would exceed the bound, even after a collection,
+     * fails with [`Error::IndexOverflow`](enum.Error.html#variant.IndexOverflow)
+     * (or panics, for [`alloc`](#method.alloc)).
+     *
+     * This requires the feature "ix32".
+     */
+    pub fn set_compact_indices(&mut self, enabled: bool) {
+        self.compact_indices = enabled;
+    }
+}
+
+impl <T : 'static> HasIx<T> for Ix32<T> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    { }
+    fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix32<T>)
+    {
+        f(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::TryFrom;
+    use crate::{Error, HasIx, Ix, Ix32, Region};
+
+    struct Node {
+        edges: Vec<Ix32<Node>>,
+    }
+    impl HasIx<Node> for Node {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+            F: FnMut(&'b mut Ix<Node>)
+        { }
+        fn foreach_ix32<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix32<Node>)
+        {
+            self.edges.foreach_ix32(f)
+        }
+    }
+
+    #[test]
+    pub fn compact_indices_are_traced() {
+        #[cfg(not(feature = "debug-arena"))]
+        assert!(core::mem::size_of::<Option<Ix32<Node>>>() <= core::mem::size_of::<Ix<Node>>());
+        let mut r = Region::new();
+        r.set_compact_indices(true);
+        assert_eq!(r.max_entries(), Ix32::<Node>::MAX_ENTRIES);
+        r.ensure(4);
+        r.alloc(|_| Node { edges: Vec::new() });
+        let a = r.alloc(|_| Node { edges: Vec::new() }).ix();
+        let b = r.alloc(|_| Node { edges: vec![Ix32::try_from(a).unwrap()] }).ix();
+        let root = r.alloc(|_| Node { edges: Vec::new() }).root();
+        root.get_mut(&mut r).edges = vec![Ix32::try_from(a).unwrap(), Ix32::try_from(b).unwrap()];
+        r.gc();
+        assert_eq!(r.len(), 3);
+        assert_eq!(r.count_reachable(), 3);
+        let edges = root.get(&r).edges.clone();
+        assert_eq!(edges[1].get(&r).edges[0].identifier(), edges[0].identifier());
+
+        if let Ok(far) = usize::try_from(u64::from(u32::MAX) + 1) {
+            assert_eq!(Ix32::try_from(r.ix_at(far)).err(), Some(Error::IndexOverflow));
+        }
+    }
+}
@@ -0,0 +1,230 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::fmt::{Debug, Formatter};
+use core::ops::Range;
+use alloc::vec::Vec;
+
+use crate::{Error, HasIx, Ix, Region};
+use crate::entry::Spot;
+
+/**
+ * A raw index to a group of objects which are allocated contiguously,
+ * created by [`Region::alloc_slice`](struct.Region.html#method.alloc_slice).
+ *
+ * The collector keeps the members of a group contiguous and in order,
+ * and keeps every member alive as long as any member is reachable.
+ * As with [`Ix`](struct.Ix.html), a range is invalidated by collections,
+ * unless it is exposed through [`HasIx`](trait.HasIx.html),
+ * which it implements.
+ */
+pub struct IxRange<T> {
+    start: Ix<T>,
+    len: usize,
+}
+impl <T> Clone for IxRange<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl <T> Copy for IxRange<T> {}
+impl <T> Debug for IxRange<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        (self.start.ix()..self.start.ix() + self.len).fmt(f)
+    }
+}
+
+impl <T> IxRange<T> {
+    /**
+     * Get the index of the ith member of this group.
+     */
+    #[inline]
+    pub fn get(self, i: usize) -> Option<Ix<T>> {
+        if i < self.len { Some(self.start.offset(i)) } else { None }
+    }
+    #[inline]
+    pub fn len(self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.len == 0
+    }
+    /**
+     * Iterate over the indices of every member, in order.
+     */
+    pub fn iter(self) -> impl Iterator<Item=Ix<T>> {
+        (0..self.len).map(move |i| self.start.offset(i))
+    }
+}
+impl <T : 'static> HasIx<T> for IxRange<T> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        if self.len > 0 {
+            f(&mut self.start)
+        }
+    }
+}
+
+// Get the positions of the surviving groups after a collection from src
+pub(crate) fn forward_groups<T>(src: &[Spot<T>], groups: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut new: Vec<Range<usize>> = groups.iter().filter_map(|g| {
+        let start = src[g.start].forwarded()?.ix();
+        Some(start..start + g.len())
+    }).collect();
+    new.sort_unstable_by_key(|g| g.start);
+    new
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Allocate every item in adjacent positions, returning the range of their indices.
+     *
+     * This may trigger a garbage collection and invalidate raw indices.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let range = r.alloc_slice(vec![(), (), ()]);
+     * let mut roots = r.root_set();
+     * roots.insert(range.get(1).unwrap());
+     *
+     * r.gc();
+     * // The whole group is kept alive by a single member
+     * assert_eq!(r.len(), 3);
+     * ```
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn alloc_slice<I>(&mut self, items: I) -> IxRange<T> where
+        I: IntoIterator<Item=T>
+    {
+        match self.try_alloc_slice(items) {
+            Ok(range) => range,
+            Err(e) => panic!("Region::alloc_slice: {}", e),
+        }
+    }
+    /**
+     * Allocate every item in adjacent positions, as with
+     * [`alloc_slice`](#method.alloc_slice), but return an error
+     * rather than panicking if the allocation is not permitted.
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn try_alloc_slice<I>(&mut self, items: I) -> Result<IxRange<T>, Error> where
+        I: IntoIterator<Item=T>
+    {
+        let start = self.try_extend(items.into_iter().collect())?;
+        let len = self.data.len() - start;
+        if len > 1 {
+            self.groups.push(start..start + len);
+            #[cfg(feature = "history")]
+            self.history_event(crate::HistoryEvent::Group { start, len });
+        }
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_alloc();
+        Ok(IxRange { start: self.ix_at(start), len })
+    }
+    /**
+     * Allocate every item, such as when loading a graph,
+     * reserving space once rather than for each item. Unlike
+     * [`alloc_slice`](#method.alloc_slice), each object is
+     * kept alive only by its own edges and roots.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let ixs = r.alloc_extend((0..100).map(|_| ()));
+     * assert_eq!(ixs.len(), 100);
+     * let root = r.root(ixs[42]).unwrap();
+     * r.gc();
+     * assert_eq!(r.len(), 1);
+     * assert!(root.try_get(&r).is_ok());
+     * ```
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn alloc_extend<I>(&mut self, items: I) -> Vec<Ix<T>> where
+        I: IntoIterator<Item=T>
+    {
+        match self.try_alloc_extend(items) {
+            Ok(ixs) => ixs,
+            Err(e) => panic!("Region::alloc_extend: {}", e),
+        }
+    }
+    /**
+     * Allocate every item, as with [`alloc_extend`](#method.alloc_extend),
+     * but return an error rather than panicking if the allocation
+     * is not permitted, in which case no item is allocated.
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn try_alloc_extend<I>(&mut self, items: I) -> Result<Vec<Ix<T>>, Error> where
+        I: IntoIterator<Item=T>
+    {
+        let start = self.try_extend(items.into_iter().collect())?;
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_alloc();
+        Ok((start..self.data.len()).map(|i| self.ix_at(i)).collect())
+    }
+
+    // Push every item, returning the position of the first
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub(crate) fn try_extend(&mut self, items: Vec<T>) -> Result<usize, Error> {
+        let len = items.len();
+        self.try_ensure(len)?;
+        self.consume_fuel(len)?;
+        let start = self.data.len();
+        self.data.extend(items.into_iter().map(Spot::new));
+        #[cfg(feature = "std")]
+        self.index_keys(start);
+        #[cfg(feature = "history")]
+        self.history_alloc(start);
+        #[cfg(feature = "alloc-sites")]
+        self.record_alloc_site(start);
+        Ok(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn groups_stay_contiguous() {
+        let mut r = Region::new();
+        r.ensure(6);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let range = r.alloc_slice((0..4).map(|_| Elem::new()));
+        let b = r.alloc(|_| {Elem::new()}).root();
+        // Reach the group through its third member, after b
+        b.get_mut(&mut r).ix = range.get(2);
+        drop(a);
+
+        r.gc();
+        // The members before the third are copied first, in order
+        assert_eq!(b.get(&r).ix.unwrap().identifier(), 3);
+        assert_eq!(r.len(), 5);
+        r.alloc(|_| {Elem::new()});
+        r.gc();
+        assert_eq!(r.len(), 5);
+        assert_eq!(b.get(&r).ix.unwrap().identifier(), 3);
+    }
+
+    #[test]
+    pub fn extended_objects_are_independent() {
+        let mut r = Region::new();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let ixs = r.alloc_extend((0..4).map(|_| Elem::new()));
+        let ids: Vec<usize> = ixs.iter().map(|ix| ix.identifier()).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        a.get_mut(&mut r).ix = Some(ixs[2]);
+        r.gc();
+        assert_eq!(r.len(), 2);
+
+        r.set_fuel(2, crate::FuelUnit::Allocation);
+        assert!(r.try_alloc_extend((0..3).map(|_| Elem::new())).is_err());
+        assert_eq!(r.len(), 2);
+    }
+}
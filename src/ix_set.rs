@@ -0,0 +1,175 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::fmt::{Debug, Formatter};
+use core::marker::PhantomData;
+use alloc::{vec, vec::Vec};
+
+use crate::{Ix, Region};
+use crate::remap::{Forwarding, Remap};
+
+const BITS: usize = 64;
+
+#[derive(Default)]
+pub(crate) struct Bits {
+    words: Vec<u64>,
+}
+impl Bits {
+    #[inline]
+    pub(crate) fn get(&self, i: usize) -> bool {
+        self.words.get(i / BITS).is_some_and(|w| w & (1 << (i % BITS)) != 0)
+    }
+    // Set a bit, returning its previous value
+    pub(crate) fn set(&mut self, i: usize, b: bool) -> bool {
+        let word = i / BITS;
+        if word >= self.words.len() {
+            if !b { return false }
+            self.words.resize(word + 1, 0);
+        }
+        let mask = 1 << (i % BITS);
+        let old = self.words[word] & mask != 0;
+        if b { self.words[word] |= mask } else { self.words[word] &= !mask }
+        old
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item=usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(n, &w)| {
+            (0..BITS).filter(move |b| w & (1 << b) != 0).map(move |b| n * BITS + b)
+        })
+    }
+}
+impl Remap for Bits {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        let mut new = Bits { words: vec![0; forwarding.len().div_ceil(BITS)] };
+        for i in self.iter() {
+            if let Some(j) = forwarding.get(i) {
+                new.set(j, true);
+            }
+        }
+        *self = new;
+    }
+}
+
+/**
+ * A set of objects in a region, stored as one bit per object.
+ *
+ * The set is registered with the region, and
+ * is updated by each collection, so it can be kept
+ * across collections, such as for visited or dirty sets.
+ * Unlike a [`RootSet`](struct.RootSet.html), it does not keep
+ * objects alive: collected objects are removed from the set.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut visited = r.ix_set();
+ *
+ * let a = r.alloc(|_|{()}).root();
+ * let b = r.alloc(|_|{()}).root();
+ * visited.insert(b.ix());
+ *
+ * drop(a);
+ * r.gc();
+ * assert!(visited.contains(b.ix()));
+ * assert_eq!(visited.len(), 1);
+ * ```
+ */
+pub struct IxSet<T> {
+    bits: Rc<RefCell<Bits>>,
+    _t: PhantomData<Ix<T>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new, empty, index set which is registered with this region.
+     */
+    pub fn ix_set(&mut self) -> IxSet<T> {
+        let bits = Rc::new(RefCell::new(Bits::default()));
+        self.remaps.push(Rc::downgrade(&bits) as _);
+        IxSet { bits, _t: PhantomData }
+    }
+}
+
+impl <T> IxSet<T> {
+    /**
+     * Add an index to this set, returning true if it was not already present.
+     * The index must be valid for the region this set was created from.
+     */
+    #[inline]
+    pub fn insert(&mut self, ix: Ix<T>) -> bool {
+        !self.bits.borrow_mut().set(ix.ix(), true)
+    }
+    /**
+     * Remove an index from this set, returning whether it was present.
+     */
+    #[inline]
+    pub fn remove(&mut self, ix: Ix<T>) -> bool {
+        self.bits.borrow_mut().set(ix.ix(), false)
+    }
+    #[inline]
+    pub fn contains(&self, ix: Ix<T>) -> bool {
+        self.bits.borrow().get(ix.ix())
+    }
+    /**
+     * Remove every index from this set.
+     */
+    #[inline]
+    pub fn clear(&mut self) {
+        self.bits.borrow_mut().words.clear()
+    }
+    /**
+     * Return the number of objects in this set.
+     */
+    pub fn len(&self) -> usize {
+        self.bits.borrow().words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bits.borrow().words.iter().all(|&w| w == 0)
+    }
+    /**
+     * Get the indices in this set, in order, given
+     * the region this set was created from.
+     */
+    pub fn to_vec(&self, region: &Region<T>) -> Vec<Ix<T>> {
+        self.bits.borrow().iter().map(|i| region.ix_at(i)).collect()
+    }
+}
+impl <T> Debug for IxSet<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_set().entries(self.bits.borrow().iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn ix_set_follows_collections() {
+        let mut r = Region::new();
+        r.ensure(70);
+        let roots: Vec<_> = (0..70).map(|_| r.alloc(|_| {Elem::new()}).root()).collect();
+        let mut set = r.ix_set();
+        for (i, root) in roots.iter().enumerate() {
+            if i % 3 == 0 {
+                assert!(set.insert(root.ix()));
+            }
+        }
+        assert!(!set.insert(roots[0].ix()));
+        assert_eq!(set.len(), 24);
+
+        let kept: Vec<_> = roots.into_iter().enumerate().filter(|(i, _)| i % 2 == 1).collect();
+        r.gc();
+        assert_eq!(r.len(), 35);
+        for (i, root) in &kept {
+            assert_eq!(set.contains(root.ix()), i % 3 == 0);
+        }
+        assert_eq!(set.len(), 12);
+        assert_eq!(set.to_vec(&r).len(), set.len());
+    }
+}
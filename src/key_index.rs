@@ -0,0 +1,182 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::any::Any;
+use core::cell::RefCell;
+use std::collections::HashMap;
+use core::hash::Hash;
+use alloc::rc::Rc;
+
+use crate::entry::Spot;
+use crate::remap::{Forwarding, Remap};
+use crate::{Ix, Region};
+
+/**
+ * An index from keys to the positions of objects,
+ * erasing the type of the keys.
+ */
+pub(crate) trait Keys<T>: Remap {
+    // Index every object from start
+    fn index(&mut self, spots: &[Spot<T>], start: usize);
+    // The object at a position changed from old to new
+    fn replace(&mut self, old: &T, new: &T, at: usize);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+struct KeyIndex<K, T> {
+    key: fn(&T) -> K,
+    positions: HashMap<K, usize>,
+}
+impl <K: 'static + Hash + Eq, T: 'static> Keys<T> for KeyIndex<K, T> {
+    fn index(&mut self, spots: &[Spot<T>], start: usize) {
+        for (i, spot) in spots.iter().enumerate().skip(start) {
+            if let Some(e) = spot.get() {
+                self.positions.insert((self.key)(e.get()), i);
+            }
+        }
+    }
+    fn replace(&mut self, old: &T, new: &T, at: usize) {
+        let old = (self.key)(old);
+        if self.positions.get(&old) == Some(&at) {
+            self.positions.remove(&old);
+        }
+        self.positions.insert((self.key)(new), at);
+    }
+    fn clear(&mut self) {
+        self.positions.clear();
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+impl <K, T> Remap for KeyIndex<K, T> {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        self.positions.retain(|_, i| match forwarding.get(*i) {
+            Some(j) => { *i = j; true },
+            None => false,
+        });
+    }
+}
+
+impl <T> Region<T> {
+    // Index the keys of every object from start
+    pub(crate) fn index_keys(&mut self, start: usize) {
+        if let Some(ref keys) = self.key_index {
+            keys.borrow_mut().index(&self.data, start);
+        }
+    }
+    // Index every object again, such as after their values are replaced
+    pub(crate) fn reindex_all_keys(&mut self) {
+        if let Some(ref keys) = self.key_index {
+            let mut keys = keys.borrow_mut();
+            keys.clear();
+            keys.index(&self.data, 0);
+        }
+    }
+    // Re-index the object at a position whose value was old
+    pub(crate) fn reindex_key(&mut self, old: &T, at: usize) {
+        if let (Some(keys), Some(e)) = (&self.key_index, self.data[at].get()) {
+            keys.borrow_mut().replace(old, e.get(), at);
+        }
+    }
+}
+
+impl <T: 'static> Region<T> {
+    /**
+     * Index the objects of this region by a key, such as a symbol's name,
+     * for lookup with [`find_by_key`](#method.find_by_key). This replaces
+     * any previous index, and indexes the objects already in the region.
+     *
+     * The index is maintained as objects are allocated, including by
+     * [`gc_into`](#method.gc_into) and [`adopt`](#method.adopt), and collected.
+     * Keys are expected to be unique: an object takes over
+     * the key of any object indexed before it, which can
+     * then no longer be found. An object whose key changes should be
+     * updated with [`replace`](#method.replace) or [`set`](#method.set),
+     * rather than through a mutable reference, which does not re-index it.
+     *
+     * This requires the feature "std", which is enabled by default.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Symbol(&'static str);
+     * impl gc::HasIx<Symbol> for Symbol {
+     *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+     *         F: FnMut(&'b mut gc::Ix<Symbol>)
+     *     { }
+     * }
+     *
+     * let mut r = gc::Region::new();
+     * r.set_key_index(|s: &Symbol| s.0);
+     * let root = r.alloc(|_| Symbol("car")).root();
+     * r.alloc(|_| Symbol("cdr"));
+     * r.gc();
+     * assert_eq!(r.find_by_key(&"car").unwrap().identifier(), root.ix().identifier());
+     * assert!(r.find_by_key(&"cdr").is_none());
+     * ```
+     */
+    pub fn set_key_index<K: 'static + Hash + Eq>(&mut self, key: fn(&T) -> K) {
+        let keys = Rc::new(RefCell::new(KeyIndex { key, positions: HashMap::new() }));
+        keys.borrow_mut().index(&self.data, 0);
+        self.remaps.push(Rc::downgrade(&keys) as _);
+        self.key_index = Some(keys);
+    }
+    /**
+     * Stop indexing the objects of this region.
+     */
+    pub fn clear_key_index(&mut self) {
+        self.key_index = None;
+    }
+    /**
+     * Find the object with a key, as indexed by
+     * [`set_key_index`](#method.set_key_index). This is
+     * always None if the index has a different type of key.
+     */
+    pub fn find_by_key<K: 'static + Hash + Eq>(&self, key: &K) -> Option<Ix<T>> {
+        let keys = self.key_index.as_ref()?.borrow();
+        let index = keys.as_any().downcast_ref::<KeyIndex<K, T>>()?;
+        index.positions.get(key).map(|&i| self.ix_at(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn keys_follow_objects() {
+        let mut r = Region::new();
+        r.ensure(4);
+        r.set_key_index(|e: &Elem| e.ix.is_some());
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        assert_eq!(r.find_by_key(&true).unwrap().identifier(), b.ix().identifier());
+        assert!(r.find_by_key(&0).is_none());
+        // Takes over the key of a, and is then collected
+        r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        r.gc();
+        assert_eq!(r.find_by_key(&true).unwrap().identifier(), b.ix().identifier());
+        assert!(r.find_by_key(&false).is_none());
+
+        r.set(b.ix(), Elem::new()).unwrap();
+        assert!(r.find_by_key(&true).is_none());
+        assert_eq!(r.find_by_key(&false).unwrap().identifier(), b.ix().identifier());
+
+        let mut other = Region::new();
+        other.set_key_index(|e: &Elem| e.ix.is_none());
+        r.gc_into(&mut other);
+        assert!(other.find_by_key(&true).is_some());
+
+        // Adopted objects are indexed
+        let mut nursery = Region::new();
+        let c = nursery.alloc(|_| {Elem::new()}).ix();
+        let promotion = nursery.promote(&[c]).unwrap();
+        let roots = other.adopt(promotion);
+        assert_eq!(other.find_by_key(&true).unwrap().identifier(), roots[0].ix().identifier());
+    }
+}
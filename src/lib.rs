@@ -5,22 +5,168 @@
  */
 
 #![doc(html_root_url = "https://docs.rs/moving_gc_arena/0.2.1")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-use std::rc::Rc;
-use std::rc;
-use std::cell::Cell;
-use std::fmt::{Debug, Formatter};
+extern crate alloc;
+
+use alloc::rc::Rc;
+use alloc::rc;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, IndexMut, Range};
+use core::fmt::{Debug, Formatter};
+use alloc::{vec, vec::Vec};
+use alloc::boxed::Box;
+use alloc::string::String;
 
 mod types;
 #[cfg(feature = "debug-arena")]
 mod nonce;
 mod entry;
 mod has_ix;
+mod arena_index;
+mod descriptor;
+mod root_set;
+mod root_slab;
+mod root_scope;
+#[cfg(feature = "std")]
+mod root_map;
+mod fuel;
+mod finalize;
+mod limit;
+mod pin;
+mod arena;
+pub mod compat;
+mod identity_map;
+mod remap;
+mod ix_set;
+mod alloc_mark;
+mod watermark;
+mod ix_range;
+mod gc_log;
+mod policy;
+mod graph_hash;
+mod reachability;
+#[cfg(feature = "std")]
+mod lru;
+mod gc_stack;
+mod promotion;
+mod image;
+mod space;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod builder;
+mod partition;
+#[cfg(feature = "std")]
+mod drop_thread;
+mod frame;
+mod dangling;
+mod region_weak;
+#[cfg(feature = "std")]
+mod key_index;
+mod weak_ix;
+mod hot_column;
+mod secondary_map;
+mod incremental;
+mod generational;
+mod compact;
+mod raw_parts;
+#[cfg(feature = "std")]
+mod shared_ix;
+mod gc_ref;
+#[cfg(feature = "ix32")]
+mod ix32;
+mod extract;
+mod snapshot;
+mod ephemeron;
+// Indices do not fit in a C uintptr_t with "debug-arena"
+#[cfg(all(feature = "ffi", not(feature = "debug-arena")))]
+pub mod ffi;
+#[cfg(feature = "petgraph")]
+mod petgraph_export;
+#[cfg(feature = "proptest-support")]
+pub mod testing;
+#[cfg(feature = "shadow-heap")]
+mod shadow;
+#[cfg(feature = "tracked-ix")]
+mod tracked;
+#[cfg(feature = "history")]
+mod history;
+#[cfg(feature = "alloc-sites")]
+mod alloc_sites;
+#[cfg(feature = "refresh")]
+mod refresh;
+#[cfg(feature = "sync")]
+mod sync;
+#[cfg(feature = "sync")]
+mod nursery;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+mod graph_dump;
 
 pub use types::{Ix, Weak};
-use types::{IxCell, SpotVariant};
+#[cfg(feature = "ix32")]
+pub use ix32::Ix32;
+use types::SpotVariant;
 use entry::{Entry, Spot};
-pub use has_ix::HasIx;
+use has_ix::foreach_edge;
+pub use has_ix::{EdgeLabel, HasIx, HasIxDyn, ValidateCtx};
+pub use arena_index::ArenaIndex;
+#[cfg(feature = "std")]
+pub use shared_ix::SharedIx;
+pub use gc_ref::GcRef;
+pub use dangling::{DanglingEdge, DanglingEdgePolicy};
+pub use region_weak::RegionWeak;
+pub use weak_ix::WeakIx;
+pub use hot_column::HotColumn;
+pub use secondary_map::SecondaryMap;
+pub use raw_parts::{RawObject, RawParts};
+pub use descriptor::{DynField, DynObject, TraceDescriptor, TraceFn};
+pub use root_set::RootSet;
+#[cfg(feature = "std")]
+pub use root_map::RootMap;
+pub use root_scope::{Handle, RootScope};
+use root_set::RootSetCell;
+use root_slab::{RootSlabCell, Roots};
+pub use fuel::FuelUnit;
+pub use limit::Limit;
+pub use pin::{PinGuard, RegionPin};
+pub use arena::Arena;
+pub use identity_map::IdentityMap;
+pub use ix_set::IxSet;
+pub use alloc_mark::AllocMark;
+pub use watermark::Mark;
+pub use ix_range::IxRange;
+pub use gc_log::{GcEvent, GcStats, GcTrigger};
+pub use policy::{DefaultPolicy, GcAlgorithm, GcConfig, GcPolicy, SurvivalPolicy};
+#[cfg(feature = "std")]
+pub use lru::LruCache;
+pub use gc_stack::GcStack;
+pub use promotion::Promotion;
+pub use image::Image;
+pub use snapshot::Snapshot;
+pub use ephemeron::EphemeronTable;
+#[cfg(feature = "std")]
+pub use builder::RegionBuilder;
+#[cfg(feature = "mmap")]
+pub use space::Backing;
+#[cfg(feature = "history")]
+pub use history::{History, HistoryEvent};
+#[cfg(feature = "alloc-sites")]
+pub use alloc_sites::AllocSite;
+#[cfg(feature = "sync")]
+pub use sync::{ObjectGuard, RegionWriteGuard, SharedRegion, SyncRegion, SyncRoot, SyncWeak};
+#[cfg(feature = "sync")]
+pub use nursery::Nursery;
+#[cfg(feature = "serde")]
+pub use serialize::RegionWithRoots;
+#[cfg(feature = "std")]
+pub use graph_dump::DumpFormat;
 
 #[derive(Debug, PartialEq, Eq)]
 #[allow(unused)]
@@ -58,9 +204,77 @@ pub enum Error {
     // with debug-arena. That is of course still unexpected
     // and still requires an error to occur.
     UnexpectedInternalState,
+    /**
+     * An allocation was refused because the
+     * region has run out of allocation fuel.
+     */
+    FuelExhausted,
+    /**
+     * The operation would move objects, but the
+     * region is pinned by a [`PinGuard`](struct.PinGuard.html).
+     */
+    Pinned,
+    /**
+     * An allocation was refused because the region
+     * would exceed its [`Limit`](enum.Limit.html),
+     * even after a collection.
+     */
+    HeapFull,
+    /**
+     * An index does not fit in an [`Ix32`](struct.Ix32.html),
+     * or an allocation was refused because the region would exceed
+     * the bound set by [`Region::set_compact_indices`](struct.Region.html#method.set_compact_indices).
+     *
+     * This requires the feature "ix32".
+     */
+    #[cfg(feature = "ix32")]
+    IndexOverflow,
+}
+
+/**
+ * Type of errors detected by [`Region::try_gc`](struct.Region.html#method.try_gc).
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub enum GcError {
+    /**
+     * An object exposed an invalid index through [`HasIx`](trait.HasIx.html).
+     * The fields give the [`identifier`](struct.Ix.html#method.identifier)
+     * of the object, the position the index pointed to,
+     * and the reason it was invalid.
+     */
+    InvalidEdge { object: usize, index: usize, error: Error },
+    /**
+     * A [`RootSet`](struct.RootSet.html) contained an invalid index.
+     */
+    InvalidRoot { index: usize, error: Error },
+    /**
+     * An object failed its own check in [`HasIx::validate`](trait.HasIx.html#method.validate),
+     * with the given description.
+     */
+    InvalidObject { object: usize, message: String },
+    /**
+     * The region is pinned, so the collection would only be deferred.
+     */
+    Pinned,
+}
+
+use core::fmt;
+impl fmt::Display for GcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            GcError::InvalidEdge { object, index, error } =>
+                write!(f, "Invalid index {} found from object {}: {}", index, object, error),
+            GcError::InvalidRoot { index, error } =>
+                write!(f, "Invalid root index {}: {}", index, error),
+            GcError::InvalidObject { object, message } =>
+                write!(f, "Invalid object {}: {}", object, message),
+            GcError::Pinned => write!(f, "Region is pinned"),
+        }
+    }
 }
+#[cfg(feature = "std")]
+impl std::error::Error for GcError { }
 
-use std::fmt;
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
@@ -68,12 +282,34 @@ impl fmt::Display for Error {
             Error::IncorrectRegion => write!(f, "Incorrect region for index"),
             Error::EntryExpired => write!(f, "Index expired"),
             Error::UnexpectedInternalState => write!(f, "Correct region has invalid internal state"),
+            Error::FuelExhausted => write!(f, "Allocation fuel exhausted"),
+            Error::Pinned => write!(f, "Region is pinned"),
+            Error::HeapFull => write!(f, "Region limit exceeded"),
+            #[cfg(feature = "ix32")]
+            Error::IndexOverflow => write!(f, "Index does not fit in a compact index"),
         }
     }
 
 }
+#[cfg(feature = "std")]
 impl std::error::Error for Error { }
 
+// The assumed time to collect each object, in nanoseconds,
+// before any collection of a region has been timed
+#[cfg(feature = "std")]
+const IDLE_COST_PER_OBJECT: u64 = 100;
+
+// Panic for a failed access through an index, reporting the caller
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn access_failed<T>(method: &str, ix: Option<Ix<T>>, e: Error) -> ! {
+    match ix {
+        Some(ix) => panic!("{}: {} at index {}", method, e, ix.describe()),
+        None => panic!("{}: {}", method, e),
+    }
+}
+
 impl <T> Ix<T> {
     /**
      * If this crate has been compiled with support for validity checking,
@@ -86,14 +322,34 @@ impl <T> Ix<T> {
     #[inline]
     #[allow(unused)]
     pub fn check_region(self, region: &Region<T>) -> Result<(), Error> {
+        self.check_current(region.ix_at(0),
+            #[cfg(feature = "debug-arena")]
+            &region.kept)
+    }
+    // Whether this is the index of a removed object, that is, the
+    // index of no position. Packed headers drop its top bit
+    #[inline]
+    pub(crate) fn is_dead(self) -> bool {
+        self.ix() >= usize::MAX >> 1
+    }
+    // Check this index against an index known to be valid for the
+    // current generation of its region, and the positions which
+    // minor collections have kept in place since earlier generations
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn check_current(self, current: Ix<T>,
+        #[cfg(feature = "debug-arena")] kept: &[(usize, nonce::Word)]) -> Result<(), Error>
+    {
         #[cfg(feature = "debug-arena")]
         {
-            if self.nonce != region.nonce {
+            if self.nonce != current.nonce {
                 Err(Error::IncorrectRegion)?;
-            } else if self.generation < region.generation {
-                Err(Error::EntryExpired)?;
-            } else if self.generation > region.generation {
-                Err(Error::UnexpectedInternalState)?;
+            }
+            match nonce::cmp_generation(self.generation, current.generation) {
+                Ordering::Less if nonce::is_kept(kept, self.ix(), self.generation) => (),
+                Ordering::Less => Err(Error::EntryExpired)?,
+                Ordering::Greater => Err(Error::UnexpectedInternalState)?,
+                Ordering::Equal => (),
             }
         }
         Ok(())
@@ -106,15 +362,23 @@ impl <T> Ix<T> {
      * Use try_get to avoid panics.
      */
     #[inline]
-    pub fn get<'a>(self, region: &'a Region<T>) -> &'a T {
-        self.try_get(region).expect("Ix::get")
+    #[track_caller]
+    pub fn get(self, region: &Region<T>) -> &T {
+        match self.try_get(region) {
+            Ok(t) => t,
+            Err(e) => access_failed("Ix::get", Some(self), e),
+        }
     }
     #[inline]
-    pub fn get_mut<'a>(self, region: &'a mut Region<T>) -> &'a mut T {
-        self.try_get_mut(region).expect("Ix::get_mut")
+    #[track_caller]
+    pub fn get_mut(self, region: &mut Region<T>) -> &mut T {
+        match self.try_get_mut(region) {
+            Ok(t) => t,
+            Err(e) => access_failed("Ix::get_mut", Some(self), e),
+        }
     }
     #[inline]
-    pub fn try_get<'a>(self, region: &'a Region<T>) -> Result<&'a T, Error> {
+    pub fn try_get(self, region: &Region<T>) -> Result<&T, Error> {
         self.check_region(region)?;
         Ok(region.data.get(self.ix())
             .ok_or(Error::Indeterminable)?
@@ -123,7 +387,7 @@ impl <T> Ix<T> {
             .get())
     }
     #[inline]
-    pub fn try_get_mut<'a>(self, region: &'a mut Region<T>) -> Result<&'a mut T, Error> {
+    pub fn try_get_mut(self, region: &mut Region<T>) -> Result<&mut T, Error> {
         self.check_region(region)?;
         Ok(region.data.get_mut(self.ix())
             .ok_or(Error::Indeterminable)?
@@ -143,8 +407,7 @@ impl <T> Ix<T> {
 pub struct MutEntry<'a, T> {
     ix: Ix<T>,
     entry: &'a mut Entry<T>,
-    root: rc::Weak<IxCell<T>>,
-    roots: &'a mut Vec<rc::Weak<IxCell<T>>>,
+    roots: &'a Roots<T>,
 }
 
 /**
@@ -161,20 +424,81 @@ pub struct MutEntry<'a, T> {
  * may cause uncollectable reference cycles.
  */
 pub struct Root<T> {
-    cell: Rc<IxCell<T>>
+    slab: Rc<RootSlabCell<T>>,
+    slot: usize,
+}
+impl <T> Default for Weak<T> {
+    fn default() -> Self {
+        Weak::new()
+    }
+}
+/**
+ * Roots are compared by identity: a root is equal to its clones,
+ * but not to other roots of the same object.
+ */
+impl <T> PartialEq for Root<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+    }
+}
+impl <T> Eq for Root<T> {}
+impl <T> Hash for Root<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::ptr::hash(Rc::as_ptr(&self.slab), state);
+        self.slot.hash(state);
+    }
+}
+/**
+ * Weak pointers are compared by identity: every weak pointer
+ * to the same object is equal, as are those created by
+ * [`Weak::new`](struct.Weak.html#method.new).
+ */
+impl <T> PartialEq for Weak<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+    }
+}
+impl <T> Eq for Weak<T> {}
+impl <T> Hash for Weak<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::ptr::hash(self.cell.as_ptr(), state);
+    }
 }
 impl <T> Clone for Root<T> {
     fn clone(&self) -> Self {
-        Root {cell: self.cell.clone()}
+        self.slab.borrow_mut().acquire(self.slot);
+        Root { slab: self.slab.clone(), slot: self.slot }
+    }
+}
+impl <T> Drop for Root<T> {
+    fn drop(&mut self) {
+        self.slab.borrow_mut().release(self.slot);
     }
 }
 impl <T> Debug for Root<T> {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        self.cell.get().fmt(f)
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.get_ix().fmt(f)
     }
 }
 
 impl <T> Weak<T> {
+    /**
+     * Create a weak pointer which does not point to any object,
+     * as if its object had already been collected, such as
+     * to initialize a field before any region exists.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let r = gc::Region::<()>::new();
+     * let w = gc::Weak::new();
+     * assert!(w.ix().is_none());
+     * assert!(w.try_get(&r).is_err());
+     * ```
+     */
+    #[inline]
+    pub fn new() -> Self {
+        Weak { cell: rc::Weak::new() }
+    }
     /**
      * Gets the value at this location, when
      * passed the correct region. As with Ix,
@@ -182,12 +506,20 @@ impl <T> Weak<T> {
      * unspecified (but is still safe).
      */
     #[inline]
+    #[track_caller]
     pub fn get<'a>(&self, r: &'a Region<T>) -> &'a T {
-        self.try_get(r).unwrap()
+        match self.try_get(r) {
+            Ok(t) => t,
+            Err(e) => access_failed("Weak::get", self.cell.upgrade().map(|c| c.get()), e),
+        }
     }
     #[inline]
+    #[track_caller]
     pub fn get_mut<'a>(&self, r: &'a mut Region<T>) -> &'a mut T {
-        self.try_get_mut(r).unwrap()
+        match self.try_get_mut(r) {
+            Ok(t) => t,
+            Err(e) => access_failed("Weak::get_mut", self.cell.upgrade().map(|c| c.get()), e),
+        }
     }
     /**
      * Try to get a reference to this data, possibly returning an error.
@@ -197,18 +529,53 @@ impl <T> Weak<T> {
      */
     #[inline]
     pub fn try_get<'a>(&self, r: &'a Region<T>) -> Result<&'a T, Error> {
-        match self.ix() {
-            Some(i) => i.try_get(r),
+        match self.cell.upgrade() {
+            Some(i) => i.get().try_get(r),
             None => Err(Error::EntryExpired)
         }
     }
     #[inline]
     pub fn try_get_mut<'a>(&self, r: &'a mut Region<T>) -> Result<&'a mut T, Error> {
-        match self.ix() {
-            Some(i) => i.try_get_mut(r),
+        match self.cell.upgrade() {
+            Some(i) => i.get().try_get_mut(r),
             None => Err(Error::EntryExpired)
         }
     }
+    /**
+     * Create a root to the object of this weak pointer,
+     * if it has not been collected, which keeps it live
+     * until the root is dropped.
+     */
+    pub fn upgrade(&self, r: &mut Region<T>) -> Option<Root<T>> {
+        r.root(self.ix()?).ok()
+    }
+    /**
+     * Returns true if both weak pointers point to the same object,
+     * even if it has been collected.
+     */
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.cell.ptr_eq(&other.cell)
+    }
+    /**
+     * Returns true if the object of this weak pointer is still
+     * in its region, without needing the region. An unreachable
+     * object remains until it is collected, and an object is
+     * also gone once it is removed, or its region is dropped.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let weak = r.alloc(|_|{()}).weak();
+     * assert!(weak.is_alive());
+     * r.gc();
+     * assert!(!weak.is_alive());
+     * ```
+     */
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        self.cell.strong_count() > 0
+    }
 }
 
 
@@ -224,12 +591,20 @@ impl <T> Root<T> {
      * unspecified (but is still safe).
      */
     #[inline]
+    #[track_caller]
     pub fn get<'a>(&self, r: &'a Region<T>) -> &'a T {
-        self.try_get(r).unwrap()
+        match self.try_get(r) {
+            Ok(t) => t,
+            Err(e) => access_failed("Root::get", Some(self.get_ix()), e),
+        }
     }
     #[inline]
+    #[track_caller]
     pub fn get_mut<'a>(&self, r: &'a mut Region<T>) -> &'a mut T {
-        self.try_get_mut(r).unwrap()
+        match self.try_get_mut(r) {
+            Ok(t) => t,
+            Err(e) => access_failed("Root::get_mut", Some(self.get_ix()), e),
+        }
     }
     /**
      * Try to get a reference to this data, possibly returning an error.
@@ -239,11 +614,40 @@ impl <T> Root<T> {
      */
     #[inline]
     pub fn try_get<'a>(&self, r: &'a Region<T>) -> Result<&'a T, Error> {
-        self.ix().try_get(&r)
+        self.get_ix().try_get(r)
     }
     #[inline]
     pub fn try_get_mut<'a>(&self, r: &'a mut Region<T>) -> Result<&'a mut T, Error> {
-        self.ix().try_get_mut(r)
+        self.get_ix().try_get_mut(r)
+    }
+    /**
+     * Create a weak pointer to the object of this root, which
+     * remains valid for as long as the object is live.
+     * Unlike a root, a weak pointer is kept with the object,
+     * so the region is needed to create one. This panics if
+     * passed the wrong region.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let root = r.alloc(|_|{()}).root();
+     * let weak = root.downgrade(&mut r);
+     * drop(root);
+     * let root = weak.upgrade(&mut r).unwrap();
+     * r.gc();
+     * assert!(weak.try_get(&r).is_ok());
+     *
+     * drop(root);
+     * r.gc();
+     * assert!(weak.upgrade(&mut r).is_none());
+     * ```
+     */
+    #[track_caller]
+    pub fn downgrade(&self, r: &mut Region<T>) -> Weak<T> {
+        match r.weak(self.get_ix()) {
+            Ok(weak) => weak,
+            Err(e) => access_failed("Root::downgrade", Some(self.get_ix()), e),
+        }
     }
 
     /**
@@ -253,8 +657,32 @@ impl <T> Root<T> {
      * that is owned by an element of the Region
      */
     #[inline(always)]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
     pub fn ix(&self) -> Ix<T> {
-        self.cell.get()
+        self.get_ix().tracked()
+    }
+    #[inline(always)]
+    pub(crate) fn get_ix(&self) -> Ix<T> {
+        self.slab.borrow().get(self.slot)
+    }
+    /**
+     * Returns true if both roots are the same root, that is,
+     * if one is a clone of the other. Separately created roots
+     * of the same object are not the same, but have equal
+     * [`ix`](#method.ix)es.
+     */
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.slab, &other.slab) && self.slot == other.slot
+    }
+}
+impl <T: 'static + HasIx<T>> Root<T> {
+    /**
+     * Remove the object of this root from the region, returning it,
+     * as with [`Region::take`](struct.Region.html#method.take).
+     */
+    pub fn take(self, r: &mut Region<T>) -> Result<T, Error> {
+        r.take(self.get_ix())
     }
 }
 
@@ -264,16 +692,7 @@ impl <'a, T> MutEntry<'a, T> {
      * live across garbage collections.
      */
     pub fn root(&mut self) -> Root<T> {
-        let i = self.ix;
-        match self.root.upgrade() {
-            None => {
-                let rc = Rc::new(Cell::new(i));
-                self.roots.push(Rc::downgrade(&rc));
-                self.root = Rc::downgrade(&rc);
-                Root { cell: rc }
-            },
-            Some(cell) => Root { cell }
-        }
+        self.roots.insert(self.ix)
     }
 
     /**
@@ -286,11 +705,13 @@ impl <'a, T> MutEntry<'a, T> {
         self.entry.weak(self.ix)
     }
     #[inline]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
     pub fn ix(&self) -> Ix<T> {
-        self.ix
+        self.ix.tracked()
     }
     #[inline]
     #[deprecated(since="0.2.0", note="Please use MutEntry::get")]
+    #[allow(clippy::should_implement_trait)]
     pub fn as_ref(&self) -> &T {
         self.entry.get()
     }
@@ -330,15 +751,66 @@ impl <'a, T> MutEntry<'a, T> {
  * objects, such as for a garbage collection.
  * These will be documented.
  *
+ * Where unwinding is unavailable or forbidden, such as with `panic = "abort"`
+ * or across an FFI boundary, the fallible methods can be used exclusively:
+ * [`try_alloc`](#method.try_alloc), [`try_alloc_slice`](#method.try_alloc_slice),
+ * [`try_ensure`](#method.try_ensure), [`try_gc`](#method.try_gc),
+ * [`try_gc_into`](#method.try_gc_into), and the `try_get` and `try_get_mut` methods
+ * of indices, return errors where their counterparts would panic.
+ * Panics then only indicate allocation failure, a panic in user code
+ * such as [`HasIx`](trait.HasIx.html), or a failed check of a debugging feature.
  */
 pub struct Region<T> {
-    data: Vec<Spot<T>>,
-    roots: Vec<rc::Weak<IxCell<T>>>,
+    data: space::Space<T>,
+    roots: Roots<T>,
+    root_sets: Vec<rc::Weak<RootSetCell<T>>>,
+    ephemerons: Vec<ephemeron::Registration<T>>,
+    remaps: remap::Remaps,
+    // Groups of objects which are kept contiguous, sorted by position
+    groups: Vec<Range<usize>>,
+    // The number of collections which have moved objects
+    epoch: u64,
+    gc_log: gc_log::GcLog,
+    policy: Box<dyn GcPolicy>,
+    fuel: Option<fuel::Fuel>,
+    limit: Option<limit::Limit>,
+    #[cfg(feature = "ix32")]
+    compact_indices: bool,
+    pins: pin::Pins,
+    partitions: Option<Rc<RefCell<partition::Partitions>>>,
+    #[cfg(feature = "std")]
+    drop_thread: Option<drop_thread::DropThread<T>>,
+    dangling: dangling::Dangling<T>,
+    #[cfg(feature = "std")]
+    key_index: Option<Rc<RefCell<dyn key_index::Keys<T>>>>,
+    incremental: Option<Rc<RefCell<incremental::Incremental>>>,
+    generations: Option<Rc<RefCell<generational::Generations>>>,
+    finalizers: Option<Rc<RefCell<ix_set::Bits>>>,
+    // Objects with finalizers which have been removed
+    finalized: Vec<T>,
+    // Only referenced strongly by the region, for RegionWeak
+    alive: Rc<()>,
+    // Cleared storage kept for the next collection to copy into
+    spare: Option<space::Space<T>>,
+    #[cfg(feature = "shadow-heap")]
+    shadow: Option<shadow::ShadowHeap<T>>,
+    #[cfg(feature = "history")]
+    history: Option<history::History<T>>,
+    #[cfg(feature = "alloc-sites")]
+    alloc_sites: Option<Rc<RefCell<alloc_sites::AllocSites>>>,
+    #[cfg(feature = "refresh")]
+    last_forwarding: Option<refresh::LastForwarding>,
+    #[cfg(feature = "rayon")]
+    par_gc_to: Option<GcToFn<T>>,
 
     #[cfg(feature = "debug-arena")]
-    nonce: u64,
+    nonce: crate::nonce::Word,
+    #[cfg(feature = "debug-arena")]
+    generation: crate::nonce::Word,
+    // Positions kept in place by minor collections: the end of each run,
+    // and the generation since which indices to its objects are valid
     #[cfg(feature = "debug-arena")]
-    generation: u64,
+    kept: Vec<(usize, crate::nonce::Word)>,
 }
 
 impl <T> Region<T> {
@@ -346,14 +818,142 @@ impl <T> Region<T> {
     #[inline]
     pub fn new() -> Self {
         Region {
-            data: Vec::new(),
-            roots: Vec::new(),
+            data: space::Space::new(),
+            roots: Roots::new(),
+            root_sets: Vec::new(),
+            ephemerons: Vec::new(),
+            remaps: Vec::new(),
+            groups: Vec::new(),
+            epoch: 0,
+            gc_log: gc_log::GcLog::default(),
+            policy: Box::new(DefaultPolicy),
+            fuel: None,
+            limit: None,
+            #[cfg(feature = "ix32")]
+            compact_indices: false,
+            pins: pin::Pins::default(),
+            partitions: None,
+            #[cfg(feature = "std")]
+            drop_thread: None,
+            dangling: dangling::Dangling::new(),
+            #[cfg(feature = "std")]
+            key_index: None,
+            incremental: None,
+            generations: None,
+            finalizers: None,
+            finalized: Vec::new(),
+            alive: Rc::new(()),
+            spare: None,
+            #[cfg(feature = "shadow-heap")]
+            shadow: None,
+            #[cfg(feature = "history")]
+            history: None,
+            #[cfg(feature = "alloc-sites")]
+            alloc_sites: None,
+            #[cfg(feature = "refresh")]
+            last_forwarding: None,
+            #[cfg(feature = "rayon")]
+            par_gc_to: None,
             #[cfg(feature = "debug-arena")]
             nonce: nonce::next(),
             #[cfg(feature = "debug-arena")]
             generation: 0,
+            #[cfg(feature = "debug-arena")]
+            kept: Vec::new(),
+        }
+    }
+
+    // Get the index for a position in this region
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn ix_at(&self, i: usize) -> Ix<T> {
+        Ix::new(i,
+            #[cfg(feature = "debug-arena")]
+            self.nonce,
+            #[cfg(feature = "debug-arena")]
+            self.generation,
+        )
+    }
+
+    // Advance the generation, so that every index obtained
+    // before is detected as stale
+    #[cfg(feature = "debug-arena")]
+    pub(crate) fn advance_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.kept.clear();
+        #[cfg(feature = "tracked-ix")]
+        tracked::forget(self.nonce);
+    }
+
+    // Call f with the index of every root
+    #[allow(unused)]
+    pub(crate) fn foreach_root<F>(&self, mut f: F) where
+        F: FnMut(Ix<T>)
+    {
+        self.roots.for_each(&mut f);
+        for set in &self.root_sets {
+            if let Some(set) = set.upgrade() {
+                set.borrow().iter().for_each(|ix| f(*ix))
+            }
         }
     }
+
+    /**
+     * Create a root pointer to an existing object, such as one
+     * found by following edges, which will keep it live
+     * across garbage collections. This fails if the index
+     * is not valid for this region.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let ix = r.alloc(|_|{()}).ix();
+     * let root = r.root(ix).unwrap();
+     * r.gc();
+     * assert!(root.try_get(&r).is_ok());
+     * ```
+     */
+    pub fn root(&mut self, ix: Ix<T>) -> Result<Root<T>, Error> {
+        ix.try_get(self)?;
+        Ok(self.roots.insert(ix))
+    }
+    /**
+     * Create a weak pointer to an existing object, as for
+     * [`MutEntry::weak`](struct.MutEntry.html#method.weak).
+     * This fails if the index is not valid for this region.
+     */
+    pub fn weak(&mut self, ix: Ix<T>) -> Result<Weak<T>, Error> {
+        ix.try_get(self)?;
+        let entry = self.data[ix.ix()].get_mut().ok_or(Error::EntryExpired)?;
+        Ok(entry.weak(ix))
+    }
+    /**
+     * Returns the number of [`Root`](struct.Root.html)s of the object
+     * at an index, counting each clone of a root. Objects kept live
+     * only by root sets or scoped handles have no roots, and an
+     * index which is not valid for this region has none.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let root = r.alloc(|_|{()}).root();
+     * let ix = root.ix();
+     * let _again = (root.clone(), r.root(ix).unwrap());
+     * assert_eq!(r.root_count(ix), 3);
+     * drop(root);
+     * assert_eq!(r.root_count(ix), 2);
+     * ```
+     */
+    pub fn root_count(&self, ix: Ix<T>) -> usize {
+        self.roots.count(ix)
+    }
+    /**
+     * Returns true if the object at an index has any
+     * [`Root`](struct.Root.html), as counted by [`root_count`](#method.root_count).
+     */
+    pub fn has_root(&self, ix: Ix<T>) -> bool {
+        self.root_count(ix) > 0
+    }
 }
 impl <T> Default for Region<T> {
     fn default() -> Self {
@@ -361,17 +961,157 @@ impl <T> Default for Region<T> {
     }
 }
 
+/**
+ * Indexing a region is equivalent to [`Ix::get`](struct.Ix.html#method.get)
+ * and [`Ix::get_mut`](struct.Ix.html#method.get_mut), and likewise for roots and
+ * weak pointers, panicking if the object cannot be accessed.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let root = r.alloc(|_|{()}).root();
+ * let ix = root.ix();
+ * r[ix] = ();
+ * assert_eq!(r[&root], ());
+ * ```
+ */
+impl <T> Index<Ix<T>> for Region<T> {
+    type Output = T;
+    #[inline]
+    #[track_caller]
+    fn index(&self, ix: Ix<T>) -> &T {
+        match ix.try_get(self) {
+            Ok(t) => t,
+            Err(e) => access_failed("Region::index", Some(ix), e),
+        }
+    }
+}
+impl <T> IndexMut<Ix<T>> for Region<T> {
+    #[inline]
+    #[track_caller]
+    fn index_mut(&mut self, ix: Ix<T>) -> &mut T {
+        match ix.try_get_mut(self) {
+            Ok(t) => t,
+            Err(e) => access_failed("Region::index_mut", Some(ix), e),
+        }
+    }
+}
+impl <T> Index<&Root<T>> for Region<T> {
+    type Output = T;
+    #[inline]
+    #[track_caller]
+    fn index(&self, root: &Root<T>) -> &T {
+        &self[root.get_ix()]
+    }
+}
+impl <T> IndexMut<&Root<T>> for Region<T> {
+    #[inline]
+    #[track_caller]
+    fn index_mut(&mut self, root: &Root<T>) -> &mut T {
+        &mut self[root.get_ix()]
+    }
+}
+impl <T> Index<&Weak<T>> for Region<T> {
+    type Output = T;
+    #[inline]
+    #[track_caller]
+    fn index(&self, weak: &Weak<T>) -> &T {
+        match weak.try_get(self) {
+            Ok(t) => t,
+            Err(e) => access_failed("Region::index", weak.cell.upgrade().map(|c| c.get()), e),
+        }
+    }
+}
+impl <T> IndexMut<&Weak<T>> for Region<T> {
+    #[inline]
+    #[track_caller]
+    fn index_mut(&mut self, weak: &Weak<T>) -> &mut T {
+        let ix = weak.cell.upgrade().map(|c| c.get());
+        match weak.try_get_mut(self) {
+            Ok(t) => t,
+            Err(e) => access_failed("Region::index_mut", ix, e),
+        }
+    }
+}
+
+
+// Sets the length of the destination when dropped, so that
+// objects which have been written are kept even when unwinding
+struct SetLenOnDrop<'a, T> {
+    dst: &'a mut space::Space<T>,
+    len: usize,
+}
+impl <T> Drop for SetLenOnDrop<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.dst.set_len(self.len) }
+    }
+}
+
+// The signature of Region::prim_gc_to, and of parallel collection
+#[cfg(not(feature = "debug-arena"))]
+pub(crate) type GcToFn<T> = fn(&mut [Spot<T>], &mut space::Space<T>,
+    &Roots<T>,
+    Vec<rc::Weak<RootSetCell<T>>>, &mut Vec<rc::Weak<RootSetCell<T>>>,
+    &[Range<usize>], &mut dangling::Dangling<T>);
+#[cfg(feature = "debug-arena")]
+pub(crate) type GcToFn<T> = fn(&mut [Spot<T>], &mut space::Space<T>,
+    &Roots<T>,
+    Vec<rc::Weak<RootSetCell<T>>>, &mut Vec<rc::Weak<RootSetCell<T>>>,
+    &[Range<usize>], &mut dangling::Dangling<T>,
+    (nonce::Word, nonce::Word), (nonce::Word, nonce::Word));
+
+// Check that an index found by a collection is from the generation being collected
+#[cfg(feature = "debug-arena")]
+pub(crate) fn check_generation<T>(ix: Ix<T>, internal: bool,
+    old_gen: (nonce::Word, nonce::Word), new_gen: (nonce::Word, nonce::Word))
+{
+    let prefix = if internal {"GC internal error (root)"} else {"GC"};
+    if ix.nonce != old_gen.0 {
+        if ix.nonce == new_gen.0 {
+            panic!("{}: Index processed twice", prefix);
+        } else {
+            panic!("{}: Invalid source index for root", prefix);
+        }
+    } else {
+        match nonce::cmp_generation(ix.generation, old_gen.1) {
+            Ordering::Less =>
+                panic!("{}: Index is for a generation that is too old, it may have missed processing", prefix),
+            Ordering::Greater =>
+                panic!("{}: Index is for a generation that is too new, it may have been processed twice", prefix),
+            Ordering::Equal => (),
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
 
-impl <'a, T: 'static + HasIx<T>> Region<T> {
 
 
+    // The collector used by this region
+    fn gc_to(&self) -> GcToFn<T> {
+        #[cfg(feature = "rayon")]
+        if let Some(par_gc_to) = self.par_gc_to {
+            return par_gc_to
+        }
+        Self::prim_gc_to
+    }
 
-    // Perform a gc into a new destination vector. For efficiency,
-    // the vector must have enough capacity for the new elements
-    fn prim_gc_to<'b : 'a>(src: &mut [Spot<T>], dst: &'b mut Vec<Spot<T>>,
-                           roots: &mut Vec<rc::Weak<IxCell<T>>>,
-                           #[cfg(feature = "debug-arena")] old_gen: (u64, u64),
-                           #[cfg(feature = "debug-arena")] new_gen: (u64, u64),
+    // Perform a gc into a destination vector. For efficiency,
+    // the vector must have enough capacity for the new elements.
+    //
+    // The roots are forwarded in place, and the root sets which are still
+    // live are forwarded and moved to dst_root_sets, before any user code runs.
+    // If a panic occurs while tracing, every object which has been copied
+    // is still in dst, though objects not yet traced may contain stale indices.
+    #[allow(clippy::too_many_arguments)]
+    fn prim_gc_to(src: &mut [Spot<T>], dst: &mut space::Space<T>,
+                           roots: &Roots<T>,
+                           root_sets: Vec<rc::Weak<RootSetCell<T>>>,
+                           dst_root_sets: &mut Vec<rc::Weak<RootSetCell<T>>>,
+                           groups: &[Range<usize>],
+                           dangling: &mut dangling::Dangling<T>,
+                           #[cfg(feature = "debug-arena")] old_gen: (nonce::Word, nonce::Word),
+                           #[cfg(feature = "debug-arena")] new_gen: (nonce::Word, nonce::Word),
                            )
     where
         T : HasIx<T>
@@ -380,7 +1120,10 @@ impl <'a, T: 'static + HasIx<T>> Region<T> {
         // since we need to avoid a particular invalidation later
         // This means that dst should never move for safety
         dst.reserve(src.len());
-        let dst_spot_ptr = dst.as_ptr() as *mut Spot<T>;
+        let dst_spot_ptr = dst.as_mut_ptr();
+        #[cfg(feature = "std")]
+        let _scope = shared_ix::RewriteScope::enter();
+        dangling.found.clear();
 
         //NOTE: as a closure we're unable to mark
         //this as unsafe, but it is unsafe and should
@@ -397,61 +1140,104 @@ impl <'a, T: 'static + HasIx<T>> Region<T> {
 
             unsafe {
                 let end = dst_spot_ptr.add(len);
-                std::ptr::write(end, obj);
+                core::ptr::write(end, obj);
             }
             new_index
         };
 
+        // Push the object at p, along with the rest of its group, writing
+        // from position *len of dst, and returning the new index of p.
+        // Like push_spot, this must be called from an unsafe block,
+        // and the object must be present
+        let push_group = |src: &mut [Spot<T>], p: usize, len: &mut usize| {
+            let group = match groups.binary_search_by(|g| {
+                if g.end <= p { Ordering::Less }
+                else if g.start > p { Ordering::Greater }
+                else { Ordering::Equal }
+            }) {
+                Ok(i) => groups[i].clone(),
+                Err(_) => p..p+1,
+            };
+            let mut new_index = None;
+            for q in group {
+                let n = push_spot(*len, &mut src[q]);
+                *len += 1;
+                if q == p { new_index = Some(n) }
+            }
+            new_index.unwrap()
+        };
+
         //Start searching at the vector length before any roots
-        let mut obj_index = dst.len();
+        let start = dst.len();
+        let dead = Ix::new(usize::MAX,
+            #[cfg(feature = "debug-arena")]
+            new_gen.0,
+            #[cfg(feature = "debug-arena")]
+            new_gen.1,
+        );
+        let mut obj_index = start;
 
         #[cfg(feature = "debug-arena")]
-        let check_gen = |ix: Ix<T>, internal: bool| {
-            {
-                let prefix = if internal {"GC internal error (root)"} else {"GC"};
-                if ix.nonce != old_gen.0 {
-                    if ix.nonce == new_gen.0 {
-                        panic!("{}: Index processed twice", prefix);
-                    } else {
-                        panic!("{}: Invalid source index for root", prefix);
-                    }
-                } else if ix.generation < old_gen.1 {
-                    panic!("{}: Index is for a generation that is too old, it may have missed processing", prefix);
-                } else if ix.generation > old_gen.1 {
-                    panic!("{}: Index is for a generation that is too new, it may have been processed twice", prefix);
-                }
-            }
-        };
+        let check_gen = |ix: Ix<T>, internal: bool| check_generation(ix, internal, old_gen, new_gen);
 
 
-        //Push each root onto the destination, updating roots
-        *roots = roots.drain(..).filter_map(|root| {
-            let rc = root.upgrade()?;
-            let ix = rc.get();
+        // Push a root onto the destination if it's not already there,
+        // returning the new index
+        let mut forward_root = |ix: Ix<T>| {
             #[cfg(feature = "debug-arena")]
             check_gen(ix, true);
 
-            let s = src.get_mut(ix.ix())?;
-            unsafe {
-                rc.set(push_spot(dst.len(), s));
-                dst.set_len(dst.len() + 1);
+            Some(match src.get_mut(ix.ix())?.variant() {
+                SpotVariant::Present(_) => unsafe {
+                    let mut len = dst.len();
+                    let new_index = push_group(src, ix.ix(), &mut len);
+                    dst.set_len(len);
+                    new_index
+                },
+                SpotVariant::BrokenHeart(new_index) if new_index.is_dead() => dead,
+                SpotVariant::BrokenHeart(new_index) => new_index,
+            })
+        };
+
+        //Push each root onto the destination, updating roots
+        roots.for_each_mut(|ix| if let Some(new_index) = forward_root(*ix) { *ix = new_index });
+
+        //Likewise for every member of the root sets
+        dst_root_sets.extend(root_sets.into_iter().filter(|set| {
+            match set.upgrade() {
+                Some(set) => {
+                    set.borrow_mut().retain_mut(|ix| {
+                        match forward_root(*ix) {
+                            Some(new_index) => { *ix = new_index; true },
+                            None => false,
+                        }
+                    });
+                    true
+                },
+                None => false,
             }
-            Some(root)
-        }).collect();
+        }));
 
         //Cheney copy starting at each of the roots
         while obj_index < dst.len() {
 
-            let len = dst.len();
-            let obj = dst.get_mut(obj_index).unwrap()
+            // safety: obj_index < dst.len(), so this is a valid object,
+            // and it is never moved or overwritten by push_spot,
+            // which only writes past the length of dst
+            let obj = unsafe { &mut *dst_spot_ptr.add(obj_index) }
                 .get_mut().unwrap().get_mut();
-            let mut len_offset = 0;
+            let mut guard = SetLenOnDrop { len: dst.len(), dst: &mut *dst };
 
             // NOTE for safety:
-            // foreach_ix can panic,
-            // therefore, length should never
-            // be set until a valid object is in the location
-            obj.foreach_ix( |pointed| {
+            // foreach_ix can panic, therefore the length is
+            // extended only by the guard, which also runs when unwinding,
+            // and only once a valid object is in the location
+            foreach_edge(obj, |pointed| {
+                // Indices of other regions are also dangling
+                #[cfg(feature = "debug-arena")]
+                if pointed.nonce != old_gen.0 && pointed.nonce != new_gen.0 {
+                    return dangling.handle(obj_index, pointed);
+                }
                 #[cfg(feature = "debug-arena")]
                 check_gen(*pointed, false);
 
@@ -462,89 +1248,554 @@ impl <'a, T: 'static + HasIx<T>> Region<T> {
                                 //safety requirement for push_spot
                                 #[allow(unused)]
                                 unsafe {
-                                    *pointed = push_spot(len + len_offset, s);
+                                    *pointed = push_group(src, pointed.ix(), &mut guard.len);
                                 }
-                                len_offset += 1;
+                            },
+                            // The object was removed in place, such as by take
+                            SpotVariant::BrokenHeart(new_index) if new_index.is_dead() => {
+                                dangling.handle_removed(obj_index, pointed, dead)
                             },
                             SpotVariant::BrokenHeart(new_index) => {
                                 *pointed = new_index
                             }
                         }
                     },
-                    None => {
-                        panic!("Invalid index {} found from HasIx<T> at {} during GC.", pointed.ix(), obj_index);
-                    }
+                    None => dangling.handle(obj_index, pointed),
                 }
             });
-            unsafe {
-                dst.set_len(len + len_offset);
-            }
+            drop(guard);
             obj_index += 1;
         }
+
+        // Weak indices are updated once every object has been traced
+        for obj in dst[start..].iter_mut().filter_map(|s| s.get_mut()) {
+            obj.get_mut().foreach_weak_ix(|weak| weak.forward(|ix| {
+                #[cfg(feature = "debug-arena")]
+                if ix.nonce != old_gen.0 { return None }
+                src.get(ix.ix())?.forwarded().filter(|new| new.ix() < obj_index)
+            }));
+        }
     }
 
     /**
      * Ensure that the capacity supports new_elems more
      * elements, collecting garbage if necessary.
+     *
+     * This panics if a collection is needed but the region is pinned.
      */
     pub fn ensure(&mut self, additional: usize) {
+        if let Err(e) = self.try_ensure(additional) {
+            panic!("Region::ensure: {}", e)
+        }
+    }
+    /**
+     * Ensure capacity as with [`ensure`](#method.ensure), but return
+     * an error rather than panicking if the region is pinned,
+     * or would exceed its [`limit`](#method.set_limit).
+     */
+    pub fn try_ensure(&mut self, additional: usize) -> Result<(), Error> {
         let len = self.data.len();
         let cap = self.data.capacity();
-        if cap >= len + additional { return }
-        let mut dst = Vec::with_capacity(len + std::cmp::max(len, additional));
-
-        #[cfg(feature = "debug-arena")]
-        let new_gen = (self.nonce, self.generation+1);
-
-        Self::prim_gc_to(&mut self.data, &mut dst, &mut self.roots,
-            #[cfg(feature = "debug-arena")]
-            (self.nonce, self.generation),
-            #[cfg(feature = "debug-arena")]
-            new_gen);
-        self.roots = self.roots.drain(..).filter(|root| {root.upgrade().is_some()}).collect();
-        self.data = dst;
-
-        #[cfg(feature = "debug-arena")]
-        {
-            self.generation = new_gen.1;
+        if cap >= len + additional && self.max_entries() >= len + additional { return Ok(()) }
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
         }
+        let max = self.max_entries();
+        if self.nursery_pending() {
+            self.minor_gc()?;
+            let len = self.data.len();
+            if cap >= len + additional && max >= len + additional { return Ok(()) }
+        }
+        let len = self.data.len();
+        if max < len + additional {
+            // Collect without growing, as the limit would be exceeded
+            self.gc_with_capacity(cap, GcTrigger::Allocation);
+            let len = self.data.len();
+            if max < len + additional {
+                Err(self.full_error())?;
+            }
+            if cap < len + additional {
+                self.data.reserve_exact(core::cmp::min(core::cmp::max(len, additional), max - len));
+            }
+            return Ok(())
+        }
+        let target = core::cmp::max(self.policy.target_capacity(len, additional), len + additional);
+        let grow = core::cmp::min(target - len, max - len);
+        match self.policy.on_full(len, cap, additional) {
+            GcAlgorithm::Copying =>
+                self.gc_with_capacity(len + grow, GcTrigger::Allocation),
+            GcAlgorithm::None => self.data.reserve_exact(grow),
+        }
+        Ok(())
     }
 
     /**
-     * Allocate a new object, returning a temporary handle,
-     * which can be used to mutate the object and to get
-     * roots, weak pointers, and internal pointers to
-     * the object.
-     *
-     * This may trigger a garbage collection and invalidate
-     * raw indices.  As such, a function is used to
-     * generate the new value, which
-     * can query the state of the world post-collection.
+     * Returns true if a collection is pending, that is,
+     * the region is nearly full, and allocation will soon
+     * trigger a collection.
      */
-    pub fn alloc<F>(&mut self, make_t: F) -> MutEntry<T> where
-        F: FnOnce(&Self) -> T
-    {
-        //else the index could be incorrect
-        self.ensure(1);
-        let n = self.data.len();
-        self.data.push(Spot::new(make_t(&self)));
-        MutEntry {
-            ix: Ix::new(n,
-                #[cfg(feature = "debug-arena")]
-                self.nonce,
-                #[cfg(feature = "debug-arena")]
+    #[inline]
+    pub fn gc_pending(&self) -> bool {
+        let len = self.data.len();
+        self.pins.deferred || self.policy.is_pending(len, self.data.capacity())
+    }
+
+    /**
+     * Poll for a collection, at a point where indices
+     * may safely be invalidated, such as the back-edge of
+     * an interpreter loop.
+     *
+     * If a collection is pending, and could be performed by
+     * visiting at most `budget` entries, then this performs it,
+     * making room for further allocation. Otherwise,
+     * no work is done, and the collection may still occur
+     * at a later allocation.
+     *
+     * Returns true if a collection was performed, in which case
+     * raw indices have been invalidated.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     *
+     * for _ in 0..100 {
+     *     r.alloc(|_|{()});
+     *     if r.poll_gc(1000) {
+     *         // reload any raw indices here
+     *     }
+     * }
+     * ```
+     */
+    pub fn poll_gc(&mut self, budget: usize) -> bool {
+        let len = self.data.len();
+        if !self.gc_pending() || len > budget || self.pins.is_pinned() { return false }
+        self.gc_with_capacity(2 * len, GcTrigger::Poll);
+        true
+    }
+
+    /**
+     * Spend idle time, such as between frames or requests, on
+     * collection, rather than stalling on a later allocation.
+     *
+     * A collection is performed if the region is at least half full,
+     * or a collection is pending, and the collection is expected to
+     * finish before `deadline`. The duration is estimated from
+     * the recent collections of this region, so this is
+     * a best effort, since a collection cannot be interrupted.
+     * Otherwise, if there is time, this does the smaller work of
+     * [`prune_handles`](#method.prune_handles).
+     *
+     * Returns true if a collection was performed, in which case
+     * raw indices have been invalidated.
+     *
+     * This requires the feature "std", which is enabled by default.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * use std::time::{Duration, Instant};
+     * let mut r = gc::Region::new();
+     *
+     * for _ in 0..100 {
+     *     r.alloc(|_|{()});
+     *     // the frame is done
+     *     r.gc_idle(Instant::now() + Duration::from_millis(5));
+     * }
+     * ```
+     */
+    #[cfg(feature = "std")]
+    pub fn gc_idle(&mut self, deadline: std::time::Instant) -> bool {
+        let start = std::time::Instant::now();
+        if start >= deadline || self.pins.is_pinned() { return false }
+        let len = self.data.len();
+        let worthwhile = len > 0 && (self.gc_pending() || 2 * len >= self.data.capacity());
+        // Without any record, assume a conservative cost per object
+        let cost = self.gc_log.cost_per_object()
+            .unwrap_or_else(|| core::time::Duration::from_nanos(IDLE_COST_PER_OBJECT));
+        let estimate = <u32 as core::convert::TryFrom<usize>>::try_from(len).ok().and_then(|n| cost.checked_mul(n));
+        if worthwhile && estimate.is_some_and(|e| start + e <= deadline) {
+            self.gc_with_capacity(2 * len, GcTrigger::Idle);
+            true
+        } else {
+            self.prune_handles();
+            false
+        }
+    }
+
+    // Collect into a new buffer of the given capacity, which must
+    // be at least the current length.
+    fn gc_with_capacity(&mut self, capacity: usize, trigger: GcTrigger) {
+        self.sweep_ephemerons();
+        self.gc_starting(trigger);
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        #[cfg(feature = "shadow-heap")]
+        let shadow_roots = self.shadow_pre_gc();
+        #[cfg(feature = "history")]
+        self.history_collect();
+
+        #[cfg(feature = "debug-arena")]
+        self.restamp_kept();
+        #[cfg(feature = "debug-arena")]
+        let old_gen = (self.nonce, self.generation);
+        #[cfg(feature = "debug-arena")]
+        self.advance_generation();
+
+        // The region is updated in place, so that it remains
+        // consistent if a panic occurs during the collection
+        #[allow(unused_mut)]
+        let empty = match self.spare.take() {
+            Some(spare) if spare.capacity() >= capacity => spare,
+            _ => self.data.empty_like(capacity),
+        };
+        let mut src = core::mem::replace(&mut self.data, empty);
+        self.roots.prune();
+        let root_sets = core::mem::take(&mut self.root_sets);
+        self.pins.deferred = false;
+        self.epoch += 1;
+
+        let gc_to = self.gc_to();
+        gc_to(&mut src, &mut self.data,
+            &self.roots,
+            root_sets, &mut self.root_sets,
+            &self.groups,
+            &mut self.dangling,
+            #[cfg(feature = "debug-arena")]
+            old_gen,
+            #[cfg(feature = "debug-arena")]
+            (self.nonce, self.generation));
+        self.groups = ix_range::forward_groups(&src, &self.groups);
+        self.finalize_remaining(&mut src);
+        let len = self.data.len();
+        remap::remap_all(&mut self.remaps, || remap::Forwarding::from_spots(&src, len));
+        #[cfg(feature = "refresh")]
+        self.retain_forwarding(remap::Forwarding::from_spots(&src, len),
+            #[cfg(feature = "debug-arena")]
+            old_gen.1);
+        let event = GcEvent {
+            trigger,
+            before: src.len(),
+            after: len,
+            capacity: self.data.capacity(),
+            // Collections are only timed with std
+            #[cfg(feature = "std")]
+            duration: start.elapsed(),
+            #[cfg(not(feature = "std"))]
+            duration: core::time::Duration::ZERO,
+            epoch: self.epoch,
+        };
+        self.policy.on_collected(&event);
+        self.gc_finished(event, len);
+
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_post_gc(&src, shadow_roots);
+        #[cfg(feature = "std")]
+        if self.drop_thread.is_some() {
+            return self.drop_space(src);
+        }
+        // Collections alternate between two spaces
+        src.truncate(0);
+        self.spare = Some(src);
+    }
+
+    /**
+     * Allocate a new object, returning a temporary handle,
+     * which can be used to mutate the object and to get
+     * roots, weak pointers, and internal pointers to
+     * the object.
+     *
+     * This may trigger a garbage collection and invalidate
+     * raw indices.  As such, a function is used to
+     * generate the new value, which
+     * can query the state of the world post-collection.
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn alloc<F>(&mut self, make_t: F) -> MutEntry<'_, T> where
+        F: FnOnce(&Self) -> T
+    {
+        match self.try_alloc(make_t) {
+            Ok(entry) => entry,
+            Err(e) => panic!("Region::alloc: {}", e),
+        }
+    }
+
+    /**
+     * Allocate a new object, as with [`alloc`](#method.alloc),
+     * but return an error rather than panicking if
+     * the allocation is not permitted.
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn try_alloc<F>(&mut self, make_t: F) -> Result<MutEntry<'_, T>, Error> where
+        F: FnOnce(&Self) -> T
+    {
+        self.try_alloc_cyclic(|_, r| make_t(r))
+    }
+
+    /**
+     * Allocate a new object, as with [`alloc`](#method.alloc),
+     * passing the index which the object will have to the
+     * function which creates it, as with `Rc::new_cyclic`. This allows
+     * an object to point to itself, or to objects which point to it,
+     * without a placeholder.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Node {
+     *     next: gc::Ix<Node>,
+     * }
+     * gc::impl_has_ix!(Node { next });
+     *
+     * let mut r = gc::Region::new();
+     * let a = r.alloc_cyclic(|a, _| Node { next: a }).root();
+     * r.gc();
+     * assert_eq!(a.get(&r).next.identifier(), a.ix().identifier());
+     * ```
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn alloc_cyclic<F>(&mut self, make_t: F) -> MutEntry<'_, T> where
+        F: FnOnce(Ix<T>, &Self) -> T
+    {
+        match self.try_alloc_cyclic(make_t) {
+            Ok(entry) => entry,
+            Err(e) => panic!("Region::alloc_cyclic: {}", e),
+        }
+    }
+
+    /**
+     * Allocate a new object, as with [`alloc_cyclic`](#method.alloc_cyclic),
+     * but return an error rather than panicking if
+     * the allocation is not permitted.
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn try_alloc_cyclic<F>(&mut self, make_t: F) -> Result<MutEntry<'_, T>, Error> where
+        F: FnOnce(Ix<T>, &Self) -> T
+    {
+        //else the index could be incorrect
+        self.try_ensure(1)?;
+        self.consume_fuel(1)?;
+        let n = self.data.len();
+        self.data.push(Spot::new(make_t(self.ix_at(n), self)));
+        #[cfg(feature = "std")]
+        self.index_keys(n);
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_alloc();
+        #[cfg(feature = "history")]
+        self.history_alloc(n);
+        #[cfg(feature = "alloc-sites")]
+        self.record_alloc_site(n);
+        Ok(MutEntry {
+            ix: Ix::new(n,
+                #[cfg(feature = "debug-arena")]
+                self.nonce,
+                #[cfg(feature = "debug-arena")]
                 self.generation,
                 ),
             entry: self.data.get_mut(n).unwrap().get_mut().unwrap(),
-            root: rc::Weak::new(),
-            roots: &mut self.roots
+            roots: &self.roots,
+        })
+    }
+
+    /**
+     * Notify the region that the object at an index has been
+     * mutated. This is only necessary for features
+     * which track changes to objects, such as "shadow-heap",
+     * and for objects which gain edges into other partitions,
+     * as described in [`gc_partition`](#method.gc_partition).
+     */
+    #[inline]
+    pub fn write_barrier(&mut self, ix: Ix<T>) {
+        self.partition_write(ix.ix());
+        self.incremental_write(ix.ix());
+        self.generational_write(ix.ix());
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_write(ix);
+        #[cfg(feature = "history")]
+        self.history_write(ix.ix());
+    }
+
+    /**
+     * Replace the object at an index with a new value, returning
+     * the old value. The object keeps its identity, so roots, weak
+     * pointers and edges to it are unaffected, and the write
+     * is reported as with [`write_barrier`](#method.write_barrier).
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Name(&'static str);
+     * impl gc::HasIx<Name> for Name {
+     *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+     *         F: FnMut(&'b mut gc::Ix<Name>)
+     *     { }
+     * }
+     *
+     * let mut r = gc::Region::new();
+     * let root = r.alloc(|_| Name("old")).root();
+     * let old = r.replace(root.ix(), Name("new")).unwrap();
+     * assert_eq!(old.0, "old");
+     * assert_eq!(root.get(&r).0, "new");
+     * ```
+     */
+    pub fn replace(&mut self, ix: Ix<T>, value: T) -> Result<T, Error> {
+        let old = core::mem::replace(ix.try_get_mut(self)?, value);
+        #[cfg(feature = "std")]
+        self.reindex_key(&old, ix.ix());
+        self.write_barrier(ix);
+        Ok(old)
+    }
+    /**
+     * Set the object at an index to a new value, dropping
+     * the old value, as with [`replace`](#method.replace).
+     */
+    #[inline]
+    pub fn set(&mut self, ix: Ix<T>, value: T) -> Result<(), Error> {
+        self.replace(ix, value).map(drop)
+    }
+    /**
+     * Remove the object at an index from the region, returning it.
+     * Its position is left empty, so that no other object moves and
+     * every other index remains valid, until the next collection.
+     * Its weak pointers expire, and any other index or root which still
+     * refers to it becomes invalid, as if the object had been collected.
+     * Its edges are not followed, so objects which were
+     * only reachable from it are collected as usual.
+     * Edges from other objects to it are dangling, and are handled by the
+     * next collection according to the
+     * [`DanglingEdgePolicy`](enum.DanglingEdgePolicy.html).
+     *
+     * This fails if the index is invalid, or the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Name(&'static str);
+     * gc::impl_has_ix!(Name {});
+     *
+     * let mut r = gc::Region::new();
+     * let root = r.alloc(|_| Name("taken")).root();
+     * let weak = root.downgrade(&mut r);
+     * let name = root.take(&mut r).unwrap();
+     * assert_eq!(name.0, "taken");
+     * assert!(weak.ix().is_none());
+     * ```
+     */
+    pub fn take(&mut self, ix: Ix<T>) -> Result<T, Error> {
+        ix.try_get(self)?;
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        let i = ix.ix();
+        #[cfg(feature = "history")]
+        self.history_event(HistoryEvent::Free { positions: vec![i] });
+        let dead = self.ix_at(usize::MAX);
+        let t = self.data[i].move_to(dead).into_t();
+        self.forget_freed();
+        t.ok_or(Error::UnexpectedInternalState)
+    }
+    /**
+     * Remove every object for which `keep` returns false, as with
+     * [`take`](#method.take), dropping them. No other object moves, so
+     * every other index remains valid, and objects which were only
+     * reachable from removed objects are freed by the next collection.
+     * Finalizers run for removed objects, as if they had been collected.
+     * Edges to removed objects are dangling, as with `take`.
+     *
+     * Returns the number of objects removed, or an error if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Entry { hits: u32 }
+     * gc::impl_has_ix!(Entry {});
+     *
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * let hot = r.alloc(|_| Entry { hits: 10 }).root();
+     * let cold = r.alloc(|_| Entry { hits: 1 }).root();
+     * assert_eq!(r.retain(|_, e| e.hits > 5), Ok(1));
+     * assert!(hot.try_get(&r).is_ok());
+     * assert!(cold.try_get(&r).is_err());
+     * ```
+     */
+    pub fn retain<F>(&mut self, mut keep: F) -> Result<usize, Error> where
+        F: FnMut(Ix<T>, &mut T) -> bool
+    {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
         }
+        let current = self.ix_at(0);
+        let removed: Vec<usize> = self.data.iter_mut().enumerate()
+            .filter_map(|(i, s)| {
+                let e = s.get_mut()?;
+                Some(i).filter(|&i| !keep(current.offset(i), e.get_mut()))
+            })
+            .collect();
+        #[cfg(feature = "history")]
+        self.history_event(HistoryEvent::Free { positions: removed.clone() });
+        self.free_in_place(&removed);
+        Ok(removed.len())
+    }
+
+    /**
+     * Rewrite every edge to the object at `from` to point to the
+     * object at `to` instead, including the edges of roots and root sets,
+     * such as to merge two objects or to replace an object which is deleted.
+     * Weak pointers to `from` are unaffected. Every object which
+     * is changed is reported as with [`write_barrier`](#method.write_barrier).
+     *
+     * Returns the number of edges rewritten, or an
+     * error if either index is invalid.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * let old = r.alloc(|_|{()}).root();
+     * let new = r.alloc(|_|{()}).ix();
+     * assert_eq!(r.redirect(old.ix(), new), Ok(1));
+     * r.gc();
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    pub fn redirect(&mut self, from: Ix<T>, to: Ix<T>) -> Result<usize, Error> {
+        from.try_get(self)?;
+        to.try_get(self)?;
+        let mut count = 0;
+        let mut changed = Vec::new();
+        {
+            #[cfg(feature = "std")]
+            let _scope = shared_ix::RewriteScope::enter();
+            for (i, spot) in self.data.iter_mut().enumerate() {
+                let before = count;
+                if let Some(e) = spot.get_mut() {
+                    foreach_edge(e.get_mut(), |ix| if ix.ix() == from.ix() {
+                        *ix = to;
+                        count += 1;
+                    });
+                }
+                if count > before { changed.push(i) }
+            }
+        }
+        self.roots.for_each_mut(|ix| if ix.ix() == from.ix() {
+            *ix = to;
+            count += 1;
+        });
+        for set in &self.root_sets {
+            if let Some(set) = set.upgrade() {
+                for ix in set.borrow_mut().iter_mut().filter(|ix| ix.ix() == from.ix()) {
+                    *ix = to;
+                    count += 1;
+                }
+            }
+        }
+        for i in changed {
+            self.write_barrier(self.ix_at(i));
+        }
+        Ok(count)
     }
 
     /**
      * Immediately trigger a standard garbage collection.
      *
-     * This invalidates raw indices.
+     * This invalidates raw indices. If the region is pinned,
+     * the collection is instead deferred until the next call
+     * to gc or poll_gc after the region is unpinned.
      *
      * ```rust
      * use moving_gc_arena as gc;
@@ -556,32 +1807,237 @@ impl <'a, T: 'static + HasIx<T>> Region<T> {
      * ```
      */
     pub fn gc(&mut self) {
-        let mut dst = Vec::with_capacity(self.data.len());
-        Self::prim_gc_to(&mut self.data, &mut dst, &mut self.roots,
-            #[cfg(feature = "debug-arena")]
-            (self.nonce, self.generation),
-            #[cfg(feature = "debug-arena")]
-            (self.nonce, self.generation+1));
-        self.roots = self.take_valid_roots().collect();
-        self.data = dst;
+        if self.pins.is_pinned() {
+            self.pins.deferred = true;
+            return
+        }
+        match self.policy.on_explicit() {
+            GcAlgorithm::Copying => self.gc_with_capacity(self.data.len(), GcTrigger::Explicit),
+            GcAlgorithm::None => (),
+        }
+    }
+    /**
+     * Trigger a garbage collection as with [`gc`](#method.gc), treating
+     * the given indices as additional roots, and updating them in place.
+     * This suits roots which are held outside the region, such as
+     * the operand stack of an interpreter, without creating a root for each.
+     * As with indices within objects, each must be valid for this region.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * r.alloc(|_|{()});
+     * let mut stack = vec![r.alloc(|_|{()}).ix()];
+     *
+     * r.gc_with_roots(&mut stack);
+     * assert_eq!(r.len(), 1);
+     * assert!(stack[0].try_get(&r).is_ok());
+     * ```
+     */
+    pub fn gc_with_roots<'a, I>(&mut self, extra: I) where
+        I: IntoIterator<Item=&'a mut Ix<T>>,
+    {
+        let extra: Vec<&mut Ix<T>> = extra.into_iter().collect();
+        // The indices are held as scoped handles, which
+        // are released even if the collection panics
+        self.scope(|s| {
+            let base = s.region().roots.scoped.borrow().len();
+            s.region().roots.scoped.borrow_mut().extend(extra.iter().map(|ix| **ix));
+            s.region().gc();
+            let scoped = s.region().roots.scoped.borrow();
+            for (ix, &new) in extra.into_iter().zip(&scoped[base..]) {
+                *ix = new;
+            }
+        })
+    }
+    /**
+     * Trigger a garbage collection as with [`gc`](#method.gc), but first
+     * check the heap with [`check_integrity`](#method.check_integrity),
+     * returning an error rather than panicking if it is invalid,
+     * or if the region is pinned and the collection would be deferred.
+     * When an error is returned, no collection has occurred,
+     * and all indices remain valid.
+     *
+     * This requires an additional traversal of the heap.
+     */
+    pub fn try_gc(&mut self) -> Result<(), GcError> {
+        if self.pins.is_pinned() {
+            Err(GcError::Pinned)?;
+        }
+        self.check_integrity()?;
+        self.gc();
+        Ok(())
+    }
+
+    /**
+     * Check that every index reachable from the roots is valid, and then
+     * that every reachable object satisfies its own invariants, as given by
+     * [`HasIx::validate`](trait.HasIx.html#method.validate).
+     */
+    pub fn check_integrity(&mut self) -> Result<(), GcError> {
+        let reachable = self.check_edges()?;
+        for object in reachable {
+            if let Some(e) = self.data[object].get() {
+                let ctx = ValidateCtx { region: self, ix: self.ix_at(object) };
+                if let Err(message) = e.get().validate(ctx) {
+                    Err(GcError::InvalidObject { object, message })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Assert that the object at `ix` is reachable from the roots, that is,
+     * that it would survive a collection through its edges rather than
+     * only because it shares a group with a reachable object. This is a
+     * debugging aid for finding edges missing from
+     * [`HasIx::foreach_ix`](trait.HasIx.html#tymethod.foreach_ix),
+     * such as at the end of each phase of a program.
+     *
+     * This panics if the object is unreachable, if `ix` is invalid, or
+     * if an invalid index is found, as reported by
+     * [`check_integrity`](#method.check_integrity).
+     * It requires a traversal of the heap.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * let a = r.alloc(|_|{()}).root();
+     * let b = r.alloc(|_|{()}).ix();
+     * r.assert_reachable(a.ix());
+     * let unreachable = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+     *     r.assert_reachable(b)
+     * }));
+     * assert!(unreachable.is_err());
+     * ```
+     */
+    #[track_caller]
+    pub fn assert_reachable(&mut self, ix: Ix<T>) {
+        if let Err(e) = ix.try_get(self) {
+            panic!("Region::assert_reachable: {} at index {}", e, ix.describe());
+        }
+        match self.check_edges() {
+            Err(e) => panic!("Region::assert_reachable: {}", e),
+            Ok(reachable) => if !reachable.contains(&ix.ix()) {
+                panic!("Region::assert_reachable: object {} is not reachable from any root", ix.describe());
+            },
+        }
+    }
+    /**
+     * Assert that the object at `ix` is reachable, as with
+     * [`assert_reachable`](#method.assert_reachable), only
+     * when debug assertions are enabled.
+     */
+    #[inline]
+    #[track_caller]
+    pub fn debug_assert_reachable(&mut self, ix: Ix<T>) {
+        if cfg!(debug_assertions) {
+            self.assert_reachable(ix)
+        }
+    }
+
+    // Check the indices of all objects reachable from the roots,
+    // returning the positions of those objects in the order they were found
+    fn check_edges(&mut self) -> Result<Vec<usize>, GcError> {
+        let len = self.data.len();
+        let current = self.ix_at(0);
         #[cfg(feature = "debug-arena")]
-        {
-            self.generation = self.generation+1;
+        let kept = &self.kept;
+        let mut visited = vec![false; len];
+        let mut stack = Vec::new();
+        let mut found = Vec::new();
+        let mut err = None;
+        self.foreach_root(|ix| {
+            match ix.check_current(current, #[cfg(feature = "debug-arena")] kept) {
+                Err(error) => { err.get_or_insert(GcError::InvalidRoot { index: ix.ix(), error }); },
+                // The collector ignores root set entries which are out of range
+                Ok(()) => if ix.ix() < len && !visited[ix.ix()] {
+                    visited[ix.ix()] = true;
+                    stack.push(ix.ix());
+                },
+            }
+        });
+        while err.is_none() {
+            let object = match stack.pop() {
+                Some(object) => object,
+                None => break,
+            };
+            found.push(object);
+            let entry = match self.data[object].get_mut() {
+                Some(entry) => entry,
+                None => continue,
+            };
+            foreach_edge(entry.get_mut(), |ix| {
+                let index = ix.ix();
+                if err.is_some() {
+                } else if let Err(error) = ix.check_current(current, #[cfg(feature = "debug-arena")] kept) {
+                    err = Some(GcError::InvalidEdge { object, index, error });
+                } else if index >= len {
+                    err = Some(GcError::InvalidEdge { object, index, error: Error::Indeterminable });
+                } else if !visited[index] {
+                    visited[index] = true;
+                    stack.push(index);
+                }
+            });
         }
+        err.map_or(Ok(found), Err)
     }
     /**
      * Move the elements of this region onto the end of another Region.
      * This can trigger a collection in the other region if it
      * must be re-allocated.
+     *
+     * This panics if either region is pinned.
      */
-    pub fn gc_into(mut self, other: &mut Region<T>) {
-        other.ensure(self.data.len());
-        Self::prim_gc_to(&mut self.data, &mut other.data, &mut self.roots,
+    pub fn gc_into(self, other: &mut Region<T>) {
+        if let Err((e, _)) = self.try_gc_into(other) {
+            panic!("Region::gc_into: {}", e)
+        }
+    }
+    /**
+     * Move the elements of this region onto the end of another,
+     * as with [`gc_into`](#method.gc_into), but if either
+     * region is pinned, return an error along with this region.
+     */
+    #[allow(clippy::result_large_err)]
+    pub fn try_gc_into(mut self, other: &mut Region<T>) -> Result<(), (Error, Self)> {
+        if self.pins.is_pinned() {
+            return Err((Error::Pinned, self));
+        }
+        if let Err(e) = other.try_ensure(self.data.len()) {
+            return Err((e, self));
+        }
+        #[cfg(any(feature = "std", feature = "history"))]
+        let start = other.data.len();
+        #[cfg(feature = "debug-arena")]
+        self.restamp_kept();
+        let gc_to = self.gc_to();
+        gc_to(&mut self.data, &mut other.data,
+            &self.roots,
+            core::mem::take(&mut self.root_sets), &mut other.root_sets,
+            &self.groups,
+            &mut other.dangling,
             #[cfg(feature = "debug-arena")]
             (self.nonce, self.generation),
             #[cfg(feature = "debug-arena")]
             (other.nonce, other.generation));
-        other.roots.extend(self.take_valid_roots());
+        other.groups.extend(ix_range::forward_groups(&self.data, &self.groups));
+        self.finalize_from(0);
+        let (src, len) = (&self.data, other.data.len());
+        remap::remap_all(&mut self.remaps, || remap::Forwarding::from_spots(src, len));
+        other.remaps.append(&mut self.remaps);
+        other.roots.append(core::mem::take(&mut self.roots));
+        other.adopt_finalizers(&mut self);
+        #[cfg(feature = "std")]
+        other.index_keys(start);
+        #[cfg(feature = "shadow-heap")]
+        other.shadow_alloc();
+        #[cfg(feature = "history")]
+        other.history_alloc(start);
+        Ok(())
     }
     /**
      * Return the current capacity of this region. A collection won't
@@ -591,6 +2047,71 @@ impl <'a, T: 'static + HasIx<T>> Region<T> {
     pub fn capacity(&self) -> usize {
         self.data.capacity()
     }
+    /**
+     * Release the capacity of this region beyond its current length
+     * to the allocator, or with the feature "mmap", unmap it,
+     * such as after a large transient heap has been collected.
+     * This also releases the storage kept from the previous
+     * collection for the next one to copy into.
+     * This does not invalidate any index.
+     *
+     * The next allocation will trigger a collection, or
+     * reallocation, as the region is then full.
+     *
+     * This fails if the region is pinned, as the
+     * storage may be moved when it is shrunk.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(1000);
+     * let a = r.alloc(|_|{()}).root();
+     * r.release_unused_memory().unwrap();
+     * assert_eq!(r.capacity(), 1);
+     * assert!(a.try_get(&r).is_ok());
+     * ```
+     */
+    pub fn release_unused_memory(&mut self) -> Result<(), Error> {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        self.data.shrink_to_fit();
+        self.spare = None;
+        Ok(())
+    }
+    /**
+     * Reserve capacity for at least `additional` more objects,
+     * without collecting, and touch all of the spare capacity so that
+     * the operating system commits its pages now, rather than
+     * on first use, such as during a bulk load.
+     *
+     * This is a warm-up step for latency-sensitive programs.
+     * Only the current storage is prefaulted; a collection
+     * copies objects into the storage kept from the previous
+     * collection, or newly allocated storage.
+     *
+     * This fails if the storage must be reallocated
+     * while the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.alloc(|_|{()});
+     * r.prefault(1000).unwrap();
+     * assert!(r.capacity() >= 1001);
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    pub fn prefault(&mut self, additional: usize) -> Result<(), Error> {
+        if self.data.capacity() < self.data.len() + additional {
+            if self.pins.is_pinned() {
+                Err(Error::Pinned)?;
+            }
+            self.data.reserve(additional);
+        }
+        self.data.prefault();
+        Ok(())
+    }
     /**
      * Return the current number of entries in the region.
      */
@@ -605,19 +2126,86 @@ impl <'a, T: 'static + HasIx<T>> Region<T> {
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
-    fn take_valid_roots(&mut self) -> impl Iterator<Item=rc::Weak<IxCell<T>>> + '_ {
-        self.roots.drain(..).filter(|root| {root.upgrade().is_some()})
+    /**
+     * Iterate over every object in the region, in order of position,
+     * such as for a pass over the whole heap. This includes
+     * unreachable objects which have not yet been collected.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Count(i32);
+     * gc::impl_has_ix!(Count {});
+     *
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * r.alloc(|_| Count(1));
+     * let two = r.alloc(|_| Count(2)).root();
+     * assert_eq!(r.iter().map(|c| c.0).sum::<i32>(), 3);
+     *
+     * r.gc();
+     * let objects: Vec<_> = r.iter_with_ix().map(|(ix, c)| (ix.identifier(), c.0)).collect();
+     * assert_eq!(objects, vec![(two.ix().identifier(), 2)]);
+     * ```
+     */
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        self.data.iter().filter_map(|s| Some(s.get()?.get()))
+    }
+    /**
+     * Iterate over every object in the region, as for [`iter`](#method.iter),
+     * along with its index.
+     */
+    pub fn iter_with_ix(&self) -> impl Iterator<Item=(Ix<T>, &T)> {
+        self.data.iter().enumerate()
+            .filter_map(move |(i, s)| Some((self.ix_at(i), s.get()?.get())))
+    }
+    /**
+     * Iterate mutably over every object in the region,
+     * along with its index. Changes to the edges of an object
+     * must be reported as with [`write_barrier`](#method.write_barrier).
+     */
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(Ix<T>, &mut T)> {
+        #[cfg(feature = "debug-arena")]
+        let (nonce, generation) = (self.nonce, self.generation);
+        self.data.iter_mut().enumerate().filter_map(move |(i, s)| {
+            let ix = Ix::new(i,
+                #[cfg(feature = "debug-arena")]
+                nonce,
+                #[cfg(feature = "debug-arena")]
+                generation,
+            );
+            Some((ix, s.get_mut()?.get_mut()))
+        })
+    }
+
+    /**
+     * Release the bookkeeping for roots, root sets and weak pointers
+     * which have been dropped, without moving any objects.
+     *
+     * This is done by every collection, so it is only
+     * needed when a region goes a long time between
+     * collections while creating many short-lived handles.
+     * This does not invalidate raw indices.
+     */
+    pub fn prune_handles(&mut self) {
+        self.roots.prune();
+        self.root_sets.retain(|set| set.strong_count() > 0);
+        self.remaps.retain(|r| r.strong_count() > 0);
+        for spot in self.data.iter_mut() {
+            if let Some(e) = spot.get_mut() {
+                e.check_clear_rc();
+            }
+        }
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use super::{Ix, Region, HasIx};
+    use super::{Ix, Weak, Region, HasIx, EdgeLabel, Error, GcError, DanglingEdgePolicy};
 
-    #[derive(Debug)]
-    struct Elem {
-        ix: Option<Ix<Elem>>,
+    #[derive(Debug, Clone)]
+    pub(crate) struct Elem {
+        pub(crate) ix: Option<Ix<Elem>>,
     }
     impl Elem {
         pub fn new() -> Self {
@@ -630,6 +2218,11 @@ mod tests {
         {
             self.ix.iter_mut().for_each(f)
         }
+        fn foreach_ix_labeled<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+            F: FnMut(EdgeLabel, &'b mut Ix<Elem>)
+        {
+            self.ix.iter_mut().for_each(|ix| f(EdgeLabel::Field("ix"), ix))
+        }
     }
 
     #[test]
@@ -653,6 +2246,120 @@ mod tests {
 
         // touch r
         drop(r2);
+
+        let w4: Weak<Elem> = Default::default();
+        assert!(w4.ix().is_none());
+        assert!(w4.try_get(&r).is_err());
+    }
+
+    #[test]
+    pub fn fallible_methods_report_pins() {
+        let mut r = Region::new();
+        let mut other = Region::new();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let pin = other.pin();
+        assert_eq!(other.try_ensure(1), Err(Error::Pinned));
+        let (e, mut r) = r.try_gc_into(&mut other).err().unwrap();
+        assert_eq!(e, Error::Pinned);
+        assert!(a.try_get(&r).is_ok());
+        let pin2 = r.pin();
+        assert!(r.try_alloc(|_| {Elem::new()}).is_err());
+        drop((pin, pin2));
+        assert!(r.try_gc_into(&mut other).is_ok());
+        assert!(a.try_get(&other).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Weak::get: Index expired")]
+    pub fn failed_gets_describe_the_error() {
+        let mut r = Region::new();
+        let w = r.alloc(|_| {Elem::new()}).weak();
+        r.gc();
+        w.get(&r);
+    }
+
+    #[test]
+    pub fn reachability_is_asserted() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let c = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(c) }}).ix();
+        let a = r.alloc(|_| {Elem { ix: Some(b) }}).root();
+        r.assert_reachable(c);
+        a.get_mut(&mut r).ix = None;
+        r.debug_assert_reachable(a.ix());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            r.assert_reachable(c)
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn regions_are_indexed_by_handles() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let root = r.alloc(|_| {Elem::new()}).root();
+        let weak = root.downgrade(&mut r);
+        r[&root].ix = Some(a);
+        assert_eq!(r[&weak].ix.map(Ix::identifier), Some(a.identifier()));
+        r[a].ix = Some(root.ix());
+        assert!(r[&root].ix.is_some() && r[a].ix.is_some());
+
+        r.gc();
+        assert!(weak.try_get(&r).is_ok());
+        let stale = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            drop(root);
+            r.gc();
+            let _ = &r[&weak];
+        }));
+        assert!(stale.is_err());
+    }
+
+    #[test]
+    pub fn handles_compare_by_identity() {
+        use std::collections::{BTreeSet, HashSet};
+        let mut r = Region::new();
+        r.ensure(2);
+        let mut e = r.alloc(|_| {Elem::new()});
+        let (root, weak) = (e.root(), e.weak());
+        let other = r.alloc(|_| {Elem::new()}).root();
+        let again = r.root(root.ix()).unwrap();
+
+        assert_eq!(root, root.clone());
+        assert!(root != again && root.ix() == again.ix());
+        assert_eq!(weak, root.downgrade(&mut r));
+        assert!(weak != other.downgrade(&mut r) && Weak::new() == Weak::<Elem>::new());
+        // Roots hash by identity, which their interior mutability does not change
+        #[allow(clippy::mutable_key_type)]
+        let roots: HashSet<_> = vec![root.clone(), again.clone(), root.clone()].into_iter().collect();
+        assert_eq!(roots.len(), 2);
+        let ixs: BTreeSet<_> = vec![other.ix(), root.ix(), again.ix()].into_iter().collect();
+        assert_eq!(ixs.into_iter().collect::<Vec<_>>(), vec![root.ix(), other.ix()]);
+
+        r.gc();
+        assert!(roots.contains(&root) && weak.ptr_eq(&root.downgrade(&mut r)));
+    }
+
+    #[test]
+    pub fn extra_roots_are_updated() {
+        let mut r = Region::new();
+        r.ensure(4);
+        r.alloc(|_| {Elem::new()});
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        r.alloc(|_| {Elem::new()});
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).ix();
+        let mut stack = [b, a, b];
+
+        r.gc_with_roots(stack.iter_mut());
+        assert_eq!(r.len(), 2);
+        assert_eq!(stack[0], stack[2]);
+        assert_eq!(stack[0].get(&r).ix, Some(stack[1]));
+        assert!(r.roots.scoped.borrow().is_empty());
+        r.gc_with_roots(&mut stack[..1]);
+        assert_eq!(r.len(), 2);
+        r.gc();
+        assert!(r.is_empty());
     }
 
     #[test]
@@ -662,7 +2369,7 @@ mod tests {
         let w1 = e1.weak();
         let r1 = e1.root();
         let r2 = r.alloc(|_| {Elem::new()}).root();
-        std::mem::drop(r1);
+        core::mem::drop(r1);
         r.gc();
 
         //r1 should have stopped being root on drop
@@ -680,7 +2387,7 @@ mod tests {
         let w1 = e1.weak();
         let r1 = e1.root();
         let r2 = r.alloc(|_| {Elem {ix: Some(r1.ix())}}).root();
-        std::mem::drop(r1);
+        core::mem::drop(r1);
 
         let mut e3 = r.alloc(|_| {Elem::new()});
         e3.get_mut().ix = Some(e3.ix());
@@ -695,7 +2402,7 @@ mod tests {
         //nothing changed with r4 and w5 during access
         assert!(r4.try_get(&r).is_ok());
         assert!(w5.try_get(&r).is_ok());
-        std::mem::drop(r4);
+        core::mem::drop(r4);
 
         r.gc();
 
@@ -717,4 +2424,385 @@ mod tests {
     }
 
 
+    #[test]
+    pub fn poll_gc_respects_budget() {
+        let mut r = Region::new();
+        let root = r.alloc(|_| {Elem::new()}).root();
+        while !r.gc_pending() {
+            r.alloc(|_| {Elem::new()});
+        }
+        let len = r.len();
+        assert!(!r.poll_gc(len - 1));
+        assert_eq!(r.len(), len);
+
+        assert!(r.poll_gc(len));
+        assert_eq!(r.len(), 1);
+        assert!(!r.gc_pending());
+        assert!(!r.poll_gc(len));
+        assert!(root.try_get(&r).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn gc_idle_respects_deadline() {
+        use std::time::{Duration, Instant};
+        use crate::GcTrigger;
+        let mut r = Region::new();
+        let root = r.alloc(|_| {Elem::new()}).root();
+        while !r.gc_pending() {
+            r.alloc(|_| {Elem::new()});
+        }
+        let len = r.len();
+        assert!(!r.gc_idle(Instant::now()));
+        assert_eq!(r.len(), len);
+
+        assert!(r.gc_idle(Instant::now() + Duration::from_secs(10)));
+        assert_eq!(r.len(), 1);
+        assert_eq!(r.recent_gcs().last().unwrap().trigger, GcTrigger::Idle);
+        assert!(root.try_get(&r).is_ok());
+    }
+
+    #[test]
+    pub fn replace_keeps_identity() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let mut e = r.alloc(|_| {Elem::new()});
+        let w = e.weak();
+        let a = e.root();
+        let old = r.replace(a.ix(), Elem { ix: Some(b) }).unwrap();
+        assert!(old.ix.is_none());
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert!(w.get(&r).ix.is_some());
+        r.set(a.ix(), Elem::new()).unwrap();
+        r.gc();
+        assert_eq!(r.len(), 1);
+        assert_eq!(r.set(r.ix_at(5), Elem::new()), Err(Error::Indeterminable));
+    }
+
+    #[test]
+    pub fn redirect_rewrites_edges() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let c = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        let mut set = r.root_set();
+        set.insert(a);
+        assert_eq!(r.redirect(a, b), Ok(2));
+        assert_eq!(r.redirect(a, r.ix_at(7)), Err(Error::Indeterminable));
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert!(c.get(&r).ix.unwrap().try_get(&r).is_ok());
+        assert_eq!(set.get(0).unwrap().identifier(), c.get(&r).ix.unwrap().identifier());
+    }
+
+    #[test]
+    pub fn try_gc_reports_invalid_edges() {
+        let mut other = Region::new();
+        let others: Vec<_> = (0..4).map(|_| other.alloc(|_| {Elem::new()}).root()).collect();
+
+        let mut r = Region::new();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(others[3].ix()) }}).ix();
+        a.get_mut(&mut r).ix = Some(b);
+        match r.try_gc() {
+            Err(GcError::InvalidEdge { object, index: 3, .. }) => assert_eq!(object, b.identifier()),
+            res => panic!("Unexpected result {:?}", res),
+        }
+        assert_eq!(r.len(), 2);
+        assert!(b.try_get(&r).is_ok());
+
+        b.get_mut(&mut r).ix = None;
+        let pin = r.pin();
+        assert_eq!(r.try_gc(), Err(GcError::Pinned));
+        drop(pin);
+        assert_eq!(r.try_gc(), Ok(()));
+        assert_eq!(r.len(), 2);
+    }
+
+    struct Bomb {
+        ix: Option<Ix<Bomb>>,
+        armed: bool,
+    }
+    impl HasIx<Bomb> for Bomb {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Bomb>)
+        {
+            if self.armed { panic!("Bomb") }
+            self.ix.iter_mut().for_each(f)
+        }
+    }
+
+    #[test]
+    pub fn panics_during_gc_leave_region_consistent() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Bomb { ix: None, armed: false }}).root();
+        let mut e = r.alloc(|_| {Bomb { ix: None, armed: true }});
+        let (b, wb) = (e.ix(), e.weak());
+        let wc = r.alloc(|_| {Bomb { ix: None, armed: false }}).weak();
+        a.get_mut(&mut r).ix = Some(b);
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| r.gc()));
+        assert!(res.is_err());
+        assert_eq!(r.len(), 2);
+        assert!(wc.try_get(&r).is_err());
+        let b = a.get(&r).ix.unwrap();
+        assert_eq!(b.identifier(), wb.ix().unwrap().identifier());
+
+        b.get_mut(&mut r).armed = false;
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert!(wb.try_get(&r).is_ok());
+    }
+
+    struct Outer {
+        inner: Option<Region<Inner>>,
+    }
+    struct Inner {
+        outer: Ix<Outer>,
+        _count: alloc::rc::Rc<()>,
+    }
+    impl HasIx<Outer> for Outer {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Outer>)
+        {
+            self.inner.foreach_ix(f)
+        }
+    }
+    impl HasIx<Outer> for Inner {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+            F: FnMut(&'b mut Ix<Outer>)
+        {
+            f(&mut self.outer)
+        }
+    }
+    impl HasIx<Inner> for Inner {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+            F: FnMut(&'b mut Ix<Inner>)
+        {
+        }
+    }
+
+    #[test]
+    pub fn nested_regions() {
+        let count = alloc::rc::Rc::new(());
+        let mut r = Region::new();
+        r.ensure(3);
+        r.alloc(|_| {Outer { inner: None }});
+        let b = r.alloc(|_| {Outer { inner: None }}).weak();
+        let a = r.alloc(|_| {Outer { inner: Some(Region::new()) }}).root();
+        let b_ix = b.ix().unwrap();
+        let inner = a.get_mut(&mut r).inner.as_mut().unwrap();
+        inner.alloc(|_| {Inner { outer: b_ix, _count: count.clone() }});
+
+        // b is only reachable from the inner region
+        r.gc();
+        assert_eq!(r.len(), 2);
+        let inner = a.get(&r).inner.as_ref().unwrap();
+        assert_eq!(inner.data[0].get().unwrap().get().outer.identifier(), b.ix().unwrap().identifier());
+
+        drop(a);
+        r.gc();
+        assert!(r.is_empty());
+        assert_eq!(alloc::rc::Rc::strong_count(&count), 1);
+    }
+
+    #[test]
+    pub fn prune_handles_releases_dead_handles() {
+        let mut r = Region::new();
+        r.ensure(11);
+        let ixs: Vec<_> = (0..10).map(|_| {
+            let mut e = r.alloc(|_| {Elem::new()});
+            let (_root, _weak) = (e.root(), e.weak());
+            e.ix()
+        }).collect();
+        let kept = r.alloc(|_| {Elem::new()}).root();
+        let set = r.root_set();
+        drop(set);
+        // Roots are released as soon as they are dropped
+        assert_eq!(r.roots.len(), 1);
+
+        r.prune_handles();
+        assert_eq!(r.roots.len(), 1);
+        assert!(r.root_sets.is_empty());
+        // Nothing has moved
+        assert!(ixs[3].try_get(&r).is_ok());
+        assert!(kept.try_get(&r).is_ok());
+    }
+
+    #[test]
+    pub fn collections_reuse_spaces() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let first = r.data.as_mut_ptr();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        r.alloc(|_| {Elem { ix: Some(a.ix()) }});
+        r.gc();
+        let second = r.data.as_mut_ptr();
+        assert_ne!(first, second);
+        r.gc();
+        assert_eq!(r.data.as_mut_ptr(), first);
+        r.gc();
+        assert_eq!(r.data.as_mut_ptr(), second);
+        assert_eq!(r.len(), 1);
+        r.release_unused_memory().unwrap();
+        assert!(r.spare.is_none());
+    }
+
+    #[test]
+    pub fn handles_from_indices() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let a = r.alloc(|_| {Elem::new()}).weak();
+        let b = r.alloc(|_| {Elem { ix: a.ix() }}).ix();
+        let root = r.root(b).unwrap();
+        let found = root.get(&r).ix.unwrap();
+        let weak = r.weak(found).unwrap();
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert_eq!(weak.ix().unwrap().identifier(), a.ix().unwrap().identifier());
+        assert_eq!(root.get(&r).ix.unwrap().identifier(), weak.ix().unwrap().identifier());
+
+        drop(root);
+        r.gc();
+        assert!(weak.ix().is_none());
+        assert!(r.root(b).is_err());
+    }
+
+    #[test]
+    pub fn cyclic_allocation_sees_its_index() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc_cyclic(|b, r| {
+            // Not yet allocated
+            assert!(b.try_get(r).is_err());
+            Elem { ix: Some(b) }
+        }).root();
+        assert_eq!(b.ix().identifier(), 1);
+        a.get_mut(&mut r).ix = Some(b.ix());
+        drop(b);
+        r.gc();
+        let b = a.get(&r).ix.unwrap();
+        assert_eq!(b.get(&r).ix.unwrap().identifier(), b.identifier());
+        assert_eq!(r.try_alloc_cyclic(|_, _| {Elem::new()}).unwrap().ix().identifier(), 2);
+    }
+
+    #[test]
+    pub fn iterators_visit_present_objects() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        r.alloc(|_| {Elem::new()});
+        r.alloc(|_| {Elem { ix: Some(a.ix()) }});
+        assert_eq!(r.iter().count(), 3);
+        for (ix, e) in r.iter_mut() {
+            if ix.identifier() == 1 {
+                e.ix = Some(a.ix());
+            }
+        }
+        r.write_barrier(r.ix_at(1));
+        assert_eq!(r.iter().filter(|e| e.ix.is_some()).count(), 2);
+        let mut other = Region::new();
+        r.gc_into(&mut other);
+        let ixs: Vec<_> = other.iter_with_ix().map(|(ix, e)| (ix.identifier(), e.ix.is_none())).collect();
+        assert_eq!(ixs, vec![(0, true)]);
+    }
+
+    #[test]
+    pub fn taken_objects_leave_the_region() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).root();
+        let c = r.alloc(|_| {Elem::new()}).root();
+        let weak = b.downgrade(&mut r);
+        let stale = b.ix();
+        let taken = b.take(&mut r).unwrap();
+        assert_eq!(taken.ix.unwrap().identifier(), a.ix().identifier());
+        assert!(weak.ix().is_none());
+        assert!(c.try_get(&r).is_ok());
+        assert!(r.take(stale).is_err());
+
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert!(r.take(c.ix()).is_ok());
+        assert!(c.try_get(&r).is_err());
+    }
+
+    #[test]
+    pub fn edges_to_taken_objects_are_dangling() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).root();
+        r.alloc(|_| {Elem::new()});
+        a.take(&mut r).unwrap();
+        r.set_dangling_edge_policy(DanglingEdgePolicy::Skip);
+        r.gc();
+        r.gc();
+        assert_eq!(r.len(), 1);
+        let edges = r.dangling_edges();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].object.identifier(), b.ix().identifier());
+        assert!(b.get(&r).ix.unwrap().try_get(&r).is_err());
+
+        // Or rewritten to a root
+        let c = r.alloc(|_| {Elem::new()}).root();
+        b.get_mut(&mut r).ix = Some(c.ix());
+        c.take(&mut r).unwrap();
+        let null = r.alloc(|_| {Elem::new()}).root();
+        r.set_dangling_edge_policy(DanglingEdgePolicy::Rewrite(null.clone()));
+        r.gc();
+        r.gc();
+        assert_eq!(b.get(&r).ix.unwrap().identifier(), null.ix().identifier());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid index")]
+    pub fn edges_to_taken_objects_panic_by_default() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let _b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).root();
+        a.take(&mut r).unwrap();
+        r.gc();
+    }
+
+    #[test]
+    pub fn retain_removes_rejected_objects() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let leaf = r.alloc(|_| {Elem::new()}).weak();
+        let evicted = r.alloc(|_| {Elem { ix: leaf.ix() }}).root();
+        let weak = evicted.downgrade(&mut r);
+        let kept = r.alloc(|_| {Elem { ix: Some(evicted.ix()) }}).root();
+        let mut seen = Vec::new();
+        assert_eq!(r.retain(|ix, e| {
+            seen.push(ix.identifier());
+            e.ix.is_none() || ix == kept.ix()
+        }), Ok(1));
+        assert_eq!(seen, vec![0, 1, 2]);
+        assert!(weak.ix().is_none() && evicted.try_get(&r).is_err());
+        assert!(leaf.try_get(&r).is_ok() && kept.try_get(&r).is_ok());
+
+        // The edge to the removed object is dangling, and stays so
+        r.set_dangling_edge_policy(DanglingEdgePolicy::Skip);
+        r.gc();
+        assert_eq!(r.len(), 1);
+        assert!(!leaf.is_alive());
+        let edges = r.dangling_edges();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].object.identifier(), kept.ix().identifier());
+        assert!(kept.get(&r).ix.unwrap().try_get(&r).is_err());
+        r.gc();
+        assert_eq!(r.len(), 1);
+        assert_eq!(r.dangling_edges().len(), 1);
+        assert!(kept.get(&r).ix.unwrap().try_get(&r).is_err());
+        let _pin = r.pin();
+        assert_eq!(r.retain(|_, _| false), Err(Error::Pinned));
+    }
 }
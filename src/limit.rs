@@ -0,0 +1,124 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{Error, Region};
+#[cfg(feature = "ix32")]
+use crate::Ix32;
+use crate::entry::Spot;
+
+/**
+ * A bound on the size of a region, as set by
+ * [`Region::set_limit`](struct.Region.html#method.set_limit).
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /**
+     * At most this many entries.
+     */
+    Entries(usize),
+    /**
+     * At most this many bytes of entries, including
+     * the entry headers, as with [`FuelUnit::Byte`](enum.FuelUnit.html#variant.Byte).
+     */
+    Bytes(usize),
+}
+
+impl <T> Region<T> {
+    /**
+     * Bound the number of entries in this region. An allocation
+     * which would exceed the limit first collects the region,
+     * and if the limit would still be exceeded, fails with
+     * [`Error::HeapFull`](enum.Error.html#variant.HeapFull)
+     * (or panics, for [`alloc`](#method.alloc)).
+     * The capacity of the region also does not grow past the limit.
+     *
+     * This is intended for running untrusted code, whose heap
+     * must not grow without bound. Unlike
+     * [`set_fuel`](#method.set_fuel), collected objects
+     * do not count against the limit.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.set_limit(gc::Limit::Entries(2));
+     *
+     * let _a = r.try_alloc(|_|{()}).unwrap().root();
+     * r.try_alloc(|_|{()}).unwrap();
+     * // The second object is collected to make room
+     * let _b = r.try_alloc(|_|{()}).unwrap().root();
+     * assert_eq!(r.try_alloc(|_|{()}).err(), Some(gc::Error::HeapFull));
+     * ```
+     */
+    pub fn set_limit(&mut self, limit: Limit) {
+        self.limit = Some(limit);
+    }
+    /**
+     * Remove any limit on the size of this region.
+     */
+    pub fn remove_limit(&mut self) {
+        self.limit = None;
+    }
+    /**
+     * Get the limit on the size of this region, if any.
+     */
+    #[inline]
+    pub fn limit(&self) -> Option<Limit> {
+        self.limit
+    }
+
+    // The maximum number of entries permitted by the limit
+    fn limit_entries(&self) -> usize {
+        match self.limit {
+            None => usize::MAX,
+            Some(Limit::Entries(n)) => n,
+            Some(Limit::Bytes(n)) => n / core::mem::size_of::<Spot<T>>(),
+        }
+    }
+    // The maximum number of entries permitted by the limit,
+    // and by compact indices if they are enabled
+    pub(crate) fn max_entries(&self) -> usize {
+        #[cfg(feature = "ix32")]
+        if self.compact_indices {
+            return core::cmp::min(self.limit_entries(), Ix32::<T>::MAX_ENTRIES)
+        }
+        self.limit_entries()
+    }
+    // The error for an allocation which would exceed max_entries
+    pub(crate) fn full_error(&self) -> Error {
+        #[cfg(feature = "ix32")]
+        if self.limit_entries() > self.max_entries() {
+            return Error::IndexOverflow
+        }
+        Error::HeapFull
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Limit, Region};
+    use crate::entry::Spot;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn limits_bound_capacity() {
+        let mut r = Region::new();
+        let size = core::mem::size_of::<Spot<Elem>>();
+        r.set_limit(Limit::Bytes(3 * size));
+        let a = r.alloc(|_| {Elem::new()}).root();
+        for _ in 0..10 {
+            let b = r.alloc(|_| {Elem::new()}).ix();
+            a.get_mut(&mut r).ix = Some(b);
+        }
+        assert!(r.capacity() <= 3);
+        r.alloc(|_| {Elem::new()});
+        assert_eq!(r.try_ensure(2), Err(Error::HeapFull));
+        assert_eq!(r.len(), 2);
+
+        r.remove_limit();
+        r.ensure(2);
+        assert_eq!(r.limit(), None);
+    }
+}
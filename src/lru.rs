@@ -0,0 +1,208 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use core::fmt::{Debug, Formatter};
+use core::hash::Hash;
+
+use crate::Weak;
+
+/**
+ * A cache of weak pointers to objects in a region, with a
+ * fixed capacity, evicting the least recently used entry when full.
+ *
+ * Since values are [`Weak`](struct.Weak.html), the cache never keeps
+ * an object alive. Entries whose objects have been collected are
+ * never returned, and are evicted when they are next looked up,
+ * when space is needed, or by [`purge`](#method.purge).
+ *
+ * This requires the feature "std", which is enabled by default.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut cache = gc::LruCache::new(2);
+ *
+ * let mut e = r.alloc(|_|{()});
+ * let (a, root) = (e.weak(), e.root());
+ * cache.insert("a", a);
+ * cache.insert("b", r.alloc(|_|{()}).weak());
+ * r.gc();
+ * // Only objects which are otherwise reachable remain
+ * assert!(cache.get(&"a").is_some());
+ * assert!(cache.get(&"b").is_none());
+ * assert_eq!(cache.len(), 1);
+ * ```
+ */
+pub struct LruCache<K, T> {
+    map: HashMap<K, (Weak<T>, u64)>,
+    // Keys by the time of their last use
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl <K: Hash + Eq + Clone, T> LruCache<K, T> {
+    /**
+     * Create an empty cache which holds at most `capacity` entries.
+     */
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            capacity,
+        }
+    }
+
+    fn touch(&mut self, k: &K) {
+        if let Some((_, t)) = self.map.get_mut(k) {
+            self.order.remove(t);
+            self.tick += 1;
+            *t = self.tick;
+            self.order.insert(self.tick, k.clone());
+        }
+    }
+
+    /**
+     * Insert a weak pointer for the given key, making it the most recently used,
+     * and returning the previous value for the key if its object is still alive.
+     * If the cache is full, entries whose objects have been collected are
+     * evicted first, and then the least recently used.
+     */
+    pub fn insert(&mut self, k: K, v: Weak<T>) -> Option<Weak<T>> {
+        if self.capacity == 0 { return None }
+        if let Some(old) = self.remove(&k) {
+            self.insert_new(k, v);
+            return Some(old)
+        }
+        if self.map.len() >= self.capacity {
+            self.purge();
+        }
+        if self.map.len() >= self.capacity {
+            if let Some((_, lru)) = self.order.pop_first() {
+                self.map.remove(&lru);
+            }
+        }
+        self.insert_new(k, v);
+        None
+    }
+    fn insert_new(&mut self, k: K, v: Weak<T>) {
+        self.tick += 1;
+        self.order.insert(self.tick, k.clone());
+        self.map.insert(k, (v, self.tick));
+    }
+
+    /**
+     * Look up the given key, making it the most recently used.
+     * If its object has been collected, the entry is evicted.
+     */
+    pub fn get(&mut self, k: &K) -> Option<Weak<T>> {
+        let v = self.peek(k);
+        if v.is_some() {
+            self.touch(k);
+        } else {
+            self.remove(k);
+        }
+        v
+    }
+    /**
+     * Look up the given key, without changing the order of use.
+     */
+    pub fn peek(&self, k: &K) -> Option<Weak<T>> {
+        let (v, _) = self.map.get(k)?;
+        if v.cell.strong_count() > 0 { Some(v.clone()) } else { None }
+    }
+    #[inline]
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.peek(k).is_some()
+    }
+    /**
+     * Remove the entry for the given key, returning
+     * its value if its object is still alive.
+     */
+    pub fn remove(&mut self, k: &K) -> Option<Weak<T>> {
+        let (v, t) = self.map.remove(k)?;
+        self.order.remove(&t);
+        if v.cell.strong_count() > 0 { Some(v) } else { None }
+    }
+    /**
+     * Evict every entry whose object has been collected.
+     */
+    pub fn purge(&mut self) {
+        let order = &mut self.order;
+        self.map.retain(|_, (v, t)| {
+            let live = v.cell.strong_count() > 0;
+            if !live { order.remove(t); }
+            live
+        });
+    }
+    /**
+     * Return the number of entries, including any whose
+     * objects have been collected but not yet evicted.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
+impl <K: Debug, T> Debug for LruCache<K, T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_list().entries(self.order.values()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LruCache, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn lru_cache_evicts() {
+        let mut r = Region::new();
+        let mut cache = LruCache::new(3);
+        r.ensure(4);
+        let (roots, weaks): (Vec<_>, Vec<_>) = (0..4).map(|_| {
+            let mut e = r.alloc(|_| {Elem::new()});
+            (e.root(), e.weak())
+        }).unzip();
+        for (i, w) in weaks.iter().take(3).enumerate() {
+            assert!(cache.insert(i, w.clone()).is_none());
+        }
+        assert!(cache.get(&0).is_some());
+        // 1 is the least recently used
+        cache.insert(3, weaks[3].clone());
+        assert!(cache.peek(&1).is_none());
+        assert_eq!(cache.len(), 3);
+
+        // Collected objects are evicted before the least recently used
+        let mut roots = roots.into_iter();
+        let root0 = roots.next().unwrap();
+        drop(roots);
+        r.gc();
+        assert_eq!(cache.len(), 3);
+        cache.insert(4, weaks[0].clone());
+        assert!(cache.get(&0).is_some());
+        assert!(cache.get(&4).is_some());
+        assert_eq!(cache.len(), 2);
+        drop(root0);
+        r.gc();
+        cache.purge();
+        assert!(cache.is_empty());
+    }
+}
@@ -4,10 +4,66 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use core::cmp::Ordering;
+use core::sync::atomic;
 
-static NONCE: AtomicU64 = AtomicU64::new(0);
+// The type of the nonce and generation in each index.
+// With "debug-arena-u32", nonces are reused after 2^32 regions,
+// and generations are compared with wrap-around, so an index
+// is only known to be expired for 2^31 collections.
+#[cfg(not(feature = "debug-arena-u32"))]
+pub(crate) type Word = u64;
+#[cfg(not(feature = "debug-arena-u32"))]
+type SignedWord = i64;
+#[cfg(not(feature = "debug-arena-u32"))]
+static NONCE: atomic::AtomicU64 = atomic::AtomicU64::new(0);
 
-pub(crate) fn next() -> u64 {
-    NONCE.fetch_add(1, Ordering::AcqRel)
+#[cfg(feature = "debug-arena-u32")]
+pub(crate) type Word = u32;
+#[cfg(feature = "debug-arena-u32")]
+type SignedWord = i32;
+#[cfg(feature = "debug-arena-u32")]
+static NONCE: atomic::AtomicU32 = atomic::AtomicU32::new(0);
+
+pub(crate) fn next() -> Word {
+    NONCE.fetch_add(1, atomic::Ordering::AcqRel)
+}
+
+// Compare two generations, allowing for wrap-around
+#[inline]
+pub(crate) fn cmp_generation(a: Word, b: Word) -> Ordering {
+    (a.wrapping_sub(b) as SignedWord).cmp(&0)
+}
+
+// Whether an index of an earlier generation is still valid, as its position
+// has been kept in place since that generation, given the end of each run
+// of kept positions and the generation since which it was kept
+#[inline]
+pub(crate) fn is_kept(kept: &[(usize, Word)], position: usize, generation: Word) -> bool {
+    let run = kept.partition_point(|&(end, _)| end <= position);
+    kept.get(run).is_some_and(|&(_, since)| cmp_generation(generation, since) != Ordering::Less)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cmp::Ordering;
+    use super::{cmp_generation, is_kept, Word};
+
+    #[test]
+    pub fn generations_wrap_around() {
+        assert_eq!(cmp_generation(1, 2), Ordering::Less);
+        assert_eq!(cmp_generation(Word::MAX, 0), Ordering::Less);
+        assert_eq!(cmp_generation(0, Word::MAX), Ordering::Greater);
+        assert_eq!(cmp_generation(5, 5), Ordering::Equal);
+    }
+
+    #[test]
+    pub fn kept_positions_accept_earlier_generations() {
+        let kept = [(2, 3), (5, 6)];
+        assert!(is_kept(&kept, 1, 3));
+        assert!(!is_kept(&kept, 1, 2));
+        assert!(!is_kept(&kept, 2, 5));
+        assert!(is_kept(&kept, 4, 6));
+        assert!(!is_kept(&kept, 5, 6));
+    }
 }
@@ -0,0 +1,223 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Per-thread nurseries, whose survivors are promoted
+//! into a shared tenured region.
+//!
+//! Objects of a nursery may point to objects of the shared region through
+//! imports: each import is a root of the shared region, held by the nursery
+//! until its next promotion, and its index is a position past any object,
+//! which collections of the nursery leave unchanged. When the survivors are
+//! promoted, under the write lock of the shared region, each such index is
+//! replaced by the current index of the imported object. Objects of the
+//! shared region are never given indices into a nursery, only roots to the
+//! objects promoted from it, so no edge of the shared region must be tracked.
+
+use alloc::vec::Vec;
+
+use crate::{Error, HasIx, Ix, Region, SharedRegion, SyncRoot};
+
+// The position of the index of the first import. Imports
+// are below the positions of removed objects
+pub(crate) const IMPORTS: usize = usize::MAX >> 2;
+
+/**
+ * A region owned by one worker thread, which allocates into it without
+ * synchronization, and periodically promotes its survivors into a
+ * [`SharedRegion`](struct.SharedRegion.html) with
+ * [`promote`](#method.promote).
+ *
+ * An object of the nursery points to an object of the shared region through
+ * the index returned by [`import`](#method.import), which is only meaningful
+ * as an edge of the nursery: it is left unchanged by collections of the
+ * nursery, and keeps the imported object live until the next promotion,
+ * which replaces it with the index of that object in the shared region.
+ * The root of an import is found with [`imported`](#method.imported).
+ * Weak indices to the shared region are not supported, nor are
+ * imports held by [`Ix32`](struct.Ix32.html).
+ *
+ * Objects of the shared region must not hold indices into a nursery;
+ * they are given the roots returned by `promote` instead.
+ *
+ * This requires the feature "sync".
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use std::sync::Arc;
+ * struct Node(u32, Option<gc::Ix<Node>>);
+ * gc::impl_has_ix!(Node { 1 });
+ *
+ * let mut tenured = gc::SyncRegion::new();
+ * let ix = tenured.alloc(Node(0, None));
+ * let old = tenured.root(ix).unwrap();
+ * let shared = Arc::new(gc::SharedRegion::new(tenured));
+ *
+ * let worker = {
+ *     let shared = shared.clone();
+ *     std::thread::spawn(move || {
+ *         let mut nursery = gc::Nursery::new();
+ *         let edge = nursery.import(&old);
+ *         let r = nursery.region_mut();
+ *         r.alloc(|_| Node(1, None));
+ *         let young = r.alloc(|_| Node(2, Some(edge))).ix();
+ *         nursery.promote(&shared, &[young]).unwrap().remove(0)
+ *     })
+ * };
+ * let young = worker.join().unwrap();
+ *
+ * let r = shared.write();
+ * assert_eq!(r.len(), 2);
+ * let edge = young.get(&r).1.unwrap();
+ * assert_eq!(r.try_get(edge).unwrap().0, 0);
+ * ```
+ */
+pub struct Nursery<T> {
+    region: Region<T>,
+    // The roots of the shared region which the objects point to,
+    // by the position of their index past IMPORTS
+    imports: Vec<SyncRoot<T>>,
+}
+
+impl <T> Nursery<T> {
+    pub fn new() -> Self {
+        let mut region = Region::new();
+        region.dangling.external = IMPORTS..usize::MAX >> 1;
+        Nursery { region, imports: Vec::new() }
+    }
+    /**
+     * Get the region of the nursery.
+     */
+    #[inline]
+    pub fn region(&self) -> &Region<T> {
+        &self.region
+    }
+    /**
+     * Get the region of the nursery, to allocate into it, or to collect it.
+     */
+    #[inline]
+    pub fn region_mut(&mut self) -> &mut Region<T> {
+        &mut self.region
+    }
+    /**
+     * Get an index through which objects of the nursery may point
+     * to the object of a root of the shared region. The object is
+     * kept live until the next promotion, at which the index is replaced.
+     */
+    pub fn import(&mut self, root: &SyncRoot<T>) -> Ix<T> {
+        self.imports.push(root.clone());
+        root.outside_ix(IMPORTS + self.imports.len() - 1)
+    }
+    /**
+     * Get the root of the shared region for an index
+     * returned by [`import`](#method.import), if it is one.
+     */
+    pub fn imported(&self, ix: Ix<T>) -> Option<&SyncRoot<T>> {
+        self.imports.get(ix.ix().checked_sub(IMPORTS)?)
+    }
+    /**
+     * The number of imports held until the next promotion.
+     */
+    #[inline]
+    pub fn imports_len(&self) -> usize {
+        self.imports.len()
+    }
+}
+impl <T> Default for Nursery<T> {
+    fn default() -> Self {
+        Nursery::new()
+    }
+}
+
+impl <T: 'static + HasIx<T>> Nursery<T> {
+    /**
+     * Move every object reachable from the given indices onto the end of
+     * the shared region, as with [`Region::promote`](struct.Region.html#method.promote)
+     * and [`SyncRegion`](struct.SyncRegion.html), returning a root
+     * in the shared region for each of the indices, in order.
+     * Indices of imports are replaced by the indices of the
+     * imported objects, and the imports are released.
+     *
+     * This empties the nursery, invalidating its roots, weak pointers
+     * and raw indices. It takes the write lock of the shared region,
+     * so the thread must not hold the lock of any of its objects.
+     * An error is returned if any of the indices is invalid, if the
+     * nursery is pinned, or if an import is not of the shared region.
+     */
+    pub fn promote(&mut self, shared: &SharedRegion<T>, ixs: &[Ix<T>]) -> Result<Vec<SyncRoot<T>>, Error> {
+        let mut tenured = shared.write();
+        for root in &self.imports {
+            root.try_get(&tenured)?;
+        }
+        let promotion = self.region.promote(ixs)?;
+        let roots = tenured.adopt_nursery(promotion, &self.imports);
+        self.imports.clear();
+        Ok(roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use crate::{Nursery, SharedRegion, SyncRegion};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn nurseries_promote_into_a_shared_region() {
+        let mut tenured = SyncRegion::new();
+        tenured.ensure(2);
+        tenured.alloc(Elem::new());
+        let ix = tenured.alloc(Elem::new());
+        let old = tenured.root(ix).unwrap();
+        let shared = Arc::new(SharedRegion::with_shards(tenured, 2));
+        let barrier = Arc::new(Barrier::new(3));
+
+        let workers: Vec<_> = (0..2).map(|_| {
+            let (shared, barrier, old) = (shared.clone(), barrier.clone(), old.clone());
+            std::thread::spawn(move || {
+                let mut nursery = Nursery::new();
+                let edge = nursery.import(&old);
+                assert_eq!(nursery.imported(edge).map(|root| root.get(&shared.write()).ix), Some(None));
+                let r = nursery.region_mut();
+                r.ensure(4);
+                let a = r.alloc(|_| {Elem { ix: Some(edge) }}).ix();
+                r.alloc(|_| {Elem::new()});
+                let b = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+                // Imports are kept by collections of the nursery
+                assert_eq!(r.minor_gc(), Ok(1));
+                assert_eq!(b.get(r).ix.unwrap().get(r).ix, Some(edge));
+                r.gc();
+                assert_eq!(r.len(), 2);
+
+                // The shared region moves its objects in the meantime
+                barrier.wait();
+                barrier.wait();
+                let b = b.ix();
+                let promoted = nursery.promote(&shared, &[b]).unwrap();
+                assert!(nursery.region().is_empty());
+                assert_eq!(nursery.imports_len(), 0);
+                promoted.into_iter().next().unwrap()
+            })
+        }).collect();
+        barrier.wait();
+        shared.write().gc();
+        assert_eq!(shared.write().len(), 1);
+        barrier.wait();
+        let promoted: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        let mut r = shared.write();
+        assert_eq!(r.len(), 5);
+        for b in &promoted {
+            let a = b.get(&r).ix.unwrap();
+            assert_eq!(r.try_get(a).unwrap().ix, Some(old.ix(&r)));
+        }
+        drop(old);
+        r.gc();
+        assert_eq!(r.len(), 5);
+        drop(promoted);
+        r.gc();
+        assert!(r.is_empty());
+    }
+}
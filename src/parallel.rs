@@ -0,0 +1,260 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Collections which trace objects on several threads.
+//!
+//! The copy proceeds in levels, as a breadth-first Cheney scan:
+//! every object copied by the previous level is scanned in parallel,
+//! with each thread collecting the objects it finds into its own buffer.
+//! The buffers are merged in order, so that the layout is deterministic,
+//! and the new objects are copied as the next level. Once every object
+//! has been copied, edges are rewritten in parallel.
+
+use std::ops::Range;
+use std::rc;
+
+use rayon::prelude::*;
+
+use crate::{Ix, HasIx, Region};
+use crate::entry::Spot;
+use crate::dangling::Dangling;
+use crate::has_ix::foreach_edge;
+use crate::partition::group_of;
+use crate::root_set::RootSetCell;
+use crate::space::Space;
+use crate::root_slab::Roots;
+use crate::types::SpotVariant;
+
+// The smallest number of objects scanned by a thread at once
+const MIN_CHUNK: usize = 256;
+
+// The objects of a space, shared between the threads of a collection.
+// Each thread only accesses its own objects, and only their values,
+// so the reference counts of their weak pointers are never touched.
+struct Objects<T>(*mut Spot<T>);
+impl <T> Clone for Objects<T> {
+    fn clone(&self) -> Self { *self }
+}
+impl <T> Copy for Objects<T> {}
+unsafe impl <T: Send> Send for Objects<T> {}
+unsafe impl <T: Send> Sync for Objects<T> {}
+impl <T> Objects<T> {
+    // safety: the object at i must be present, and no other
+    // reference to it may exist for the lifetime 'a
+    unsafe fn get_mut<'a>(self, i: usize) -> &'a mut T {
+        unsafe { (*self.0.add(i)).get_mut().unwrap().get_mut() }
+    }
+}
+
+impl <T: 'static + HasIx<T> + Send> Region<T> {
+    /**
+     * Choose whether collections of this region trace objects on
+     * several threads, using the global rayon thread pool, such as for
+     * heaps with millions of objects, whose tracing would dominate the pause.
+     * The layout after a collection differs from the single-threaded
+     * collector, but is still deterministic.
+     *
+     * `HasIx::foreach_ix` and `HasIx::foreach_weak_ix` are then called
+     * on other threads, and twice for each object which is kept.
+     * A panic in either is propagated once the other threads have
+     * finished, and leaves the region consistent, as for the single-threaded
+     * collector. Since objects must be `Send`, they cannot hold a
+     * [`SharedIx`](struct.SharedIx.html).
+     *
+     * This requires the feature "rayon".
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * struct Node(Option<gc::Ix<Node>>);
+     * gc::impl_has_ix!(Node { 0 });
+     *
+     * let mut r = gc::Region::new();
+     * r.set_parallel_tracing(true);
+     * r.ensure(3);
+     * let leaf = r.alloc(|_| Node(None)).ix();
+     * r.alloc(|_| Node(None));
+     * let root = r.alloc(|_| Node(Some(leaf))).root();
+     * r.gc();
+     * assert_eq!(r.len(), 2);
+     * assert!(root.get(&r).0.unwrap().try_get(&r).is_ok());
+     * ```
+     */
+    pub fn set_parallel_tracing(&mut self, enabled: bool) {
+        self.par_gc_to = if enabled { Some(Self::par_gc_to) } else { None };
+    }
+
+    // As prim_gc_to, tracing each level of objects in parallel
+    #[allow(clippy::too_many_arguments)]
+    fn par_gc_to(src: &mut [Spot<T>], dst: &mut Space<T>,
+                 roots: &Roots<T>,
+                 root_sets: Vec<rc::Weak<RootSetCell<T>>>,
+                 dst_root_sets: &mut Vec<rc::Weak<RootSetCell<T>>>,
+                 groups: &[Range<usize>],
+                 dangling: &mut Dangling<T>,
+                 #[cfg(feature = "debug-arena")] old_gen: (crate::nonce::Word, crate::nonce::Word),
+                 #[cfg(feature = "debug-arena")] new_gen: (crate::nonce::Word, crate::nonce::Word),
+                 )
+    {
+        // The objects of dst are shared with the tracing threads,
+        // so dst must not reallocate
+        dst.reserve(src.len());
+        dangling.found.clear();
+        let start = dst.len();
+        let new_ix = |i: usize| Ix::new(i,
+            #[cfg(feature = "debug-arena")]
+            new_gen.0,
+            #[cfg(feature = "debug-arena")]
+            new_gen.1,
+        );
+        // Objects which have been copied, or were already removed
+        let mut copied: Vec<bool> = src.iter().map(|s| s.get().is_none()).collect();
+        let copy = |src: &mut [Spot<T>], dst: &mut Space<T>, copied: &mut [bool], p: usize| {
+            for q in group_of(groups, p) {
+                copied[q] = true;
+                dst.push(src[q].move_to(new_ix(dst.len())));
+            }
+        };
+
+        // Roots are copied and forwarded before any user code runs
+        let mut forward_root = |ix: Ix<T>| {
+            #[cfg(feature = "debug-arena")]
+            crate::check_generation(ix, true, old_gen, new_gen);
+            if let SpotVariant::Present(_) = src.get_mut(ix.ix())?.variant() {
+                copy(src, dst, &mut copied, ix.ix());
+            }
+            src[ix.ix()].forwarded().map(|new_index| if new_index.is_dead() { new_ix(usize::MAX) } else { new_index })
+        };
+        roots.for_each_mut(|ix| if let Some(new_index) = forward_root(*ix) { *ix = new_index });
+        dst_root_sets.extend(root_sets.into_iter().filter(|set| {
+            match set.upgrade() {
+                Some(set) => {
+                    set.borrow_mut().retain_mut(|ix| {
+                        match forward_root(*ix) {
+                            Some(new_index) => { *ix = new_index; true },
+                            None => false,
+                        }
+                    });
+                    true
+                },
+                None => false,
+            }
+        }));
+
+        // Scan each level in parallel, copying the objects it finds
+        let mut scan = start;
+        while scan < dst.len() {
+            let end = dst.len();
+            let objects = Objects(dst.as_mut_ptr());
+            let copied_ref = &copied;
+            let found: Vec<Vec<usize>> = (scan..end).into_par_iter()
+                .with_min_len(MIN_CHUNK)
+                .fold(Vec::new, |mut found, i| {
+                    // safety: each object of the level is scanned by one thread
+                    let obj = unsafe { objects.get_mut(i) };
+                    foreach_edge(obj, |pointed| {
+                        #[cfg(feature = "debug-arena")]
+                        if pointed.nonce != old_gen.0 { return }
+                        if copied_ref.get(pointed.ix()) == Some(&false) {
+                            found.push(pointed.ix());
+                        }
+                    });
+                    found
+                })
+                .collect();
+            for p in found.into_iter().flatten() {
+                if !copied[p] {
+                    copy(src, dst, &mut copied, p);
+                }
+            }
+            scan = end;
+        }
+
+        // Rewrite every edge, recording dangling edges by their order
+        let forwarding: Vec<Option<Ix<T>>> = src.iter().map(Spot::forwarded).collect();
+        let end = dst.len();
+        let objects = Objects(dst.as_mut_ptr());
+        let dangling_edges: Vec<Vec<(usize, usize, bool)>> = (start..end).into_par_iter()
+            .with_min_len(MIN_CHUNK)
+            .fold(Vec::new, |mut found, i| {
+                // safety: each object is rewritten by one thread
+                let obj = unsafe { objects.get_mut(i) };
+                let mut edge = 0;
+                foreach_edge(obj, |pointed| {
+                    #[cfg(feature = "debug-arena")]
+                    if pointed.nonce != old_gen.0 && pointed.nonce != new_gen.0 {
+                        found.push((i, edge, false));
+                        edge += 1;
+                        return
+                    }
+                    #[cfg(feature = "debug-arena")]
+                    crate::check_generation(*pointed, false, old_gen, new_gen);
+                    match forwarding.get(pointed.ix()) {
+                        // The object was removed in place, such as by take
+                        Some(&Some(new_index)) if new_index.is_dead() => found.push((i, edge, true)),
+                        Some(&Some(new_index)) => *pointed = new_index,
+                        _ => found.push((i, edge, false)),
+                    }
+                    edge += 1;
+                });
+                obj.foreach_weak_ix(|weak| weak.forward(|ix| {
+                    #[cfg(feature = "debug-arena")]
+                    if ix.nonce != old_gen.0 { return None }
+                    forwarding.get(ix.ix()).copied().flatten().filter(|new| new.ix() < end)
+                }));
+                found
+            })
+            .collect();
+
+        // Dangling edges are handled on this thread, as the policy may hold a root
+        for (i, edge, removed) in dangling_edges.into_iter().flatten() {
+            let obj = dst[i].get_mut().unwrap().get_mut();
+            let mut n = 0;
+            foreach_edge(obj, |pointed| {
+                if n == edge && removed { dangling.handle_removed(i, pointed, new_ix(usize::MAX)) }
+                else if n == edge { dangling.handle(i, pointed) }
+                n += 1;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn parallel_tracing_keeps_reachable_objects() {
+        let mut r = Region::new();
+        r.set_parallel_tracing(true);
+        r.ensure(2000);
+        let root = r.alloc(|_| {Elem::new()}).root();
+        let mut last = root.ix();
+        for i in 0..1000 {
+            // Every other object is garbage
+            let e = r.alloc(|_| {Elem { ix: Some(last) }}).ix();
+            if i % 2 == 0 { last = e }
+        }
+        let head = r.alloc(|_| {Elem { ix: Some(last) }}).root();
+        root.get_mut(&mut r).ix = Some(head.ix());
+        // Groups are kept whole
+        let group = r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        let weak = r.weak(group.get(0).unwrap()).unwrap();
+        let _kept = r.root(group.get(1).unwrap()).unwrap();
+        r.gc();
+        assert_eq!(r.len(), 504);
+        assert!(weak.try_get(&r).is_ok());
+
+        let mut n = 0;
+        let mut ix = head.ix();
+        while let Some(next) = ix.get(&r).ix {
+            ix = next;
+            n += 1;
+            if ix.identifier() == root.ix().identifier() { break }
+        }
+        assert_eq!(n, 501);
+    }
+}
@@ -0,0 +1,286 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Partitions of a region, which can be collected individually.
+//!
+//! A partition is collected without moving any object, by freeing
+//! its unreachable objects in place. Edges into the partition from
+//! other partitions are found from a remembered set, which is
+//! updated lazily from the objects reported by the write barrier.
+
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::ops::Range;
+use alloc::rc::Rc;
+use alloc::{vec, vec::Vec};
+
+use crate::{Error, HasIx, Ix, Region};
+use crate::entry::Spot;
+use crate::ix_set::Bits;
+use crate::remap::{self, Forwarding, Remap};
+use crate::has_ix::foreach_edge;
+
+pub(crate) struct Partitions {
+    // The partition of each object, by position. Objects
+    // after the end were allocated in the current partition.
+    of: Vec<u32>,
+    current: u32,
+    // Objects which may have edges into other partitions
+    remembered: Bits,
+    // Objects which have changed since the remembered set was updated
+    dirty: Vec<usize>,
+}
+impl Partitions {
+    // Record the partition of every object before len
+    fn catch_up(&mut self, len: usize) {
+        while self.of.len() < len {
+            self.dirty.push(self.of.len());
+            self.of.push(self.current);
+        }
+    }
+    fn get(&self, i: usize) -> u32 {
+        self.of.get(i).copied().unwrap_or(self.current)
+    }
+    // Rescan every dirty object, which must be before the end of of
+    fn update_remembered<T: 'static + HasIx<T>>(&mut self, data: &mut [Spot<T>]) {
+        for i in core::mem::take(&mut self.dirty) {
+            let from = self.of[i];
+            let mut crosses = false;
+            if let Some(e) = data.get_mut(i).and_then(Spot::get_mut) {
+                foreach_edge(e.get_mut(), |ix| {
+                    crosses |= self.of.get(ix.ix()).is_some_and(|&to| to != from)
+                });
+            }
+            self.remembered.set(i, crosses);
+        }
+    }
+}
+impl Remap for Partitions {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        let mut of = vec![None; forwarding.len()];
+        for (i, &p) in self.of.iter().enumerate() {
+            if let Some(j) = forwarding.get(i) {
+                of[j] = Some(p);
+            }
+        }
+        self.remembered.remap(forwarding);
+        let mut dirty: Vec<usize> = self.dirty.iter().filter_map(|&i| forwarding.get(i)).collect();
+        // Objects which were not yet recorded keep the current partition
+        let current = self.current;
+        self.of = of.into_iter().enumerate().map(|(j, p)| p.unwrap_or_else(|| {
+            dirty.push(j);
+            current
+        })).collect();
+        self.dirty = dirty;
+    }
+}
+
+// Find the group containing position p
+pub(crate) fn group_of(groups: &[Range<usize>], p: usize) -> Range<usize> {
+    match groups.binary_search_by(|g| {
+        if g.end <= p { Ordering::Less }
+        else if g.start > p { Ordering::Greater }
+        else { Ordering::Equal }
+    }) {
+        Ok(i) => groups[i].clone(),
+        Err(_) => p..p+1,
+    }
+}
+
+impl <T> Region<T> {
+    fn partitions(&mut self) -> Rc<RefCell<Partitions>> {
+        if let Some(ref parts) = self.partitions {
+            return parts.clone();
+        }
+        let parts = Rc::new(RefCell::new(Partitions {
+            of: Vec::new(),
+            current: 0,
+            remembered: Bits::default(),
+            dirty: Vec::new(),
+        }));
+        self.remaps.push(Rc::downgrade(&parts) as _);
+        self.partitions = Some(parts.clone());
+        parts
+    }
+
+    /**
+     * Place every object allocated from now on in the given partition,
+     * until this is next called. Objects are otherwise in partition 0.
+     * Partitions can be collected individually with
+     * [`gc_partition`](#method.gc_partition).
+     */
+    pub fn set_partition(&mut self, partition: u32) {
+        let len = self.data.len();
+        let parts = self.partitions();
+        let mut parts = parts.borrow_mut();
+        parts.catch_up(len);
+        parts.current = partition;
+    }
+    /**
+     * Get the partition of the object at an index.
+     */
+    pub fn partition(&self, ix: Ix<T>) -> u32 {
+        match self.partitions {
+            Some(ref parts) => parts.borrow().get(ix.ix()),
+            None => 0,
+        }
+    }
+
+    pub(crate) fn partition_write(&mut self, index: usize) {
+        if let Some(ref parts) = self.partitions {
+            parts.borrow_mut().dirty.push(index);
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    // Free the objects at each position in place,
+    // removing any groups which are entirely freed.
+    pub(crate) fn free_in_place(&mut self, positions: &[usize]) {
+        let dead = self.ix_at(usize::MAX);
+        self.gc_freed(positions.len());
+        for &i in positions {
+            let spot = self.data[i].move_to(dead);
+            self.finalize_spot(i, spot);
+        }
+        self.forget_freed();
+    }
+
+    // Clear the weak indices, groups and side structures
+    // of objects which have been removed in place
+    pub(crate) fn forget_freed(&mut self) {
+        let present: Vec<bool> = self.data.iter().map(|s| s.get().is_some()).collect();
+        for e in self.data.iter_mut().filter_map(|s| s.get_mut()) {
+            e.get_mut().foreach_weak_ix(|weak| weak.forward(|ix| {
+                Some(ix).filter(|ix| present.get(ix.ix()) == Some(&true))
+            }));
+        }
+        let data = &self.data;
+        self.groups.retain(|g| data[g.start].get().is_some());
+        remap::remap_all(&mut self.remaps, || Forwarding::in_place(data));
+    }
+
+    /**
+     * Collect a single partition, freeing its unreachable objects
+     * without moving any object, so that every index remains valid.
+     * Returns the number of objects freed.
+     *
+     * Objects in the partition are kept if they are reachable from a root,
+     * or from an object in another partition, whether or not that object
+     * is itself reachable. Edges between partitions are found using a remembered
+     * set, so any object which is mutated after allocation to hold an edge
+     * into another partition must be reported with
+     * [`write_barrier`](#method.write_barrier).
+     *
+     * Freed positions are not reused until the next full collection,
+     * and are still counted by [`len`](#method.len).
+     *
+     * This fails if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(3);
+     * let a = r.alloc(|_|{()}).root();
+     * r.set_partition(1);
+     * let b = r.alloc(|_|{()}).weak();
+     * r.alloc(|_|{()});
+     *
+     * assert_eq!(r.gc_partition(1), Ok(2));
+     * assert!(a.try_get(&r).is_ok());
+     * assert!(b.try_get(&r).is_err());
+     * ```
+     */
+    pub fn gc_partition(&mut self, partition: u32) -> Result<usize, Error> {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        let len = self.data.len();
+        let parts = self.partitions();
+        let mut parts = parts.borrow_mut();
+        parts.catch_up(len);
+        parts.update_remembered(&mut self.data);
+
+        let of = &parts.of;
+        let groups = &self.groups;
+        let mut marked = vec![false; len];
+        let mut stack = Vec::new();
+        let mut mark = |p: usize, stack: &mut Vec<usize>| {
+            if p >= len || of[p] != partition || marked[p] { return }
+            for q in group_of(groups, p) {
+                if !marked[q] {
+                    marked[q] = true;
+                    stack.push(q);
+                }
+            }
+        };
+
+        self.foreach_root(|ix| mark(ix.ix(), &mut stack));
+        for i in parts.remembered.iter().filter(|&i| i < len && of[i] != partition) {
+            if let Some(e) = self.data[i].get_mut() {
+                foreach_edge(e.get_mut(), |ix| mark(ix.ix(), &mut stack));
+            }
+        }
+        // Groups which span partitions are kept whole
+        for g in groups {
+            if g.clone().any(|q| of[q] != partition) {
+                mark(g.start, &mut stack);
+            }
+        }
+        while let Some(i) = stack.pop() {
+            if let Some(e) = self.data[i].get_mut() {
+                foreach_edge(e.get_mut(), |ix| mark(ix.ix(), &mut stack));
+            }
+        }
+
+        let freed: Vec<usize> = (0..len)
+            .filter(|&i| of[i] == partition && !marked[i] && self.data[i].get().is_some())
+            .collect();
+        drop(parts);
+        #[cfg(feature = "history")]
+        self.history_event(crate::HistoryEvent::Free { positions: freed.clone() });
+        self.free_in_place(&freed);
+        Ok(freed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn partitions_collect_alone() {
+        let mut r = Region::new();
+        r.ensure(8);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let garbage0 = r.alloc(|_| {Elem::new()}).weak();
+        r.set_partition(1);
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let c = r.alloc(|_| {Elem { ix: Some(b) }}).weak();
+        let d = r.alloc(|_| {Elem::new()}).weak();
+        r.set_partition(0);
+        // Only reachable from an unreachable object in another partition
+        r.alloc(|_| {Elem { ix: c.ix() }});
+        assert_eq!(r.partition(b), 1);
+
+        // An edge added after allocation must be reported
+        a.get_mut(&mut r).ix = Some(b);
+        r.write_barrier(a.ix());
+
+        assert_eq!(r.gc_partition(1), Ok(1));
+        assert!(d.try_get(&r).is_err());
+        assert!(c.try_get(&r).is_ok());
+        assert!(b.try_get(&r).is_ok());
+        assert!(garbage0.try_get(&r).is_ok());
+        assert_eq!(r.len(), 6);
+
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r.partition(a.get(&r).ix.unwrap()), 1);
+        assert_eq!(r.gc_partition(1), Ok(0));
+    }
+}
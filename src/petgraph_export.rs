@@ -0,0 +1,106 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::vec::Vec;
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::{EdgeLabel, HasIx, Ix, Region};
+use crate::has_ix::foreach_edge_labeled;
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Export the objects in this region as a petgraph graph.
+     *
+     * Each object becomes a node, carrying the result of `node_fn`
+     * and each edge exposed by [`HasIx`](trait.HasIx.html) becomes a graph
+     * edge, in the order they are exposed. Objects which are unreachable
+     * but have not yet been collected are included, and edges which
+     * do not point to an object are omitted.
+     *
+     * Requires the feature "petgraph".
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let _a = r.alloc(|_|{()}).root();
+     * let _b = r.alloc(|_|{()}).root();
+     *
+     * let g = r.to_petgraph(|ix, _| ix.identifier());
+     * assert_eq!(g.node_count(), 2);
+     * assert_eq!(g.edge_count(), 0);
+     * ```
+     */
+    pub fn to_petgraph<N, F>(&mut self, node_fn: F) -> DiGraph<N, ()> where
+        F: FnMut(Ix<T>, &T) -> N
+    {
+        self.export(node_fn, |_| ())
+    }
+
+    /**
+     * Export the objects in this region as a petgraph graph,
+     * as with [`to_petgraph`](#method.to_petgraph), with each edge
+     * weighted by the label given by
+     * [`HasIx::foreach_ix_labeled`](trait.HasIx.html#method.foreach_ix_labeled).
+     *
+     * Requires the feature "petgraph".
+     */
+    pub fn to_petgraph_labeled<N, F>(&mut self, node_fn: F) -> DiGraph<N, EdgeLabel> where
+        F: FnMut(Ix<T>, &T) -> N
+    {
+        self.export(node_fn, |label| label)
+    }
+
+    fn export<N, E, F, G>(&mut self, mut node_fn: F, mut edge_fn: G) -> DiGraph<N, E> where
+        F: FnMut(Ix<T>, &T) -> N,
+        G: FnMut(EdgeLabel) -> E,
+    {
+        let mut graph = DiGraph::with_capacity(self.data.len(), self.data.len());
+        let mut nodes: Vec<Option<NodeIndex>> = Vec::with_capacity(self.data.len());
+        for i in 0..self.data.len() {
+            let ix = self.ix_at(i);
+            nodes.push(self.data[i].get().map(|e| {
+                graph.add_node(node_fn(ix, e.get()))
+            }));
+        }
+        for (i, spot) in self.data.iter_mut().enumerate() {
+            let (src, entry) = match (nodes[i], spot.get_mut()) {
+                (Some(src), Some(entry)) => (src, entry),
+                _ => continue,
+            };
+            foreach_edge_labeled(entry.get_mut(), |label, dst| {
+                if let Some(&Some(dst)) = nodes.get(dst.ix()) {
+                    graph.add_edge(src, dst, edge_fn(label));
+                }
+            });
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::kosaraju_scc;
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn exported_graph_mirrors_heap() {
+        let mut r = Region::new();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).ix();
+        a.get_mut(&mut r).ix = Some(b);
+        r.alloc(|_| {Elem::new()});
+
+        let g = r.to_petgraph(|ix, _| ix.identifier());
+        assert_eq!(g.node_count(), 3);
+        assert_eq!(g.edge_count(), 2);
+        assert_eq!(kosaraju_scc(&g).len(), 2);
+
+        let g = r.to_petgraph_labeled(|ix, _| ix.identifier());
+        assert!(g.raw_edges().iter().all(|e| e.weight.to_string() == "ix"));
+    }
+}
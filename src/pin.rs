@@ -0,0 +1,210 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+use crate::{Error, Ix, Region};
+
+#[derive(Default)]
+pub(crate) struct Pins {
+    count: Rc<Cell<usize>>,
+    // A collection was requested while pinned
+    pub(crate) deferred: bool,
+}
+impl Pins {
+    #[inline]
+    pub(crate) fn is_pinned(&self) -> bool {
+        self.count.get() > 0
+    }
+}
+
+/**
+ * A guard which keeps the objects of a region in place.
+ *
+ * While any guard for a region is alive, collections
+ * of that region are deferred, and allocations which would
+ * require moving the region's storage fail with
+ * [`Error::Pinned`](enum.Error.html#variant.Pinned).
+ * Deferred collections remain pending, and may be performed with
+ * [`Region::gc`](struct.Region.html#method.gc) or
+ * [`Region::poll_gc`](struct.Region.html#method.poll_gc)
+ * once every guard has been dropped.
+ */
+pub struct PinGuard {
+    count: Rc<Cell<usize>>,
+}
+impl Drop for PinGuard {
+    fn drop(&mut self) {
+        self.count.set(self.count.get() - 1);
+    }
+}
+impl Clone for PinGuard {
+    fn clone(&self) -> Self {
+        self.count.set(self.count.get() + 1);
+        PinGuard { count: self.count.clone() }
+    }
+}
+
+/**
+ * A shared borrow of a whole region, returned by
+ * [`Region::pin_all`](struct.Region.html#method.pin_all), through which
+ * raw indices can be dereferenced with a compile-time guarantee that
+ * no collection or other method which invalidates them can run
+ * while the pin, or any reference obtained from it, is alive.
+ *
+ * Unlike a [`PinGuard`](struct.PinGuard.html), this costs nothing
+ * at runtime, and references from it live as long as the pin's borrow,
+ * rather than the borrow of each call, so many objects can be held at once.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * struct Node(Option<gc::Ix<Node>>);
+ * gc::impl_has_ix!(Node { 0 });
+ *
+ * let mut r = gc::Region::new();
+ * r.ensure(2);
+ * let a = r.alloc(|_| Node(None)).ix();
+ * let b = r.alloc(|_| Node(Some(a))).ix();
+ *
+ * let pin = r.pin_all();
+ * let (x, y) = (pin.get(b), pin.get(a));
+ * // r.gc(); // error: r is borrowed by the pin
+ * assert!(std::ptr::eq(pin.get(x.0.unwrap()), y));
+ * ```
+ */
+pub struct RegionPin<'r, T> {
+    region: &'r Region<T>,
+}
+impl <T> Clone for RegionPin<'_, T> {
+    fn clone(&self) -> Self { *self }
+}
+impl <T> Copy for RegionPin<'_, T> {}
+
+impl <'r, T> RegionPin<'r, T> {
+    #[inline]
+    #[track_caller]
+    pub fn get(self, ix: Ix<T>) -> &'r T {
+        ix.get(self.region)
+    }
+    #[inline]
+    pub fn try_get(self, ix: Ix<T>) -> Result<&'r T, Error> {
+        ix.try_get(self.region)
+    }
+    /**
+     * Returns the region of this pin, for any other
+     * method which does not need to mutate it.
+     */
+    #[inline]
+    pub fn region(self) -> &'r Region<T> {
+        self.region
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Pin the whole region by borrowing it, so that raw indices can be
+     * dereferenced with a static guarantee that they are not invalidated.
+     * See [`RegionPin`](struct.RegionPin.html).
+     */
+    #[inline]
+    pub fn pin_all(&self) -> RegionPin<'_, T> {
+        RegionPin { region: self }
+    }
+    /**
+     * Get a stable pointer to the object at an index, such as
+     * for passing to foreign code. The pointer remains valid
+     * for as long as the returned guard is alive, and the
+     * region has not been dropped.
+     *
+     * As with any raw pointer, it is the caller's responsibility to
+     * avoid dereferencing it while a reference to the same object
+     * obtained through the region is in use.
+     */
+    pub fn pin_ptr(&mut self, ix: Ix<T>) -> Result<(NonNull<T>, PinGuard), Error> {
+        let ptr = NonNull::from(ix.try_get_mut(self)?);
+        Ok((ptr, self.pin()))
+    }
+    /**
+     * Prevent objects from moving, for as long as the returned guard is alive.
+     * See [`PinGuard`](struct.PinGuard.html).
+     */
+    pub fn pin(&mut self) -> PinGuard {
+        let count = self.pins.count.clone();
+        count.set(count.get() + 1);
+        PinGuard { count }
+    }
+    /**
+     * Returns true if some guard is currently preventing
+     * objects in this region from moving.
+     */
+    #[inline]
+    pub fn is_pinned(&self) -> bool {
+        self.pins.is_pinned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn pinned_objects_stay_in_place() {
+        let mut r = Region::new();
+        let root = r.alloc(|_| {Elem::new()}).root();
+        let w = r.alloc(|_| {Elem::new()}).weak();
+        let (ptr, guard) = r.pin_ptr(root.ix()).unwrap();
+        drop(root);
+
+        r.gc();
+        assert!(r.gc_pending());
+        assert!(w.try_get(&r).is_ok());
+        unsafe {
+            assert!(ptr.as_ref().ix.is_none());
+        }
+        while r.len() < r.capacity() {
+            r.alloc(|_| {Elem::new()});
+        }
+        assert_eq!(r.try_alloc(|_| {Elem::new()}).err(), Some(Error::Pinned));
+
+        drop(guard);
+        assert!(!r.is_pinned());
+        assert!(r.poll_gc(usize::MAX));
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    pub fn pinned_storage_is_not_released() {
+        let mut r = Region::new();
+        r.ensure(1000);
+        let root = r.alloc(|_| {Elem::new()}).root();
+        let (ptr, guard) = r.pin_ptr(root.ix()).unwrap();
+
+        assert_eq!(r.release_unused_memory(), Err(Error::Pinned));
+        assert_eq!(r.pin_ptr(root.ix()).unwrap().0, ptr);
+        assert!(r.capacity() >= 1000);
+
+        drop(guard);
+        r.release_unused_memory().unwrap();
+        assert_eq!(r.capacity(), 1);
+    }
+
+    #[test]
+    pub fn region_pins_follow_edges() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).ix();
+        let pin = r.pin_all();
+        let from_b = pin.get(b);
+        let from_a = pin.get(from_b.ix.unwrap());
+        assert!(from_a.ix.is_none());
+        assert_eq!(pin.try_get(r.ix_at(2)).err(), Some(Error::Indeterminable));
+        assert_eq!(pin.region().iter().count(), 2);
+    }
+}
@@ -0,0 +1,334 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::boxed::Box;
+
+use crate::{GcEvent, Region};
+
+/**
+ * The way in which a region makes room, or responds to a request for collection.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcAlgorithm {
+    /**
+     * Perform a copying collection, invalidating raw indices.
+     */
+    Copying,
+    /**
+     * Do not collect. If more capacity is needed, the storage
+     * of the region is grown without moving any objects,
+     * so raw indices remain valid.
+     */
+    None,
+}
+
+/**
+ * A strategy which decides when a region collects, and how.
+ *
+ * Every method has a default, which gives the standard behavior
+ * of regions, so a policy need only override the decisions it cares about.
+ * A policy can be installed with
+ * [`Region::set_gc_policy`](struct.Region.html#method.set_gc_policy).
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use gc::{GcAlgorithm, GcPolicy};
+ *
+ * // Collect only when asked, such as at frame boundaries
+ * struct Explicit;
+ * impl GcPolicy for Explicit {
+ *     fn on_full(&mut self, _len: usize, _capacity: usize, _additional: usize) -> GcAlgorithm {
+ *         GcAlgorithm::None
+ *     }
+ *     fn is_pending(&self, _len: usize, _capacity: usize) -> bool {
+ *         false
+ *     }
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * r.set_gc_policy(Explicit);
+ * let ix = r.alloc(|_|{()}).ix();
+ * for _ in 0..100 {
+ *     r.alloc(|_|{()});
+ * }
+ * // ix has not been invalidated
+ * assert!(ix.try_get(&r).is_ok());
+ * r.gc();
+ * assert!(r.is_empty());
+ * ```
+ */
+pub trait GcPolicy {
+    /**
+     * Decide how to make room for `additional` objects, when a region
+     * with `len` objects does not have the capacity for them.
+     */
+    fn on_full(&mut self, _len: usize, _capacity: usize, _additional: usize) -> GcAlgorithm {
+        GcAlgorithm::Copying
+    }
+    /**
+     * Decide how to respond to an explicit call to
+     * [`Region::gc`](struct.Region.html#method.gc).
+     */
+    fn on_explicit(&mut self) -> GcAlgorithm {
+        GcAlgorithm::Copying
+    }
+    /**
+     * Decide whether a collection is pending, for
+     * [`Region::gc_pending`](struct.Region.html#method.gc_pending)
+     * and [`Region::poll_gc`](struct.Region.html#method.poll_gc).
+     * By default, this is when the region is at least three-quarters full.
+     */
+    fn is_pending(&self, len: usize, capacity: usize) -> bool {
+        len > 0 && len >= capacity - capacity / 4
+    }
+    /**
+     * Observe a collection which has just completed,
+     * such as to track how many objects survive.
+     */
+    fn on_collected(&mut self, _event: &GcEvent) {}
+    /**
+     * Choose the capacity of a region with `len` objects which needs
+     * room for `additional` more, when it collects or grows. The result
+     * is raised to at least `len + additional`, and lowered to any
+     * [`Limit`](enum.Limit.html). By default, this doubles the length.
+     */
+    fn target_capacity(&self, len: usize, additional: usize) -> usize {
+        len + core::cmp::max(len, additional)
+    }
+}
+
+/**
+ * The standard policy of regions.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultPolicy;
+impl GcPolicy for DefaultPolicy {}
+
+/**
+ * A policy which avoids copying the heap when nearly every object
+ * survives. When the last collection kept more than the given fraction
+ * of objects, a full region grows instead of collecting, since
+ * a collection would likely free little. The following time it
+ * is full, it collects as usual, so garbage is still reclaimed.
+ * Explicit collections always proceed.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * r.set_gc_policy(gc::SurvivalPolicy::new(0.9));
+ * let roots: Vec<_> = (0..100).map(|_| r.alloc(|_|{()}).root()).collect();
+ * r.gc();
+ * let epoch = r.recent_gcs().last().unwrap().epoch;
+ * r.alloc(|_|{()});
+ * assert_eq!(r.recent_gcs().last().unwrap().epoch, epoch);
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct SurvivalPolicy {
+    max_survival: f64,
+    // The fraction of objects which survived the last collection
+    survival: f64,
+    skipped: bool,
+}
+impl SurvivalPolicy {
+    pub fn new(max_survival: f64) -> Self {
+        SurvivalPolicy { max_survival, survival: 0.0, skipped: false }
+    }
+}
+impl GcPolicy for SurvivalPolicy {
+    fn on_full(&mut self, _len: usize, _capacity: usize, _additional: usize) -> GcAlgorithm {
+        if self.survival > self.max_survival && !self.skipped {
+            self.skipped = true;
+            GcAlgorithm::None
+        } else {
+            GcAlgorithm::Copying
+        }
+    }
+    fn on_collected(&mut self, event: &GcEvent) {
+        self.survival = if event.before == 0 { 0.0 } else { event.after as f64 / event.before as f64 };
+        self.skipped = false;
+    }
+}
+
+/**
+ * A policy configured by a few common settings, trading memory
+ * for fewer or more predictable pauses.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let config = gc::GcConfig::new()
+ *     .growth_factor(1.5)
+ *     .min_capacity(64)
+ *     .collect_threshold(1024);
+ * let mut r = gc::Region::<()>::with_gc_config(config);
+ * assert!(r.capacity() >= 64);
+ *
+ * // Below the threshold, a full region grows rather than collecting
+ * for _ in 0..100 {
+ *     r.alloc(|_|{()});
+ * }
+ * assert_eq!(r.len(), 100);
+ * assert_eq!(r.recent_gcs().count(), 0);
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct GcConfig {
+    growth_factor: f64,
+    min_capacity: usize,
+    collect_threshold: usize,
+    automatic: bool,
+}
+impl GcConfig {
+    /**
+     * Create a configuration with the standard behavior of regions.
+     */
+    pub fn new() -> Self {
+        GcConfig { growth_factor: 2.0, min_capacity: 0, collect_threshold: 0, automatic: true }
+    }
+    /**
+     * Set the factor by which the length of a region is
+     * multiplied to give its capacity after it is full.
+     * The default is 2.
+     */
+    pub fn growth_factor(mut self, factor: f64) -> Self {
+        self.growth_factor = factor;
+        self
+    }
+    /**
+     * Set the least capacity of a region after it collects or grows, which is
+     * also reserved when the region is created by
+     * [`Region::with_gc_config`](struct.Region.html#method.with_gc_config).
+     */
+    pub fn min_capacity(mut self, capacity: usize) -> Self {
+        self.min_capacity = capacity;
+        self
+    }
+    /**
+     * Set the capacity below which a full region grows rather than collecting,
+     * such as to avoid frequent collections of a small heap.
+     */
+    pub fn collect_threshold(mut self, capacity: usize) -> Self {
+        self.collect_threshold = capacity;
+        self
+    }
+    /**
+     * Choose whether a full region may collect. If not, it always grows,
+     * and is only collected explicitly, such as by [`Region::gc`](struct.Region.html#method.gc),
+     * so raw indices are only invalidated at known points.
+     */
+    pub fn automatic(mut self, automatic: bool) -> Self {
+        self.automatic = automatic;
+        self
+    }
+}
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl GcPolicy for GcConfig {
+    fn on_full(&mut self, _len: usize, capacity: usize, _additional: usize) -> GcAlgorithm {
+        if self.automatic && capacity >= self.collect_threshold {
+            GcAlgorithm::Copying
+        } else {
+            GcAlgorithm::None
+        }
+    }
+    fn is_pending(&self, len: usize, capacity: usize) -> bool {
+        self.automatic && capacity >= self.collect_threshold
+            && DefaultPolicy.is_pending(len, capacity)
+    }
+    fn target_capacity(&self, len: usize, additional: usize) -> usize {
+        let grown = (len as f64 * self.growth_factor) as usize;
+        core::cmp::max(core::cmp::max(grown, len + additional), self.min_capacity)
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new region with a configured policy,
+     * reserving its minimum capacity.
+     */
+    pub fn with_gc_config(config: GcConfig) -> Self {
+        let mut r = Region::new();
+        r.data.reserve(config.min_capacity);
+        r.set_gc_policy(config);
+        r
+    }
+    /**
+     * Replace the policy which decides when this region collects.
+     */
+    pub fn set_gc_policy<P: GcPolicy + 'static>(&mut self, policy: P) {
+        self.policy = Box::new(policy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+    use crate::{GcAlgorithm, GcConfig, GcPolicy, GcTrigger, Region, SurvivalPolicy};
+    use crate::tests::Elem;
+
+    // Never collect during a transaction
+    struct Transactional(Rc<Cell<bool>>);
+    impl GcPolicy for Transactional {
+        fn on_full(&mut self, _len: usize, _capacity: usize, _additional: usize) -> GcAlgorithm {
+            if self.0.get() { GcAlgorithm::None } else { GcAlgorithm::Copying }
+        }
+        fn on_explicit(&mut self) -> GcAlgorithm {
+            if self.0.get() { GcAlgorithm::None } else { GcAlgorithm::Copying }
+        }
+    }
+
+    #[test]
+    pub fn policy_prevents_collection() {
+        let in_transaction = Rc::new(Cell::new(true));
+        let mut r = Region::new();
+        r.set_gc_policy(Transactional(in_transaction.clone()));
+        for _ in 0..50 {
+            r.alloc(|_| {Elem::new()});
+        }
+        r.gc();
+        assert_eq!(r.len(), 50);
+        assert_eq!(r.recent_gcs().count(), 0);
+
+        in_transaction.set(false);
+        r.gc();
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    pub fn configured_regions_grow_without_collecting() {
+        let mut r = Region::with_gc_config(GcConfig::new().automatic(false).growth_factor(1.25));
+        let ix = r.alloc(|_| {Elem::new()}).ix();
+        for _ in 0..99 {
+            r.alloc(|_| {Elem::new()});
+        }
+        assert!(!r.gc_pending());
+        assert!(ix.try_get(&r).is_ok());
+        assert!(r.capacity() < 125);
+        r.gc();
+        assert!(r.is_empty());
+        assert_eq!(r.recent_gcs().count(), 1);
+    }
+
+    #[test]
+    pub fn survival_policy_skips_copies() {
+        // Every object survives, so copies alternate with growth
+        let gcs = |r: &mut Region<Elem>| {
+            let roots: Vec<_> = (0..200).map(|_| r.alloc(|_| {Elem::new()}).root()).collect();
+            assert!(roots.iter().all(|root| root.try_get(r).is_ok()));
+            r.recent_gcs().filter(|e| e.trigger == GcTrigger::Allocation).count()
+        };
+        let copied = gcs(&mut Region::new());
+        let mut r = Region::new();
+        r.set_gc_policy(SurvivalPolicy::new(0.9));
+        let skipped = gcs(&mut r);
+        assert!(skipped > 0);
+        assert!(2 * skipped <= copied + 1);
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::ops::Range;
+use alloc::{vec, vec::Vec};
+
+use crate::{Error, HasIx, Ix, Region, Root};
+use crate::ix_range::forward_groups;
+use crate::root_slab::Roots;
+use crate::space::Space;
+
+/**
+ * A set of objects which have been moved out of one region, to be
+ * moved into another, possibly on another thread.
+ *
+ * Regions are not `Send`, since their roots are reference-counted,
+ * but a promotion contains only the objects themselves, with their
+ * internal indices rewritten relative to the promotion. So, if `T` is `Send`,
+ * so is a promotion. This allows each worker thread to allocate into
+ * its own small nursery region, and periodically promote the survivors into
+ * a tenured region owned by another thread, which adopts them.
+ *
+ * Objects in a nursery must only have indices to other objects in the same nursery.
+ * With the feature "sync", a [`Nursery`](struct.Nursery.html) also lets its
+ * objects point into a shared tenured region, which it promotes into.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use std::sync::mpsc::channel;
+ *
+ * let (send, recv) = channel();
+ * std::thread::spawn(move || {
+ *     let mut nursery = gc::Region::new();
+ *     let ix = nursery.alloc(|_|{()}).ix();
+ *     send.send(nursery.promote(&[ix]).unwrap()).unwrap();
+ * });
+ *
+ * let mut tenured = gc::Region::new();
+ * let roots = tenured.adopt(recv.recv().unwrap());
+ * assert!(roots[0].try_get(&tenured).is_ok());
+ * ```
+ */
+pub struct Promotion<T> {
+    objects: Vec<T>,
+    roots: Vec<usize>,
+    groups: Vec<Range<usize>>,
+}
+impl <T> Promotion<T> {
+    /**
+     * Return the number of objects promoted.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Move every object reachable from the given indices out of this region,
+     * into a promotion which can be adopted by another region.
+     *
+     * This empties the region: objects which are not reachable
+     * from the given indices are dropped, and every root and weak
+     * pointer of this region is invalidated, as are raw indices.
+     * An error is returned if any of the indices is invalid,
+     * or if the region is pinned.
+     */
+    pub fn promote(&mut self, ixs: &[Ix<T>]) -> Result<Promotion<T>, Error> {
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        for ix in ixs {
+            ix.try_get(self)?;
+        }
+        #[cfg(feature = "debug-arena")]
+        self.restamp_kept();
+        // Indices kept in place by a minor collection may be of earlier generations
+        let set = Rc::new(RefCell::new(ixs.iter().map(|ix| self.ix_at(ix.ix())).collect::<Vec<_>>()));
+        let mut dst = Space::with_capacity(self.data.len());
+        let gc_to = self.gc_to();
+        gc_to(&mut self.data, &mut dst,
+            &Roots::new(),
+            vec![Rc::downgrade(&set)], &mut Vec::new(),
+            &self.groups,
+            &mut self.dangling,
+            #[cfg(feature = "debug-arena")]
+            (self.nonce, self.generation),
+            #[cfg(feature = "debug-arena")]
+            (self.nonce, self.generation.wrapping_add(1)));
+        let groups = forward_groups(&self.data, &self.groups);
+        self.truncate_data(0);
+        self.epoch += 1;
+        #[cfg(feature = "debug-arena")]
+        self.advance_generation();
+
+        let roots = set.borrow().iter().map(|ix| ix.ix()).collect();
+        Ok(Promotion {
+            // Dropping each entry also drops its weak pointer cell
+            objects: dst.into_vec().into_iter().filter_map(|spot| spot.into_t()).collect(),
+            roots,
+            groups,
+        })
+    }
+
+    /**
+     * Move the objects of a promotion onto the end of this region,
+     * returning a root for each of the indices it was promoted from, in order.
+     *
+     * This can trigger a collection if the region must be re-allocated.
+     */
+    pub fn adopt(&mut self, promotion: Promotion<T>) -> Vec<Root<T>> {
+        self.ensure(promotion.objects.len());
+        // The collector only produces indices within the promotion
+        self.adopt_with(promotion, |_| None)
+    }
+    // Adopt a promotion for which there is capacity, replacing each edge
+    // past its objects, such as an import of a nursery, as given by external
+    pub(crate) fn adopt_with<F>(&mut self, promotion: Promotion<T>, external: F) -> Vec<Root<T>> where
+        F: Fn(usize) -> Option<Ix<T>>
+    {
+        let start = self.append_positional_with(promotion.objects, promotion.groups, external).unwrap();
+        let base = self.ix_at(start);
+        promotion.roots.into_iter()
+            .map(|i| self.root(base.offset(i)).unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn promotions_move_between_threads() {
+        let (send, recv) = channel();
+        std::thread::spawn(move || {
+            let mut nursery = Region::new();
+            nursery.ensure(4);
+            let a = nursery.alloc(|_| {Elem::new()}).ix();
+            nursery.alloc(|_| {Elem::new()});
+            let b = nursery.alloc(|_| {Elem { ix: Some(a) }}).ix();
+            let root = nursery.alloc(|_| {Elem::new()}).root();
+            let promotion = nursery.promote(&[b, a]).unwrap();
+            assert!(nursery.is_empty());
+            assert!(root.try_get(&nursery).is_err());
+            send.send(promotion).unwrap();
+        });
+
+        let mut tenured = Region::new();
+        tenured.ensure(3);
+        let old = tenured.alloc(|_| {Elem::new()}).root();
+        let promotion = recv.recv().unwrap();
+        assert_eq!(promotion.len(), 2);
+        let roots = tenured.adopt(promotion);
+        assert_eq!(tenured.len(), 3);
+        let b_next = roots[0].get(&tenured).ix.unwrap();
+        assert_eq!(b_next.identifier(), roots[1].ix().identifier());
+
+        tenured.gc();
+        assert_eq!(tenured.len(), 3);
+        assert!(old.try_get(&tenured).is_ok());
+    }
+}
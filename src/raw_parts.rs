@@ -0,0 +1,198 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::fmt::{Debug, Formatter};
+use core::ops::Range;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use crate::entry::Spot;
+use crate::types::IxCell;
+use crate::{HasIx, Region, Root, RootSet};
+
+/**
+ * An object of a region which has been taken apart
+ * with [`Region::into_raw_parts`](struct.Region.html#method.into_raw_parts),
+ * along with the weak pointers to it.
+ */
+pub struct RawObject<T> {
+    pub value: T,
+    weak: Option<Rc<IxCell<T>>>,
+}
+impl <T> RawObject<T> {
+    /**
+     * Create an object which no weak pointer points to.
+     */
+    pub fn new(value: T) -> Self {
+        RawObject { value, weak: None }
+    }
+    /**
+     * Returns true if any weak pointer points to this object.
+     */
+    pub fn has_weak(&self) -> bool {
+        self.weak.as_ref().is_some_and(|rc| Rc::weak_count(rc) > 0)
+    }
+}
+impl <T: Debug> Debug for RawObject<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("RawObject").field("value", &self.value).finish()
+    }
+}
+
+/**
+ * The storage and root bookkeeping of a region, for
+ * persistence, inspection or migration which the
+ * region does not provide itself.
+ *
+ * Every index is a position in `objects`, as given by
+ * [`Ix::identifier`](struct.Ix.html#method.identifier), so
+ * indices within objects, and those of roots and weak pointers,
+ * remain valid across [`Region::into_raw_parts`](struct.Region.html#method.into_raw_parts)
+ * and [`Region::from_raw_parts`](struct.Region.html#method.from_raw_parts),
+ * as long as the objects are not moved. Changing the parts can invalidate
+ * indices, but as with any invalid index, this is not unsafe.
+ */
+#[derive(Debug)]
+pub struct RawParts<T> {
+    /**
+     * The object at each position, or None where there is no object.
+     */
+    pub objects: Vec<Option<RawObject<T>>>,
+    /**
+     * The roots of the region. A root which is dropped
+     * here may still be held elsewhere.
+     */
+    pub roots: Vec<Root<T>>,
+    /**
+     * The root sets of the region, including
+     * those of [`RootMap`](struct.RootMap.html)s.
+     */
+    pub root_sets: Vec<RootSet<T>>,
+    /**
+     * Ranges of positions which are kept contiguous
+     * by collections, sorted by position.
+     */
+    pub groups: Vec<Range<usize>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Take this region apart into its objects and roots.
+     *
+     * Side structures, such as [`IdentityMap`](struct.IdentityMap.html)s
+     * and partitions, along with the policy and other settings,
+     * are not included, and are no longer updated.
+     */
+    pub fn into_raw_parts(mut self) -> RawParts<T> {
+        let data = core::mem::replace(&mut self.data, crate::space::Space::new());
+        let objects = data.into_vec().into_iter()
+            .map(|spot| spot.into_parts().map(|(value, weak)| RawObject { value, weak }))
+            .collect();
+        let roots = self.roots.handles();
+        let root_sets = self.root_sets.iter()
+            .filter_map(|set| Some(RootSet { ixs: set.upgrade()? }))
+            .collect();
+        RawParts { objects, roots, root_sets, groups: core::mem::take(&mut self.groups) }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Assemble a region from its objects and roots, which may
+     * have been taken from a region with [`into_raw_parts`](#method.into_raw_parts),
+     * or built directly. Groups which are out of order, overlap, or extend
+     * past the objects are ignored.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * let a = r.alloc(|_|{()}).root();
+     * r.alloc(|_|{()});
+     *
+     * let mut parts = r.into_raw_parts();
+     * assert_eq!(parts.objects.len(), 2);
+     * parts.objects.push(Some(gc::RawObject::new(())));
+     * let mut r = gc::Region::from_raw_parts(parts);
+     * assert!(a.try_get(&r).is_ok());
+     * r.gc();
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    pub fn from_raw_parts(parts: RawParts<T>) -> Self {
+        let mut region = Region::new();
+        let len = parts.objects.len();
+        region.data.reserve(len);
+        let current = region.ix_at(0);
+        let dead = region.ix_at(usize::MAX);
+        region.data.extend(parts.objects.into_iter().enumerate().map(|(i, object)| match object {
+            Some(RawObject { value, weak }) => {
+                if let Some(ref rc) = weak { rc.set(current.offset(i)) }
+                Spot::from_parts(value, weak)
+            },
+            None => Spot::broken_heart(dead),
+        }));
+        // The roots keep their slots, which are adopted by this region
+        for root in &parts.roots {
+            region.roots.adopt(root);
+        }
+        region.roots.for_each_mut(|ix| *ix = current.offset(ix.ix()));
+        for set in parts.root_sets {
+            set.ixs.borrow_mut().iter_mut().for_each(|ix| *ix = region.ix_at(ix.ix()));
+            region.root_sets.push(Rc::downgrade(&set.ixs));
+        }
+        let mut end = 0;
+        for group in parts.groups {
+            if group.start >= end && group.start < group.end && group.end <= len {
+                end = group.end;
+                region.groups.push(group);
+            }
+        }
+        // Indices within objects carry the identity of their old region
+        #[cfg(feature = "debug-arena")]
+        {
+            #[cfg(feature = "std")]
+            let _scope = crate::shared_ix::RewriteScope::enter();
+            for spot in region.data.iter_mut() {
+                if let Some(e) = spot.get_mut() {
+                    crate::has_ix::foreach_edge(e.get_mut(), |ix| *ix = current.offset(ix.ix()));
+                    e.get_mut().foreach_weak_ix(|weak| weak.forward(|ix| Some(current.offset(ix.ix()))));
+                }
+            }
+        }
+        region
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RawObject, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn raw_parts_round_trip() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let mut set = r.root_set();
+        let mut e = r.alloc(|_| {Elem::new()});
+        let w = e.weak();
+        let b = e.ix();
+        set.insert(r.alloc(|_| {Elem { ix: Some(b) }}).ix());
+        let c = r.alloc(|_| {Elem::new()}).root();
+
+        let mut parts = r.into_raw_parts();
+        assert_eq!(parts.objects.len(), 3);
+        assert_eq!((parts.roots.len(), parts.root_sets.len()), (1, 1));
+        assert!(parts.objects[0].as_ref().unwrap().has_weak());
+        parts.objects[2] = Some(RawObject::new(Elem { ix: Some(b) }));
+        let mut r = Region::from_raw_parts(parts);
+        r.gc();
+        assert_eq!(r.len(), 3);
+        assert!(w.try_get(&r).is_ok());
+        assert!(c.get(&r).ix.is_some());
+        assert!(set.get(0).unwrap().get(&r).ix.unwrap().try_get(&r).is_ok());
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::{vec, vec::Vec};
+
+use crate::partition::group_of;
+use crate::{HasIx, Ix, Region, Root};
+use crate::has_ix::foreach_edge;
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    // Mark the objects reachable from the given positions, without
+    // moving any object, returning their positions in the order they
+    // were found. Groups are kept whole, as in a collection, and
+    // invalid indices are ignored.
+    fn mark_from(&mut self, start: Vec<usize>) -> Vec<usize> {
+        let mut marked = vec![false; self.data.len()];
+        self.mark_into(start, &mut marked)
+    }
+    // Mark as with mark_from, skipping objects which are already marked
+    pub(crate) fn mark_into(&mut self, start: Vec<usize>, marked: &mut [bool]) -> Vec<usize> {
+        let current = self.ix_at(0);
+        #[cfg(feature = "debug-arena")]
+        let kept = &self.kept;
+        let len = self.data.len();
+        let mut found = Vec::new();
+        let mut stack = Vec::new();
+        let mut pointed = start;
+        loop {
+            for p in pointed.drain(..) {
+                if p >= len || marked[p] || self.data[p].get().is_none() { continue }
+                for q in group_of(&self.groups, p) {
+                    if !marked[q] && self.data[q].get().is_some() {
+                        marked[q] = true;
+                        stack.push(q);
+                    }
+                }
+            }
+            let object = match stack.pop() {
+                Some(object) => object,
+                None => break,
+            };
+            found.push(object);
+            foreach_edge(self.data[object].get_mut().unwrap().get_mut(), |ix| {
+                if ix.check_current(current, #[cfg(feature = "debug-arena")] kept).is_ok() {
+                    pointed.push(ix.ix())
+                }
+            });
+        }
+        found
+    }
+    fn mark_roots(&mut self) -> Vec<usize> {
+        let mut roots = Vec::new();
+        self.foreach_root(|ix| roots.push(ix.ix()));
+        self.mark_from(roots)
+    }
+
+    /**
+     * Returns true if the object at `ix` is reachable from the roots,
+     * that is, if it would survive a collection. Objects which
+     * share a group with a reachable object are reachable as well.
+     * Returns false if `ix` is not valid for this region.
+     *
+     * This does not move any objects, so indices remain valid,
+     * but it requires a traversal of the heap.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * let a = r.alloc(|_|{()}).root();
+     * let b = r.alloc(|_|{()}).ix();
+     * assert!(r.is_reachable(a.ix()));
+     * assert!(!r.is_reachable(b));
+     * ```
+     */
+    pub fn is_reachable(&mut self, ix: Ix<T>) -> bool {
+        ix.try_get(self).is_ok() && self.mark_roots().contains(&ix.ix())
+    }
+
+    /**
+     * Returns the indices of the objects retained by a root,
+     * that is, those reachable from it, including itself,
+     * in the order in which they are found.
+     *
+     * This does not move any objects, so indices remain valid.
+     * It panics if the root is not from this region.
+     */
+    pub fn reachable_from(&mut self, root: &Root<T>) -> impl Iterator<Item=Ix<T>> {
+        let ix = root.ix();
+        if let Err(e) = ix.try_get(self) {
+            panic!("Region::reachable_from: {} at index {}", e, ix.describe());
+        }
+        let found = self.mark_from(vec![ix.ix()]);
+        let ixs: Vec<Ix<T>> = found.into_iter().map(|i| self.ix_at(i)).collect();
+        ixs.into_iter()
+    }
+
+    /**
+     * Returns the number of objects reachable from the roots,
+     * which is the number of objects that a collection would keep.
+     *
+     * This does not move any objects, so indices remain valid.
+     */
+    pub fn count_reachable(&mut self) -> usize {
+        self.mark_roots().len()
+    }
+}
+
+impl <T: 'static + HasIx<T> + Clone> Region<T> {
+    /**
+     * Copy every object reachable from a root, returning a root for
+     * the copy of its object. Edges between the copied objects are
+     * updated to point to the copies, so the copy shares no object
+     * with the original, as for a persistent data structure.
+     * Weak indices to copied objects are updated likewise, while
+     * those to other objects are kept.
+     *
+     * The copies are allocated at the end of the region, as with
+     * [`alloc_slice`](#method.alloc_slice), so this can trigger
+     * a collection. Groups are copied whole.
+     * This panics if the root is not from this region,
+     * or if the allocation is not permitted.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * #[derive(Clone)]
+     * struct Node(u32, Option<gc::Ix<Node>>);
+     * gc::impl_has_ix!(Node { 1 });
+     *
+     * let mut r = gc::Region::new();
+     * r.ensure(4);
+     * let tail = r.alloc(|_| Node(1, None)).ix();
+     * let list = r.alloc(|_| Node(0, Some(tail))).root();
+     *
+     * let copy = r.clone_subgraph(&list);
+     * let copied_tail = copy.get(&r).1.unwrap();
+     * copied_tail.get_mut(&mut r).0 = 2;
+     * assert_eq!(list.get(&r).1.unwrap().get(&r).0, 1);
+     * assert_eq!(r.len(), 4);
+     * ```
+     */
+    #[cfg_attr(feature = "alloc-sites", track_caller)]
+    pub fn clone_subgraph(&mut self, root: &Root<T>) -> Root<T> {
+        let mut ix = root.get_ix();
+        if let Err(e) = ix.try_get(self) {
+            crate::access_failed("Region::clone_subgraph", Some(ix), e);
+        }
+        let epoch = self.epoch;
+        let mut found = self.mark_from(vec![ix.ix()]);
+        if let Err(e) = self.try_ensure(found.len()) {
+            panic!("Region::clone_subgraph: {}", e);
+        }
+        // The objects have moved if the region was collected
+        if self.epoch != epoch {
+            ix = root.get_ix();
+            found = self.mark_from(vec![ix.ix()]);
+        }
+        // In order of position, so that groups stay contiguous
+        found.sort_unstable();
+
+        let start = self.data.len();
+        let current = self.ix_at(0);
+        let mut copy_of = vec![None; start];
+        for (n, &i) in found.iter().enumerate() {
+            copy_of[i] = Some(current.offset(start + n));
+        }
+        let forward = |ix: Ix<T>| {
+            ix.check_current(current, #[cfg(feature = "debug-arena")] &self.kept).ok()
+                .and_then(|_| copy_of.get(ix.ix()).copied().flatten())
+        };
+        let copies = found.iter().map(|&i| {
+            let mut t = self.data[i].get().unwrap().get().clone();
+            foreach_edge(&mut t, |ix| if let Some(copy) = forward(*ix) { *ix = copy });
+            t.foreach_weak_ix(|weak| weak.forward(|ix| Some(forward(ix).unwrap_or(ix))));
+            t
+        }).collect();
+        if let Err(e) = self.try_extend(copies) {
+            panic!("Region::clone_subgraph: {}", e);
+        }
+
+        let groups: Vec<_> = self.groups.iter()
+            .filter_map(|g| copy_of.get(g.start).copied().flatten().map(|copy| copy.ix()..copy.ix() + g.len()))
+            .collect();
+        for g in groups {
+            #[cfg(feature = "history")]
+            self.history_event(crate::HistoryEvent::Group { start: g.start, len: g.len() });
+            self.groups.push(g);
+        }
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_alloc();
+        self.roots.insert(copy_of[ix.ix()].unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn reachability_queries_match_collection() {
+        let mut r = Region::new();
+        r.ensure(6);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        let garbage = r.alloc(|_| {Elem { ix: Some(a) }}).ix();
+        let group = r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        let _member = r.root(group.get(1).unwrap()).unwrap();
+
+        assert!(r.is_reachable(a));
+        assert!(!r.is_reachable(garbage));
+        assert!(r.is_reachable(group.get(0).unwrap()));
+        let retained: Vec<_> = r.reachable_from(&b).map(|ix| ix.identifier()).collect();
+        assert_eq!(retained, vec![b.ix().identifier(), a.identifier()]);
+        assert_eq!(r.count_reachable(), 4);
+        assert!(garbage.try_get(&r).is_ok());
+
+        r.gc();
+        assert_eq!(r.len(), 4);
+    }
+
+    #[test]
+    pub fn subgraphs_are_cloned() {
+        let mut r = Region::new();
+        r.ensure(7);
+        r.alloc(|_| {Elem::new()});
+        let group = r.alloc_slice(vec![Elem::new(), Elem::new()]);
+        let a = r.alloc(|_| {Elem { ix: group.get(1) }}).root();
+        group.get(1).unwrap().get_mut(&mut r).ix = Some(a.ix());
+
+        let copy = r.clone_subgraph(&a);
+        assert_eq!(r.len(), 7);
+        let member = copy.get(&r).ix.unwrap();
+        assert_eq!(member.identifier(), 5);
+        assert_eq!(member.get(&r).ix.unwrap().identifier(), copy.ix().identifier());
+
+        // The copied group is kept whole by its second member
+        drop(a);
+        r.gc();
+        assert_eq!(r.len(), 3);
+        let member = copy.get(&r).ix.unwrap();
+        assert_eq!(member.get(&r).ix.unwrap().identifier(), copy.ix().identifier());
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::remap::Forwarding;
+use crate::{Error, Ix, Region};
+
+// Where the most recent collection moved each object
+pub(crate) struct LastForwarding {
+    forwarding: Forwarding,
+    #[cfg(feature = "debug-arena")]
+    generation: crate::nonce::Word,
+}
+
+impl <T> Region<T> {
+    pub(crate) fn retain_forwarding(&mut self, forwarding: Forwarding,
+        #[cfg(feature = "debug-arena")] generation: crate::nonce::Word)
+    {
+        self.last_forwarding = Some(LastForwarding {
+            forwarding,
+            #[cfg(feature = "debug-arena")]
+            generation,
+        });
+    }
+}
+
+impl <T> Ix<T> {
+    /**
+     * Repair an index which was invalidated by the most recent
+     * collection of its region, returning the new index of its object,
+     * such as for indices held outside the region which cannot be
+     * exposed by [`HasIx`](trait.HasIx.html), like those in serialized bytecode.
+     *
+     * An error is returned if the object was collected, or if
+     * the index is older than the most recent collection.
+     * With the feature "debug-arena", an index which is already
+     * valid is returned unchanged. Otherwise, the index
+     * is assumed to be from before the most recent collection,
+     * which cannot be checked.
+     *
+     * This requires the feature "refresh", with which each
+     * collection keeps a forwarding table until the next one.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(2);
+     * r.alloc(|_|{()});
+     * let mut e = r.alloc(|_|{()});
+     * let (ix, root) = (e.ix(), e.root());
+     * r.gc();
+     * assert_eq!(ix.refresh(&r).unwrap().identifier(), root.ix().identifier());
+     * ```
+     */
+    pub fn refresh(self, region: &Region<T>) -> Result<Ix<T>, Error> {
+        #[cfg(feature = "debug-arena")]
+        {
+            if self.nonce != region.nonce {
+                Err(Error::IncorrectRegion)?;
+            }
+            if self.generation == region.generation
+                || crate::nonce::is_kept(&region.kept, self.ix(), self.generation) {
+                return self.try_get(region).map(|_| self);
+            }
+        }
+        let last = region.last_forwarding.as_ref().ok_or(Error::EntryExpired)?;
+        #[cfg(feature = "debug-arena")]
+        if self.generation != last.generation {
+            Err(Error::EntryExpired)?;
+        }
+        let ix = region.ix_at(last.forwarding.get(self.ix()).ok_or(Error::EntryExpired)?);
+        // The object may since have been freed in place
+        ix.try_get(region).map(|_| ix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn stale_indices_are_refreshed() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let dead = r.alloc(|_| {Elem::new()}).ix();
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let root = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        r.gc();
+        let new_a = a.refresh(&r).unwrap();
+        assert_eq!(new_a.identifier(), root.get(&r).ix.unwrap().identifier());
+        assert!(new_a.try_get(&r).is_ok());
+        assert_eq!(dead.refresh(&r).err(), Some(Error::EntryExpired));
+        #[cfg(feature = "debug-arena")]
+        assert_eq!(new_a.refresh(&r).unwrap().identifier(), new_a.identifier());
+        r.gc();
+        #[cfg(feature = "debug-arena")]
+        assert_eq!(a.refresh(&r).err(), Some(Error::EntryExpired));
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::fmt::{Debug, Formatter};
+use alloc::rc;
+use alloc::rc::Rc;
+
+use crate::Region;
+
+/**
+ * A token which tells whether a region still exists,
+ * such as for a subsystem which holds roots and weak pointers into
+ * a region which may be torn down, and must tell a dead handle
+ * of a live region from a handle of a dropped region.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let root = r.alloc(|_|{()}).root();
+ * let token = r.region_weak();
+ * assert!(token.is_alive());
+ * assert!(token.is_region(&r));
+ * drop(r);
+ * assert!(!token.is_alive());
+ * ```
+ */
+pub struct RegionWeak {
+    alive: rc::Weak<()>,
+}
+impl RegionWeak {
+    /**
+     * Returns true if the region has not been dropped. A region
+     * is also dropped when it is consumed, such as by
+     * [`Region::gc_into`](struct.Region.html#method.gc_into).
+     */
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        self.alive.strong_count() > 0
+    }
+    /**
+     * Returns true if this token was created from the given region.
+     */
+    #[inline]
+    pub fn is_region<T>(&self, region: &Region<T>) -> bool {
+        core::ptr::eq(self.alive.as_ptr(), Rc::as_ptr(&region.alive))
+    }
+}
+impl Clone for RegionWeak {
+    fn clone(&self) -> Self {
+        RegionWeak { alive: self.alive.clone() }
+    }
+}
+impl Debug for RegionWeak {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("RegionWeak").field("alive", &self.is_alive()).finish()
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Get a token which tells whether this region still exists.
+     */
+    #[inline]
+    pub fn region_weak(&self) -> RegionWeak {
+        RegionWeak { alive: Rc::downgrade(&self.alive) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn tokens_outlive_regions() {
+        let mut r = Region::new();
+        let mut other = Region::new();
+        r.alloc(|_| {Elem::new()});
+        let token = r.region_weak();
+        assert!(!token.clone().is_region(&other));
+        r.gc_into(&mut other);
+        assert!(!token.is_alive());
+        assert!(other.region_weak().is_alive());
+    }
+}
@@ -0,0 +1,104 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc;
+use alloc::vec::Vec;
+
+use crate::entry::Spot;
+
+// Used for positions which were not forwarded
+const NONE: usize = usize::MAX;
+
+/**
+ * The new position of each object after a collection.
+ */
+pub(crate) struct Forwarding {
+    map: Vec<usize>,
+    len: usize,
+}
+impl Forwarding {
+    // Read the forwarding pointers left in the old data. Objects which were
+    // freed in place are forwarded past the end, and were not forwarded.
+    pub(crate) fn from_spots<T>(src: &[Spot<T>], len: usize) -> Self {
+        Forwarding {
+            map: src.iter().map(|s| s.forwarded().map_or(NONE, |ix| ix.ix()))
+                .map(|i| if i < len { i } else { NONE })
+                .collect(),
+            len,
+        }
+    }
+    // Present objects stay in place, and the rest are removed
+    pub(crate) fn in_place<T>(spots: &[Spot<T>]) -> Self {
+        Forwarding {
+            map: spots.iter().enumerate().map(|(i, s)| if s.get().is_some() { i } else { NONE }).collect(),
+            len: spots.len(),
+        }
+    }
+    // Each object moves to the given position, if any
+    pub(crate) fn from_positions(map: Vec<Option<usize>>, len: usize) -> Self {
+        Forwarding {
+            map: map.into_iter().map(|i| i.unwrap_or(NONE)).collect(),
+            len,
+        }
+    }
+    // Objects before len stay in place, and the rest are removed
+    pub(crate) fn truncation(old_len: usize, len: usize) -> Self {
+        Forwarding {
+            map: (0..old_len).map(|i| if i < len { i } else { NONE }).collect(),
+            len,
+        }
+    }
+    /**
+     * Get the new position of the object at the old position, if it survived.
+     */
+    #[inline]
+    pub(crate) fn get(&self, old: usize) -> Option<usize> {
+        match self.map.get(old) {
+            Some(&NONE) | None => None,
+            Some(&new) => Some(new),
+        }
+    }
+    /**
+     * The number of objects in the source region.
+     */
+    #[inline]
+    pub(crate) fn old_len(&self) -> usize {
+        self.map.len()
+    }
+    /**
+     * The number of objects in the destination region.
+     */
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/**
+ * A side-structure which stores data by position,
+ * and must be updated when objects move.
+ */
+pub(crate) trait Remap {
+    fn remap(&mut self, forwarding: &Forwarding);
+}
+
+pub(crate) type Remaps = Vec<rc::Weak<RefCell<dyn Remap>>>;
+
+// Update every live side-structure after objects move,
+// dropping those which are no longer alive
+pub(crate) fn remap_all<F>(remaps: &mut Remaps, forwarding: F) where
+    F: FnOnce() -> Forwarding
+{
+    remaps.retain(|r| r.strong_count() > 0);
+    if remaps.is_empty() { return }
+    let forwarding = forwarding();
+    for r in remaps.iter() {
+        if let Some(r) = r.upgrade() {
+            r.borrow_mut().remap(&forwarding);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use std::collections::HashMap;
+use core::fmt::{Debug, Formatter};
+use core::hash::Hash;
+use alloc::rc::Rc;
+
+use crate::types::Ix;
+use crate::root_set::RootSetCell;
+use crate::Region;
+
+/**
+ * A map from keys to objects, in which every value is a root,
+ * such as the global environment or module table of an interpreter.
+ *
+ * As with a [`RootSet`](struct.RootSet.html), the values are
+ * registered with the region only once, and are updated
+ * in place by each collection, so inserting or removing
+ * entries does not touch any reference counts.
+ *
+ * Values must be valid for the region the map was created from.
+ * If an invalid index is inserted, the behavior of the map is
+ * unspecified, as with other invalid indices.
+ *
+ * This requires the feature "std", which is enabled by default.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut globals = r.root_map();
+ *
+ * let ix = r.alloc(|_|{()}).ix();
+ * globals.insert("main", ix);
+ * r.alloc(|_|{()});
+ *
+ * r.gc();
+ * assert_eq!(r.len(), 1);
+ * assert!(globals.get("main").unwrap().try_get(&r).is_ok());
+ * ```
+ */
+pub struct RootMap<K, T> {
+    ixs: Rc<RootSetCell<T>>,
+    // The key of each value in ixs, by position
+    keys: Vec<K>,
+    positions: HashMap<K, usize>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new, empty, root map which is registered with this region.
+     */
+    pub fn root_map<K: Hash + Eq + Clone>(&mut self) -> RootMap<K, T> {
+        let ixs = Rc::new(RefCell::new(Vec::new()));
+        self.root_sets.push(Rc::downgrade(&ixs));
+        RootMap { ixs, keys: Vec::new(), positions: HashMap::new() }
+    }
+}
+
+impl <K: Hash + Eq + Clone, T> RootMap<K, T> {
+    /**
+     * Insert a value for the given key, returning the previous value, if any.
+     */
+    pub fn insert(&mut self, key: K, ix: Ix<T>) -> Option<Ix<T>> {
+        let mut ixs = self.ixs.borrow_mut();
+        match self.positions.get(&key) {
+            Some(&pos) => Some(core::mem::replace(&mut ixs[pos], ix)),
+            None => {
+                self.positions.insert(key.clone(), ixs.len());
+                self.keys.push(key);
+                ixs.push(ix);
+                None
+            },
+        }
+    }
+    /**
+     * Get the value for the given key. As with other
+     * raw indices, it is only valid until the region
+     * is next collected.
+     */
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
+    pub fn get<Q>(&self, key: &Q) -> Option<Ix<T>> where
+        K: core::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = *self.positions.get(key)?;
+        let ix = *self.ixs.borrow().get(pos)?;
+        Some(ix.tracked())
+    }
+    /**
+     * Check if this map has a value for the given key.
+     */
+    pub fn contains_key<Q>(&self, key: &Q) -> bool where
+        K: core::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.positions.contains_key(key)
+    }
+    /**
+     * Remove the value for the given key, returning it, if any.
+     */
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Ix<T>> where
+        K: core::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = self.positions.remove(key)?;
+        self.keys.swap_remove(pos);
+        if let Some(moved) = self.keys.get(pos) {
+            self.positions.insert(moved.clone(), pos);
+        }
+        let mut ixs = self.ixs.borrow_mut();
+        if pos < ixs.len() { Some(ixs.swap_remove(pos)) } else { None }
+    }
+    /**
+     * Keep only the entries for which the predicate returns true,
+     * removing the others in one pass.
+     */
+    pub fn retain<F>(&mut self, mut f: F) where
+        F: FnMut(&K, Ix<T>) -> bool
+    {
+        let mut ixs = self.ixs.borrow_mut();
+        let mut kept = 0;
+        for pos in 0..core::cmp::min(self.keys.len(), ixs.len()) {
+            if f(&self.keys[pos], ixs[pos]) {
+                self.keys.swap(kept, pos);
+                ixs.swap(kept, pos);
+                kept += 1;
+            }
+        }
+        self.keys.truncate(kept);
+        ixs.truncate(kept);
+        self.positions = self.keys.iter().cloned().zip(0..).collect();
+    }
+    /**
+     * Remove every entry from this map.
+     */
+    pub fn clear(&mut self) {
+        self.ixs.borrow_mut().clear();
+        self.keys.clear();
+        self.positions.clear();
+    }
+    /**
+     * Return the number of entries in this map.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /**
+     * Returns true if there are currently no entries in this map.
+     */
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /**
+     * Iterate over the keys of this map.
+     */
+    pub fn keys(&self) -> impl Iterator<Item=&K> + '_ {
+        self.keys.iter()
+    }
+    /**
+     * Iterate over the entries of this map, in no particular order.
+     * As with other raw indices, the values are only valid until
+     * the region is next collected.
+     */
+    pub fn iter(&self) -> impl Iterator<Item=(&K, Ix<T>)> + '_ {
+        self.keys.iter().enumerate()
+            .filter_map(move |(pos, k)| Some((k, *self.ixs.borrow().get(pos)?)))
+    }
+}
+impl <K: Debug, T> Debug for RootMap<K, T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_map().entries(self.keys.iter().zip(self.ixs.borrow().iter())).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn map_values_are_roots() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let mut map = r.root_map();
+        let w1 = r.alloc(|_| {Elem::new()}).weak();
+        let w2 = r.alloc(|_| {Elem::new()}).weak();
+        let w3 = r.alloc(|_| {Elem::new()}).weak();
+        map.insert("a", w1.ix().unwrap());
+        map.insert("b", w2.ix().unwrap());
+        map.insert("c", w3.ix().unwrap());
+        assert!(map.insert("c", w1.ix().unwrap()).is_some());
+
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert!(w3.try_get(&r).is_err());
+        assert_eq!(map.get("c").unwrap().identifier(), w1.ix().unwrap().identifier());
+
+        assert!(map.remove("a").is_some());
+        assert!(map.get("b").unwrap().try_get(&r).is_ok());
+        map.retain(|&k, _| k == "b");
+        assert_eq!(map.len(), 1);
+        r.gc();
+        assert_eq!(r.len(), 1);
+        assert!(w2.try_get(&r).is_ok());
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![&"b"]);
+    }
+}
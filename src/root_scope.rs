@@ -0,0 +1,168 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::marker::PhantomData;
+
+use crate::{Error, Ix, Region};
+
+/**
+ * A scope of temporary roots, created with
+ * [`Region::scope`](struct.Region.html#method.scope).
+ *
+ * Every [`Handle`](struct.Handle.html) created in the scope keeps its
+ * object live, as a [`Root`](struct.Root.html) would, until the scope
+ * ends. Handles are kept in a stack in the region, so creating one
+ * only pushes an index, and every handle of a scope is released at
+ * once when it ends. The region can be used through
+ * [`region`](#method.region) while the scope is open, including to
+ * allocate or collect, and handles are updated by each collection.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * struct Node(Option<gc::Ix<Node>>);
+ * gc::impl_has_ix!(Node { 0 });
+ *
+ * let mut r = gc::Region::new();
+ * let found = r.scope(|s| {
+ *     s.region().ensure(2);
+ *     let leaf = s.region().alloc(|_| Node(None)).ix();
+ *     let leaf = s.handle(leaf).unwrap();
+ *     let node = s.region().alloc(|_| Node(None)).ix();
+ *     let node = s.handle(node).unwrap();
+ *     s.get_mut(node).0 = Some(s.ix(leaf));
+ *
+ *     s.region().gc();
+ *     s.get(node).0.unwrap().try_get(s.region()).is_ok()
+ * });
+ * assert!(found);
+ * r.gc();
+ * assert!(r.is_empty());
+ * ```
+ */
+pub struct RootScope<'s, T> {
+    region: &'s mut Region<T>,
+    // The length of the stack when this scope began
+    base: usize,
+}
+
+/**
+ * A temporary root, which keeps its object live until
+ * its [`RootScope`](struct.RootScope.html) ends.
+ *
+ * A handle is only a position in the stack of its scope,
+ * so it is `Copy`, and is used through the scope,
+ * which cannot be escaped.
+ */
+pub struct Handle<'s, T> {
+    index: usize,
+    // Invariant in 's, so that a handle cannot outlive its scope
+    _scope: PhantomData<fn(&'s ()) -> &'s ()>,
+    _type: PhantomData<fn(T) -> T>,
+}
+impl <T> Clone for Handle<'_, T> {
+    fn clone(&self) -> Self { *self }
+}
+impl <T> Copy for Handle<'_, T> {}
+
+impl <T> Region<T> {
+    /**
+     * Open a scope of temporary roots, which are released
+     * when `f` returns, or unwinds.
+     * See [`RootScope`](struct.RootScope.html).
+     */
+    pub fn scope<R, F>(&mut self, f: F) -> R where
+        F: for<'s> FnOnce(&mut RootScope<'s, T>) -> R,
+    {
+        let base = self.roots.scoped.borrow().len();
+        f(&mut RootScope { region: self, base })
+    }
+}
+
+impl <T> Drop for RootScope<'_, T> {
+    fn drop(&mut self) {
+        self.region.roots.scoped.borrow_mut().truncate(self.base);
+    }
+}
+
+impl <'s, T> RootScope<'s, T> {
+    /**
+     * Create a handle to an object, which keeps it live until
+     * this scope ends. This fails if the index is not valid for this region.
+     */
+    pub fn handle(&mut self, ix: Ix<T>) -> Result<Handle<'s, T>, Error> {
+        ix.try_get(self.region)?;
+        let mut scoped = self.region.roots.scoped.borrow_mut();
+        scoped.push(ix);
+        Ok(Handle { index: scoped.len() - 1, _scope: PhantomData, _type: PhantomData })
+    }
+    /**
+     * Returns the current index of the object of a handle,
+     * which is valid until the next collection.
+     * The handle may be from this scope or any enclosing one.
+     */
+    #[track_caller]
+    pub fn ix(&self, handle: Handle<'_, T>) -> Ix<T> {
+        match self.region.roots.scoped.borrow().get(handle.index) {
+            Some(&ix) => ix,
+            None => panic!("RootScope::ix: handle {} is not from this region", handle.index),
+        }
+    }
+    #[track_caller]
+    pub fn get(&self, handle: Handle<'_, T>) -> &T {
+        self.ix(handle).get(self.region)
+    }
+    #[track_caller]
+    pub fn get_mut(&mut self, handle: Handle<'_, T>) -> &mut T {
+        self.ix(handle).get_mut(self.region)
+    }
+    /**
+     * Access the region of this scope, such as
+     * to allocate, or to run a collection.
+     */
+    #[inline]
+    pub fn region(&mut self) -> &mut Region<T> {
+        self.region
+    }
+    /**
+     * Open a nested scope, whose handles are released when `f` returns,
+     * while those of this scope remain.
+     */
+    pub fn scope<R, F>(&mut self, f: F) -> R where
+        F: for<'t> FnOnce(&mut RootScope<'t, T>) -> R,
+    {
+        self.region.scope(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn scoped_handles_are_released_with_their_scope() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        r.scope(|s| {
+            let a = s.handle(a).unwrap();
+            s.scope(|inner| {
+                let b = inner.region().alloc(|_| {Elem::new()}).ix();
+                let b = inner.handle(b).unwrap();
+                inner.get_mut(a).ix = Some(inner.ix(b));
+                inner.region().alloc(|_| {Elem::new()});
+                inner.region().gc();
+                assert_eq!(inner.region().len(), 2);
+            });
+            assert_eq!(s.region().roots.scoped.borrow().len(), 1);
+            s.get_mut(a).ix = None;
+            s.region().gc();
+            assert_eq!(s.region().len(), 1);
+        });
+        r.gc();
+        assert!(r.is_empty());
+    }
+}
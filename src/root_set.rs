@@ -0,0 +1,213 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::fmt::{Debug, Formatter};
+use alloc::vec::Vec;
+
+use crate::types::Ix;
+use crate::Region;
+
+pub(crate) type RootSetCell<T> = RefCell<Vec<Ix<T>>>;
+
+/**
+ * A group of roots which are managed collectively.
+ *
+ * Every index in a root set keeps the object it points
+ * to live, exactly like a [`Root`](struct.Root.html),
+ * and is updated in place by each collection. However,
+ * the whole set is registered with the region only once,
+ * so adding, removing or clearing thousands of roots
+ * does not touch any reference counts.
+ *
+ * A set may contain the same index more than once.
+ * Indices which are invalid when a collection occurs are
+ * removed from the set.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut current = r.root_set();
+ * let mut previous = r.root_set();
+ *
+ * let ix = r.alloc(|_|{()}).ix();
+ * current.insert(ix);
+ *
+ * // Start a new frame
+ * current.swap(&mut previous);
+ * current.clear();
+ *
+ * r.gc();
+ * assert_eq!(r.len(), 1);
+ * ```
+ */
+pub struct RootSet<T> {
+    pub(crate) ixs: Rc<RootSetCell<T>>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new, empty, root set which is registered with this region.
+     */
+    pub fn root_set(&mut self) -> RootSet<T> {
+        let ixs = Rc::new(RefCell::new(Vec::new()));
+        self.root_sets.push(Rc::downgrade(&ixs));
+        RootSet { ixs }
+    }
+}
+
+impl <T> RootSet<T> {
+    /**
+     * Add an index to this set. The index must be valid
+     * for the region this set was created from.
+     */
+    #[inline]
+    pub fn insert(&mut self, ix: Ix<T>) {
+        self.ixs.borrow_mut().push(ix);
+    }
+    /**
+     * Remove one occurrence of an index from this set,
+     * returning whether it was present.
+     *
+     * This is linear in the size of the set and may
+     * reorder the remaining indices.
+     */
+    pub fn remove(&mut self, ix: Ix<T>) -> bool {
+        let mut ixs = self.ixs.borrow_mut();
+        match ixs.iter().position(|i| i.ix() == ix.ix()) {
+            Some(pos) => {
+                ixs.swap_remove(pos);
+                true
+            },
+            None => false,
+        }
+    }
+    /**
+     * Check if this set contains a given index.
+     */
+    pub fn contains(&self, ix: Ix<T>) -> bool {
+        self.ixs.borrow().iter().any(|i| i.ix() == ix.ix())
+    }
+    /**
+     * Keep only the indices for which the predicate returns true.
+     */
+    pub fn retain<F>(&mut self, mut f: F) where
+        F: FnMut(Ix<T>) -> bool
+    {
+        self.ixs.borrow_mut().retain(|i| f(*i))
+    }
+    /**
+     * Remove every index from this set in one step.
+     */
+    #[inline]
+    pub fn clear(&mut self) {
+        self.ixs.borrow_mut().clear()
+    }
+    /**
+     * Exchange the contents of two root sets.
+     *
+     * Both sets remain registered, so this is useful
+     * for keeping e.g. the roots of the previous and current
+     * frame, without ever re-registering.
+     */
+    #[inline]
+    pub fn swap(&mut self, other: &mut RootSet<T>) {
+        core::mem::swap(&mut self.ixs, &mut other.ixs)
+    }
+    /**
+     * Return the number of indices in this set, counting duplicates.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ixs.borrow().len()
+    }
+    /**
+     * Returns true if there are currently no indices in this set.
+     */
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ixs.borrow().is_empty()
+    }
+    /**
+     * Get the index at a given position in this set.
+     */
+    #[inline]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
+    pub fn get(&self, i: usize) -> Option<Ix<T>> {
+        let ix = *self.ixs.borrow().get(i)?;
+        Some(ix.tracked())
+    }
+    /**
+     * Iterate over the indices in this set. As with
+     * other raw indices, these are only valid until the
+     * region is next collected.
+     */
+    pub fn iter(&self) -> impl Iterator<Item=Ix<T>> + '_ {
+        (0..self.len()).filter_map(move |i| self.ixs.borrow().get(i).copied())
+    }
+}
+impl <T> Extend<Ix<T>> for RootSet<T> {
+    fn extend<I: IntoIterator<Item=Ix<T>>>(&mut self, iter: I) {
+        self.ixs.borrow_mut().extend(iter)
+    }
+}
+impl <T> Debug for RootSet<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        self.ixs.borrow().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn sets_are_roots() {
+        let mut r = Region::new();
+        let mut set = r.root_set();
+        let mut e1 = r.alloc(|_| {Elem::new()});
+        let w1 = e1.weak();
+        set.insert(e1.ix());
+        let w2 = r.alloc(|_| {Elem::new()}).weak();
+        let ix3 = r.alloc(|_| {Elem::new()}).ix();
+        set.insert(ix3);
+        set.insert(ix3);
+
+        r.gc();
+        assert!(w1.try_get(&r).is_ok());
+        assert!(w2.try_get(&r).is_err());
+        assert_eq!(r.len(), 2);
+        assert_eq!(set.len(), 3);
+        for ix in set.iter() {
+            assert!(ix.try_get(&r).is_ok());
+        }
+
+        set.clear();
+        r.gc();
+        assert!(w1.try_get(&r).is_err());
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    pub fn swapped_sets_stay_registered() {
+        let mut r = Region::new();
+        let mut current = r.root_set();
+        let mut previous = r.root_set();
+        let w1 = r.alloc(|_| {Elem::new()}).weak();
+        current.insert(w1.ix().unwrap());
+
+        current.swap(&mut previous);
+        assert!(current.is_empty());
+        r.gc();
+        assert!(w1.try_get(&r).is_ok());
+
+        drop(previous);
+        r.gc();
+        assert!(w1.try_get(&r).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::RefCell;
+use alloc::rc::Rc;
+use alloc::{vec, vec::Vec};
+
+use crate::types::Ix;
+use crate::Root;
+
+// Marks the end of the free list
+const NO_SLOT: usize = usize::MAX;
+
+enum Slot<T> {
+    // A root, and the number of handles to it
+    Used { ix: Ix<T>, count: usize },
+    Free { next: usize },
+}
+
+// The roots of a region, held in slots which are released
+// as soon as the last handle to them is dropped, and reused
+pub(crate) struct RootSlab<T> {
+    slots: Vec<Slot<T>>,
+    free: usize,
+    live: usize,
+}
+pub(crate) type RootSlabCell<T> = RefCell<RootSlab<T>>;
+
+impl <T> RootSlab<T> {
+    fn new() -> Self {
+        RootSlab { slots: Vec::new(), free: NO_SLOT, live: 0 }
+    }
+    fn insert(&mut self, ix: Ix<T>) -> usize {
+        self.live += 1;
+        let used = Slot::Used { ix, count: 1 };
+        if self.free == NO_SLOT {
+            self.slots.push(used);
+            return self.slots.len() - 1
+        }
+        let slot = self.free;
+        match core::mem::replace(&mut self.slots[slot], used) {
+            Slot::Free { next } => self.free = next,
+            Slot::Used { .. } => unreachable!("root slot on the free list is in use"),
+        }
+        slot
+    }
+    pub(crate) fn get(&self, slot: usize) -> Ix<T> {
+        match self.slots[slot] {
+            Slot::Used { ix, .. } => ix,
+            Slot::Free { .. } => unreachable!("root handle to a free slot"),
+        }
+    }
+    pub(crate) fn acquire(&mut self, slot: usize) {
+        if let Slot::Used { ref mut count, .. } = self.slots[slot] {
+            *count += 1;
+        }
+    }
+    pub(crate) fn release(&mut self, slot: usize) {
+        if let Slot::Used { ref mut count, .. } = self.slots[slot] {
+            *count -= 1;
+            if *count == 0 {
+                self.slots[slot] = Slot::Free { next: self.free };
+                self.free = slot;
+                self.live -= 1;
+            }
+        }
+    }
+    fn iter(&self) -> impl Iterator<Item=Ix<T>> + '_ {
+        self.slots.iter().filter_map(|slot| match *slot {
+            Slot::Used { ix, .. } => Some(ix),
+            Slot::Free { .. } => None,
+        })
+    }
+    // The number of handles to roots of an object
+    fn count(&self, ix: Ix<T>) -> usize {
+        self.slots.iter().map(|slot| match *slot {
+            Slot::Used { ix: i, count } if i == ix => count,
+            _ => 0,
+        }).sum()
+    }
+    fn iter_mut(&mut self) -> impl Iterator<Item=&mut Ix<T>> {
+        self.slots.iter_mut().filter_map(|slot| match slot {
+            Slot::Used { ix, .. } => Some(ix),
+            Slot::Free { .. } => None,
+        })
+    }
+}
+
+// Every root of a region. New roots are created in the first slab,
+// and the others hold roots adopted from other regions, which are
+// released once none of their roots are left. The handles of
+// scopes are kept in a stack, and released by truncating it.
+pub(crate) struct Roots<T> {
+    slabs: Vec<Rc<RootSlabCell<T>>>,
+    pub(crate) scoped: RefCell<Vec<Ix<T>>>,
+}
+impl <T> Default for Roots<T> {
+    fn default() -> Self {
+        Roots::new()
+    }
+}
+impl <T> Roots<T> {
+    pub(crate) fn new() -> Self {
+        Roots { slabs: vec![Rc::new(RefCell::new(RootSlab::new()))], scoped: RefCell::new(Vec::new()) }
+    }
+    pub(crate) fn insert(&self, ix: Ix<T>) -> Root<T> {
+        let slab = self.slabs[0].clone();
+        let slot = slab.borrow_mut().insert(ix);
+        Root { slab, slot }
+    }
+    // The number of roots which have not been dropped
+    #[allow(unused)]
+    pub(crate) fn len(&self) -> usize {
+        self.slabs.iter().map(|slab| slab.borrow().live).sum()
+    }
+    // The number of handles to roots of an object, other than scoped handles
+    pub(crate) fn count(&self, ix: Ix<T>) -> usize {
+        self.slabs.iter().map(|slab| slab.borrow().count(ix)).sum()
+    }
+    pub(crate) fn for_each<F>(&self, mut f: F) where
+        F: FnMut(Ix<T>)
+    {
+        for slab in &self.slabs {
+            slab.borrow().iter().for_each(&mut f);
+        }
+        self.scoped.borrow().iter().copied().for_each(f);
+    }
+    // Update every root in place. No handle may be
+    // created or dropped by f.
+    pub(crate) fn for_each_mut<F>(&self, mut f: F) where
+        F: FnMut(&mut Ix<T>)
+    {
+        for slab in &self.slabs {
+            slab.borrow_mut().iter_mut().for_each(&mut f);
+        }
+        self.scoped.borrow_mut().iter_mut().for_each(f);
+    }
+    // A new handle to every root
+    pub(crate) fn handles(&self) -> Vec<Root<T>> {
+        let mut handles = Vec::new();
+        for slab in &self.slabs {
+            let mut s = slab.borrow_mut();
+            for slot in 0..s.slots.len() {
+                if let Slot::Used { .. } = s.slots[slot] {
+                    s.acquire(slot);
+                    handles.push(Root { slab: slab.clone(), slot });
+                }
+            }
+        }
+        handles
+    }
+    // Take over the roots of the slab of a handle, such as
+    // one from another region, if they are not already held
+    pub(crate) fn adopt(&mut self, root: &Root<T>) {
+        if !self.slabs.iter().any(|slab| Rc::ptr_eq(slab, &root.slab)) {
+            self.slabs.push(root.slab.clone());
+        }
+    }
+    // Take over all of the roots of another region
+    pub(crate) fn append(&mut self, other: Roots<T>) {
+        for slab in other.slabs {
+            if slab.borrow().live > 0 {
+                self.slabs.push(slab);
+            }
+        }
+    }
+    // Release the adopted slabs whose roots have all been dropped
+    pub(crate) fn prune(&mut self) {
+        let mut first = true;
+        self.slabs.retain(|slab| core::mem::replace(&mut first, false) || slab.borrow().live > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn dropped_roots_release_their_slots() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem::new()}).root();
+        let b2 = b.clone();
+        drop(b);
+        assert_eq!(r.roots.len(), 2);
+        drop(b2);
+        assert_eq!(r.roots.len(), 1);
+        // The slot is reused
+        let c = r.alloc(|_| {Elem::new()}).root();
+        assert_eq!(c.slot, 1);
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert!(a.try_get(&r).is_ok() && c.try_get(&r).is_ok());
+    }
+
+    #[test]
+    pub fn roots_are_counted() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let mut e = r.alloc(|_| {Elem::new()});
+        let (a, weak) = (e.root(), e.weak());
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        let a2 = a.clone();
+        let mut set = r.root_set();
+        set.insert(b);
+        assert_eq!((r.root_count(a.ix()), r.root_count(b)), (2, 0));
+        assert!(r.has_root(a.ix()) && !r.has_root(b));
+
+        r.gc();
+        assert!(weak.is_alive());
+        drop((a, a2));
+        assert!(!r.has_root(weak.ix().unwrap()));
+        r.gc();
+        assert!(!weak.is_alive());
+        let weak = r.alloc(|_| {Elem::new()}).weak();
+        drop(r);
+        assert!(!weak.is_alive());
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::{Ref, RefCell, RefMut};
+use alloc::collections::BTreeMap;
+use core::fmt::{Debug, Formatter};
+use core::marker::PhantomData;
+use alloc::rc::Rc;
+
+use crate::remap::{Forwarding, Remap};
+use crate::{Ix, Region};
+
+struct Entries<V> {
+    map: BTreeMap<usize, V>,
+}
+impl <V> Remap for Entries<V> {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        self.map = core::mem::take(&mut self.map).into_iter()
+            .filter_map(|(i, v)| Some((forwarding.get(i)?, v)))
+            .collect();
+    }
+}
+
+/**
+ * A map from some of the objects of a region to values, such as
+ * colors or distances for a traversal, which is updated by each
+ * collection. Entries move with their objects, and are removed
+ * when their objects are collected.
+ *
+ * Unlike a [`HotColumn`](struct.HotColumn.html), only objects
+ * which have been inserted take space. Unlike an
+ * [`IdentityMap`](struct.IdentityMap.html), keys are plain indices,
+ * and as with other side structures, they must be valid for the region.
+ * Values are not traced, so they should not hold indices.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * let mut r = gc::Region::new();
+ * let mut colors = r.secondary_map();
+ *
+ * r.ensure(2);
+ * let dead = r.alloc(|_|{()}).ix();
+ * let root = r.alloc(|_|{()}).root();
+ * colors.insert(dead, "white");
+ * colors.insert(root.ix(), "black");
+ * r.gc();
+ * assert_eq!(colors.len(), 1);
+ * assert_eq!(colors.get(root.ix()).as_deref(), Some(&"black"));
+ * ```
+ */
+pub struct SecondaryMap<T, V> {
+    entries: Rc<RefCell<Entries<V>>>,
+    _t: PhantomData<fn(&T)>,
+}
+
+impl <T> Region<T> {
+    /**
+     * Create a new, empty secondary map which
+     * is kept in step with the objects of this region.
+     */
+    pub fn secondary_map<V: 'static>(&mut self) -> SecondaryMap<T, V> {
+        let entries = Rc::new(RefCell::new(Entries { map: BTreeMap::new() }));
+        self.remaps.push(Rc::downgrade(&entries) as _);
+        SecondaryMap { entries, _t: PhantomData }
+    }
+}
+
+impl <T, V> SecondaryMap<T, V> {
+    /**
+     * Insert a value for the object at an index,
+     * returning the previous value, if any.
+     */
+    pub fn insert(&mut self, ix: Ix<T>, value: V) -> Option<V> {
+        self.entries.borrow_mut().map.insert(ix.ix(), value)
+    }
+    /**
+     * Remove the value for the object at an index, if any.
+     */
+    pub fn remove(&mut self, ix: Ix<T>) -> Option<V> {
+        self.entries.borrow_mut().map.remove(&ix.ix())
+    }
+    /**
+     * Get the value for the object at an index, if any.
+     */
+    pub fn get(&self, ix: Ix<T>) -> Option<Ref<'_, V>> {
+        Ref::filter_map(self.entries.borrow(), |e| e.map.get(&ix.ix())).ok()
+    }
+    /**
+     * Get a mutable reference to the value for the object at an index, if any.
+     */
+    pub fn get_mut(&mut self, ix: Ix<T>) -> Option<RefMut<'_, V>> {
+        RefMut::filter_map(self.entries.borrow_mut(), |e| e.map.get_mut(&ix.ix())).ok()
+    }
+    /**
+     * Returns true if there is a value for the object at an index.
+     */
+    pub fn contains_key(&self, ix: Ix<T>) -> bool {
+        self.entries.borrow().map.contains_key(&ix.ix())
+    }
+    /**
+     * Return the number of objects with values.
+     */
+    pub fn len(&self) -> usize {
+        self.entries.borrow().map.len()
+    }
+    /**
+     * Returns true if no object has a value.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().map.is_empty()
+    }
+    /**
+     * Remove every value.
+     */
+    pub fn clear(&mut self) {
+        self.entries.borrow_mut().map.clear();
+    }
+}
+impl <T, V: Debug> Debug for SecondaryMap<T, V> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_map().entries(self.entries.borrow().map.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn entries_follow_objects() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let mut distances = r.secondary_map();
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        let c = r.alloc(|_| {Elem::new()}).ix();
+        distances.insert(a, 1);
+        distances.insert(b.ix(), 0);
+        distances.insert(c, 5);
+        *distances.get_mut(b.ix()).unwrap() += 10;
+        r.gc();
+        assert_eq!(distances.len(), 2);
+        let a = b.get(&r).ix.unwrap();
+        assert_eq!(distances.get(a).map(|d| *d), Some(1));
+        assert_eq!(distances.remove(b.ix()), Some(10));
+        assert!(!distances.contains_key(b.ix()));
+
+        // Dropping the map unregisters it
+        drop(distances);
+        r.gc();
+    }
+}
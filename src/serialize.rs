@@ -0,0 +1,209 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Serialization of regions with serde.
+//!
+//! An index is serialized as its position. A region is serialized
+//! as its objects in order, with the positions which have been freed
+//! in place, along with its groups, and the positions of its roots.
+
+use core::ops::Range;
+use alloc::{vec, vec::Vec};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error as _;
+
+use crate::{Error, HasIx, Ix, Region, Root};
+use crate::has_ix::foreach_edge;
+
+impl <T> Serialize for Ix<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.ix().serialize(serializer)
+    }
+}
+// Deserialized indices are positions, as in an image,
+// which are valid once their region has been deserialized
+impl <'de, T> Deserialize<'de> for Ix<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Ix::new(usize::deserialize(deserializer)?,
+            #[cfg(feature = "debug-arena")]
+            0,
+            #[cfg(feature = "debug-arena")]
+            0))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "Region")]
+struct SavedRef<'a, T> {
+    objects: Vec<&'a T>,
+    freed: Vec<usize>,
+    groups: &'a [Range<usize>],
+    roots: Vec<usize>,
+}
+#[derive(Deserialize)]
+#[serde(rename = "Region")]
+struct Saved<T> {
+    objects: Vec<T>,
+    freed: Vec<usize>,
+    groups: Vec<Range<usize>>,
+    roots: Vec<usize>,
+}
+
+/**
+ * A region together with its roots, as deserialized
+ * from a [`Region`](struct.Region.html), which is serialized
+ * along with the positions of its roots.
+ *
+ * The roots are re-created in the order of their slots in the
+ * original region, which is the order in which they were created
+ * unless the slots of dropped roots have been reused.
+ * Weak pointers and root sets are not serialized.
+ *
+ * This requires the feature "serde".
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use serde::{Serialize, Deserialize};
+ *
+ * #[derive(Serialize, Deserialize)]
+ * struct Node(Option<gc::Ix<Node>>);
+ * gc::impl_has_ix!(Node { 0 });
+ *
+ * let mut r = gc::Region::new();
+ * r.ensure(2);
+ * let leaf = r.alloc(|_| Node(None)).ix();
+ * let _root = r.alloc(|_| Node(Some(leaf))).root();
+ *
+ * let json = serde_json::to_string(&r).unwrap();
+ * let loaded: gc::RegionWithRoots<Node> = serde_json::from_str(&json).unwrap();
+ * let leaf = loaded.roots[0].get(&loaded.region).0.unwrap();
+ * assert!(leaf.try_get(&loaded.region).is_ok());
+ * ```
+ */
+pub struct RegionWithRoots<T> {
+    pub region: Region<T>,
+    pub roots: Vec<Root<T>>,
+}
+
+impl <T: Serialize> Serialize for Region<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut roots = Vec::new();
+        self.roots.for_each(|ix| if ix.ix() < self.data.len() { roots.push(ix.ix()) });
+        SavedRef {
+            objects: self.data.iter().filter_map(|s| Some(s.get()?.get())).collect(),
+            freed: (0..self.data.len()).filter(|&i| self.data[i].get().is_none()).collect(),
+            groups: &self.groups,
+            roots,
+        }.serialize(serializer)
+    }
+}
+
+impl <'de, T: 'static + HasIx<T> + Deserialize<'de>> Deserialize<'de> for RegionWithRoots<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let saved = Saved::<T>::deserialize(deserializer)?;
+        Region::from_saved(saved).map_err(D::Error::custom)
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    // Rebuild a region, closing up the positions which had been freed
+    fn from_saved(saved: Saved<T>) -> Result<RegionWithRoots<T>, Error> {
+        let mut forward = vec![Some(0); saved.objects.len() + saved.freed.len()];
+        for &i in &saved.freed {
+            *forward.get_mut(i).ok_or(Error::Indeterminable)? = None;
+        }
+        let mut next = 0;
+        for p in forward.iter_mut().flatten() {
+            *p = next;
+            next += 1;
+        }
+        if next != saved.objects.len() {
+            Err(Error::Indeterminable)?;
+        }
+        let forward_ix = |ix: Ix<T>| forward.get(ix.ix()).copied().flatten().map(|i| Ix::new(i,
+            #[cfg(feature = "debug-arena")]
+            0,
+            #[cfg(feature = "debug-arena")]
+            0));
+
+        let mut valid = true;
+        let mut objects = saved.objects;
+        for t in &mut objects {
+            foreach_edge(t, |ix| match forward_ix(*ix) {
+                Some(new) => *ix = new,
+                None => valid = false,
+            });
+            t.foreach_weak_ix(|weak| weak.forward(forward_ix));
+        }
+        // Groups with a freed position have been broken up
+        let groups = saved.groups.into_iter().filter_map(|g| {
+            let start = forward.get(g.start).copied().flatten()?;
+            let last = forward.get(g.end.checked_sub(1)?).copied().flatten()?;
+            Some(start..last + 1).filter(|r| r.len() == g.len())
+        }).collect();
+        if !valid {
+            Err(Error::Indeterminable)?;
+        }
+
+        let mut region = Region::new();
+        region.ensure(objects.len());
+        region.append_positional(objects, groups)?;
+        let roots = saved.roots.into_iter()
+            .map(|i| {
+                let ix = forward.get(i).copied().flatten().ok_or(Error::Indeterminable)?;
+                region.root(region.ix_at(ix))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(RegionWithRoots { region, roots })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::{Ix, Region, RegionWithRoots};
+
+    #[derive(Serialize, Deserialize)]
+    struct Node {
+        value: u32,
+        next: Option<Ix<Node>>,
+    }
+    crate::impl_has_ix!(Node { next });
+
+    #[test]
+    pub fn regions_round_trip() {
+        let mut r = Region::new();
+        r.ensure(5);
+        let a = r.alloc(|_| Node { value: 1, next: None }).ix();
+        let b = r.alloc(|_| Node { value: 2, next: Some(a) }).root();
+        let c = r.alloc(|_| Node { value: 3, next: None }).ix();
+        r.alloc_slice(vec![Node { value: 4, next: Some(b.ix()) }, Node { value: 5, next: None }]);
+        let d = r.root(c).unwrap();
+        r.take(a).unwrap();
+        b.get_mut(&mut r).next = Some(c);
+        drop(d);
+        let e = r.root(r.ix_at(3)).unwrap();
+
+        let json = serde_json::to_string(&r).unwrap();
+        let loaded: RegionWithRoots<Node> = serde_json::from_str(&json).unwrap();
+        let RegionWithRoots { mut region, roots } = loaded;
+        assert_eq!(region.len(), 4);
+        assert_eq!(roots.len(), 2);
+        let c = roots[0].get(&region).next.unwrap();
+        assert_eq!(c.get(&region).value, 3);
+        assert_eq!(roots[1].get(&region).value, 4);
+        assert_eq!(region.groups, vec![2..4]);
+        drop(e);
+
+        // An edge to a freed position cannot be restored
+        b.get_mut(&mut r).next = Some(a);
+        let json = serde_json::to_string(&r).unwrap();
+        assert!(serde_json::from_str::<RegionWithRoots<Node>>(&json).is_err());
+        region.gc();
+        assert_eq!(region.len(), 4);
+    }
+}
@@ -0,0 +1,290 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! A naive model of the heap, which is cross-checked
+//! against the real heap at each collection.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{HasIx, Ix, Region};
+use crate::entry::Spot;
+use crate::has_ix::foreach_edge;
+
+// Used for edges which do not point to any object
+const NO_ID: u64 = u64::MAX;
+
+pub(crate) struct ShadowHeap<T> {
+    checksum: fn(&T) -> u64,
+    // The id of the object at each position in the region
+    ids: Vec<u64>,
+    next_id: u64,
+    // The checksum and edges of every object, by id
+    model: HashMap<u64, (u64, Vec<u64>)>,
+}
+
+impl <T: 'static + HasIx<T>> ShadowHeap<T> {
+    fn snapshot(&self, t: &mut T) -> (u64, Vec<u64>) {
+        let mut edges = Vec::new();
+        foreach_edge(t, |ix| {
+            edges.push(self.ids.get(ix.ix()).copied().unwrap_or(NO_ID))
+        });
+        ((self.checksum)(t), edges)
+    }
+
+    // Assign ids to, and record, every object which doesn't yet have one
+    fn extend(&mut self, data: &mut [Spot<T>]) {
+        let start = self.ids.len();
+        for _ in start..data.len() {
+            self.ids.push(self.next_id);
+            self.next_id += 1;
+        }
+        for (i, spot) in data.iter_mut().enumerate().skip(start) {
+            if let Some(e) = spot.get_mut() {
+                let snapshot = self.snapshot(e.get_mut());
+                self.model.insert(self.ids[i], snapshot);
+            }
+        }
+    }
+
+    // Check the heap after a collection, given the id of the object now at
+    // each position, and the ids from which every survivor must be reachable
+    fn verify(&mut self, data: &mut [Spot<T>], ids: Vec<u64>, root_ids: Vec<u64>) {
+        let mut reachable: HashSet<u64> = HashSet::new();
+        let mut stack = root_ids;
+        while let Some(id) = stack.pop() {
+            if let Some((_, edges)) = self.model.get(&id) {
+                if reachable.insert(id) {
+                    stack.extend(edges);
+                }
+            }
+        }
+
+        // Positions freed in place have no object
+        let survivors: HashSet<u64> = ids.iter().zip(data.iter())
+            .filter(|(_, spot)| spot.get().is_some())
+            .map(|(&id, _)| id)
+            .collect();
+        if survivors.contains(&NO_ID) {
+            panic!("Shadow heap: collection produced an object with no source");
+        }
+        if survivors != reachable {
+            let lost: Vec<_> = reachable.difference(&survivors).collect();
+            let kept: Vec<_> = survivors.difference(&reachable).collect();
+            panic!("Shadow heap: collection lost reachable objects {:?} and kept unreachable objects {:?}", lost, kept);
+        }
+        self.model.retain(|id, _| survivors.contains(id));
+        self.ids = ids;
+        self.check(data, "after collection");
+    }
+
+    fn check(&self, data: &mut [Spot<T>], when: &str) {
+        for (i, spot) in data.iter_mut().enumerate() {
+            if let Some(e) = spot.get_mut() {
+                let id = self.ids[i];
+                let actual = self.snapshot(e.get_mut());
+                match self.model.get(&id) {
+                    Some(expected) if *expected == actual => (),
+                    Some(_) => panic!("Shadow heap: object {} (at {}) differs from the model {}. \
+                                       If it was mutated, Region::write_barrier must be called.", id, i, when),
+                    None => panic!("Shadow heap: object {} (at {}) is missing from the model {}", id, i, when),
+                }
+            }
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Begin maintaining a naive model of this region, which records
+     * the checksum and edges of every object, and cross-check
+     * it against the real heap at each collection. Collections
+     * will panic if the heap does not match the model,
+     * either because the collector has misbehaved,
+     * or because an object was mutated without
+     * calling [`write_barrier`](#method.write_barrier).
+     *
+     * This is extremely expensive, and intended only for debugging.
+     * Requires the feature "shadow-heap".
+     */
+    pub fn enable_shadow_heap(&mut self, checksum: fn(&T) -> u64) {
+        let mut shadow = ShadowHeap {
+            checksum,
+            ids: Vec::new(),
+            next_id: 0,
+            model: HashMap::new(),
+        };
+        shadow.extend(&mut self.data);
+        self.shadow = Some(shadow);
+    }
+    /**
+     * Stop maintaining the shadow heap.
+     */
+    pub fn disable_shadow_heap(&mut self) {
+        self.shadow = None;
+    }
+
+    pub(crate) fn shadow_alloc(&mut self) {
+        if let Some(ref mut shadow) = self.shadow {
+            shadow.extend(&mut self.data);
+        }
+    }
+
+    // Rebuild the model from the heap, after its objects were replaced
+    pub(crate) fn shadow_reset(&mut self) {
+        if let Some(shadow) = self.shadow.take() {
+            self.enable_shadow_heap(shadow.checksum);
+        }
+    }
+
+    pub(crate) fn shadow_write(&mut self, ix: Ix<T>) {
+        if let Some(ref mut shadow) = self.shadow {
+            if let Some(e) = self.data.get_mut(ix.ix()).and_then(Spot::get_mut) {
+                let snapshot = shadow.snapshot(e.get_mut());
+                shadow.model.insert(shadow.ids[ix.ix()], snapshot);
+            }
+        }
+    }
+
+    pub(crate) fn shadow_truncate(&mut self, len: usize) {
+        if let Some(ref mut shadow) = self.shadow {
+            for id in shadow.ids.drain(len..) {
+                shadow.model.remove(&id);
+            }
+        }
+    }
+
+    // Check the heap before a collection, returning the root ids
+    pub(crate) fn shadow_pre_gc(&mut self) -> Vec<u64> {
+        let shadow = match self.shadow {
+            Some(ref shadow) => shadow,
+            None => return Vec::new(),
+        };
+        shadow.check(&mut self.data, "before collection");
+        let mut root_ids = Vec::new();
+        self.foreach_root(|ix| {
+            if let Some(&id) = shadow.ids.get(ix.ix()) {
+                root_ids.push(id);
+            }
+        });
+        root_ids
+    }
+
+    // Check the heap after a collection, given the old data
+    pub(crate) fn shadow_post_gc(&mut self, src: &[Spot<T>], root_ids: Vec<u64>) {
+        let mut shadow = match self.shadow.take() {
+            Some(shadow) => shadow,
+            None => return,
+        };
+        let mut ids = vec![NO_ID; self.data.len()];
+        for (i, spot) in src.iter().enumerate() {
+            // Objects freed in place are forwarded past the end
+            if let Some(id) = spot.forwarded().and_then(|ix| ids.get_mut(ix.ix())) {
+                *id = shadow.ids[i];
+            }
+        }
+        shadow.verify(&mut self.data, ids, root_ids);
+        self.shadow = Some(shadow);
+    }
+
+    // Check the heap before a collection which slides objects from
+    // the boundary down in place, returning the ids of the roots and of
+    // the objects before the boundary, which are all kept
+    pub(crate) fn shadow_pre_slide(&mut self, boundary: usize) -> Vec<u64> {
+        let mut root_ids = self.shadow_pre_gc();
+        if let Some(ref shadow) = self.shadow {
+            root_ids.extend((0..boundary)
+                .filter(|&i| self.data[i].get().is_some())
+                .map(|i| shadow.ids[i]));
+        }
+        root_ids
+    }
+
+    // Check the heap after a sliding collection, given the
+    // new position of the object at each old position
+    pub(crate) fn shadow_post_slide(&mut self, map: &[Option<usize>], root_ids: Vec<u64>) {
+        let mut shadow = match self.shadow.take() {
+            Some(shadow) => shadow,
+            None => return,
+        };
+        let mut ids = vec![NO_ID; self.data.len()];
+        for (i, &new) in map.iter().enumerate() {
+            if let Some(id) = new.and_then(|j| ids.get_mut(j)) {
+                *id = shadow.ids[i];
+            }
+        }
+        shadow.verify(&mut self.data, ids, root_ids);
+        self.shadow = Some(shadow);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    fn checksum(e: &Elem) -> u64 {
+        e.ix.is_some() as u64
+    }
+
+    #[test]
+    pub fn shadow_heap_agrees() {
+        let mut r = Region::new();
+        r.enable_shadow_heap(checksum);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).ix();
+        a.get_mut(&mut r).ix = Some(b);
+        r.write_barrier(a.ix());
+        r.alloc(|_| {Elem::new()});
+        r.gc();
+        assert_eq!(r.len(), 2);
+        r.gc();
+    }
+
+    #[test]
+    pub fn shadow_heap_follows_minor_collections() {
+        let mut r = Region::new();
+        r.ensure(8);
+        r.enable_shadow_heap(checksum);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        r.alloc(|_| {Elem::new()});
+        assert_eq!(r.minor_gc(), Ok(1));
+        r.alloc(|_| {Elem::new()});
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        a.get_mut(&mut r).ix = Some(b);
+        r.write_barrier(a.ix());
+        assert_eq!(r.minor_gc(), Ok(1));
+        assert_eq!(a.ix().identifier(), 0);
+        r.gc();
+        assert_eq!(r.len(), 2);
+    }
+
+    #[test]
+    pub fn shadow_heap_follows_compaction() {
+        let mut r = Region::new();
+        r.ensure(4);
+        r.enable_shadow_heap(checksum);
+        r.alloc(|_| {Elem::new()});
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).ix();
+        a.get_mut(&mut r).ix = Some(b);
+        r.write_barrier(a.ix());
+        assert_eq!(r.compact(), Ok(1));
+        assert_eq!(a.ix().identifier(), 0);
+        r.gc();
+        assert_eq!(r.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "write_barrier")]
+    pub fn shadow_heap_detects_unrecorded_writes() {
+        let mut r = Region::new();
+        r.enable_shadow_heap(checksum);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem::new()}).ix();
+        a.get_mut(&mut r).ix = Some(b);
+        r.gc();
+    }
+}
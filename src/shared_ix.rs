@@ -0,0 +1,178 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::cell::Cell;
+use core::fmt::{Debug, Formatter};
+use alloc::rc::Rc;
+
+use crate::{HasIx, Ix};
+
+thread_local! {
+    // The collection in progress on this thread, or 0
+    static SCOPE: Cell<u64> = const { Cell::new(0) };
+    static NEXT_SCOPE: Cell<u64> = const { Cell::new(1) };
+}
+
+// Marks a traversal which updates indices, such as a collection,
+// during which each shared index is exposed only once
+pub(crate) struct RewriteScope {
+    prev: u64,
+}
+impl RewriteScope {
+    pub(crate) fn enter() -> Self {
+        let id = NEXT_SCOPE.with(|next| next.replace(next.get() + 1));
+        RewriteScope { prev: SCOPE.with(|scope| scope.replace(id)) }
+    }
+}
+impl Drop for RewriteScope {
+    fn drop(&mut self) {
+        SCOPE.with(|scope| scope.set(self.prev));
+    }
+}
+
+struct SharedCell<T> {
+    ix: Cell<Ix<T>>,
+    // The last rewrite scope which exposed this index
+    scope: Cell<u64>,
+}
+
+/**
+ * An internal index which may be held by several objects at once,
+ * such as a shared subterm of a DAG which can be redirected
+ * in one place.
+ *
+ * Clones share the same index, and [`set`](#method.set) changes it for
+ * every clone. Unlike an [`Ix`](struct.Ix.html) held in an `Rc<Cell<_>>`,
+ * a shared index is safe to expose from every owner in
+ * [`HasIx::foreach_ix`](trait.HasIx.html#tymethod.foreach_ix): a collection
+ * updates it exactly once, when it first reaches an owner. Its object
+ * is kept alive while any owner is reachable, and a shared index
+ * whose owners have all been collected is invalidated.
+ *
+ * Operations which copy objects and rewrite their indices,
+ * such as [`Region::as_image`](struct.Region.html#method.as_image),
+ * do not support shared indices, since the copies would share the
+ * index with the originals.
+ *
+ * This requires the feature "std", which is enabled by default.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ *
+ * struct Term(Vec<gc::SharedIx<Term>>);
+ * impl gc::HasIx<Term> for Term {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Term>)
+ *     {
+ *         self.0.foreach_ix(f)
+ *     }
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * r.ensure(2);
+ * let leaf = gc::SharedIx::new(r.alloc(|_| Term(vec![])).ix());
+ * let pair = r.alloc(|_| Term(vec![leaf.clone(), leaf.clone()])).root();
+ * r.gc();
+ * assert_eq!(r.len(), 2);
+ * assert!(leaf.get().try_get(&r).is_ok());
+ * ```
+ */
+pub struct SharedIx<T> {
+    cell: Rc<SharedCell<T>>,
+    // Exposed in place of the shared index
+    // outside collections, so that writes are not shared
+    scratch: Ix<T>,
+}
+impl <T> SharedIx<T> {
+    pub fn new(ix: Ix<T>) -> Self {
+        SharedIx {
+            cell: Rc::new(SharedCell { ix: Cell::new(ix), scope: Cell::new(0) }),
+            scratch: ix,
+        }
+    }
+    #[inline]
+    pub fn get(&self) -> Ix<T> {
+        self.cell.ix.get()
+    }
+    /**
+     * Point this index, and every clone of it, to another object.
+     */
+    #[inline]
+    pub fn set(&self, ix: Ix<T>) {
+        self.cell.ix.set(ix)
+    }
+    /**
+     * Returns true if both indices share the same cell.
+     */
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.cell, &other.cell)
+    }
+}
+impl <T> Clone for SharedIx<T> {
+    fn clone(&self) -> Self {
+        SharedIx { cell: self.cell.clone(), scratch: self.scratch }
+    }
+}
+impl <T> Debug for SharedIx<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_tuple("SharedIx").field(&self.get()).finish()
+    }
+}
+impl <T : 'static> HasIx<T> for SharedIx<T> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    {
+        let scope = SCOPE.with(Cell::get);
+        if scope == 0 {
+            // Other traversals see every owner, but may only read
+            self.scratch = self.get();
+            f(&mut self.scratch);
+        } else if self.cell.scope.replace(scope) != scope {
+            // safety: rewrite scopes are only entered by the collector, which
+            // uses each index before tracing any other, and never reads
+            // the cell otherwise, so this is the only reference to the index
+            f(unsafe { &mut *self.cell.ix.as_ptr() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HasIx, Ix, Region, SharedIx};
+
+    struct Term {
+        args: Vec<SharedIx<Term>>,
+    }
+    impl HasIx<Term> for Term {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Term>)
+        {
+            self.args.foreach_ix(f)
+        }
+    }
+
+    #[test]
+    pub fn shared_indices_update_once() {
+        let mut r = Region::new();
+        r.ensure(4);
+        r.alloc(|_| {Term { args: vec![] }});
+        let y = r.alloc(|_| {Term { args: vec![] }}).ix();
+        let shared = SharedIx::new(y);
+        let f = r.alloc(|_| {Term { args: vec![shared.clone()] }}).root();
+        let g = r.alloc(|_| {Term { args: vec![shared.clone(), shared.clone()] }}).root();
+        r.gc();
+        assert_eq!(r.len(), 3);
+        assert!(shared.get().try_get(&r).is_ok());
+
+        shared.set(f.ix());
+        r.gc();
+        assert_eq!(r.len(), 2);
+        let arg = g.get(&r).args[1].get();
+        assert_eq!(arg.identifier(), f.ix().identifier());
+        assert!(g.get(&r).args[0].ptr_eq(&shared));
+    }
+}
@@ -0,0 +1,259 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Snapshots of the objects of a region, which it can be rolled back to.
+//!
+//! A snapshot holds a copy of every object, along with the position
+//! which each of them has since moved to, so that roots, root sets and
+//! weak pointers can be moved back when the region is rolled back.
+
+use core::cell::RefCell;
+use core::fmt::{Debug, Formatter};
+use core::ops::Range;
+use alloc::rc::{self, Rc};
+use alloc::{vec, vec::Vec};
+
+use crate::{Error, HasIx, Ix, Region};
+use crate::entry::Spot;
+use crate::has_ix::foreach_edge;
+use crate::remap::{self, Forwarding, Remap};
+
+// The current position of the object at each position of a snapshot
+struct Tracking {
+    positions: Vec<Option<usize>>,
+}
+impl Remap for Tracking {
+    fn remap(&mut self, forwarding: &Forwarding) {
+        for p in &mut self.positions {
+            *p = p.and_then(|p| forwarding.get(p));
+        }
+    }
+}
+
+/**
+ * A copy of the objects of a region, created by
+ * [`Region::snapshot`](struct.Region.html#method.snapshot),
+ * which the region can be rolled back to with
+ * [`Region::rollback`](struct.Region.html#method.rollback).
+ *
+ * While a snapshot is held, each collection of its region
+ * also updates the snapshot, so it should be dropped
+ * once it is no longer needed.
+ */
+pub struct Snapshot<T> {
+    objects: Vec<Option<T>>,
+    groups: Vec<Range<usize>>,
+    tracking: Rc<RefCell<Tracking>>,
+    // The first index of the region when the snapshot was taken
+    start: Ix<T>,
+    region: rc::Weak<()>,
+}
+impl <T> Snapshot<T> {
+    /**
+     * The number of positions in the region when the snapshot was taken.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+impl <T> Debug for Snapshot<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("Snapshot").field("len", &self.objects.len()).finish()
+    }
+}
+
+impl <T: 'static + HasIx<T> + Clone> Region<T> {
+    /**
+     * Copy every object in this region, so that allocations and
+     * mutations made from now on can be undone with
+     * [`rollback`](#method.rollback), such as for speculative execution.
+     */
+    pub fn snapshot(&mut self) -> Snapshot<T> {
+        // Restored indices must be of the generation of the snapshot
+        #[cfg(feature = "debug-arena")]
+        self.restamp_kept();
+        let objects = self.data.iter().map(|spot| spot.get().map(|e| e.get().clone())).collect();
+        let tracking = Rc::new(RefCell::new(Tracking {
+            positions: (0..self.data.len()).map(Some).collect(),
+        }));
+        self.remaps.push(Rc::downgrade(&tracking) as _);
+        Snapshot {
+            objects,
+            groups: self.groups.clone(),
+            tracking,
+            start: self.ix_at(0),
+            region: Rc::downgrade(&self.alive),
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Restore every object of this region to its value when the
+     * snapshot was taken, dropping every object allocated since.
+     * This invalidates raw indices, as with [`gc`](#method.gc).
+     *
+     * Roots, root sets and weak pointers to objects which were in
+     * the snapshot are moved back to them, even if the objects have
+     * since been moved by a collection. Those to any other object are
+     * invalidated, including those to objects which were freed since
+     * the snapshot, though the objects themselves are restored.
+     * Objects which are dropped are not finalized.
+     *
+     * An error is returned if the snapshot is of another region,
+     * or if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * #[derive(Clone)]
+     * struct Counter(u32);
+     * gc::impl_has_ix!(Counter {});
+     *
+     * let mut r = gc::Region::new();
+     * let a = r.alloc(|_| Counter(0)).root();
+     * let snapshot = r.snapshot();
+     *
+     * a.get_mut(&mut r).0 = 1;
+     * let b = r.alloc(|_| Counter(2)).root();
+     * r.rollback(snapshot).unwrap();
+     * assert_eq!(a.get(&r).0, 0);
+     * assert!(b.try_get(&r).is_err());
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    pub fn rollback(&mut self, snapshot: Snapshot<T>) -> Result<(), Error> {
+        if !snapshot.region.ptr_eq(&Rc::downgrade(&self.alive)) {
+            Err(Error::IncorrectRegion)?;
+        }
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        let Snapshot { objects, groups, tracking, start, .. } = snapshot;
+        let positions = core::mem::take(&mut tracking.borrow_mut().positions);
+        drop(tracking);
+        let len = objects.len();
+        let mut inverse = vec![None; self.data.len()];
+        for (q, p) in positions.into_iter().enumerate() {
+            if let Some(p) = p { inverse[p] = Some(q) }
+        }
+
+        // Keep the weak pointer cells of the objects which move back
+        let capacity = core::cmp::max(self.data.capacity(), len);
+        let empty = self.data.empty_like(capacity);
+        let old = core::mem::replace(&mut self.data, empty);
+        let mut cells: Vec<_> = (0..len).map(|_| None).collect();
+        for (p, spot) in old.into_vec().into_iter().enumerate() {
+            if let (Some(q), Some((_, cell))) = (inverse[p], spot.into_parts()) {
+                cells[q] = cell;
+            }
+        }
+        self.epoch += 1;
+        #[cfg(all(feature = "debug-arena", feature = "refresh"))]
+        let old_gen = self.generation;
+        #[cfg(feature = "debug-arena")]
+        self.advance_generation();
+        let current = self.ix_at(0);
+        let dead = self.ix_at(usize::MAX);
+
+        // Indices in the copies are from the region when the snapshot was taken
+        let restore = |ix: Ix<T>| ix.check_current(start, #[cfg(feature = "debug-arena")] &[]).ok().map(|_| current.offset(ix.ix()));
+        self.data.extend(objects.into_iter().zip(cells).enumerate().map(|(q, (object, cell))| match object {
+            Some(mut t) => {
+                foreach_edge(&mut t, |ix| *ix = restore(*ix).unwrap_or(dead));
+                t.foreach_weak_ix(|weak| weak.forward(restore));
+                if let Some(ref rc) = cell { rc.set(current.offset(q)) }
+                Spot::from_parts(t, cell)
+            },
+            None => Spot::broken_heart(dead),
+        }));
+        self.groups = groups;
+
+        let forward = |ix: Ix<T>| inverse.get(ix.ix()).copied().flatten().map_or(dead, |q| current.offset(q));
+        self.roots.for_each_mut(|ix| *ix = forward(*ix));
+        self.root_sets.retain(|set| set.strong_count() > 0);
+        for set in &self.root_sets {
+            if let Some(set) = set.upgrade() {
+                set.borrow_mut().iter_mut().for_each(|ix| *ix = forward(*ix));
+            }
+        }
+        #[cfg(feature = "refresh")]
+        self.retain_forwarding(Forwarding::from_positions(inverse.clone(), len),
+            #[cfg(feature = "debug-arena")]
+            old_gen);
+        remap::remap_all(&mut self.remaps, || Forwarding::from_positions(inverse, len));
+        // The restored objects may have other keys
+        #[cfg(feature = "std")]
+        self.reindex_all_keys();
+
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_reset();
+        #[cfg(feature = "history")]
+        {
+            self.history_event(crate::HistoryEvent::Truncate { len: 0 });
+            self.history_alloc(0);
+            for g in self.groups.clone() {
+                self.history_event(crate::HistoryEvent::Group { start: g.start, len: g.len() });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn rollback_restores_the_snapshot() {
+        let mut r = Region::new();
+        r.ensure(6);
+        r.alloc(|_| {Elem::new()});
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).root();
+        let w = b.downgrade(&mut r);
+        let mut set = r.root_set();
+        set.insert(a.ix());
+        #[cfg(feature = "std")]
+        r.set_key_index(|e: &Elem| e.ix.is_some());
+        let snapshot = r.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        r.set(a.ix(), Elem { ix: Some(b.ix()) }).unwrap();
+
+        // Collections since the snapshot are undone as well
+        let c = r.alloc(|_| {Elem::new()}).root();
+        b.get_mut(&mut r).ix = Some(c.ix());
+        r.gc();
+        assert_eq!(b.ix().identifier(), 1);
+        r.rollback(snapshot).unwrap();
+
+        assert_eq!(r.len(), 3);
+        assert!(c.try_get(&r).is_err());
+        assert_eq!(b.ix().identifier(), 2);
+        assert_eq!(b.get(&r).ix.unwrap().identifier(), a.ix().identifier());
+        assert_eq!(set.get(0).unwrap().identifier(), 1);
+        assert_eq!(w.ix().unwrap().identifier(), 2);
+        assert!(a.get(&r).ix.is_none());
+        // The index has the keys of the restored objects
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(r.find_by_key(&false).unwrap().identifier(), a.ix().identifier());
+            assert_eq!(r.find_by_key(&true).unwrap().identifier(), b.ix().identifier());
+        }
+        r.gc();
+        assert_eq!(r.len(), 2);
+        assert_eq!(b.get(&r).ix.unwrap().identifier(), a.ix().identifier());
+
+        let mut other = Region::new();
+        let snapshot = other.snapshot();
+        assert_eq!(r.rollback(snapshot).unwrap_err(), Error::IncorrectRegion);
+    }
+}
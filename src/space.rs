@@ -0,0 +1,368 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! The storage for the objects of a region, which is
+//! either a Vec or, with the feature "mmap", a mapping.
+
+use core::ops::{Deref, DerefMut};
+use alloc::vec::Vec;
+
+use crate::entry::Spot;
+#[cfg(feature = "mmap")]
+use crate::Region;
+
+/**
+ * Where the storage of a region is mapped, for
+ * [`Region::with_backing`](struct.Region.html#method.with_backing).
+ *
+ * Requires the feature "mmap".
+ */
+#[cfg(feature = "mmap")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backing {
+    /**
+     * Anonymous mappings, whose pages are only committed
+     * by the operating system when they are first used.
+     */
+    Anonymous,
+    /**
+     * Mappings of temporary files in the given directory, so that
+     * the heap is paged to those files rather than to swap.
+     * Each file is removed as soon as it is mapped, where the
+     * operating system allows.
+     */
+    Directory(std::path::PathBuf),
+}
+
+#[cfg(feature = "mmap")]
+impl <T> Region<T> {
+    /**
+     * Create a region whose storage, including the space
+     * which objects are copied to during collection, is
+     * mapped rather than allocated, such as for heaps which are
+     * larger than the allocator handles comfortably.
+     *
+     * Requires the feature "mmap".
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::with_backing(gc::Backing::Anonymous);
+     * let a = r.alloc(|_|{()}).root();
+     * r.alloc(|_|{()});
+     * r.gc();
+     * assert_eq!(r.len(), 1);
+     * ```
+     */
+    pub fn with_backing(backing: Backing) -> Self {
+        let mut r = Region::new();
+        r.data = Space::mapped(backing, 0);
+        r
+    }
+}
+
+pub(crate) struct Space<T> {
+    repr: Repr<T>,
+}
+enum Repr<T> {
+    Heap(Vec<Spot<T>>),
+    #[cfg(feature = "mmap")]
+    Mapped(mapped::Mapped<T>),
+}
+
+impl <T> Space<T> {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Space { repr: Repr::Heap(Vec::new()) }
+    }
+    #[inline]
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Space { repr: Repr::Heap(Vec::with_capacity(capacity)) }
+    }
+    #[cfg(feature = "mmap")]
+    pub(crate) fn mapped(backing: Backing, capacity: usize) -> Self {
+        Space { repr: Repr::Mapped(mapped::Mapped::new(backing, capacity)) }
+    }
+    // An empty space with the same backing as this one
+    pub(crate) fn empty_like(&self, capacity: usize) -> Self {
+        match self.repr {
+            Repr::Heap(_) => Self::with_capacity(capacity),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref m) => Self::mapped(m.backing().clone(), capacity),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn capacity(&self) -> usize {
+        match self.repr {
+            Repr::Heap(ref v) => v.capacity(),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref m) => m.capacity(),
+        }
+    }
+    // Ensure there is capacity for at least additional more spots
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.reserve(additional),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.reserve(additional),
+        }
+    }
+    // Ensure there is capacity for additional more spots, without over-allocating
+    pub(crate) fn reserve_exact(&mut self, additional: usize) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.reserve_exact(additional),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.reserve_exact(additional),
+        }
+    }
+    #[inline]
+    pub(crate) fn push(&mut self, spot: Spot<T>) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.push(spot),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.push(spot),
+        }
+    }
+    pub(crate) fn extend<I: IntoIterator<Item=Spot<T>>>(&mut self, iter: I) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.extend(iter),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => iter.into_iter().for_each(|s| m.push(s)),
+        }
+    }
+    // Release any capacity beyond the length
+    pub(crate) fn shrink_to_fit(&mut self) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.shrink_to_fit(),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.shrink_to_fit(),
+        }
+    }
+    // Write to the capacity beyond the length, so that
+    // its pages are committed before they are used
+    pub(crate) fn prefault(&mut self) {
+        let len = self.len();
+        let spare = self.capacity() - len;
+        // safety: the spare capacity is allocated, and holds no spots
+        unsafe {
+            let start = self.as_mut_ptr().add(len);
+            core::ptr::write_bytes(start as *mut u8, 0, spare * core::mem::size_of::<Spot<T>>());
+        }
+    }
+    pub(crate) fn truncate(&mut self, len: usize) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.truncate(len),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.truncate(len),
+        }
+    }
+    #[inline]
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut Spot<T> {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.as_mut_ptr(),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.as_mut_ptr(),
+        }
+    }
+    // Safety: as with Vec::set_len
+    #[inline]
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        match self.repr {
+            Repr::Heap(ref mut v) => v.set_len(len),
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.set_len(len),
+        }
+    }
+    pub(crate) fn into_vec(self) -> Vec<Spot<T>> {
+        match self.repr {
+            Repr::Heap(v) => v,
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(m) => m.into_vec(),
+        }
+    }
+}
+impl <T> Deref for Space<T> {
+    type Target = [Spot<T>];
+    #[inline]
+    fn deref(&self) -> &[Spot<T>] {
+        match self.repr {
+            Repr::Heap(ref v) => v,
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref m) => m.as_slice(),
+        }
+    }
+}
+impl <T> DerefMut for Space<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [Spot<T>] {
+        match self.repr {
+            Repr::Heap(ref mut v) => v,
+            #[cfg(feature = "mmap")]
+            Repr::Mapped(ref mut m) => m.as_mut_slice(),
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+mod mapped {
+    use std::fs::OpenOptions;
+    use core::marker::PhantomData;
+    use core::mem;
+    use core::ptr;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use memmap2::MmapMut;
+
+    use super::Backing;
+    use crate::entry::Spot;
+
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+
+    fn map(backing: &Backing, bytes: usize) -> MmapMut {
+        // Mappings may not be empty
+        let bytes = core::cmp::max(bytes, 1);
+        let map = match backing {
+            Backing::Anonymous => MmapMut::map_anon(bytes),
+            Backing::Directory(dir) => {
+                let path = dir.join(format!("moving_gc_arena-{}-{}.space",
+                    std::process::id(), FILES.fetch_add(1, Ordering::Relaxed)));
+                let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)
+                    .and_then(|file| file.set_len(bytes as u64).map(|()| file));
+                let map = file.and_then(|file| unsafe { MmapMut::map_mut(&file) });
+                // The mapping remains valid after the file is removed
+                let _ = std::fs::remove_file(&path);
+                map
+            },
+        };
+        match map {
+            Ok(map) => map,
+            Err(e) => panic!("Region: unable to map storage: {}", e),
+        }
+    }
+
+    // Spots stored in a mapping, which behaves as a Vec
+    pub(crate) struct Mapped<T> {
+        map: MmapMut,
+        backing: Backing,
+        len: usize,
+        capacity: usize,
+        _t: PhantomData<Spot<T>>,
+    }
+    impl <T> Mapped<T> {
+        pub(crate) fn new(backing: Backing, capacity: usize) -> Self {
+            // Mappings are page-aligned
+            assert!(mem::align_of::<Spot<T>>() <= 4096);
+            let bytes = capacity.checked_mul(mem::size_of::<Spot<T>>())
+                .expect("Region: capacity overflow");
+            Mapped { map: map(&backing, bytes), backing, len: 0, capacity, _t: PhantomData }
+        }
+        #[inline]
+        pub(crate) fn backing(&self) -> &Backing {
+            &self.backing
+        }
+        #[inline]
+        pub(crate) fn capacity(&self) -> usize {
+            self.capacity
+        }
+        #[inline]
+        pub(crate) fn as_mut_ptr(&mut self) -> *mut Spot<T> {
+            self.map.as_mut_ptr() as *mut Spot<T>
+        }
+        #[inline]
+        pub(crate) fn as_slice(&self) -> &[Spot<T>] {
+            unsafe { core::slice::from_raw_parts(self.map.as_ptr() as *const Spot<T>, self.len) }
+        }
+        #[inline]
+        pub(crate) fn as_mut_slice(&mut self) -> &mut [Spot<T>] {
+            unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+        }
+        pub(crate) fn reserve(&mut self, additional: usize) {
+            let needed = self.len.checked_add(additional).expect("Region: capacity overflow");
+            if needed <= self.capacity { return }
+            self.remap(core::cmp::max(needed, 2 * self.capacity));
+        }
+        pub(crate) fn reserve_exact(&mut self, additional: usize) {
+            let needed = self.len.checked_add(additional).expect("Region: capacity overflow");
+            if needed <= self.capacity { return }
+            self.remap(needed);
+        }
+        pub(crate) fn shrink_to_fit(&mut self) {
+            if self.len < self.capacity {
+                self.remap(self.len);
+            }
+        }
+        // Move the spots to a new mapping of the given capacity
+        fn remap(&mut self, capacity: usize) {
+            let mut new = Mapped::new(self.backing.clone(), capacity);
+            unsafe {
+                ptr::copy_nonoverlapping(self.as_mut_ptr(), new.as_mut_ptr(), self.len);
+            }
+            // The spots now belong to the new mapping
+            new.len = mem::replace(&mut self.len, 0);
+            *self = new;
+        }
+        #[inline]
+        pub(crate) fn push(&mut self, spot: Spot<T>) {
+            self.reserve(1);
+            unsafe { self.as_mut_ptr().add(self.len).write(spot) };
+            self.len += 1;
+        }
+        pub(crate) fn truncate(&mut self, len: usize) {
+            while self.len > len {
+                self.len -= 1;
+                unsafe { ptr::drop_in_place(self.as_mut_ptr().add(self.len)) };
+            }
+        }
+        #[inline]
+        pub(crate) unsafe fn set_len(&mut self, len: usize) {
+            debug_assert!(len <= self.capacity);
+            self.len = len;
+        }
+        pub(crate) fn into_vec(mut self) -> Vec<Spot<T>> {
+            let mut v = Vec::with_capacity(self.len);
+            unsafe {
+                ptr::copy_nonoverlapping(self.as_mut_ptr(), v.as_mut_ptr(), self.len);
+                v.set_len(self.len);
+            }
+            self.len = 0;
+            v
+        }
+    }
+    impl <T> Drop for Mapped<T> {
+        fn drop(&mut self) {
+            self.truncate(0)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mmap"))]
+mod tests {
+    use crate::{Backing, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn mapped_regions_collect() {
+        let dir = std::env::temp_dir();
+        for backing in [Backing::Anonymous, Backing::Directory(dir)] {
+            let mut r = Region::with_backing(backing);
+            let roots: Vec<_> = (0..100).map(|_| r.alloc(|_| {Elem::new()}).root()).collect();
+            for _ in 0..100 {
+                r.alloc(|_| {Elem::new()});
+            }
+            r.gc();
+            assert_eq!(r.len(), 100);
+            assert!(roots.iter().all(|root| root.try_get(&r).is_ok()));
+            r.truncate_data(50);
+            assert_eq!(r.len(), 50);
+            r.release_unused_memory().unwrap();
+            assert_eq!(r.capacity(), 50);
+            assert!(roots[..50].iter().all(|root| root.try_get(&r).is_ok()));
+            r.prefault(50).unwrap();
+            assert!(r.capacity() >= 100);
+            assert!(roots[..50].iter().all(|root| root.try_get(&r).is_ok()));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Streaming serialization of the reachable heap.
+//!
+//! The stream contains a header, the positions of the roots, and
+//! then each object in the order it is reached, as a tag byte,
+//! its edges as positions, and its value as written by the caller.
+//! All integers are little-endian u64.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use crate::{HasIx, Ix, Region, Root};
+use crate::entry::Spot;
+use crate::has_ix::foreach_edge;
+
+const MAGIC: &[u8; 4] = b"MGCA";
+const VERSION: u8 = 1;
+const TAG_OBJECT: u8 = 1;
+const TAG_END: u8 = 0;
+
+// Used for objects which have not yet been numbered
+const UNSEEN: usize = usize::MAX;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_u64<W: Write>(w: &mut W, n: usize) -> io::Result<()> {
+    w.write_all(&(n as u64).to_le_bytes())
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    let n = u64::from_le_bytes(bytes);
+    if n > usize::MAX as u64 {
+        Err(invalid("position out of range"))?;
+    }
+    Ok(n as usize)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    /**
+     * Write every object reachable from the given roots to a sink,
+     * one object at a time, so that no buffer the size of the heap is needed.
+     *
+     * The edges of each object are written by the region, and `encode`
+     * writes the rest of the value. The stream can be read
+     * with [`read_stream`](#method.read_stream).
+     *
+     * This fails with `InvalidInput` if any reachable index is invalid.
+     *
+     * This requires the feature "std", which is enabled by default.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let a = r.alloc(|_|{()}).root();
+     *
+     * let mut bytes = Vec::new();
+     * r.write_stream(Some(a.ix()), &mut bytes, |_, _| Ok(())).unwrap();
+     * let (copy, roots) = gc::Region::read_stream(&mut &bytes[..], |_, _| Ok(())).unwrap();
+     * assert_eq!(copy.len(), 1);
+     * assert!(roots[0].try_get(&copy).is_ok());
+     * ```
+     */
+    pub fn write_stream<I, W, F>(&mut self, roots: I, w: &mut W, mut encode: F) -> io::Result<()> where
+        I: IntoIterator<Item=Ix<T>>,
+        W: Write,
+        F: FnMut(&T, &mut W) -> io::Result<()>,
+    {
+        let current = self.ix_at(0);
+        #[cfg(feature = "debug-arena")]
+        let kept = &self.kept;
+        let len = self.data.len();
+        let mut numbers = vec![UNSEEN; len];
+        let mut next = 0;
+        let mut queue = VecDeque::new();
+        let mut number = |ix: Ix<T>, queue: &mut VecDeque<usize>| {
+            let i = ix.ix();
+            if ix.check_current(current, #[cfg(feature = "debug-arena")] kept).is_err() || i >= len {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid index"));
+            }
+            if numbers[i] == UNSEEN {
+                numbers[i] = next;
+                next += 1;
+                queue.push_back(i);
+            }
+            Ok(numbers[i])
+        };
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        let roots = roots.into_iter().map(|ix| number(ix, &mut queue)).collect::<io::Result<Vec<_>>>()?;
+        write_u64(w, roots.len())?;
+        for root in roots {
+            write_u64(w, root)?;
+        }
+
+        let mut edges = Vec::new();
+        while let Some(i) = queue.pop_front() {
+            let entry = match self.data[i].get_mut() {
+                Some(entry) => entry,
+                None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid index")),
+            };
+            foreach_edge(entry.get_mut(), |ix| edges.push(*ix));
+            w.write_all(&[TAG_OBJECT])?;
+            write_u64(w, edges.len())?;
+            for ix in edges.drain(..) {
+                write_u64(w, number(ix, &mut queue)?)?;
+            }
+            encode(entry.get(), w)?;
+        }
+        w.write_all(&[TAG_END])
+    }
+
+    /**
+     * Read a region from a stream written by
+     * [`write_stream`](#method.write_stream), one object at a time,
+     * returning the region and a root for each of the
+     * roots it was written with, in order.
+     *
+     * `decode` must read the value written by the corresponding `encode`,
+     * and create an object with the same number of indices, in the
+     * order given by `foreach_ix`. It is given a placeholder index to
+     * use for each, which is replaced as the object is added to the region.
+     *
+     * This requires the feature "std", which is enabled by default.
+     */
+    pub fn read_stream<R, F>(r: &mut R, mut decode: F) -> io::Result<(Region<T>, Vec<Root<T>>)> where
+        R: Read,
+        F: FnMut(&mut R, Ix<T>) -> io::Result<T>,
+    {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u8(r)? != VERSION {
+            Err(invalid("not a region stream"))?;
+        }
+        let mut region = Region::new();
+        let base = region.ix_at(0);
+        let root_count = read_u64(r)?;
+        let roots = (0..root_count).map(|_| read_u64(r)).collect::<io::Result<Vec<_>>>()?;
+
+        // The largest position referred to by any edge so far
+        let mut max_edge = roots.iter().copied().max();
+        let mut edges = Vec::new();
+        while read_u8(r)? == TAG_OBJECT {
+            let count = read_u64(r)?;
+            edges.clear();
+            for _ in 0..count {
+                let edge = read_u64(r)?;
+                max_edge = max_edge.max(Some(edge));
+                edges.push(edge);
+            }
+            let mut t = decode(r, base)?;
+            let mut edges_iter = edges.iter();
+            let mut matched = true;
+            foreach_edge(&mut t, |ix| match edges_iter.next() {
+                Some(&edge) => *ix = base.offset(edge),
+                None => matched = false,
+            });
+            if !matched || edges_iter.next().is_some() {
+                Err(invalid("decoded object has the wrong number of indices"))?;
+            }
+            region.data.push(Spot::new(t));
+        }
+        if max_edge.is_some_and(|e| e >= region.data.len()) {
+            Err(invalid("index out of range"))?;
+        }
+        #[cfg(feature = "shadow-heap")]
+        region.shadow_alloc();
+        let roots = roots.into_iter().map(|i| region.root(base.offset(i)).unwrap()).collect();
+        Ok((region, roots))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use crate::{HasIx, Ix, Region};
+
+    struct Node {
+        name: u8,
+        children: Vec<Ix<Node>>,
+    }
+    impl HasIx<Node> for Node {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Node>)
+        {
+            self.children.iter_mut().for_each(f)
+        }
+    }
+
+    #[test]
+    pub fn streams_round_trip() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Node { name: 0, children: vec![] }}).root();
+        r.alloc(|_| {Node { name: 1, children: vec![] }});
+        let c = r.alloc(|_| {Node { name: 2, children: vec![a.ix()] }}).ix();
+        a.get_mut(&mut r).children = vec![c, c];
+
+        let mut bytes = Vec::new();
+        r.write_stream(vec![a.ix(), c], &mut bytes, |n, w| {
+            w.write_all(&[n.name, n.children.len() as u8])
+        }).unwrap();
+        let decode = |r: &mut &[u8], ix| {
+            let mut b = [0; 2];
+            r.read_exact(&mut b)?;
+            Ok(Node { name: b[0], children: vec![ix; b[1] as usize] })
+        };
+        let (copy, roots) = Region::read_stream(&mut &bytes[..], decode).unwrap();
+        assert_eq!(copy.len(), 2);
+        let a2 = roots[0].get(&copy);
+        assert_eq!(a2.name, 0);
+        assert_eq!(a2.children[1].identifier(), roots[1].ix().identifier());
+        assert_eq!(roots[1].get(&copy).children[0].identifier(), roots[0].ix().identifier());
+
+        // Truncated streams are rejected
+        assert!(Region::read_stream(&mut &bytes[..bytes.len() - 1], decode).is_err());
+    }
+}
@@ -0,0 +1,697 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Regions which can be moved between threads.
+//!
+//! A region is not `Send`, since its roots and weak pointers are
+//! reference-counted. A sync region owns every reference-counted handle
+//! into its inner region, and gives out handles which are shared with
+//! `Arc`. Each handle holds the position of its object in an atomic,
+//! which the region updates after any operation which moves objects.
+//!
+//! A shared region wraps a sync region so that it can be used by
+//! several threads at once. Objects are locked by shard, by their
+//! position, which is found under a short read lock of the whole region,
+//! while operations which may move objects take every shard and then the
+//! write lock. The storage of the region is recorded whenever the write
+//! lock is released, and objects are only reached through it while
+//! their shard is held.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{self, Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockWriteGuard, TryLockError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{access_failed, Error, HasIx, Ix, Promotion, Region, Root, Weak};
+use crate::entry::Spot;
+
+// The position of an expired weak pointer
+const EXPIRED: usize = usize::MAX;
+
+/**
+ * A region which is `Send` whenever `T` is, so that a whole heap
+ * can be handed to another thread, along with its handles.
+ *
+ * Objects are created from values, and accessed by index or
+ * through [`SyncRoot`](struct.SyncRoot.html) and
+ * [`SyncWeak`](struct.SyncWeak.html), which are `Send` and `Sync`,
+ * and take the place of [`Root`](struct.Root.html) and
+ * [`Weak`](struct.Weak.html). Since the inner region is never exposed,
+ * its other features are not available.
+ *
+ * A sync root is released when its last clone is dropped,
+ * but its object is only freed by a collection after the next call to
+ * [`gc`](#method.gc), so an automatic collection may keep it.
+ *
+ * This requires the feature "sync".
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * struct Node(Option<gc::Ix<Node>>);
+ * gc::impl_has_ix!(Node { 0 });
+ *
+ * let mut r = gc::SyncRegion::new();
+ * r.ensure(2);
+ * let leaf = r.alloc(Node(None));
+ * let node = r.alloc(Node(Some(leaf)));
+ * let root = r.root(node).unwrap();
+ * let worker = std::thread::spawn(move || {
+ *     r.gc();
+ *     r
+ * });
+ * let r = worker.join().unwrap();
+ * assert_eq!(r.len(), 2);
+ * assert!(root.get(&r).0.is_some());
+ * ```
+ */
+pub struct SyncRegion<T> {
+    region: Region<T>,
+    roots: Vec<(sync::Weak<AtomicUsize>, Root<T>)>,
+    weaks: Vec<(sync::Weak<AtomicUsize>, Weak<T>)>,
+    // The epoch of the region when the handles were last updated
+    epoch: u64,
+}
+
+// Safety: every reference-counted pointer into the inner region is
+// owned by the sync region, as the inner region is never exposed
+// and no handle is given out. So, the counts are only touched by
+// the thread which owns the sync region.
+unsafe impl <T: Send> Send for SyncRegion<T> {}
+
+/**
+ * A root of a [`SyncRegion`](struct.SyncRegion.html), which can be
+ * shared between threads. As with [`Root`](struct.Root.html),
+ * its object remains live until every clone is dropped.
+ */
+pub struct SyncRoot<T> {
+    cell: Arc<AtomicUsize>,
+    #[cfg(feature = "debug-arena")]
+    nonce: crate::nonce::Word,
+    _t: PhantomData<fn(T) -> T>,
+}
+
+/**
+ * A weak pointer into a [`SyncRegion`](struct.SyncRegion.html),
+ * which can be shared between threads. As with [`Weak`](struct.Weak.html),
+ * it expires once its object is collected.
+ */
+pub struct SyncWeak<T> {
+    cell: Arc<AtomicUsize>,
+    #[cfg(feature = "debug-arena")]
+    nonce: crate::nonce::Word,
+    _t: PhantomData<fn(T) -> T>,
+}
+
+impl <T> SyncRegion<T> {
+    #[inline]
+    pub fn new() -> Self {
+        SyncRegion {
+            region: Region::new(),
+            roots: Vec::new(),
+            weaks: Vec::new(),
+            epoch: 0,
+        }
+    }
+    /**
+     * Get the object at an index.
+     */
+    #[inline]
+    pub fn try_get(&self, ix: Ix<T>) -> Result<&T, Error> {
+        ix.try_get(&self.region)
+    }
+    /**
+     * Get a mutable reference to the object at an index.
+     */
+    #[inline]
+    pub fn try_get_mut(&mut self, ix: Ix<T>) -> Result<&mut T, Error> {
+        ix.try_get_mut(&mut self.region)
+    }
+
+    /**
+     * Create a root to the object at an index,
+     * as with [`Region::root`](struct.Region.html#method.root).
+     */
+    pub fn root(&mut self, ix: Ix<T>) -> Result<SyncRoot<T>, Error> {
+        let root = self.region.root(ix)?;
+        Ok(self.share(root))
+    }
+    // Take ownership of a root of the inner region
+    fn share(&mut self, root: Root<T>) -> SyncRoot<T> {
+        let cell = Arc::new(AtomicUsize::new(root.ix().ix()));
+        self.roots.push((Arc::downgrade(&cell), root));
+        SyncRoot {
+            cell,
+            #[cfg(feature = "debug-arena")]
+            nonce: self.region.nonce,
+            _t: PhantomData,
+        }
+    }
+    /**
+     * Create a weak pointer to the object at an index,
+     * as with [`Region::weak`](struct.Region.html#method.weak).
+     */
+    pub fn weak(&mut self, ix: Ix<T>) -> Result<SyncWeak<T>, Error> {
+        let weak = self.region.weak(ix)?;
+        let cell = Arc::new(AtomicUsize::new(ix.ix()));
+        self.weaks.push((Arc::downgrade(&cell), weak));
+        Ok(SyncWeak {
+            cell,
+            #[cfg(feature = "debug-arena")]
+            nonce: self.region.nonce,
+            _t: PhantomData,
+        })
+    }
+
+    // Store the new position of every handle, if objects may have moved.
+    // Handles are only read with the region, and the region is only
+    // sent with synchronization, so no stronger ordering is needed.
+    fn update_handles(&mut self) {
+        if self.epoch == self.region.epoch { return }
+        self.epoch = self.region.epoch;
+        for (cell, root) in &self.roots {
+            if let Some(cell) = cell.upgrade() {
+                cell.store(root.ix().ix(), Ordering::Relaxed);
+            }
+        }
+        for (cell, weak) in &self.weaks {
+            if let Some(cell) = cell.upgrade() {
+                cell.store(weak.ix().map_or(EXPIRED, |ix| ix.ix()), Ordering::Relaxed);
+            }
+        }
+    }
+
+    // The index at a position read from a handle
+    fn handle_ix(&self, i: usize,
+        #[cfg(feature = "debug-arena")] nonce: crate::nonce::Word) -> Result<Ix<T>, Error>
+    {
+        #[cfg(feature = "debug-arena")]
+        {
+            if nonce != self.region.nonce {
+                Err(Error::IncorrectRegion)?;
+            }
+        }
+        if i == EXPIRED {
+            Err(Error::EntryExpired)?;
+        }
+        Ok(self.region.ix_at(i))
+    }
+}
+impl <T> Default for SyncRegion<T> {
+    fn default() -> Self {
+        SyncRegion::new()
+    }
+}
+
+impl <T: 'static + HasIx<T>> SyncRegion<T> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.region.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.region.is_empty()
+    }
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.region.capacity()
+    }
+    /**
+     * Ensure that there is capacity for at least `additional` more
+     * objects, as with [`Region::ensure`](struct.Region.html#method.ensure).
+     */
+    pub fn ensure(&mut self, additional: usize) {
+        self.region.ensure(additional);
+        self.update_handles();
+    }
+    /**
+     * Allocate a new object, returning its index.
+     * As with [`Region::alloc`](struct.Region.html#method.alloc),
+     * this may trigger a collection, which invalidates other indices.
+     */
+    pub fn alloc(&mut self, t: T) -> Ix<T> {
+        let ix = self.region.alloc(move |_| t).ix();
+        self.update_handles();
+        ix
+    }
+    /**
+     * Allocate a new object, returning its index, or an error
+     * as with [`Region::try_alloc`](struct.Region.html#method.try_alloc).
+     */
+    pub fn try_alloc(&mut self, t: T) -> Result<Ix<T>, Error> {
+        let ix = self.region.try_alloc(move |_| t).map(|e| e.ix());
+        self.update_handles();
+        ix
+    }
+    // Move the survivors of a nursery onto the end of this region, with
+    // their edges to imports replaced by the indices of the imported
+    // objects, returning a root for each index they were promoted from
+    pub(crate) fn adopt_nursery(&mut self, promotion: Promotion<T>, imports: &[SyncRoot<T>]) -> Vec<SyncRoot<T>> {
+        self.ensure(promotion.len());
+        let imported: Vec<_> = imports.iter().map(|root| root.try_ix(self).ok()).collect();
+        let roots = self.region.adopt_with(promotion, |p| {
+            imported.get(p.checked_sub(crate::nursery::IMPORTS)?).copied().flatten()
+        });
+        roots.into_iter().map(|root| self.share(root)).collect()
+    }
+    /**
+     * Collect the region, as with [`Region::gc`](struct.Region.html#method.gc),
+     * first releasing every handle which has been dropped.
+     */
+    pub fn gc(&mut self) {
+        self.roots.retain(|(cell, _)| cell.strong_count() > 0);
+        self.weaks.retain(|(cell, _)| cell.strong_count() > 0);
+        self.region.gc();
+        self.update_handles();
+        self.weaks.retain(|(_, weak)| weak.ix().is_some());
+    }
+}
+
+impl <T> SyncRoot<T> {
+    /**
+     * Get the index of this root in the given region.
+     */
+    #[track_caller]
+    pub fn ix(&self, r: &SyncRegion<T>) -> Ix<T> {
+        match self.try_ix(r) {
+            Ok(ix) => ix,
+            Err(e) => access_failed::<T>("SyncRoot::ix", None, e),
+        }
+    }
+    fn try_ix(&self, r: &SyncRegion<T>) -> Result<Ix<T>, Error> {
+        r.handle_ix(self.cell.load(Ordering::Relaxed),
+            #[cfg(feature = "debug-arena")]
+            self.nonce)
+    }
+    // The index at a position outside any region, which is
+    // of the region of this root with the feature "debug-arena"
+    pub(crate) fn outside_ix(&self, i: usize) -> Ix<T> {
+        Ix::new(i,
+            #[cfg(feature = "debug-arena")]
+            self.nonce,
+            #[cfg(feature = "debug-arena")]
+            0)
+    }
+    #[inline]
+    pub fn try_get<'a>(&self, r: &'a SyncRegion<T>) -> Result<&'a T, Error> {
+        r.try_get(self.try_ix(r)?)
+    }
+    #[inline]
+    pub fn try_get_mut<'a>(&self, r: &'a mut SyncRegion<T>) -> Result<&'a mut T, Error> {
+        let ix = self.try_ix(r)?;
+        r.try_get_mut(ix)
+    }
+    #[track_caller]
+    pub fn get<'a>(&self, r: &'a SyncRegion<T>) -> &'a T {
+        match self.try_get(r) {
+            Ok(t) => t,
+            Err(e) => access_failed::<T>("SyncRoot::get", None, e),
+        }
+    }
+    #[track_caller]
+    pub fn get_mut<'a>(&self, r: &'a mut SyncRegion<T>) -> &'a mut T {
+        match self.try_get_mut(r) {
+            Ok(t) => t,
+            Err(e) => access_failed::<T>("SyncRoot::get_mut", None, e),
+        }
+    }
+    /**
+     * Create a weak pointer to the object of this root.
+     */
+    #[track_caller]
+    pub fn downgrade(&self, r: &mut SyncRegion<T>) -> SyncWeak<T> {
+        match self.try_ix(r).and_then(|ix| r.weak(ix)) {
+            Ok(weak) => weak,
+            Err(e) => access_failed::<T>("SyncRoot::downgrade", None, e),
+        }
+    }
+}
+impl <T> Clone for SyncRoot<T> {
+    fn clone(&self) -> Self {
+        SyncRoot {
+            cell: self.cell.clone(),
+            #[cfg(feature = "debug-arena")]
+            nonce: self.nonce,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl <T> SyncWeak<T> {
+    /**
+     * Get the index of the object of this weak pointer in
+     * the given region, if it has not been collected.
+     */
+    pub fn ix(&self, r: &SyncRegion<T>) -> Option<Ix<T>> {
+        self.try_ix(r).ok()
+    }
+    fn try_ix(&self, r: &SyncRegion<T>) -> Result<Ix<T>, Error> {
+        r.handle_ix(self.cell.load(Ordering::Relaxed),
+            #[cfg(feature = "debug-arena")]
+            self.nonce)
+    }
+    #[inline]
+    pub fn try_get<'a>(&self, r: &'a SyncRegion<T>) -> Result<&'a T, Error> {
+        r.try_get(self.try_ix(r)?)
+    }
+    #[inline]
+    pub fn try_get_mut<'a>(&self, r: &'a mut SyncRegion<T>) -> Result<&'a mut T, Error> {
+        let ix = self.try_ix(r)?;
+        r.try_get_mut(ix)
+    }
+    /**
+     * Create a root to the object of this weak pointer,
+     * if it has not been collected.
+     */
+    pub fn upgrade(&self, r: &mut SyncRegion<T>) -> Option<SyncRoot<T>> {
+        let ix = self.try_ix(r).ok()?;
+        r.root(ix).ok()
+    }
+}
+impl <T> Clone for SyncWeak<T> {
+    fn clone(&self) -> Self {
+        SyncWeak {
+            cell: self.cell.clone(),
+            #[cfg(feature = "debug-arena")]
+            nonce: self.nonce,
+            _t: PhantomData,
+        }
+    }
+}
+
+// The number of shards of a shared region by default
+const DEFAULT_SHARDS: usize = 64;
+
+/**
+ * A [`SyncRegion`](struct.SyncRegion.html) which can be shared
+ * between threads, such as a scene graph updated by worker threads.
+ *
+ * Each object is locked by [`lock`](#method.lock) with the lock of its
+ * shard, so threads can mutate objects in different shards at once.
+ * Operations which may move objects, such as allocation and collection,
+ * are done through [`write`](#method.write), which waits for every
+ * object to be unlocked, and stops other threads until it is dropped.
+ *
+ * Objects are assigned to shards by position, so two objects may
+ * share a shard. The lock of an object holds only the lock of its shard,
+ * so a thread may lock objects in different shards while another thread
+ * waits in `write`. As with any set of mutexes, a thread which locks an object
+ * while it holds the lock of another object in the same shard, or which calls
+ * `write` while it holds the lock of any object, deadlocks, and threads
+ * which lock several objects at once must do so in a consistent order.
+ *
+ * This requires the feature "sync".
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use std::sync::Arc;
+ * struct Node(u32, Option<gc::Ix<Node>>);
+ * gc::impl_has_ix!(Node { 1 });
+ *
+ * let mut r = gc::SyncRegion::new();
+ * r.ensure(2);
+ * let a = r.alloc(Node(0, None));
+ * let b = r.alloc(Node(0, None));
+ * let roots = [r.root(a).unwrap(), r.root(b).unwrap()];
+ * let shared = Arc::new(gc::SharedRegion::new(r));
+ * let workers: Vec<_> = roots.iter().cloned().map(|root| {
+ *     let shared = shared.clone();
+ *     std::thread::spawn(move || shared.lock(&root).unwrap().0 += 1)
+ * }).collect();
+ * workers.into_iter().for_each(|w| w.join().unwrap());
+ *
+ * shared.write().gc();
+ * let r = Arc::try_unwrap(shared).ok().unwrap().into_inner();
+ * assert!(roots.iter().all(|root| root.get(&r).0 == 1));
+ * ```
+ */
+pub struct SharedRegion<T> {
+    inner: RwLock<Shared<T>>,
+    shards: Box<[Mutex<()>]>,
+}
+struct Shared<T> {
+    region: SyncRegion<T>,
+    // The storage of the region, recorded whenever the write lock
+    // is released, while the region can not move any object
+    spots: *mut Spot<T>,
+    len: usize,
+    // The number of times the write lock has been released
+    epoch: u64,
+}
+impl <T> Shared<T> {
+    fn record(&mut self) {
+        self.spots = self.region.region.data.as_mut_ptr();
+        self.len = self.region.region.data.len();
+        self.epoch += 1;
+    }
+}
+
+// Safety: objects are only reached through the recorded storage,
+// under the lock of their shard, so each object is only accessed
+// by one thread at a time, as with a Mutex. The write lock is only
+// taken along with every shard, so the storage does not move while
+// any object is locked. Otherwise, the region is only read while the
+// read lock is held, and its handles are only used while the write lock is held.
+unsafe impl <T: Send> Send for SharedRegion<T> {}
+unsafe impl <T: Send> Sync for SharedRegion<T> {}
+
+/**
+ * The lock of an object of a [`SharedRegion`](struct.SharedRegion.html),
+ * which dereferences to the object.
+ */
+pub struct ObjectGuard<'a, T> {
+    t: *mut T,
+    _shard: MutexGuard<'a, ()>,
+}
+
+/**
+ * The write lock of a [`SharedRegion`](struct.SharedRegion.html),
+ * which dereferences to its [`SyncRegion`](struct.SyncRegion.html).
+ */
+pub struct RegionWriteGuard<'a, T> {
+    inner: RwLockWriteGuard<'a, Shared<T>>,
+    _shards: Vec<MutexGuard<'a, ()>>,
+}
+
+impl <T> SharedRegion<T> {
+    #[inline]
+    pub fn new(region: SyncRegion<T>) -> Self {
+        SharedRegion::with_shards(region, DEFAULT_SHARDS)
+    }
+    /**
+     * Share a region, with the given number of shards, which must be at least 1.
+     */
+    pub fn with_shards(region: SyncRegion<T>, shards: usize) -> Self {
+        assert!(shards > 0, "SharedRegion::with_shards: no shards");
+        let mut inner = Shared { region, spots: std::ptr::null_mut(), len: 0, epoch: 0 };
+        inner.record();
+        SharedRegion {
+            inner: RwLock::new(inner),
+            shards: (0..shards).map(|_| Mutex::new(())).collect(),
+        }
+    }
+    /**
+     * Lock the object of a root, waiting for any thread which
+     * holds the lock of its shard, or the write lock.
+     */
+    pub fn lock(&self, root: &SyncRoot<T>) -> Result<ObjectGuard<'_, T>, Error> {
+        self.lock_by(|region| root.try_ix(region))
+    }
+    /**
+     * Lock the object at an index, such as one read from another object.
+     * As with any index, it is invalidated by operations which may
+     * move objects, through [`write`](#method.write).
+     */
+    pub fn lock_ix(&self, ix: Ix<T>) -> Result<ObjectGuard<'_, T>, Error> {
+        self.lock_by(|_| Ok(ix))
+    }
+    // Lock the object at the index found in the region. The read lock
+    // is never held while waiting for a shard, nor kept by the guard,
+    // so the position is checked again once the shard is locked
+    fn lock_by<F>(&self, find: F) -> Result<ObjectGuard<'_, T>, Error> where
+        F: Fn(&SyncRegion<T>) -> Result<Ix<T>, Error>
+    {
+        loop {
+            let (i, epoch) = {
+                let region = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+                let ix = find(&region.region)?;
+                ix.check_region(&region.region.region)?;
+                if ix.ix() >= region.len {
+                    Err(Error::Indeterminable)?;
+                }
+                (ix.ix(), region.epoch)
+            };
+            let shard = self.shards[i % self.shards.len()].lock().unwrap_or_else(PoisonError::into_inner);
+            let region = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+            if region.epoch != epoch {
+                continue
+            }
+            // safety: the storage is valid while the shard is held,
+            // and no other thread accesses this position without the shard
+            let spot = unsafe { &mut *region.spots.add(i) };
+            let t: *mut T = spot.get_mut().ok_or(Error::Indeterminable)?.get_mut();
+            return Ok(ObjectGuard { t, _shard: shard })
+        }
+    }
+    /**
+     * Lock the whole region, waiting for every other lock to be released,
+     * such as to allocate, to create handles, or to collect.
+     */
+    pub fn write(&self) -> RegionWriteGuard<'_, T> {
+        // Every shard is taken before the region, and the writer only waits
+        // while it holds no shard, so that it never blocks a thread which
+        // holds the lock of an object from locking another
+        let mut wait: Option<usize> = None;
+        let shards = loop {
+            if let Some(k) = wait {
+                drop(self.shards[k].lock().unwrap_or_else(PoisonError::into_inner));
+            }
+            let mut shards = Vec::with_capacity(self.shards.len());
+            for (k, shard) in self.shards.iter().enumerate() {
+                match shard.try_lock() {
+                    Ok(guard) => shards.push(guard),
+                    Err(TryLockError::Poisoned(e)) => shards.push(e.into_inner()),
+                    Err(TryLockError::WouldBlock) => {
+                        wait = Some(k);
+                        break
+                    },
+                }
+            }
+            if shards.len() == self.shards.len() {
+                break shards
+            }
+        };
+        RegionWriteGuard {
+            inner: self.inner.write().unwrap_or_else(PoisonError::into_inner),
+            _shards: shards,
+        }
+    }
+    pub fn into_inner(self) -> SyncRegion<T> {
+        self.inner.into_inner().unwrap_or_else(PoisonError::into_inner).region
+    }
+}
+
+impl <T> Deref for ObjectGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // safety: the object is locked by this guard
+        unsafe { &*self.t }
+    }
+}
+impl <T> DerefMut for ObjectGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // safety: the object is locked by this guard
+        unsafe { &mut *self.t }
+    }
+}
+
+impl <T> Deref for RegionWriteGuard<'_, T> {
+    type Target = SyncRegion<T>;
+    fn deref(&self) -> &SyncRegion<T> {
+        &self.inner.region
+    }
+}
+impl <T> DerefMut for RegionWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut SyncRegion<T> {
+        &mut self.inner.region
+    }
+}
+impl <T> Drop for RegionWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.inner.record();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use crate::{Error, SharedRegion, SyncRegion, SyncRoot};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn sync_regions_move_between_threads() {
+        let mut r = SyncRegion::new();
+        r.ensure(4);
+        let dead = r.alloc(Elem::new());
+        let weak = r.weak(dead).unwrap();
+        let a = r.alloc(Elem::new());
+        let b = r.alloc(Elem { ix: Some(a) });
+        let root = r.root(b).unwrap();
+        let dropped = r.root(dead).unwrap();
+        drop(dropped);
+
+        let (r, root): (SyncRegion<Elem>, SyncRoot<Elem>) = std::thread::spawn(move || {
+            r.gc();
+            let b = r.alloc(Elem::new());
+            root.get_mut(&mut r).ix = Some(b);
+            r.gc();
+            (r, root)
+        }).join().unwrap();
+        assert_eq!(r.len(), 2);
+        assert!(weak.ix(&r).is_none());
+        let b = root.get(&r).ix.unwrap();
+        assert!(r.try_get(b).is_ok());
+    }
+
+    #[test]
+    pub fn shared_regions_lock_objects_by_shard() {
+        let mut r = SyncRegion::new();
+        r.ensure(3);
+        let garbage = r.alloc(Elem::new());
+        let a = r.alloc(Elem::new());
+        let b = r.alloc(Elem::new());
+        let ra = r.root(a).unwrap();
+        let rb = r.root(b).unwrap();
+        let shared = Arc::new(SharedRegion::with_shards(r, 2));
+
+        // Objects in different shards are locked at once
+        let barrier = Arc::new(Barrier::new(2));
+        let workers: Vec<_> = vec![(ra.clone(), b), (rb.clone(), a)].into_iter().map(|(own, other)| {
+            let (shared, barrier) = (shared.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                let mut guard = shared.lock(&own).unwrap();
+                barrier.wait();
+                guard.ix = Some(other);
+            })
+        }).collect();
+        workers.into_iter().for_each(|w| w.join().unwrap());
+
+        shared.write().gc();
+        assert_eq!(shared.write().len(), 2);
+        let next = shared.lock(&ra).unwrap().ix.unwrap();
+        assert_eq!(next.identifier(), rb.ix(&shared.write()).identifier());
+        assert!(shared.lock_ix(next).unwrap().ix.is_some());
+        if cfg!(feature = "debug-arena") {
+            assert_eq!(shared.lock_ix(garbage).err(), Some(Error::EntryExpired));
+        }
+        let r = Arc::try_unwrap(shared).ok().unwrap().into_inner();
+        assert_eq!(ra.get(&r).ix.unwrap().identifier(), rb.ix(&r).identifier());
+    }
+
+    #[test]
+    pub fn shared_regions_lock_objects_while_writers_wait() {
+        let mut r = SyncRegion::new();
+        r.ensure(2);
+        let a = r.alloc(Elem::new());
+        let b = r.alloc(Elem::new());
+        let ra = r.root(a).unwrap();
+        let rb = r.root(b).unwrap();
+        let shared = Arc::new(SharedRegion::with_shards(r, 2));
+
+        let first = shared.lock(&ra).unwrap();
+        let writer = {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.write().gc())
+        };
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        // The waiting writer does not block the lock of another shard
+        let mut second = shared.lock(&rb).unwrap();
+        second.ix = Some(a);
+        drop((first, second));
+        writer.join().unwrap();
+        let next = shared.lock(&rb).unwrap().ix.unwrap();
+        assert_eq!(next.identifier(), ra.ix(&shared.write()).identifier());
+    }
+}
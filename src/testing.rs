@@ -0,0 +1,235 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+/*!
+ * Support for property-testing code built on regions.
+ *
+ * This module provides a [proptest](https://docs.rs/proptest) strategy which generates
+ * random object graphs, described by a [`HeapShape`](struct.HeapShape.html).
+ * A shape can be built into any region, using functions to create objects
+ * and add edges, which allows testing of user [`HasIx`](../trait.HasIx.html)
+ * implementations against collections:
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * use gc::testing::{heap_shape, HeapConfig};
+ * use proptest::prelude::*;
+ *
+ * struct Node(Vec<gc::Ix<Node>>);
+ * impl gc::HasIx<Node> for Node {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Node>)
+ *     {
+ *         self.0.iter_mut().for_each(f)
+ *     }
+ * }
+ *
+ * proptest!(|(shape in heap_shape(HeapConfig::default()))| {
+ *     let mut r = gc::Region::new();
+ *     let heap = shape.build(&mut r, |_| Node(vec![]), |n, ix| n.0.push(ix));
+ *     r.gc();
+ *     let reachable = shape.reachable();
+ *     for (w, live) in heap.weaks.iter().zip(reachable) {
+ *         prop_assert_eq!(w.try_get(&r).is_ok(), live);
+ *     }
+ * });
+ * ```
+ *
+ * This module is only available with the feature "proptest-support".
+ */
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::{HasIx, Region, Root, Weak};
+
+/**
+ * Parameters for the generation of random heaps.
+ */
+#[derive(Debug, Clone)]
+pub struct HeapConfig {
+    /**
+     * The maximum number of objects in the heap.
+     */
+    pub max_size: usize,
+    /**
+     * The maximum number of edges from a single object.
+     */
+    pub max_fan_out: usize,
+    /**
+     * The probability that any given edge points backwards,
+     * to an object created no later than its source,
+     * which will usually create a cycle.
+     */
+    pub cycle_probability: f64,
+    /**
+     * The maximum number of roots, which may include duplicates.
+     */
+    pub max_roots: usize,
+}
+impl Default for HeapConfig {
+    fn default() -> Self {
+        HeapConfig {
+            max_size: 64,
+            max_fan_out: 4,
+            cycle_probability: 0.2,
+            max_roots: 4,
+        }
+    }
+}
+
+/**
+ * A description of a heap, with objects numbered in allocation order.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeapShape {
+    /**
+     * The targets of the edges of each object, in order.
+     */
+    pub edges: Vec<Vec<usize>>,
+    /**
+     * The objects which are rooted.
+     */
+    pub roots: Vec<usize>,
+}
+
+/**
+ * The handles of a heap created by [`HeapShape::build`](struct.HeapShape.html#method.build).
+ */
+pub struct GeneratedHeap<T> {
+    /**
+     * A root for each entry of the shape's roots.
+     */
+    pub roots: Vec<Root<T>>,
+    /**
+     * A weak pointer to every object, in allocation order.
+     */
+    pub weaks: Vec<Weak<T>>,
+}
+
+/**
+ * A strategy which generates heap shapes according to the given configuration.
+ */
+pub fn heap_shape(config: HeapConfig) -> impl Strategy<Value = HeapShape> {
+    let HeapConfig { max_size, max_fan_out, cycle_probability, max_roots } = config;
+    (0..=max_size).prop_flat_map(move |size| {
+        let edge = (prop::bool::weighted(cycle_probability), any::<Index>());
+        let edges = prop::collection::vec(
+            prop::collection::vec(edge, 0..=max_fan_out), size);
+        let roots = prop::collection::vec(any::<Index>(),
+            if size == 0 { 0..=0 } else { 0..=max_roots });
+        (edges, roots).prop_map(move |(edges, roots)| {
+            let edges = edges.into_iter().enumerate().map(|(i, out)| {
+                out.into_iter().map(|(backward, target)| {
+                    if backward || i + 1 == size {
+                        target.index(i + 1)
+                    } else {
+                        i + 1 + target.index(size - i - 1)
+                    }
+                }).collect()
+            }).collect();
+            let roots = roots.into_iter().map(|r| r.index(size)).collect();
+            HeapShape { edges, roots }
+        })
+    })
+}
+
+impl HeapShape {
+    /**
+     * Return the number of objects in this heap.
+     */
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+    /**
+     * Returns true if this heap has no objects.
+     */
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+    /**
+     * Compute which objects are reachable from the roots.
+     */
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.len()];
+        let mut stack = self.roots.clone();
+        while let Some(i) = stack.pop() {
+            if !reachable[i] {
+                reachable[i] = true;
+                stack.extend(&self.edges[i]);
+            }
+        }
+        reachable
+    }
+    /**
+     * Allocate this heap into a region. `make` creates the object with the given
+     * number, and `add_edge` adds an edge from an object to the given index.
+     *
+     * No collection will occur while the heap is being built, but one may occur
+     * before it begins.
+     */
+    pub fn build<T, M, E>(&self, region: &mut Region<T>, mut make: M, mut add_edge: E) -> GeneratedHeap<T> where
+        T: 'static + HasIx<T>,
+        M: FnMut(usize) -> T,
+        E: FnMut(&mut T, crate::Ix<T>),
+    {
+        region.ensure(self.len());
+        let weaks: Vec<Weak<T>> = (0..self.len()).map(|i| {
+            region.alloc(|_| make(i)).weak()
+        }).collect();
+        for (i, out) in self.edges.iter().enumerate() {
+            for &target in out {
+                let target = weaks[target].ix().unwrap();
+                add_edge(weaks[i].get_mut(region), target);
+            }
+        }
+        let roots = self.roots.iter().map(|&i| {
+            region.root(weaks[i].ix().unwrap()).unwrap()
+        }).collect();
+        GeneratedHeap { roots, weaks }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use super::{heap_shape, HeapConfig};
+    use crate::{HasIx, Ix, Region};
+
+    struct Node {
+        id: usize,
+        edges: Vec<Ix<Node>>,
+    }
+    impl HasIx<Node> for Node {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Node>)
+        {
+            self.edges.iter_mut().for_each(f)
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn gc_preserves_exactly_reachable(shape in heap_shape(HeapConfig::default())) {
+            let mut r = Region::new();
+            let heap = shape.build(&mut r, |id| Node { id, edges: vec![] },
+                                   |n, ix| n.edges.push(ix));
+            r.gc();
+            let reachable = shape.reachable();
+            prop_assert_eq!(r.len(), reachable.iter().filter(|&&b| b).count());
+            for (i, w) in heap.weaks.iter().enumerate() {
+                prop_assert_eq!(w.try_get(&r).is_ok(), reachable[i]);
+                if let Ok(node) = w.try_get(&r) {
+                    prop_assert_eq!(node.id, i);
+                    let targets: Vec<usize> = node.edges.iter().map(|e| e.get(&r).id).collect();
+                    prop_assert_eq!(&targets, &shape.edges[i]);
+                }
+            }
+        }
+    }
+}
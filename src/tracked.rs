@@ -0,0 +1,149 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::panic::Location;
+
+use crate::{Ix, Region};
+use crate::nonce::cmp_generation;
+
+type Site = &'static Location<'static>;
+
+// The generation of each region, and the sites which
+// created raw indices during that generation
+struct Sites {
+    generation: crate::nonce::Word,
+    counts: HashMap<Site, usize>,
+}
+
+thread_local! {
+    static SITES: RefCell<HashMap<crate::nonce::Word, Sites>> = RefCell::new(HashMap::new());
+}
+
+// Record the caller as having obtained a raw index
+#[track_caller]
+#[inline]
+pub(crate) fn record<T>(ix: Ix<T>) {
+    let site = Location::caller();
+    SITES.with(|sites| {
+        let mut sites = sites.borrow_mut();
+        let entry = sites.entry(ix.nonce).or_insert_with(|| Sites {
+            generation: ix.generation,
+            counts: HashMap::new(),
+        });
+        if cmp_generation(entry.generation, ix.generation) == Ordering::Less {
+            entry.generation = ix.generation;
+            entry.counts.clear();
+        }
+        if entry.generation == ix.generation {
+            *entry.counts.entry(site).or_insert(0) += 1;
+        }
+    });
+}
+
+// Forget the sites recorded for a region, once its
+// generation has advanced or it has been dropped
+pub(crate) fn forget(nonce: crate::nonce::Word) {
+    // The table may already be destroyed if a region
+    // is dropped while its thread exits
+    let _ = SITES.try_with(|sites| {
+        sites.borrow_mut().remove(&nonce);
+    });
+}
+
+impl <T> Drop for Region<T> {
+    fn drop(&mut self) {
+        forget(self.nonce);
+    }
+}
+
+impl <T> Region<T> {
+    /**
+     * Report the locations which have obtained raw indices into
+     * this region since it was last collected, along with the
+     * number of indices obtained at each, most frequent first.
+     *
+     * These are the indices which the next invalidating call will
+     * invalidate, so a non-empty report just before such a call
+     * indicates a location which may be holding an index across it.
+     * Indices are counted as they are obtained from a root, weak pointer,
+     * entry or root set, but since `Ix` is `Copy`, copies
+     * and drops of an index are not counted.
+     *
+     * This requires the feature "tracked-ix".
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let root = r.alloc(|_|{()}).root();
+     * let ix = root.ix();
+     * assert_eq!(r.outstanding_ix()[0].1, 1);
+     *
+     * r.gc();
+     * assert!(r.outstanding_ix().is_empty());
+     * ```
+     */
+    pub fn outstanding_ix(&self) -> Vec<(&'static Location<'static>, usize)> {
+        SITES.with(|sites| {
+            let sites = sites.borrow();
+            let mut report: Vec<_> = match sites.get(&self.nonce) {
+                Some(s) if s.generation == self.generation =>
+                    s.counts.iter().map(|(&site, &n)| (site, n)).collect(),
+                _ => Vec::new(),
+            };
+            report.sort_by(|a, b| b.1.cmp(&a.1)
+                .then_with(|| (a.0.file(), a.0.line()).cmp(&(b.0.file(), b.0.line()))));
+            report
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Region;
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn outstanding_ix_reports_sites() {
+        let mut r = Region::new();
+        r.ensure(2);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let b = r.alloc(|_| {Elem::new()}).weak();
+        for _ in 0..3 {
+            let _ = a.ix();
+        }
+        let _ = b.ix();
+        let report = r.outstanding_ix();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].1, 3);
+        assert_eq!(report[0].0.file(), file!());
+        // Accesses through roots are not counted
+        a.get(&r);
+        assert_eq!(r.outstanding_ix().len(), 2);
+
+        r.gc();
+        assert!(r.outstanding_ix().is_empty());
+    }
+
+    #[test]
+    pub fn sites_are_forgotten() {
+        let recorded = |r: &Region<Elem>| super::SITES.with(|sites| sites.borrow().contains_key(&r.nonce));
+        let mut r = Region::new();
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let _ = a.ix();
+        assert!(recorded(&r));
+        r.gc();
+        assert!(!recorded(&r));
+
+        let _ = a.ix();
+        let nonce = r.nonce;
+        drop(a);
+        drop(r);
+        assert!(!super::SITES.with(|sites| sites.borrow().contains_key(&nonce)));
+    }
+}
@@ -4,9 +4,13 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
-use std::rc;
-use std::cell::Cell;
-use std::marker::PhantomData;
+use alloc::rc;
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use alloc::string::String;
+use alloc::format;
 
 #[repr(C)]
 // repr(C) Needed for unsafe header
@@ -37,39 +41,65 @@ pub struct Ix<T> {
     ix: usize,
     _t: PhantomData<*mut T>,
     #[cfg(feature = "debug-arena")]
-    pub(crate) nonce: u64,
+    pub(crate) nonce: crate::nonce::Word,
     #[cfg(feature = "debug-arena")]
-    pub(crate) generation: u64,
+    pub(crate) generation: crate::nonce::Word,
 }
-use std::fmt;
-impl <T> std::fmt::Debug for Ix<T> {
+use core::fmt;
+impl <T> core::fmt::Debug for Ix<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.ix.fmt(f)
     }
 }
 impl <T> Clone for Ix<T> {
     fn clone(&self) -> Self {
-        Ix {
-            ix: self.ix,
-            _t: PhantomData,
-            #[cfg(feature = "debug-arena")]
-            nonce: self.nonce,
-            #[cfg(feature = "debug-arena")]
-            generation: self.generation,
-        }
+        *self
     }
 }
 impl <T> Copy for Ix<T> {}
 unsafe impl <T> Send for Ix<T> {}
 unsafe impl <T> Sync for Ix<T> {}
 
+// Indices are compared by position, and under debug-arena,
+// by the region and collection they were created for
+impl <T> PartialEq for Ix<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl <T> Eq for Ix<T> {}
+impl <T> PartialOrd for Ix<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl <T> Ord for Ix<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ord = self.ix.cmp(&other.ix);
+        #[cfg(feature = "debug-arena")]
+        let ord = ord.then(self.nonce.cmp(&other.nonce))
+            .then(self.generation.cmp(&other.generation));
+        ord
+    }
+}
+impl <T> Hash for Ix<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ix.hash(state);
+        #[cfg(feature = "debug-arena")]
+        {
+            self.nonce.hash(state);
+            self.generation.hash(state);
+        }
+    }
+}
+
 
 impl <T> Ix<T> {
     pub(crate) fn new(ix: usize,
                       #[cfg(feature = "debug-arena")]
-                      nonce: u64,
+                      nonce: crate::nonce::Word,
                       #[cfg(feature = "debug-arena")]
-                      generation: u64,
+                      generation: crate::nonce::Word,
     ) -> Self {
         Ix { ix, _t: PhantomData,
             #[cfg(feature = "debug-arena")]
@@ -81,6 +111,29 @@ impl <T> Ix<T> {
     #[inline(always)]
     pub(crate) fn ix(self) -> usize {self.ix}
 
+    // Mark this index as handed out to the caller, for tracked-ix
+    #[inline(always)]
+    #[cfg_attr(feature = "tracked-ix", track_caller)]
+    pub(crate) fn tracked(self) -> Self {
+        #[cfg(feature = "tracked-ix")]
+        crate::tracked::record(self);
+        self
+    }
+
+    // The index n positions after this one
+    #[inline(always)]
+    pub(crate) fn offset(self, n: usize) -> Self {
+        Ix { ix: self.ix + n, ..self }
+    }
+
+    // Describe this index in a panic message
+    pub(crate) fn describe(self) -> String {
+        #[cfg(feature = "debug-arena")]
+        return format!("{} (region {}, generation {})", self.ix, self.nonce, self.generation);
+        #[cfg(not(feature = "debug-arena"))]
+        return format!("{}", self.ix);
+    }
+
     /**
      * Get an identifier for this index.
      * It is unique amongst indices in this region,
@@ -95,6 +148,7 @@ impl <T> Ix<T> {
 }
 pub type IxCell<T> = Cell<Ix<T>>;
 
+#[allow(unused)]
 pub enum SpotVariant<'a, E, T> {
     Present(&'a mut E),
     BrokenHeart(Ix<T>),
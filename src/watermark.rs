@@ -0,0 +1,234 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{Error, HasIx, Region};
+use crate::remap::{self, Forwarding};
+
+/**
+ * A point in the allocation history of a region,
+ * created by [`Region::watermark`](struct.Region.html#method.watermark).
+ *
+ * A mark is only usable until the next collection of its region.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mark {
+    len: usize,
+    epoch: u64,
+    #[cfg(feature = "debug-arena")]
+    nonce: crate::nonce::Word,
+}
+
+impl <T> Region<T> {
+    /**
+     * Record the current point in allocation, so that every object
+     * allocated later can be freed at once with
+     * [`truncate_to`](#method.truncate_to).
+     */
+    #[inline]
+    pub fn watermark(&self) -> Mark {
+        Mark {
+            len: self.data.len(),
+            epoch: self.epoch,
+            #[cfg(feature = "debug-arena")]
+            nonce: self.nonce,
+        }
+    }
+}
+
+impl <T: 'static + HasIx<T>> Region<T> {
+    // Remove every object at or after len, without a collection.
+    // Roots of those objects are invalidated.
+    pub(crate) fn truncate_data(&mut self, len: usize) {
+        let dead = self.ix_at(usize::MAX);
+        self.roots.for_each_mut(|ix| if ix.ix() >= len { *ix = dead });
+        self.roots.prune();
+        self.root_sets.retain(|set| match set.upgrade() {
+            Some(set) => {
+                set.borrow_mut().retain(|ix| ix.ix() < len);
+                true
+            },
+            None => false,
+        });
+        self.groups.retain_mut(|g| {
+            g.end = core::cmp::min(g.end, len);
+            g.start < g.end
+        });
+        self.finalize_from(len);
+        let old_len = self.data.len();
+        remap::remap_all(&mut self.remaps, || Forwarding::truncation(old_len, len));
+        #[cfg(feature = "shadow-heap")]
+        self.shadow_truncate(len);
+        #[cfg(feature = "history")]
+        self.history_event(crate::HistoryEvent::Truncate { len });
+        self.data.truncate(len);
+    }
+
+    /**
+     * Drop every object allocated after the mark, without
+     * a reachability pass, such as for scratch objects
+     * which are known to be unreachable.
+     *
+     * Roots of the dropped objects are invalidated, and weak pointers
+     * to them expire. No remaining object may point to a dropped object.
+     * With the feature "debug-arena", this is checked, and
+     * the method panics if it is violated.
+     *
+     * This fails if the region has been collected since the mark
+     * was created, or if the region is pinned. Since allocation may
+     * trigger a collection, [`ensure`](#method.ensure) can be used to
+     * reserve space for the scratch objects before the mark is created.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * let kept = r.alloc(|_|{()}).root();
+     * r.ensure(1);
+     * let mark = r.watermark();
+     * let scratch = r.alloc(|_|{()}).weak();
+     *
+     * r.truncate_to(mark).unwrap();
+     * assert_eq!(r.len(), 1);
+     * assert!(scratch.try_get(&r).is_err());
+     * ```
+     */
+    pub fn truncate_to(&mut self, mark: Mark) -> Result<(), Error> {
+        #[cfg(feature = "debug-arena")]
+        {
+            if mark.nonce != self.nonce {
+                Err(Error::IncorrectRegion)?;
+            }
+        }
+        if mark.epoch != self.epoch {
+            Err(Error::EntryExpired)?;
+        }
+        if self.pins.is_pinned() {
+            Err(Error::Pinned)?;
+        }
+        #[cfg(feature = "debug-arena")]
+        {
+            for (i, spot) in self.data[..mark.len].iter_mut().enumerate() {
+                if let Some(e) = spot.get_mut() {
+                    crate::has_ix::foreach_edge(e.get_mut(), |ix| {
+                        if ix.ix() >= mark.len {
+                            panic!("Region::truncate_to: object {} points to truncated object {}", i, ix.ix());
+                        }
+                    });
+                }
+            }
+        }
+        self.truncate_data(mark.len);
+        Ok(())
+    }
+
+    /**
+     * Drop every object, keeping the capacity of the region for
+     * re-use, such as for a region which is returned to a pool.
+     * Objects with finalizers are queued as usual.
+     *
+     * Every root is invalidated, every weak pointer expires,
+     * and every mark expires. Unlike a new region, with the feature
+     * "debug-arena", indices from before the reset are detected
+     * as stale, rather than from another region.
+     * Settings such as the policy, limit and fuel are kept.
+     *
+     * This panics if the region is pinned.
+     *
+     * ```rust
+     * use moving_gc_arena as gc;
+     * let mut r = gc::Region::new();
+     * r.ensure(100);
+     * let a = r.alloc(|_|{()}).root();
+     * r.reset();
+     * assert!(r.is_empty());
+     * assert!(r.capacity() >= 100);
+     * assert!(a.try_get(&r).is_err());
+     * ```
+     */
+    pub fn reset(&mut self) {
+        if self.pins.is_pinned() {
+            panic!("Region::reset: {}", Error::Pinned);
+        }
+        self.truncate_data(0);
+        self.epoch += 1;
+        #[cfg(feature = "debug-arena")]
+        self.advance_generation();
+        #[cfg(feature = "refresh")]
+        {
+            self.last_forwarding = None;
+        }
+    }
+    /**
+     * Drop every object, as with [`reset`](#method.reset),
+     * and release the capacity of the region, as with
+     * [`release_unused_memory`](#method.release_unused_memory).
+     *
+     * This panics if the region is pinned.
+     */
+    pub fn clear(&mut self) {
+        if self.pins.is_pinned() {
+            panic!("Region::clear: {}", Error::Pinned);
+        }
+        self.reset();
+        if let Err(e) = self.release_unused_memory() {
+            panic!("Region::clear: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Region};
+    use crate::tests::Elem;
+
+    #[test]
+    pub fn truncate_invalidates_roots() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Elem::new()}).root();
+        let mark = r.watermark();
+        let b = r.alloc(|_| {Elem { ix: Some(a.ix()) }}).root();
+        let mut set = r.ix_set();
+        set.insert(a.ix());
+        set.insert(b.ix());
+
+        r.truncate_to(mark).unwrap();
+        assert_eq!(r.len(), 1);
+        assert!(b.try_get(&r).is_err());
+        assert_eq!(set.len(), 1);
+        let c = r.alloc(|_| {Elem::new()}).root();
+        assert!(b.try_get(&r).is_err());
+        assert!(c.try_get(&r).is_ok());
+
+        let mark = r.watermark();
+        r.gc();
+        assert_eq!(r.truncate_to(mark), Err(Error::EntryExpired));
+        assert_eq!(r.len(), 2);
+    }
+
+    #[test]
+    pub fn reset_empties_the_region() {
+        let mut r = Region::new();
+        r.ensure(4);
+        let a = r.alloc(|_| {Elem::new()}).ix();
+        let b = r.alloc(|_| {Elem { ix: Some(a) }}).root();
+        let weak = r.alloc(|_| {Elem::new()}).weak();
+        let mark = r.watermark();
+        r.reset();
+        assert!(r.is_empty());
+        assert_eq!(r.capacity(), 4);
+        assert!(b.try_get(&r).is_err());
+        assert!(weak.ix().is_none());
+        assert_eq!(r.truncate_to(mark), Err(Error::EntryExpired));
+
+        let c = r.alloc(|_| {Elem::new()}).root();
+        assert!(c.try_get(&r).is_ok());
+        #[cfg(feature = "debug-arena")]
+        assert!(a.try_get(&r).is_err());
+        r.clear();
+        assert_eq!(r.capacity(), 0);
+        assert!(c.try_get(&r).is_err());
+    }
+}
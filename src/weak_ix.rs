@@ -0,0 +1,180 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use core::fmt::{Debug, Formatter};
+
+use crate::{Error, HasIx, Ix, Region};
+
+/**
+ * An edge held within an object which does not keep its target alive,
+ * such as a back-pointer or a cache entry. Unlike a [`Weak`](struct.Weak.html),
+ * it is a plain index, with no allocation of its own.
+ *
+ * A weak index must be exposed by
+ * [`HasIx::foreach_weak_ix`](trait.HasIx.html#method.foreach_weak_ix), rather
+ * than `foreach_ix`. A collection then updates it if its target
+ * moves, and clears it if its target is collected.
+ *
+ * ```rust
+ * use moving_gc_arena as gc;
+ * struct Node {
+ *     parent: gc::WeakIx<Node>,
+ * }
+ * impl gc::HasIx<Node> for Node {
+ *     fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, _f: F) where
+ *         F: FnMut(&'b mut gc::Ix<Node>)
+ *     { }
+ *     fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+ *         F: FnMut(&'b mut gc::WeakIx<Node>)
+ *     {
+ *         self.parent.foreach_weak_ix(f)
+ *     }
+ * }
+ *
+ * let mut r = gc::Region::new();
+ * r.ensure(2);
+ * let parent = r.alloc(|_| Node { parent: gc::WeakIx::default() }).ix();
+ * let child = r.alloc(|_| Node { parent: gc::WeakIx::new(parent) }).root();
+ * r.gc();
+ * assert!(child.get(&r).parent.is_empty());
+ * ```
+ */
+pub struct WeakIx<T> {
+    ix: Option<Ix<T>>,
+}
+impl <T> WeakIx<T> {
+    #[inline]
+    pub fn new(ix: Ix<T>) -> Self {
+        WeakIx { ix: Some(ix) }
+    }
+    /**
+     * Get the index of the target, if it has not been collected
+     * or cleared. This is a strong index, and is only valid until
+     * the next collection.
+     */
+    #[inline]
+    pub fn ix(&self) -> Option<Ix<T>> {
+        self.ix
+    }
+    /**
+     * Returns true if there is no target.
+     */
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ix.is_none()
+    }
+    /**
+     * Remove the target.
+     */
+    #[inline]
+    pub fn clear(&mut self) {
+        self.ix = None;
+    }
+    /**
+     * Get a reference to the target, or an error if
+     * there is none, as for [`Weak::try_get`](struct.Weak.html#method.try_get).
+     */
+    #[inline]
+    pub fn try_get<'a>(&self, r: &'a Region<T>) -> Result<&'a T, Error> {
+        self.ix.ok_or(Error::EntryExpired)?.try_get(r)
+    }
+    /**
+     * Get a mutable reference to the target, or an error if there is none.
+     */
+    #[inline]
+    pub fn try_get_mut<'a>(&self, r: &'a mut Region<T>) -> Result<&'a mut T, Error> {
+        self.ix.ok_or(Error::EntryExpired)?.try_get_mut(r)
+    }
+    // Point to the new position of the target, or
+    // clear it if the target was not kept
+    #[inline]
+    pub(crate) fn forward<F>(&mut self, f: F) where
+        F: FnOnce(Ix<T>) -> Option<Ix<T>>
+    {
+        self.ix = self.ix.and_then(f);
+    }
+}
+impl <T> Default for WeakIx<T> {
+    #[inline]
+    fn default() -> Self {
+        WeakIx { ix: None }
+    }
+}
+impl <T> Clone for WeakIx<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl <T> Copy for WeakIx<T> {}
+impl <T> Debug for WeakIx<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_tuple("WeakIx").field(&self.ix).finish()
+    }
+}
+
+impl <T : 'static> HasIx<T> for WeakIx<T> {
+    fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, mut _f: F) where
+        F: FnMut(&'b mut Ix<T>)
+    { }
+    fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, mut f: F) where
+        F: FnMut(&'b mut WeakIx<T>)
+    {
+        f(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HasIx, Ix, Region, WeakIx};
+
+    struct Cached {
+        next: Option<Ix<Cached>>,
+        cache: Vec<WeakIx<Cached>>,
+    }
+    impl HasIx<Cached> for Cached {
+        fn foreach_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut Ix<Cached>)
+        {
+            self.next.foreach_ix(f)
+        }
+        fn foreach_weak_ix<'b, 'a : 'b, F>(&'a mut self, f: F) where
+            F: FnMut(&'b mut WeakIx<Cached>)
+        {
+            self.cache.foreach_weak_ix(f)
+        }
+    }
+
+    #[test]
+    pub fn weak_edges_are_forwarded_or_cleared() {
+        let mut r = Region::new();
+        r.ensure(3);
+        let dead = r.alloc(|_| {Cached { next: None, cache: vec![] }}).ix();
+        let b = r.alloc(|_| {Cached { next: None, cache: vec![] }}).ix();
+        let a = r.alloc(|_| {Cached {
+            next: Some(b),
+            cache: vec![WeakIx::new(dead), WeakIx::new(b)],
+        }}).root();
+        r.gc();
+        assert_eq!(r.len(), 2);
+        let cache = &a.get(&r).cache;
+        assert!(cache[0].is_empty());
+        assert_eq!(cache[1].ix().unwrap().identifier(), a.get(&r).next.unwrap().identifier());
+        assert!(cache[1].try_get(&r).is_ok());
+    }
+
+    #[test]
+    pub fn partitions_clear_weak_edges() {
+        let mut r = Region::new();
+        r.ensure(2);
+        r.set_partition(1);
+        let dead = r.alloc(|_| {Cached { next: None, cache: vec![] }}).ix();
+        r.set_partition(0);
+        let a = r.alloc(|_| {Cached { next: None, cache: vec![WeakIx::new(dead)] }}).root();
+        assert_eq!(r.gc_partition(1), Ok(1));
+        assert!(a.get(&r).cache[0].is_empty());
+    }
+}